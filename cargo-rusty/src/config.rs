@@ -0,0 +1,175 @@
+use crate::RequireApproval;
+use crate::synth::SynthArgs;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::read_to_string;
+use std::io::ErrorKind;
+
+const CONFIG_FILE: &str = "rusty.toml";
+const CARGO_MANIFEST: &str = "Cargo.toml";
+
+/// The project configuration, read from `rusty.toml` in the current directory,
+/// or from the `[package.metadata.rusty]` table of `Cargo.toml` when there is no `rusty.toml`
+///
+/// ```toml
+/// [synth]
+/// package = "infra"
+///
+/// [env.prod]
+/// name = "my-stack-prod"
+/// account = "123456789012"
+/// region = "eu-west-1"
+/// profile = "prod"
+/// tags = { team = "platform" }
+/// synth = { features = ["prod"] }
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub synth: SynthArgs,
+    pub env: HashMap<String, Environment>,
+}
+
+/// A named environment (`cargo rusty deploy --env prod`), with the stack name and where and how to deploy it
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Environment {
+    /// Name of the stack, used when no `--name` is passed in
+    pub name: Option<String>,
+    /// Account to deploy to. Commands refuse to run when the credentials belong to another account
+    pub account: Option<String>,
+    /// Region to deploy to. Commands refuse to run when another region is passed in
+    pub region: Option<String>,
+    pub profile: Option<String>,
+    /// Tags to add to the stack
+    pub tags: BTreeMap<String, String>,
+    pub require_approval: Option<RequireApproval>,
+    /// Overrides the `[synth]` arguments of the config, the command line arguments take precedence
+    pub synth: SynthArgs,
+}
+
+impl Config {
+    /// Returns the default config when there is no `rusty.toml` and no `[package.metadata.rusty]`
+    pub fn read() -> Result<Config, String> {
+        match read_to_string(CONFIG_FILE) {
+            Ok(content) => return Self::parse(&content),
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("could not read {CONFIG_FILE}: {e}")),
+        }
+
+        match read_to_string(CARGO_MANIFEST) {
+            Ok(content) => Self::parse_manifest(&content),
+            Err(_) => Ok(Config::default()),
+        }
+    }
+
+    /// Takes the environment with the given name out of the config, or returns an empty one when there is no name
+    pub fn take_environment(&mut self, name: Option<&str>) -> Result<Environment, String> {
+        let Some(name) = name else {
+            return Ok(Environment::default());
+        };
+
+        self.env.remove(name).ok_or_else(|| {
+            let mut available: Vec<_> = self.env.keys().map(|k| k.as_str()).collect();
+            available.sort();
+            format!(
+                "environment {name} not found in the config, available environments: {}",
+                if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                }
+            )
+        })
+    }
+
+    fn parse(content: &str) -> Result<Config, String> {
+        toml::from_str(content).map_err(|e| format!("{CONFIG_FILE} is not valid: {e}"))
+    }
+
+    fn parse_manifest(content: &str) -> Result<Config, String> {
+        let manifest: toml::Table = toml::from_str(content).map_err(|e| format!("{CARGO_MANIFEST} is not valid: {e}"))?;
+
+        match manifest.get("package").and_then(|p| p.get("metadata")).and_then(|m| m.get("rusty")) {
+            Some(config) => config
+                .clone()
+                .try_into()
+                .map_err(|e| format!("[package.metadata.rusty] in {CARGO_MANIFEST} is not valid: {e}")),
+            None => Ok(Config::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::RequireApproval;
+    use crate::config::Config;
+
+    #[test]
+    fn should_parse_config_with_environments() {
+        let content = r#"
+[synth]
+package = "infra"
+
+[env.prod]
+name = "my-stack-prod"
+account = "123456789012"
+region = "eu-west-1"
+tags = { team = "platform" }
+require_approval = "never"
+synth = { features = ["prod"] }
+"#;
+
+        let mut config = Config::parse(content).unwrap();
+        let prod = config.take_environment(Some("prod")).unwrap();
+
+        assert_eq!(
+            format!("{:?}", config.synth.to_cargo_args()),
+            r#"["run", "--quiet", "--package", "infra"]"#
+        );
+        assert_eq!(prod.name.as_deref(), Some("my-stack-prod"));
+        assert_eq!(prod.account.as_deref(), Some("123456789012"));
+        assert_eq!(prod.region.as_deref(), Some("eu-west-1"));
+        assert_eq!(prod.tags.get("team").map(String::as_str), Some("platform"));
+        assert!(matches!(prod.require_approval, Some(RequireApproval::Never)));
+        assert_eq!(
+            format!("{:?}", prod.synth.to_cargo_args()),
+            r#"["run", "--quiet", "--features", "prod"]"#
+        );
+    }
+
+    #[test]
+    fn should_refuse_unknown_fields() {
+        assert!(Config::parse("[synth]\nbinary = \"infra\"").is_err());
+        assert!(Config::parse("[env.prod]\nstack = \"my-stack\"").is_err());
+    }
+
+    #[test]
+    fn should_return_error_for_unknown_environment() {
+        let mut config = Config::parse("[env.dev]\n[env.prod]").unwrap();
+
+        let error = config.take_environment(Some("staging")).unwrap_err();
+
+        assert!(error.ends_with("available environments: dev, prod"));
+        assert!(config.take_environment(None).unwrap().name.is_none());
+    }
+
+    #[test]
+    fn should_read_config_from_package_metadata() {
+        let manifest = r#"
+[package]
+name = "infra"
+
+[package.metadata.rusty.env.prod]
+name = "my-stack-prod"
+"#;
+
+        let mut config = Config::parse_manifest(manifest).unwrap();
+
+        assert_eq!(
+            config.take_environment(Some("prod")).unwrap().name.as_deref(),
+            Some("my-stack-prod")
+        );
+        assert!(Config::parse_manifest("[package]\nname = \"infra\"").unwrap().env.is_empty());
+    }
+}
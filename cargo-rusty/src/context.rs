@@ -0,0 +1,83 @@
+use crate::synth::{DEFAULT_OUTPUT_DIR, SynthArgs, synth_to_dir_with_env};
+use rusty_cdk::context::{CONTEXT_FILE, LookupKey, read_lookups, touch_lookups, update_lookups};
+use std::path::Path;
+
+const RUSTY_CDK_RECHECK_ENV_VAR_NAME: &str = "RUSTY_CDK_RECHECK";
+
+/// The lookups in the context file, as `kind identifier (account, region)`
+pub(crate) fn list_lookups(path: &Path) -> Result<Vec<String>, String> {
+    let lookups = read_lookups(path)?;
+
+    Ok(lookups
+        .keys()
+        .map(|key| match LookupKey::parse(key) {
+            Some(LookupKey {
+                account,
+                region,
+                kind,
+                identifier,
+            }) => format!("{kind} {identifier} (account {account}, region {region})"),
+            None => key.clone(),
+        })
+        .collect())
+}
+
+/// Removes all lookups, so they are done again during the next build. Returns the number of lookups that were removed
+pub(crate) fn clear_lookups(path: &Path) -> Result<usize, String> {
+    update_lookups(path, |lookups| {
+        let count = lookups.len();
+        lookups.clear();
+        count
+    })
+}
+
+/// Does the lookups of the stack again, by synthing it with `RUSTY_CDK_RECHECK`.
+/// The file is written first, because it is a dependency of the lookups, so cargo expands them again.
+/// Lookups of crates that are not part of the synth are kept, and the old lookups are restored when the synth fails.
+///
+/// Returns the number of lookups, and how many of them changed
+pub(crate) async fn refresh_lookups(synth_args: &SynthArgs) -> Result<(usize, usize), String> {
+    let path = Path::new(CONTEXT_FILE);
+    let before = read_lookups(path)?;
+    touch_lookups(path)?;
+
+    match synth_to_dir_with_env(DEFAULT_OUTPUT_DIR, synth_args, &[(RUSTY_CDK_RECHECK_ENV_VAR_NAME, "true")]).await {
+        Ok(_) => {
+            let after = read_lookups(path)?;
+            let changed = after.iter().filter(|(key, value)| before.get(*key) != Some(*value)).count();
+            Ok((after.len(), changed))
+        }
+        Err(e) => {
+            update_lookups(path, |lookups| *lookups = before)?;
+            Err(format!("could not refresh the lookups, restored {CONTEXT_FILE}: {e}"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::{clear_lookups, list_lookups};
+    use rusty_cdk::context::{read_lookups, update_lookups};
+    use serde_json::json;
+    use std::env::temp_dir;
+    use std::fs::{create_dir_all, remove_dir_all};
+
+    #[test]
+    fn should_list_and_clear_lookups() {
+        let dir = temp_dir().join(format!("cargo-rusty-context-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let path = dir.join("rusty.context.json");
+        update_lookups(&path, |lookups| {
+            lookups.insert("123456789012:eu-west-1:vpc:vpc-123".to_string(), json!({"id": "vpc-123"}));
+            lookups.insert("invalid".to_string(), json!("value"));
+        })
+        .unwrap();
+
+        let listed = list_lookups(&path).unwrap();
+        assert_eq!(listed, vec!["vpc vpc-123 (account 123456789012, region eu-west-1)", "invalid"]);
+
+        assert_eq!(clear_lookups(&path).unwrap(), 2);
+        assert!(read_lookups(&path).unwrap().is_empty());
+        remove_dir_all(dir).unwrap();
+    }
+}
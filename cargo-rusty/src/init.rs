@@ -0,0 +1,224 @@
+use clap::ValueEnum;
+use std::path::Path;
+use tokio::fs::{create_dir_all, write};
+
+const DEFAULT_LAMBDA_BUCKET: &str = "replace-with-your-lambda-code-bucket";
+const MAX_NAME_LENGTH: usize = 50;
+
+const COMMON_FILES: [(&str, &str); 6] = [
+    ("Cargo.toml", include_str!("../templates/common/Cargo.toml.tmpl")),
+    ("rusty.toml", include_str!("../templates/common/rusty.toml.tmpl")),
+    (".gitignore", include_str!("../templates/common/gitignore.tmpl")),
+    ("README.md", include_str!("../templates/common/README.md.tmpl")),
+    ("infra/Cargo.toml", include_str!("../templates/common/infra/Cargo.toml.tmpl")),
+    ("infra/src/main.rs", include_str!("../templates/common/infra/src/main.rs.tmpl")),
+];
+
+const APIGATEWAY_LAMBDA_DYNAMODB_FILES: [(&str, &str); 4] = [
+    (
+        "infra/src/lib.rs",
+        include_str!("../templates/apigateway-lambda-dynamodb/infra/src/lib.rs.tmpl"),
+    ),
+    (
+        "infra/tests/snapshots.rs",
+        include_str!("../templates/apigateway-lambda-dynamodb/infra/tests/snapshots.rs.tmpl"),
+    ),
+    (
+        "lambdas/books/Cargo.toml",
+        include_str!("../templates/apigateway-lambda-dynamodb/lambdas/books/Cargo.toml.tmpl"),
+    ),
+    (
+        "lambdas/books/src/main.rs",
+        include_str!("../templates/apigateway-lambda-dynamodb/lambdas/books/src/main.rs.tmpl"),
+    ),
+];
+
+const APPCONFIG_SECRETS_FILES: [(&str, &str); 6] = [
+    (
+        "infra/src/lib.rs",
+        include_str!("../templates/appconfig-secrets/infra/src/lib.rs.tmpl"),
+    ),
+    (
+        "infra/tests/snapshots.rs",
+        include_str!("../templates/appconfig-secrets/infra/tests/snapshots.rs.tmpl"),
+    ),
+    (
+        "lambdas/secret/Cargo.toml",
+        include_str!("../templates/appconfig-secrets/lambdas/secret/Cargo.toml.tmpl"),
+    ),
+    (
+        "lambdas/secret/src/main.rs",
+        include_str!("../templates/appconfig-secrets/lambdas/secret/src/main.rs.tmpl"),
+    ),
+    (
+        "lambdas/appconfig/Cargo.toml",
+        include_str!("../templates/appconfig-secrets/lambdas/appconfig/Cargo.toml.tmpl"),
+    ),
+    (
+        "lambdas/appconfig/src/main.rs",
+        include_str!("../templates/appconfig-secrets/lambdas/appconfig/src/main.rs.tmpl"),
+    ),
+];
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Template {
+    /// An HTTP API with a Lambda function that reads from a DynamoDB table
+    ApigatewayLambdaDynamodb,
+    /// A secret and an AppConfig configuration profile, each read by their own Lambda function
+    AppconfigSecrets,
+}
+
+impl Template {
+    fn files(&self) -> Vec<(&'static str, &'static str)> {
+        let files: &[(&str, &str)] = match self {
+            Template::ApigatewayLambdaDynamodb => &APIGATEWAY_LAMBDA_DYNAMODB_FILES,
+            Template::AppconfigSecrets => &APPCONFIG_SECRETS_FILES,
+        };
+        COMMON_FILES.iter().chain(files).copied().collect()
+    }
+
+    fn lambdas(&self) -> &'static [&'static str] {
+        match self {
+            Template::ApigatewayLambdaDynamodb => &["books"],
+            Template::AppconfigSecrets => &["secret", "appconfig"],
+        }
+    }
+}
+
+/// Generates a workspace from the template in the given directory:
+/// an infra crate with the stack and its snapshot test, the Lambda crates and a `rusty.toml`.
+/// The name defaults to the name of the directory. Existing files are never overwritten.
+///
+/// Returns the paths of the generated files.
+pub(crate) async fn init(
+    template: Template,
+    path: &str,
+    name: Option<String>,
+    lambda_bucket: Option<String>,
+) -> Result<Vec<String>, String> {
+    let dir = Path::new(path);
+    let name = match name {
+        Some(name) => name,
+        None => dir
+            .canonicalize()
+            .unwrap_or_else(|_| dir.to_path_buf())
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or_else(|| format!("could not derive a name from path {path}, pass in a name"))?,
+    };
+    check_name(&name)?;
+
+    let existing: Vec<_> = template
+        .files()
+        .into_iter()
+        .map(|(file, _)| dir.join(file))
+        .filter(|file| file.exists())
+        .map(|file| file.display().to_string())
+        .collect();
+    if !existing.is_empty() {
+        return Err(format!("refusing to overwrite existing files: {}", existing.join(", ")));
+    }
+
+    let lambda_bucket = lambda_bucket.unwrap_or_else(|| DEFAULT_LAMBDA_BUCKET.to_string());
+    let lambda_packages: Vec<_> = template.lambdas().iter().map(|l| format!("--package {l}")).collect();
+    let placeholders = [
+        ("{{name}}", name.clone()),
+        ("{{stack_name}}", name.to_lowercase()),
+        ("{{lambda_bucket}}", lambda_bucket),
+        ("{{lambda_packages}}", lambda_packages.join(" ")),
+        // cargo-rusty is released together with rusty-cdk
+        ("{{rusty_cdk_version}}", env!("CARGO_PKG_VERSION").to_string()),
+    ];
+
+    let mut written = vec![];
+    for (file, content) in template.files() {
+        let file = dir.join(file);
+        let content = placeholders
+            .iter()
+            .fold(content.to_string(), |acc, (placeholder, value)| acc.replace(placeholder, value));
+
+        if let Some(parent) = file.parent() {
+            create_dir_all(parent)
+                .await
+                .map_err(|e| format!("could not create directory {}: {e}", parent.display()))?;
+        }
+        write(&file, content)
+            .await
+            .map_err(|e| format!("could not write file at {}: {e}", file.display()))?;
+        written.push(file.display().to_string());
+    }
+
+    Ok(written)
+}
+
+// the name is used in the stack name and in the names of resources, like the secret and the AppConfig application
+fn check_name(name: &str) -> Result<(), String> {
+    let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && name.len() <= MAX_NAME_LENGTH;
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "name {name} is not valid, it should start with a letter, only contain alphanumerics and hyphens and be at most {MAX_NAME_LENGTH} characters (pass in a name when the directory name is not valid)"
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::init::{Template, init};
+    use clap::ValueEnum;
+    use std::env::temp_dir;
+    use std::fs::{OpenOptions, create_dir_all, remove_dir_all, write};
+    use std::io::Write;
+    use std::path::Path;
+    use std::process::Command;
+
+    // an empty zip archive, standing in for the Lambda zips that `cargo lambda build` creates
+    const EMPTY_ZIP: [u8; 22] = [0x50, 0x4b, 0x05, 0x06, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+    #[tokio::test]
+    #[ignore = "downloads and checks the dependencies of the generated workspaces, run with `cargo test -p cargo-rusty -- --ignored`"]
+    async fn should_generate_workspaces_that_compile() {
+        let repository = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+
+        for template in Template::value_variants() {
+            let dir = temp_dir().join(format!("cargo-rusty-init-{template:?}-{}", std::process::id()));
+            let _ = remove_dir_all(&dir);
+
+            init(*template, dir.to_str().unwrap(), Some("my-app".to_string()), None)
+                .await
+                .expect("init to succeed");
+
+            // build against the rusty-cdk of this repository, instead of the released version
+            let mut manifest = OpenOptions::new().append(true).open(dir.join("Cargo.toml")).unwrap();
+            writeln!(
+                manifest,
+                "\n[patch.crates-io]\nrusty-cdk = {{ path = {:?} }}",
+                repository.join("rusty-cdk").display().to_string()
+            )
+            .unwrap();
+            for lambda in template.lambdas() {
+                let lambda_dir = dir.join("target").join("lambda").join(lambda);
+                create_dir_all(&lambda_dir).unwrap();
+                write(lambda_dir.join("bootstrap.zip"), EMPTY_ZIP).unwrap();
+            }
+
+            let output = Command::new(env!("CARGO"))
+                .args(["check", "--workspace", "--all-targets", "--quiet"])
+                .current_dir(&dir)
+                .env("CARGO_TARGET_DIR", repository.join("target").join("init-check"))
+                .output()
+                .expect("cargo to run");
+
+            assert!(
+                output.status.success(),
+                "generated {template:?} workspace does not compile: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            remove_dir_all(&dir).unwrap();
+        }
+    }
+}
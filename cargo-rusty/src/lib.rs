@@ -1,86 +1,482 @@
+use crate::context::{clear_lookups, list_lookups, refresh_lookups};
+use crate::init::init;
+use crate::synth::{DEFAULT_OUTPUT_DIR, attach_synth_output, synth_to_dir, template_path};
 use clap::Parser;
 use clap::Subcommand;
+use clap::ValueEnum;
 use rusty_cdk::clean;
+use rusty_cdk::context::CONTEXT_FILE;
+use rusty_cdk::continue_update_rollback;
+use rusty_cdk::delete_rolled_back_stack;
 use rusty_cdk::deploy;
+use rusty_cdk::describe_stack;
 use rusty_cdk::destroy;
 use rusty_cdk::diff;
+use rusty_cdk::drift;
+use rusty_cdk::hotswap;
+use rusty_cdk::import_resources;
+use rusty_cdk::list_stacks;
+use rusty_cdk::security_diff;
+use rusty_cdk::shared::Region;
 use rusty_cdk::stack::Stack;
 use rusty_cdk::wrappers::StringWithOnlyAlphaNumericsAndHyphens;
-use std::fmt::Debug;
+use rusty_cdk::{Concurrency, RegionConcurrency, StackSetOptions, StackSetTargets, deploy_stack_set};
+use rusty_cdk::{DeployError, DeployOptions, DeployResult, DestroyError, get_aws_environment};
+use rusty_cdk::{JsonLinesReporter, ProgressReporter, TerminalReporter};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Display};
 use std::fs::{read_dir, read_to_string};
-use std::process::exit;
-use tokio::fs::remove_file;
+use std::io::{Write, stderr, stdin, stdout};
+use std::path::Path;
+use std::process::{Stdio, exit};
+use std::time::{Duration, SystemTime};
+use tokio::fs::{remove_dir_all, remove_file, write};
 use tokio::process::Command;
+use tokio::time::sleep;
 
-const CURRENT_DIR: &str = ".";
+mod config;
+mod context;
+mod init;
+mod synth;
+
+pub use config::{Config, Environment};
+pub use init::Template;
+pub use synth::SynthArgs;
 
 #[derive(Clone, Debug, Subcommand)]
 pub enum RustyCommand {
-    #[clap(about = "Deploy a stack")]
+    #[clap(about = "Generate a new workspace from a template, with an infra crate, Lambda crates, a snapshot test and a rusty.toml")]
+    Init {
+        /// Template to generate the workspace from
+        #[clap(short, long, value_enum)]
+        template: Template,
+        /// Directory to generate the workspace in, relative to the current directory
+        #[clap(long, default_value = ".")]
+        path: String,
+        /// Name of the project, used for the stack name and the names of resources. Defaults to the name of the directory
+        #[clap(short, long)]
+        name: Option<String>,
+        /// Existing bucket to upload the Lambda code to. Can also be changed afterwards, in the infra crate
+        #[clap(long)]
+        lambda_bucket: Option<String>,
+    },
+    #[clap(about = "Synth the stack into an output directory, with the template, an assets manifest and a tree of the resources")]
+    Synth {
+        /// Directory to write the output to, relative to the current directory
+        #[clap(short, long, default_value = DEFAULT_OUTPUT_DIR)]
+        output: String,
+    },
+    #[clap(about = "Deploy a stack (ctrl-c cancels an update that is in progress)")]
     Deploy {
         /// Name of the stack when it's deployed
+        /// Defaults to the name of the environment (`--env`)
         #[clap(short, long)]
-        name: String,
-        /// Path of synthesized stack relative to the current directory
-        /// If no path is passed in, the command will generate a synthesized stack using `cargo run`
+        name: Option<String>,
+        /// Path of a synth output directory (see `cargo rusty synth`) or a synthesized stack file, relative to the current directory
+        /// If no path is passed in, the command will synth into `rusty.out` using `cargo run`
         #[clap(short, long)]
         synth_path: Option<String>,
-        /// Cleans up the generated or passed-in synth file
+        /// Cleans up the generated or passed-in synth output
         #[clap(short, long)]
         cleanup: bool,
+        /// Writes the outputs of the deployed stack to this file, as a JSON object of output names and values
+        #[clap(short, long)]
+        outputs_file: Option<String>,
+        /// When only the code of Lambda functions changed, update that code directly instead of doing a CloudFormation deployment.
+        /// Falls back to a normal deployment for other changes. Meant for development, as the deployed stack will have drifted
+        #[clap(long, default_value_t = false)]
+        hotswap: std::primitive::bool,
+        /// Whether to ask for approval when the deployment contains security-sensitive changes,
+        /// like new IAM statements, new principals in bucket/queue/topic policies or new Lambda invoke permissions
+        /// Defaults to the setting of the environment (`--env`), or `broadening`
+        #[clap(long, value_enum)]
+        require_approval: Option<RequireApproval>,
+        /// Stop waiting for the deployment after this many minutes. The stack operation itself continues
+        #[clap(long)]
+        timeout_minutes: Option<u64>,
+    },
+    #[clap(about = "Deploy a stack as a StackSet, to multiple accounts and/or regions")]
+    DeployStackSet {
+        /// Name of the stack set
+        /// Defaults to the name of the environment (`--env`)
+        #[clap(short, long)]
+        name: Option<String>,
+        /// Path of a synth output directory (see `cargo rusty synth`) or a synthesized stack file, relative to the current directory
+        /// If no path is passed in, the command will synth into `rusty.out` using `cargo run`
+        #[clap(short, long)]
+        synth_path: Option<String>,
+        /// Cleans up the generated or passed-in synth output
+        #[clap(short, long)]
+        cleanup: bool,
+        /// Account to deploy to, using self-managed permissions. Can be repeated
+        #[clap(long, required_unless_present = "organizational_unit", conflicts_with = "organizational_unit")]
+        account: Vec<String>,
+        /// Organizational unit to deploy to, using service-managed permissions. New accounts in the unit receive the stack automatically. Can be repeated
+        #[clap(long)]
+        organizational_unit: Vec<String>,
+        /// Region to deploy to, e.g. `eu-west-1`. Can be repeated
+        #[clap(long, required = true, value_parser = parse_region)]
+        target_region: Vec<Region>,
+        /// Maximum number of accounts to deploy to at the same time, as a number (`3`) or a percentage (`25%`)
+        #[clap(long, value_parser = parse_concurrency)]
+        max_concurrent: Option<Concurrency>,
+        /// Number of accounts that can fail before the operation stops, as a number (`1`) or a percentage (`10%`)
+        #[clap(long, value_parser = parse_concurrency)]
+        failure_tolerance: Option<Concurrency>,
+        /// Deploy to all regions at the same time, instead of one region after the other
+        #[clap(long, default_value_t = false)]
+        parallel_regions: std::primitive::bool,
     },
     #[clap(about = "Generate diff with a deployed template with the given name")]
     Diff {
         /// Name of the (deployed) stack that you want to compare with
+        /// Defaults to the name of the environment (`--env`)
         #[clap(short, long)]
-        name: String,
-        /// Path of synthesized stack relative to the current directory
-        /// If no path is passed in, the command will generate a synthesized stack using `cargo run`
+        name: Option<String>,
+        /// Path of a synth output directory (see `cargo rusty synth`) or a synthesized stack file, relative to the current directory
+        /// If no path is passed in, the command will synth into `rusty.out` using `cargo run`
         #[clap(short, long)]
         synth_path: Option<String>,
-        /// Cleans up the generated or passed-in synth file
+        /// Cleans up the generated or passed-in synth output
         #[clap(short, long, default_missing_value = "false")]
         cleanup: bool,
     },
     #[clap(about = "Destroy a stack with the give name")]
     Destroy {
         /// Name of the (deployed) stack that you want to delete
+        /// Defaults to the name of the environment (`--env`)
         #[clap(short, long)]
-        name: String,
+        name: Option<String>,
         /// Force tries to make sure your stack deletes, avoiding common things that can throw a `DeleteFailed` error.
-        /// To do this, `force` will: empty S3 buckets that do not have a 'retain' and remove archival policies from SNS topics
-        /// Resources with a 'retain' policy are ignored.
+        /// To do this, `force` will: empty S3 buckets (including all object versions) that do not have a 'retain',
+        /// remove images from ECR repositories and remove archival policies from SNS topics
+        /// Resources with a 'retain' policy are ignored, and listed at the end because they will remain after the delete.
         /// *Use with caution*, and only if you don't need to retain anything from your stack that is not set to 'retain'
         #[clap(short, long, default_value_t = false)]
         force: std::primitive::bool,
+        /// Also disable deletion protection of DynamoDB tables and DocumentDB (global) clusters (that do not have a 'retain'), so they can be deleted
+        #[clap(long, default_value_t = false, requires = "force")]
+        disable_deletion_protection: std::primitive::bool,
+    },
+    #[clap(about = "Rebuild and hotswap your Lambda code whenever it changes")]
+    Watch {
+        /// Name of the stack to deploy to
+        /// Defaults to the name of the environment (`--env`)
+        #[clap(short, long)]
+        name: Option<String>,
+        /// Path of the Lambda crate to watch, relative to the current directory. Its `target` directory is ignored
+        #[clap(short, long)]
+        lambda_path: String,
+        /// Command that builds the Lambda zip file(s), run in the Lambda crate, e.g. `cargo lambda build --release --output-format zip`
+        #[clap(short, long)]
+        build_command: String,
+    },
+    #[clap(about = "Import existing resources (tables, buckets, ...) into a new or existing stack")]
+    ImportResources {
+        /// Name of the stack to import the resources into
+        /// Defaults to the name of the environment (`--env`)
+        #[clap(short, long)]
+        name: Option<String>,
+        /// Path of a synth output directory (see `cargo rusty synth`) or a synthesized stack file, relative to the current directory
+        /// If no path is passed in, the command will synth into `rusty.out` using `cargo run`
+        #[clap(short, long)]
+        synth_path: Option<String>,
+        /// Cleans up the generated or passed-in synth output
+        #[clap(short, long)]
+        cleanup: bool,
+        /// Resource to import, as the id passed to the builder and the physical identifier of the existing resource,
+        /// e.g. `--resource my-table=existing-table-name`. Can be repeated
+        #[clap(short, long, required = true, value_parser = parse_resource_to_import)]
+        resource: Vec<(String, String)>,
+    },
+    #[clap(about = "List the stacks created with rusty-cdk, with their status, last update and drift status")]
+    List,
+    #[clap(about = "Describe a deployed stack: its resources, with their ids and physical ids, and its outputs")]
+    Describe {
+        /// Name of the (deployed) stack to describe
+        /// Defaults to the name of the environment (`--env`)
+        #[clap(short, long)]
+        name: Option<String>,
+    },
+    #[clap(about = "Detect changes made to a deployed stack outside of CloudFormation (exits with code 2 when drift is found)")]
+    Drift {
+        /// Name of the (deployed) stack that you want to check for drift
+        /// Defaults to the name of the environment (`--env`)
+        #[clap(short, long)]
+        name: Option<String>,
+    },
+    #[clap(about = "Show, refresh or clear the results of the lookup macros, cached in rusty.context.json")]
+    Context {
+        /// Do the lookups of the stack again, by synthing it with `RUSTY_CDK_RECHECK=true`. Requires valid credentials
+        #[clap(long)]
+        refresh: bool,
+        /// Remove all lookups from the file, so they are done again during the next build
+        #[clap(long, conflicts_with = "refresh")]
+        clear: bool,
     },
 }
 
+#[derive(Clone, Debug, clap::Args)]
+pub struct AwsArgs {
+    /// Named AWS profile to use, instead of the default credentials
+    #[clap(long, global = true)]
+    profile: Option<String>,
+    /// AWS region to use, instead of the region of the profile or environment
+    #[clap(long, global = true)]
+    region: Option<String>,
+    /// ARN of a role to assume before calling AWS, for example to deploy to another account
+    #[clap(long, global = true)]
+    assume_role_arn: Option<String>,
+    /// External id to pass when assuming the role
+    #[clap(long, global = true, requires = "assume_role_arn")]
+    external_id: Option<String>,
+    /// ARN of the service role that CloudFormation should use for the stack operations
+    #[clap(long, global = true)]
+    cloudformation_role_arn: Option<String>,
+    /// Custom endpoint for all AWS calls, for example `http://localhost:4566` for LocalStack
+    #[clap(long, global = true)]
+    endpoint_url: Option<String>,
+}
+
+impl From<AwsArgs> for DeployOptions {
+    fn from(value: AwsArgs) -> Self {
+        DeployOptions {
+            profile: value.profile,
+            region: value.region,
+            assume_role_arn: value.assume_role_arn,
+            external_id: value.external_id,
+            cloudformation_role_arn: value.cloudformation_role_arn,
+            endpoint_url: value.endpoint_url,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RequireApproval {
+    /// Never ask for approval, for example in CI
+    Never,
+    /// Ask for approval when security-sensitive changes are found
+    Broadening,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ProgressOutput {
+    /// Coloured statuses and a spinner
+    Terminal,
+    /// One JSON object per line on stdout, for CI logs (other output goes to stderr)
+    Json,
+}
+
+impl ProgressOutput {
+    pub fn reporter(&self) -> Box<dyn ProgressReporter> {
+        match self {
+            ProgressOutput::Terminal => Box::new(TerminalReporter::new()),
+            ProgressOutput::Json => Box::new(JsonLinesReporter),
+        }
+    }
+
+    pub fn console(&self) -> Console {
+        Console {
+            to_stderr: matches!(self, ProgressOutput::Json),
+        }
+    }
+}
+
+/// Prints the human-readable output of the commands (results, questions, summaries).
+/// With JSON progress, stdout is reserved for the JSON lines, so this output goes to stderr instead
+#[derive(Clone, Copy, Debug)]
+pub struct Console {
+    to_stderr: bool,
+}
+
+impl Console {
+    pub fn print(&self, message: impl Display) {
+        if self.to_stderr {
+            eprintln!("{message}");
+        } else {
+            println!("{message}");
+        }
+    }
+
+    fn confirm(&self, question: &str) -> bool {
+        if self.to_stderr {
+            eprint!("{question} [y/N] ");
+            let _ = stderr().flush();
+        } else {
+            print!("{question} [y/N] ");
+            let _ = stdout().flush();
+        }
+
+        let mut answer = String::new();
+        match stdin().read_line(&mut answer) {
+            Ok(_) => matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"),
+            Err(_) => false,
+        }
+    }
+
+    /// The stdout of child processes, like the build command of `watch`
+    fn child_stdout(&self) -> Stdio {
+        if self.to_stderr { Stdio::from(stderr()) } else { Stdio::inherit() }
+    }
+}
+
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Args {
     #[command(subcommand)]
     pub command: RustyCommand,
+    #[command(flatten)]
+    pub aws: AwsArgs,
+    #[command(flatten)]
+    pub synth: SynthArgs,
+    /// Environment from the config (`rusty.toml`), with the stack name, account, region, profile, tags, etc. to use
+    #[clap(short, long, global = true)]
+    pub env: Option<String>,
+    /// How to show the progress of stack operations
+    #[clap(long, global = true, value_enum, default_value_t = ProgressOutput::Terminal)]
+    pub progress: ProgressOutput,
 }
 
-pub async fn entry_point(command: RustyCommand) {
+impl Args {
+    /// Combines the arguments with the config and the selected environment, the arguments take precedence.
+    /// Returns the command, the environment (with the combined synth arguments) and the options
+    pub fn resolve(self, mut config: Config) -> Result<(RustyCommand, Environment, DeployOptions), String> {
+        let mut environment = config.take_environment(self.env.as_deref())?;
+
+        match (&self.aws.region, &environment.region) {
+            (Some(region), Some(env_region)) if region != env_region => {
+                return Err(format!(
+                    "region {region} does not match region {env_region} of environment {}",
+                    self.env.unwrap_or_default()
+                ));
+            }
+            _ => {}
+        }
+
+        let mut options: DeployOptions = self.aws.into();
+        options.profile = options.profile.or(environment.profile.clone());
+        options.region = options.region.or(environment.region.clone());
+        environment.synth = self.synth.or(std::mem::take(&mut environment.synth).or(config.synth));
+
+        Ok((self.command, environment, options))
+    }
+}
+
+pub async fn entry_point(command: RustyCommand, environment: Environment, options: DeployOptions, progress: ProgressOutput) {
+    let reporter = progress.reporter();
+    let reporter = reporter.as_ref();
+    let console = progress.console();
+    if !matches!(
+        command,
+        RustyCommand::Init { .. } | RustyCommand::Synth { .. } | RustyCommand::Context { refresh: false, .. }
+    ) {
+        check_aws_environment_or_exit(&environment, &options).await;
+    }
+    let synth_args = &environment.synth;
+
     match command {
-        RustyCommand::Deploy { name, synth_path, cleanup } => {
-            println!("deploying stack with name {name}");
+        RustyCommand::Init {
+            template,
+            path,
+            name,
+            lambda_bucket,
+        } => match init(template, &path, name, lambda_bucket).await {
+            Ok(files) => {
+                files.iter().for_each(|f| console.print(format_args!("created {f}")));
+                console.print("see the generated README.md for the next steps");
+            }
+            Err(e) => print_err_and_exit(e),
+        },
+        RustyCommand::Synth { output } => match synth_to_dir(&output, synth_args).await {
+            Ok(dir) => console.print(format_args!("synthesized stack into {dir}")),
+            Err(e) => print_err_and_exit(e),
+        },
+        RustyCommand::Deploy {
+            name,
+            synth_path,
+            cleanup,
+            outputs_file,
+            hotswap,
+            require_approval,
+            timeout_minutes,
+        } => {
+            let name = stack_name_or_exit(name, &environment);
+            console.print(format_args!("deploying stack with name {name}"));
 
-            let path = if let Some(path) = synth_path {
-                path
+            let options = DeployOptions {
+                timeout: timeout_minutes.map(|m| Duration::from_secs(m * 60)),
+                cancel_on_ctrl_c: true,
+                ..options
+            };
+
+            let path = synth_path_or_synth(synth_path, synth_args).await;
+            if let RequireApproval::Broadening = require_approval
+                .or(environment.require_approval)
+                .unwrap_or(RequireApproval::Broadening)
+            {
+                approve_security_changes_or_exit(&name, &path, &options, console).await;
+            }
+            let result = if hotswap {
+                hotswap_or_exit(name, &path, &environment.tags, &options, reporter).await
             } else {
-                match run_synth_in_current_path().await {
-                    Ok(path) => path,
-                    Err(e) => print_err_and_exit(e),
-                }
+                deploy_with_recovery(name, &path, &environment.tags, &options, reporter, console).await
             };
-            match get_path_as_stack(&path) {
-                Ok(stack) => match deploy(StringWithOnlyAlphaNumericsAndHyphens(name), stack, true).await {
-                    Ok(_) => {}
-                    Err(e) => print_err_and_exit(e),
+            console.print(&result);
+
+            if let Some(outputs_file) = outputs_file {
+                write_outputs_or_exit(&outputs_file, &result.outputs).await;
+            }
+
+            if cleanup {
+                remove_fill_or_exit(&path).await;
+            }
+        }
+        RustyCommand::DeployStackSet {
+            name,
+            synth_path,
+            cleanup,
+            account,
+            organizational_unit,
+            target_region,
+            max_concurrent,
+            failure_tolerance,
+            parallel_regions,
+        } => {
+            let name = stack_name_or_exit(name, &environment);
+            console.print(format_args!("deploying stack set with name {name}"));
+
+            let targets = if account.is_empty() {
+                StackSetTargets::OrganizationalUnits(organizational_unit)
+            } else {
+                StackSetTargets::Accounts(account)
+            };
+            let stack_set_options = StackSetOptions {
+                max_concurrent,
+                failure_tolerance,
+                region_concurrency: if parallel_regions {
+                    RegionConcurrency::Parallel
+                } else {
+                    RegionConcurrency::Sequential
                 },
+                ..StackSetOptions::new(targets, target_region)
+            };
+
+            let path = synth_path_or_synth(synth_path, synth_args).await;
+            let stack = get_tagged_stack_or_exit(&path, &environment.tags);
+
+            match deploy_stack_set(
+                StringWithOnlyAlphaNumericsAndHyphens(name),
+                stack,
+                &stack_set_options,
+                reporter,
+                &options,
+            )
+            .await
+            {
+                Ok(result) => console.print(result),
                 Err(e) => print_err_and_exit(e),
             }
 
@@ -89,19 +485,13 @@ pub async fn entry_point(command: RustyCommand) {
             }
         }
         RustyCommand::Diff { name, synth_path, cleanup } => {
-            println!("creating a diff with an existing stack (name {name})");
+            let name = stack_name_or_exit(name, &environment);
+            console.print(format_args!("creating a diff with an existing stack (name {name})"));
 
-            let path = if let Some(path) = synth_path {
-                path
-            } else {
-                match run_synth_in_current_path().await {
-                    Ok(path) => path,
-                    Err(e) => print_err_and_exit(e),
-                }
-            };
+            let path = synth_path_or_synth(synth_path, synth_args).await;
             match get_path_as_stack(&path) {
-                Ok(stack) => match diff(StringWithOnlyAlphaNumericsAndHyphens(name), stack).await {
-                    Ok(_) => {}
+                Ok(stack) => match diff(StringWithOnlyAlphaNumericsAndHyphens(name), stack, &options).await {
+                    Ok(diff) => console.print(diff),
                     Err(e) => print_err_and_exit(e),
                 },
                 Err(e) => print_err_and_exit(e),
@@ -111,67 +501,383 @@ pub async fn entry_point(command: RustyCommand) {
                 remove_fill_or_exit(&path).await;
             }
         }
-        RustyCommand::Destroy { name, force } => {
-            println!("destroying stack with name {name}");
+        RustyCommand::Destroy {
+            name,
+            force,
+            disable_deletion_protection,
+        } => {
+            let name = stack_name_or_exit(name, &environment);
+            console.print(format_args!("destroying stack with name {name}"));
 
             if force {
-                match clean(StringWithOnlyAlphaNumericsAndHyphens(name.to_string()), true).await {
+                match clean(
+                    StringWithOnlyAlphaNumericsAndHyphens(name.to_string()),
+                    reporter,
+                    disable_deletion_protection,
+                    &options,
+                )
+                .await
+                {
                     Ok(_) => {}
+                    Err(DestroyError::DeletionProtected(resources)) => print_err_and_exit(format!(
+                        "resources {} have deletion protection, pass `--disable-deletion-protection` to disable it before destroying",
+                        resources.join(", ")
+                    )),
                     Err(e) => print_err_and_exit(e),
                 }
             }
-            println!("destroy");
-            match destroy(StringWithOnlyAlphaNumericsAndHyphens(name), true).await {
+            console.print("destroy");
+            match destroy(StringWithOnlyAlphaNumericsAndHyphens(name), reporter, &options).await {
                 Ok(_) => {}
                 Err(e) => print_err_and_exit(e),
             }
         }
-    }
-}
+        RustyCommand::Watch {
+            name,
+            lambda_path,
+            build_command,
+        } => {
+            let name = stack_name_or_exit(name, &environment);
+            console.print(format_args!("watching {lambda_path} for changes, stop with ctrl-c"));
+
+            let mut last_modified = None;
 
-// alternative to all these matches, an error that implements some Froms
-async fn run_synth_in_current_path() -> Result<String, String> {
-    let dir_content = read_dir(CURRENT_DIR);
+            loop {
+                let modified = latest_modification(Path::new(&lambda_path));
 
-    match dir_content {
-        Ok(content) => {
-            let is_rust_project = content
-                .flat_map(|entry| entry.ok())
-                .any(|entry| entry.file_name() == "Cargo.toml" && entry.file_type().map(|f| f.is_file()).unwrap_or(false));
+                if modified > last_modified {
+                    last_modified = modified;
 
-            if is_rust_project {
-                match Command::new("sh")
-                    .args(["-c", "cargo run > cargo-rusty-temporary-synth.json"])
-                    .output()
+                    console.print("building lambda code...");
+                    match build_and_hotswap(
+                        &name,
+                        &lambda_path,
+                        &build_command,
+                        synth_args,
+                        &environment.tags,
+                        &options,
+                        reporter,
+                        console,
+                    )
                     .await
-                {
-                    Ok(_) => Ok("./cargo-rusty-temporary-synth.json".to_string()),
-                    Err(e) => Err(format!(
-                        "Could not run `cargo run` (required to synth when no synth_path is passed in): {e}"
-                    )),
+                    {
+                        Ok(result) => console.print(result),
+                        // keep watching, the next change might fix the problem
+                        Err(e) => eprintln!("{e}"),
+                    }
+                    console.print(format_args!("watching {lambda_path} for changes, stop with ctrl-c"));
                 }
+
+                sleep(Duration::from_secs(1)).await;
+            }
+        }
+        RustyCommand::ImportResources {
+            name,
+            synth_path,
+            cleanup,
+            resource,
+        } => {
+            let name = stack_name_or_exit(name, &environment);
+            console.print(format_args!("importing resources into stack with name {name}"));
+
+            let path = synth_path_or_synth(synth_path, synth_args).await;
+            let stack = get_tagged_stack_or_exit(&path, &environment.tags);
+
+            match import_resources(
+                StringWithOnlyAlphaNumericsAndHyphens(name),
+                stack,
+                resource.into_iter().collect(),
+                reporter,
+                &options,
+            )
+            .await
+            {
+                Ok(result) => console.print(result),
+                Err(e) => print_err_and_exit(e),
+            }
+
+            if cleanup {
+                remove_fill_or_exit(&path).await;
+            }
+        }
+        RustyCommand::List => match list_stacks(&options).await {
+            Ok(stacks) if stacks.is_empty() => console.print("no stacks created with rusty-cdk found"),
+            Ok(stacks) => stacks.iter().for_each(|s| console.print(s)),
+            Err(e) => print_err_and_exit(e),
+        },
+        RustyCommand::Describe { name } => {
+            let name = stack_name_or_exit(name, &environment);
+
+            match describe_stack(StringWithOnlyAlphaNumericsAndHyphens(name), &options).await {
+                Ok(description) => console.print(description),
+                Err(e) => print_err_and_exit(e),
+            }
+        }
+        RustyCommand::Drift { name } => {
+            let name = stack_name_or_exit(name, &environment);
+            console.print(format_args!("detecting drift for stack with name {name}"));
+
+            match drift(StringWithOnlyAlphaNumericsAndHyphens(name), &options).await {
+                Ok(drifted) if drifted.is_empty() => console.print("no drift detected"),
+                Ok(drifted) => {
+                    drifted.iter().for_each(|d| console.print(d));
+                    exit(2);
+                }
+                Err(e) => print_err_and_exit(e),
+            }
+        }
+        RustyCommand::Context { refresh: true, .. } => match refresh_lookups(synth_args).await {
+            Ok((count, changed)) => console.print(format_args!(
+                "refreshed {count} lookups in {CONTEXT_FILE}, {changed} of them changed"
+            )),
+            Err(e) => print_err_and_exit(e),
+        },
+        RustyCommand::Context { clear: true, .. } => match clear_lookups(Path::new(CONTEXT_FILE)) {
+            Ok(count) => console.print(format_args!("cleared {count} lookups from {CONTEXT_FILE}")),
+            Err(e) => print_err_and_exit(e),
+        },
+        RustyCommand::Context { .. } => match list_lookups(Path::new(CONTEXT_FILE)) {
+            Ok(lookups) if lookups.is_empty() => console.print(format_args!("no lookups in {CONTEXT_FILE}")),
+            Ok(lookups) => lookups.iter().for_each(|l| console.print(l)),
+            Err(e) => print_err_and_exit(e),
+        },
+    }
+}
+
+async fn deploy_with_recovery(
+    name: String,
+    path: &str,
+    tags: &BTreeMap<String, String>,
+    options: &DeployOptions,
+    reporter: &dyn ProgressReporter,
+    console: Console,
+) -> DeployResult {
+    let stack = get_tagged_stack_or_exit(path, tags);
+
+    match deploy(StringWithOnlyAlphaNumericsAndHyphens(name.clone()), stack, reporter, options).await {
+        Ok(result) => return result,
+        Err(DeployError::RollbackComplete(_)) => {
+            if !console.confirm(&format!(
+                "stack {name} failed to create earlier and is empty (ROLLBACK_COMPLETE). Delete and recreate it?"
+            )) {
+                exit(1);
+            }
+            if let Err(e) = delete_rolled_back_stack(StringWithOnlyAlphaNumericsAndHyphens(name.clone()), reporter, options).await {
+                print_err_and_exit(e);
+            }
+        }
+        Err(DeployError::UpdateRollbackFailed(resources)) => {
+            let question = if resources.is_empty() {
+                format!("stack {name} failed to roll back an earlier update (UPDATE_ROLLBACK_FAILED). Continue the rollback?")
             } else {
-                Err("current dir does not seem to be a cargo project, could not find a Cargo.toml (required to synth when no synth_path is passed in)".to_string())
+                format!(
+                    "stack {name} failed to roll back an earlier update (UPDATE_ROLLBACK_FAILED) because of these resources: {}.\nContinue the rollback, skipping these resources? Make sure their actual state matches the previous template",
+                    resources.join(", ")
+                )
+            };
+            if !console.confirm(&question) {
+                exit(1);
+            }
+            if let Err(e) =
+                continue_update_rollback(StringWithOnlyAlphaNumericsAndHyphens(name.clone()), resources, reporter, options).await
+            {
+                print_err_and_exit(e);
             }
         }
-        Err(e) => Err(format!("could not read dir: {e}")),
+        Err(e) => print_err_and_exit(e),
+    }
+
+    console.print("stack recovered, deploying again");
+    let stack = get_tagged_stack_or_exit(path, tags);
+    match deploy(StringWithOnlyAlphaNumericsAndHyphens(name), stack, reporter, options).await {
+        Ok(result) => result,
+        Err(e) => print_err_and_exit(e),
+    }
+}
+
+async fn approve_security_changes_or_exit(name: &str, path: &str, options: &DeployOptions, console: Console) {
+    let stack = get_path_as_stack(path).unwrap_or_else(|e| print_err_and_exit(e));
+    let diff = security_diff(StringWithOnlyAlphaNumericsAndHyphens(name.to_string()), stack, options)
+        .await
+        .unwrap_or_else(|e| print_err_and_exit(e));
+
+    if !diff.is_empty() {
+        console.print("this deployment contains security-sensitive changes:");
+        diff.changes.iter().for_each(|c| console.print(format_args!("- {c}")));
+
+        if !console.confirm("Do you want to deploy these changes? (pass `--require-approval never` to skip this question)") {
+            exit(1);
+        }
+    }
+}
+
+async fn hotswap_or_exit(
+    name: String,
+    path: &str,
+    tags: &BTreeMap<String, String>,
+    options: &DeployOptions,
+    reporter: &dyn ProgressReporter,
+) -> DeployResult {
+    let stack = get_tagged_stack_or_exit(path, tags);
+
+    match hotswap(StringWithOnlyAlphaNumericsAndHyphens(name), stack, reporter, options).await {
+        Ok(result) => result,
+        Err(e) => print_err_and_exit(e),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn build_and_hotswap(
+    name: &str,
+    lambda_path: &str,
+    build_command: &str,
+    synth_args: &SynthArgs,
+    tags: &BTreeMap<String, String>,
+    options: &DeployOptions,
+    reporter: &dyn ProgressReporter,
+    console: Console,
+) -> Result<DeployResult, String> {
+    let build = Command::new("sh")
+        .args(["-c", build_command])
+        .current_dir(lambda_path)
+        .stdout(console.child_stdout())
+        .status()
+        .await
+        .map_err(|e| format!("could not run build command: {e}"))?;
+    if !build.success() {
+        return Err(format!("build command failed ({build})"));
+    }
+
+    let path = synth_to_dir(DEFAULT_OUTPUT_DIR, synth_args).await?;
+    let stack = get_tagged_stack(&path, tags)?;
+
+    hotswap(StringWithOnlyAlphaNumericsAndHyphens(name.to_string()), stack, reporter, options)
+        .await
+        .map_err(|e| format!("{e}: {e:?}"))
+}
+
+/// Latest modification time of the files in the given directory (recursively), skipping `target` and hidden directories
+fn latest_modification(path: &Path) -> Option<SystemTime> {
+    read_dir(path)
+        .ok()?
+        .flat_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name != "target" && !name.starts_with('.')
+        })
+        .flat_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            if metadata.is_dir() {
+                latest_modification(&entry.path())
+            } else {
+                metadata.modified().ok()
+            }
+        })
+        .max()
+}
+
+async fn write_outputs_or_exit(path: &str, outputs: &HashMap<String, String>) {
+    let outputs = serde_json::to_string_pretty(outputs).expect("outputs to be serializable");
+
+    if let Err(e) = write(path, outputs).await {
+        print_err_and_exit(format!("could not write outputs to file at {path}: {e}"));
+    }
+}
+
+fn parse_resource_to_import(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((id, identifier)) if !id.is_empty() && !identifier.is_empty() => Ok((id.to_string(), identifier.to_string())),
+        _ => Err(format!("expected a resource in the form `id=physical-identifier`, got `{value}`")),
+    }
+}
+
+fn parse_region(value: &str) -> Result<Region, String> {
+    Region::try_from(value)
+}
+
+fn parse_concurrency(value: &str) -> Result<Concurrency, String> {
+    match value.strip_suffix('%') {
+        Some(percentage) => match percentage.parse::<u8>() {
+            Ok(percentage) if percentage <= 100 => Ok(Concurrency::Percentage(percentage)),
+            _ => Err(format!("expected a percentage between 0 and 100, got `{value}`")),
+        },
+        None => value
+            .parse::<u32>()
+            .map(Concurrency::Count)
+            .map_err(|_| format!("expected a number or a percentage (like `25%`), got `{value}`")),
     }
 }
 
+fn stack_name_or_exit(name: Option<String>, environment: &Environment) -> String {
+    name.or(environment.name.clone())
+        .unwrap_or_else(|| print_err_and_exit("pass a stack name with `--name`, or an environment with a name with `--env`"))
+}
+
+/// Refuses to continue when the account or region of the environment does not match that of the credentials
+async fn check_aws_environment_or_exit(environment: &Environment, options: &DeployOptions) {
+    if environment.account.is_none() && environment.region.is_none() {
+        return;
+    }
+
+    let actual = get_aws_environment(options).await.unwrap_or_else(|e| print_err_and_exit(e));
+    if let Some(account) = environment.account.as_ref().filter(|a| **a != actual.account) {
+        print_err_and_exit(format!(
+            "the environment is meant for account {account}, but the credentials are for {actual}"
+        ));
+    }
+    if let Some(region) = environment.region.as_ref().filter(|r| **r != actual.region) {
+        print_err_and_exit(format!(
+            "the environment is meant for region {region}, but the options are for {actual}"
+        ));
+    }
+}
+
+async fn synth_path_or_synth(synth_path: Option<String>, synth_args: &SynthArgs) -> String {
+    match synth_path {
+        Some(path) => path,
+        None => synth_to_dir(DEFAULT_OUTPUT_DIR, synth_args)
+            .await
+            .unwrap_or_else(|e| print_err_and_exit(e)),
+    }
+}
+
+fn get_tagged_stack_or_exit(path: &str, tags: &BTreeMap<String, String>) -> Stack {
+    get_tagged_stack(path, tags).unwrap_or_else(|e| print_err_and_exit(e))
+}
+
+/// Reads the stack (see `get_path_as_stack`) and adds the tags of the environment
+fn get_tagged_stack(path: &str, tags: &BTreeMap<String, String>) -> Result<Stack, String> {
+    let mut stack = get_path_as_stack(path)?;
+    tags.iter().for_each(|(key, value)| stack.add_tag(key, value));
+    Ok(stack)
+}
+
+/// Reads the stack from a synth output directory (including its assets), or from a file containing a synthesized stack
 fn get_path_as_stack(path: &str) -> Result<Stack, String> {
-    match read_to_string(path) {
+    let path = template_path(path);
+
+    let mut stack = match read_to_string(&path) {
         Ok(as_string) => match serde_json::from_str::<Stack>(&as_string) {
-            Ok(stack) => Ok(stack),
-            Err(e) => Err(format!(
-                "content of file {path} could not be read as a `Stack` (is there non-json content present?): {e}"
-            )),
+            Ok(stack) => stack,
+            Err(e) => {
+                return Err(format!(
+                    "content of file {path} could not be read as a `Stack` (is there non-json content present?): {e}"
+                ));
+            }
         },
-        Err(e) => Err(format!("could not read file with path {path}: {e}")),
-    }
+        Err(e) => return Err(format!("could not read file with path {path}: {e}")),
+    };
+    attach_synth_output(&mut stack, &path)?;
+    Ok(stack)
 }
 
 async fn remove_fill_or_exit(path: &String) {
-    let removed = remove_file(&path).await;
+    let removed = if Path::new(path).is_dir() {
+        remove_dir_all(&path).await
+    } else {
+        remove_file(&path).await
+    };
 
     if let Err(e) = removed {
         print_err_and_exit(format!("error cleaning up file at {path}: {e}"));
@@ -182,3 +888,68 @@ fn print_err_and_exit<T: Debug>(e: T) -> ! {
     eprintln!("{e:?}");
     exit(1);
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::get_tagged_stack;
+    use rusty_cdk::lambda::{Architecture, Code, FunctionBuilder, Runtime, Zip};
+    use rusty_cdk::stack::{ASSETS_FILE, Stack, StackBuilder};
+    use rusty_cdk::wrappers::{Bucket, Memory, Timeout, ZipFile};
+    use std::collections::BTreeMap;
+    use std::env::temp_dir;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+    use std::path::PathBuf;
+
+    fn stack_with_function() -> Stack {
+        let mut stack_builder = StackBuilder::new();
+        FunctionBuilder::new("fun", Architecture::ARM64, Memory(256), Timeout(30))
+            .code(Code::Zip(Zip::new(
+                Bucket("some-bucket".to_string()),
+                ZipFile("./example.zip".to_string()),
+            )))
+            .handler("bootstrap")
+            .runtime(Runtime::ProvidedAl2023)
+            .build(&mut stack_builder);
+        stack_builder.build().unwrap()
+    }
+
+    fn write_synth_output(name: &str, stack: &Stack, with_assets: bool) -> PathBuf {
+        let dir = temp_dir().join(format!("cargo-rusty-{name}-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        write(dir.join("template.json"), stack.synth().unwrap()).unwrap();
+        if with_assets {
+            write(dir.join(ASSETS_FILE), serde_json::to_string(&stack.get_asset_manifest()).unwrap()).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn should_hotswap_code_only_change_of_synth_output() {
+        let deployed = stack_with_function().synth().unwrap();
+        // every synth uploads the zip under a new key, so this is a change to the code only
+        let dir = write_synth_output("hotswap", &stack_with_function(), true);
+        let tags = BTreeMap::from([("env".to_string(), "dev".to_string())]);
+
+        let mut stack = get_tagged_stack(dir.to_str().unwrap(), &tags).unwrap();
+        remove_dir_all(&dir).unwrap();
+
+        let assets = stack
+            .get_hotswappable_assets(&deployed)
+            .unwrap()
+            .expect("code only change to be hotswappable");
+        assert_eq!(assets.len(), 1);
+        assert_eq!(assets[0].1.path, "./example.zip");
+        assert_eq!(stack.get_tags(), vec![("env".to_string(), "dev".to_string())]);
+    }
+
+    #[test]
+    fn should_not_hotswap_synth_output_without_assets() {
+        let deployed = stack_with_function().synth().unwrap();
+        let dir = write_synth_output("hotswap-without-assets", &stack_with_function(), false);
+
+        let mut stack = get_tagged_stack(dir.to_str().unwrap(), &BTreeMap::new()).unwrap();
+        remove_dir_all(&dir).unwrap();
+
+        assert!(stack.get_hotswappable_assets(&deployed).unwrap().is_none());
+    }
+}
@@ -1,8 +1,18 @@
-use cargo_rusty::{Args, entry_point};
+use cargo_rusty::{Args, Config, entry_point};
 use clap::Parser;
+use std::process::exit;
 
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
-    entry_point(args.command).await;
+    let config = Config::read().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        exit(1);
+    });
+    let progress = args.progress;
+    let (command, environment, options) = args.resolve(config).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        exit(1);
+    });
+    entry_point(command, environment, options, progress).await;
 }
@@ -0,0 +1,407 @@
+use rusty_cdk::stack::{ASSETS_FILE, AssetManifest, SETTINGS_FILE, SYNTH_OUTPUT_DIR_ENV_VAR_NAME, Stack, StackSettings};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, absolute};
+use tokio::fs::{create_dir_all, remove_file, write};
+use tokio::process::Command;
+
+pub(crate) const DEFAULT_OUTPUT_DIR: &str = "rusty.out";
+const TEMPLATE_FILE: &str = "template.json";
+const TREE_FILE: &str = "tree.json";
+const CURRENT_DIR: &str = ".";
+
+/// Determines which binary `cargo run` runs to synth the stack, and how.
+/// Can also be set in the `[synth]` table of `rusty.toml`, the command line arguments take precedence
+#[derive(Clone, Debug, Default, clap::Args, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SynthArgs {
+    /// Binary that synths the stack, when the package has more than one
+    #[clap(long, global = true, conflicts_with = "example")]
+    bin: Option<String>,
+    /// Example that synths the stack, instead of a binary
+    #[clap(long, global = true)]
+    example: Option<String>,
+    /// Package (workspace member) that contains the binary or example
+    #[clap(short, long, global = true)]
+    package: Option<String>,
+    /// Features to enable when building the binary, comma separated or repeated
+    #[clap(long, global = true, value_delimiter = ',')]
+    features: Vec<String>,
+    /// Build the binary in release mode
+    #[clap(long, global = true)]
+    release: bool,
+    /// Argument to pass to the binary. Can be repeated
+    #[clap(long = "synth-arg", global = true, allow_hyphen_values = true)]
+    args: Vec<String>,
+}
+
+impl SynthArgs {
+    /// Fills in the arguments that were not passed in with those of the config
+    pub fn or(self, config: SynthArgs) -> SynthArgs {
+        // a binary or example on the command line replaces both of them in the config
+        let (bin, example) = if self.bin.is_some() || self.example.is_some() {
+            (self.bin, self.example)
+        } else {
+            (config.bin, config.example)
+        };
+
+        SynthArgs {
+            bin,
+            example,
+            package: self.package.or(config.package),
+            features: if self.features.is_empty() { config.features } else { self.features },
+            release: self.release || config.release,
+            args: if self.args.is_empty() { config.args } else { self.args },
+        }
+    }
+
+    pub(crate) fn to_cargo_args(&self) -> Vec<String> {
+        let mut cargo_args = vec!["run".to_string(), "--quiet".to_string()];
+
+        let options = [("--bin", &self.bin), ("--example", &self.example), ("--package", &self.package)];
+        for (option, value) in options {
+            if let Some(value) = value {
+                cargo_args.extend([option.to_string(), value.clone()]);
+            }
+        }
+        if !self.features.is_empty() {
+            cargo_args.extend(["--features".to_string(), self.features.join(",")]);
+        }
+        if self.release {
+            cargo_args.push("--release".to_string());
+        }
+        if !self.args.is_empty() {
+            cargo_args.push("--".to_string());
+            cargo_args.extend(self.args.iter().cloned());
+        }
+
+        cargo_args
+    }
+}
+
+/// Runs the synth binary (`cargo run`) in the current directory and writes the result to the output directory:
+/// - `template.json` with the synthesized stack
+/// - `assets.json` with the Lambda zip files and the bucket and key they are uploaded to, written by the synth binary itself (`Stack::write_synth_output`)
+/// - `settings.json` with the settings of the stack (termination protection, stack policy...), also written by the synth binary
+/// - `tree.json` with the resources of the stack, their CloudFormation type and the id passed to their builder
+///
+/// Returns the path of the output directory.
+pub(crate) async fn synth_to_dir(output_dir: &str, synth_args: &SynthArgs) -> Result<String, String> {
+    synth_to_dir_with_env(output_dir, synth_args, &[]).await
+}
+
+/// Like `synth_to_dir`, with extra environment variables for `cargo run`
+pub(crate) async fn synth_to_dir_with_env(output_dir: &str, synth_args: &SynthArgs, envs: &[(&str, &str)]) -> Result<String, String> {
+    let is_rust_project = read_dir(CURRENT_DIR)
+        .map_err(|e| format!("could not read dir: {e}"))?
+        .flat_map(|entry| entry.ok())
+        .any(|entry| entry.file_name() == "Cargo.toml" && entry.file_type().map(|f| f.is_file()).unwrap_or(false));
+    if !is_rust_project {
+        return Err("current dir does not seem to be a cargo project, could not find a Cargo.toml (required to synth when no synth_path is passed in)".to_string());
+    }
+
+    let dir = Path::new(output_dir);
+    create_dir_all(dir)
+        .await
+        .map_err(|e| format!("could not create output directory {output_dir}: {e}"))?;
+    let absolute_dir = absolute(dir).map_err(|e| format!("could not determine the path of output directory {output_dir}: {e}"))?;
+    // the synth binary writes the assets and settings (with `Stack::write_synth_output`), so remove those of an earlier synth
+    for file in [ASSETS_FILE, SETTINGS_FILE] {
+        let path = dir.join(file);
+        if path.exists() {
+            remove_file(&path)
+                .await
+                .map_err(|e| format!("could not remove file at {}: {e}", path.display()))?;
+        }
+    }
+
+    let cargo_args = synth_args.to_cargo_args();
+    let output = Command::new("cargo")
+        .args(&cargo_args)
+        .env(SYNTH_OUTPUT_DIR_ENV_VAR_NAME, &absolute_dir)
+        .envs(envs.iter().copied())
+        .output()
+        .await
+        .map_err(|e| {
+            format!(
+                "could not run `cargo {}` (required to synth when no synth_path is passed in): {e}",
+                cargo_args.join(" ")
+            )
+        })?;
+    if !output.status.success() {
+        return Err(format!(
+            "synth with `cargo {}` failed ({}):\n{}",
+            cargo_args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let template = String::from_utf8(output.stdout).map_err(|e| format!("synth output is not valid UTF-8: {e}"))?;
+    serde_json::from_str::<Stack>(&template).map_err(|e| {
+        format!("synth output could not be read as a `Stack` (does the synth binary print anything besides the stack?): {e}")
+    })?;
+    let template: Value = serde_json::from_str(&template).expect("stack to be valid JSON");
+    check_synth_files(dir)?;
+
+    write_json(&dir.join(TEMPLATE_FILE), &template).await?;
+    write_json(&dir.join(TREE_FILE), &json!({ "resources": tree(&template) })).await?;
+
+    Ok(output_dir.to_string())
+}
+
+/// Attaches the assets and settings in the directory of the template (when it is a synth output directory) to the stack.
+///
+/// Fails when the directory is a synth output directory, but the synth binary did not write the assets or settings
+pub(crate) fn attach_synth_output(stack: &mut Stack, template_path: &str) -> Result<(), String> {
+    let Some(dir) = Path::new(template_path).parent() else {
+        return Ok(());
+    };
+    let is_synth_output = Path::new(template_path).file_name().is_some_and(|f| f == TEMPLATE_FILE) && dir.join(TREE_FILE).is_file();
+    if is_synth_output {
+        check_synth_files(dir)?;
+    }
+
+    if let Some(manifest) = read_synth_file::<AssetManifest>(dir, ASSETS_FILE)? {
+        stack.attach_assets(manifest)?;
+    }
+    // a stack with unknown settings (read from a template) writes `null`
+    if let Some(settings) = read_synth_file::<Option<StackSettings>>(dir, SETTINGS_FILE)?.flatten() {
+        stack.set_settings(settings);
+    }
+    Ok(())
+}
+
+/// The assets and settings are written by the synth binary itself, so check they are there
+fn check_synth_files(dir: &Path) -> Result<(), String> {
+    for file in [ASSETS_FILE, SETTINGS_FILE] {
+        if !dir.join(file).is_file() {
+            return Err(format!(
+                "synth output in {} has no {file}, call `Stack::write_synth_output` with the directory of `synth_output_dir` in the synth binary",
+                dir.display()
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn read_synth_file<T: DeserializeOwned>(dir: &Path, file: &str) -> Result<Option<T>, String> {
+    let path = dir.join(file);
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let content = read_to_string(&path).map_err(|e| format!("could not read file with path {}: {e}", path.display()))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("content of file {} could not be read: {e}", path.display()))
+}
+
+/// The path of the template, for a synth output directory or a path to a template file
+pub(crate) fn template_path(path: &str) -> String {
+    let path = Path::new(path);
+
+    if path.is_dir() {
+        path.join(TEMPLATE_FILE).to_string_lossy().to_string()
+    } else {
+        path.to_string_lossy().to_string()
+    }
+}
+
+fn tree(template: &Value) -> Vec<Value> {
+    let ids_by_resource_id: HashMap<&str, &str> = template["Metadata"]
+        .as_object()
+        .map(|metadata| {
+            metadata
+                .iter()
+                .flat_map(|(id, resource_id)| resource_id.as_str().map(|r| (r, id.as_str())))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut resources: Vec<_> = template["Resources"]
+        .as_object()
+        .map(|resources| {
+            resources
+                .iter()
+                .map(|(resource_id, resource)| {
+                    json!({
+                        "id": ids_by_resource_id.get(resource_id.as_str()),
+                        "resource_id": resource_id,
+                        "type": resource["Type"],
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    resources.sort_by_key(|r| r["resource_id"].as_str().unwrap_or_default().to_string());
+    resources
+}
+
+async fn write_json(path: &Path, value: &Value) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(value).expect("value to be serializable");
+    write(path, content)
+        .await
+        .map_err(|e| format!("could not write file at {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::synth::{SynthArgs, TEMPLATE_FILE, TREE_FILE, attach_synth_output, template_path};
+    use rusty_cdk::lambda::{Architecture, Code, FunctionBuilder, Runtime, Zip};
+    use rusty_cdk::sqs::QueueBuilder;
+    use rusty_cdk::stack::{ASSETS_FILE, SETTINGS_FILE, Stack, StackBuilder, StackPolicyAction, StackPolicyBuilder};
+    use rusty_cdk::wrappers::{Bucket, Memory, Timeout, ZipFile};
+    use std::env::temp_dir;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    fn stack_with_function() -> Stack {
+        let mut stack_builder = StackBuilder::new();
+        FunctionBuilder::new("fun", Architecture::ARM64, Memory(256), Timeout(30))
+            .code(Code::Zip(Zip::new(
+                Bucket("some-bucket".to_string()),
+                ZipFile("./example.zip".to_string()),
+            )))
+            .handler("bootstrap")
+            .runtime(Runtime::ProvidedAl2023)
+            .build(&mut stack_builder);
+        stack_builder.build().unwrap()
+    }
+
+    #[test]
+    fn should_attach_assets_of_synth_output_directory() {
+        let dir = temp_dir().join(format!("cargo-rusty-synth-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let stack = stack_with_function();
+        write(dir.join(ASSETS_FILE), serde_json::to_string(&stack.get_asset_manifest()).unwrap()).unwrap();
+        let path = template_path(dir.to_str().unwrap());
+        let mut read: Stack = serde_json::from_str(&stack.synth().unwrap()).unwrap();
+
+        attach_synth_output(&mut read, &path).unwrap();
+        remove_dir_all(&dir).unwrap();
+
+        let assets = read.get_assets();
+        assert_eq!(assets.len(), 1);
+        assert_eq!(assets[0].path, "./example.zip");
+    }
+
+    #[test]
+    fn should_attach_settings_of_synth_output_directory() {
+        let dir = temp_dir().join(format!("cargo-rusty-synth-settings-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let mut stack_builder = StackBuilder::new();
+        let queue = QueueBuilder::new("queue").standard_queue().build(&mut stack_builder);
+        let stack = stack_builder
+            .termination_protection(true)
+            .stack_policy(StackPolicyBuilder::new().deny(StackPolicyAction::Replace, &queue).build())
+            .build()
+            .unwrap();
+        write(dir.join(SETTINGS_FILE), serde_json::to_string(&stack.get_settings()).unwrap()).unwrap();
+        let path = template_path(dir.to_str().unwrap());
+        let mut read: Stack = serde_json::from_str(&stack.synth().unwrap()).unwrap();
+
+        attach_synth_output(&mut read, &path).unwrap();
+        remove_dir_all(&dir).unwrap();
+
+        let settings = read.get_settings().unwrap();
+        assert_eq!(settings.termination_protection, Some(true));
+        assert_eq!(
+            settings.stack_policy.as_ref().map(|p| p.synth()),
+            stack.get_settings().unwrap().stack_policy.as_ref().map(|p| p.synth())
+        );
+    }
+
+    #[test]
+    fn should_fail_when_synth_output_directory_has_no_settings() {
+        let dir = temp_dir().join(format!("cargo-rusty-synth-no-settings-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let stack = stack_with_function();
+        write(dir.join(TEMPLATE_FILE), stack.synth().unwrap()).unwrap();
+        write(dir.join(TREE_FILE), "{}").unwrap();
+        write(dir.join(ASSETS_FILE), serde_json::to_string(&stack.get_asset_manifest()).unwrap()).unwrap();
+        let path = template_path(dir.to_str().unwrap());
+        let mut read: Stack = serde_json::from_str(&stack.synth().unwrap()).unwrap();
+
+        let result = attach_synth_output(&mut read, &path);
+        remove_dir_all(&dir).unwrap();
+
+        let err = result.unwrap_err();
+        assert!(err.contains(SETTINGS_FILE));
+        assert!(err.contains("Stack::write_synth_output"));
+    }
+
+    #[test]
+    fn should_prefer_command_line_arguments_over_config() {
+        let command_line = SynthArgs {
+            features: vec!["dev".to_string()],
+            ..Default::default()
+        };
+        let config = SynthArgs {
+            bin: Some("infra".to_string()),
+            package: Some("stacks".to_string()),
+            features: vec!["prod".to_string()],
+            release: true,
+            args: vec!["--stage".to_string(), "prod".to_string()],
+            ..Default::default()
+        };
+
+        let args = command_line.or(config);
+
+        assert_eq!(args.bin.as_deref(), Some("infra"));
+        assert_eq!(args.package.as_deref(), Some("stacks"));
+        assert_eq!(args.features, vec!["dev"]);
+        assert!(args.release);
+        assert_eq!(args.args, vec!["--stage", "prod"]);
+    }
+
+    #[test]
+    fn should_replace_binary_and_example_of_config_together() {
+        let command_line = SynthArgs {
+            example: Some("synth".to_string()),
+            ..Default::default()
+        };
+        let config = SynthArgs {
+            bin: Some("infra".to_string()),
+            ..Default::default()
+        };
+
+        let args = command_line.or(config);
+
+        assert_eq!(args.bin, None);
+        assert_eq!(args.example.as_deref(), Some("synth"));
+    }
+
+    #[test]
+    fn should_convert_to_cargo_run_arguments() {
+        let args = SynthArgs {
+            bin: Some("infra".to_string()),
+            package: Some("stacks".to_string()),
+            features: vec!["prod".to_string(), "metrics".to_string()],
+            release: true,
+            args: vec!["--stage".to_string(), "prod".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            args.to_cargo_args(),
+            vec![
+                "run",
+                "--quiet",
+                "--bin",
+                "infra",
+                "--package",
+                "stacks",
+                "--features",
+                "prod,metrics",
+                "--release",
+                "--",
+                "--stage",
+                "prod"
+            ]
+        );
+        assert_eq!(SynthArgs::default().to_cargo_args(), vec!["run", "--quiet"]);
+    }
+}
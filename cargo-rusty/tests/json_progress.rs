@@ -0,0 +1,101 @@
+use rusty_cdk::lambda::{Architecture, Code, FunctionBuilder, Runtime, Zip};
+use rusty_cdk::stack::StackBuilder;
+use rusty_cdk::wrappers::{Bucket, Memory, Timeout, ZipFile};
+use std::env::temp_dir;
+use std::fs::{create_dir_all, remove_dir_all, write};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+const AWS_ERROR: &str = "<ErrorResponse><Error><Type>Sender</Type><Code>ValidationError</Code><Message>not available in this test</Message></Error><RequestId>1</RequestId></ErrorResponse>";
+
+/// Answers every AWS call with a (non-retryable) error, so a deploy gets as far as uploading its assets
+fn start_failing_aws_endpoint() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            respond_with_error(stream);
+        }
+    });
+
+    format!("http://{address}")
+}
+
+fn respond_with_error(mut stream: TcpStream) {
+    stream.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+
+    // read the complete request (until the client has nothing left to send), so closing the connection does not reset it
+    let mut buffer = [0; 8192];
+    loop {
+        match stream.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => break,
+            Err(_) => return,
+        }
+    }
+
+    let response = format!(
+        "HTTP/1.1 400 Bad Request\r\nContent-Type: text/xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{AWS_ERROR}",
+        AWS_ERROR.len()
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+fn write_synth_output(dir: &Path) {
+    let mut stack_builder = StackBuilder::new();
+    FunctionBuilder::new("fun", Architecture::ARM64, Memory(256), Timeout(30))
+        .code(Code::Zip(Zip::new(
+            Bucket("some-bucket".to_string()),
+            ZipFile("./example.zip".to_string()),
+        )))
+        .handler("bootstrap")
+        .runtime(Runtime::ProvidedAl2023)
+        .build(&mut stack_builder);
+    let mut stack = stack_builder.build().unwrap();
+
+    let synth_dir = dir.join("cdk.out");
+    create_dir_all(&synth_dir).unwrap();
+    stack.write_synth_output(&synth_dir).unwrap();
+    write(synth_dir.join("template.json"), stack.synth().unwrap()).unwrap();
+    write(dir.join("example.zip"), "not really a zip").unwrap();
+}
+
+#[test]
+fn should_only_write_json_lines_to_stdout_with_json_progress() {
+    let dir = temp_dir().join(format!("cargo-rusty-json-progress-{}", std::process::id()));
+    create_dir_all(&dir).unwrap();
+    write_synth_output(&dir);
+    let endpoint = start_failing_aws_endpoint();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-rusty"))
+        .args(["deploy", "--name", "some-stack", "--synth-path", "cdk.out"])
+        .args(["--require-approval", "never", "--progress", "json"])
+        .args(["--endpoint-url", &endpoint, "--region", "eu-west-1"])
+        .current_dir(&dir)
+        .env("AWS_ACCESS_KEY_ID", "test")
+        .env("AWS_SECRET_ACCESS_KEY", "test")
+        .env("AWS_CONFIG_FILE", dir.join("no-config"))
+        .env("AWS_SHARED_CREDENTIALS_FILE", dir.join("no-credentials"))
+        .env("AWS_EC2_METADATA_DISABLED", "true")
+        .output()
+        .unwrap();
+    remove_dir_all(&dir).unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!output.status.success(), "deploy against failing endpoint should fail");
+    assert!(stderr.contains("deploying stack with name some-stack"), "stderr was: {stderr}");
+    assert!(!stdout.is_empty(), "expected progress events on stdout, stderr was: {stderr}");
+    for line in stdout.lines() {
+        assert!(
+            serde_json::from_str::<serde_json::Value>(line).is_ok(),
+            "stdout line is not JSON: {line}"
+        );
+    }
+}
@@ -3,7 +3,7 @@ use rusty_cdk::dynamodb::{AttributeType, Key, TableBuilder};
 use rusty_cdk::iam::Permission;
 use rusty_cdk::lambda::{Architecture, Code, FunctionBuilder, Runtime, Zip};
 use rusty_cdk::shared::HttpMethod;
-use rusty_cdk::stack::StackBuilder;
+use rusty_cdk::stack::{StackBuilder, synth_output_dir};
 use rusty_cdk::wrappers::*;
 use rusty_cdk::{env_var_key, memory, non_zero_number, string_with_only_alphanumerics_and_underscores, timeout, toml_file, zip_file};
 use std::process::exit;
@@ -53,7 +53,10 @@ async fn main() {
         eprintln!("Error: {s}");
         exit(1);
     } else {
-        let stack = stack.unwrap();
+        let mut stack = stack.unwrap();
+        if let Some(dir) = synth_output_dir() {
+            stack.write_synth_output(dir).unwrap();
+        }
         let synthesized = stack.synth().unwrap();
         println!("{}", synthesized);
         // let deploy_result = rusty_cdk::deploy_with_result(string_with_only_alphanumerics_and_hyphens!("ExampleWithGatewayLambdaAndDynamoDB"), stack).await;
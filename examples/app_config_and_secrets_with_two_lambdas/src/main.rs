@@ -2,7 +2,7 @@ use rusty_cdk::appconfig::{ApplicationBuilder, ConfigurationProfileBuilder, Envi
 use rusty_cdk::iam::Permission;
 use rusty_cdk::lambda::{Architecture, Code, FunctionBuilder, Runtime, Zip};
 use rusty_cdk::secretsmanager::{GenerateSecretStringBuilder, SecretBuilder};
-use rusty_cdk::stack::StackBuilder;
+use rusty_cdk::stack::{StackBuilder, synth_output_dir};
 use rusty_cdk::wrappers::*;
 use rusty_cdk::*;
 
@@ -75,7 +75,11 @@ async fn main() {
     .add_permission(Permission::AppConfigRead(&app_config, &app_config_env, &config_profile))
     .build(&mut stack_builder);
 
-    let synthesized = stack_builder.build().unwrap().synth().unwrap();
+    let mut stack = stack_builder.build().unwrap();
+    if let Some(dir) = synth_output_dir() {
+        stack.write_synth_output(dir).unwrap();
+    }
+    let synthesized = stack.synth().unwrap();
     println!("{}", synthesized);
     // rusty_cdk::deploy(string_with_only_alphanumerics_and_hyphens!("ExampleWithAppConfigAndSecrets"), stack_builder.build().unwrap()).await;
 }
@@ -3,7 +3,7 @@ use crate::apigateway::{
     ApiGatewayV2IntegrationProperties, ApiGatewayV2IntegrationType, ApiGatewayV2Route, ApiGatewayV2RouteProperties, ApiGatewayV2RouteType,
     ApiGatewayV2Stage, ApiGatewayV2StageProperties, ApiGatewayV2StageRef, ApiGatewayV2StageType, CorsConfiguration,
 };
-use crate::intrinsic::{AWS_ACCOUNT_PSEUDO_PARAM, AWS_PARTITION_PSEUDO_PARAM, AWS_REGION_PSEUDO_PARAM, get_arn, get_ref, join};
+use crate::intrinsic::{AWS_ACCOUNT_PSEUDO_PARAM, AWS_PARTITION_PSEUDO_PARAM, AWS_REGION_PSEUDO_PARAM, get_ref, join};
 use crate::lambda::{FunctionRef, PermissionBuilder};
 use crate::shared::HttpMethod;
 use crate::shared::Id;
@@ -20,7 +20,7 @@ struct RouteInfo {
     lambda_id: Id,
     path: String,
     method: Option<HttpMethod>,
-    resource_id: String,
+    lambda_arn: Value,
 }
 
 type_state!(ApiGatewayV2APIState, StartState, HttpState, WebsocketState,);
@@ -130,7 +130,7 @@ impl ApiGatewayV2Builder<HttpState> {
             lambda_id: lambda.get_id().clone(),
             path,
             method: Some(method),
-            resource_id: lambda.get_resource_id().to_string(),
+            lambda_arn: lambda.get_arn(),
         });
         Self { ..self }
     }
@@ -156,7 +156,7 @@ impl ApiGatewayV2Builder<WebsocketState> {
             lambda_id: lambda.get_id().clone(),
             path: route_key.into(),
             method: None,
-            resource_id: lambda.get_resource_id().to_string(),
+            lambda_arn: lambda.get_arn(),
         });
         Self { ..self }
     }
@@ -182,7 +182,7 @@ impl<T: ApiGatewayV2APIState> ApiGatewayV2Builder<T> {
             lambda_id: lambda.get_id().clone(),
             path: "$default".to_string(),
             method: None,
-            resource_id: lambda.get_resource_id().to_string(),
+            lambda_arn: lambda.get_arn(),
         });
         Self { ..self }
     }
@@ -205,7 +205,7 @@ impl<T: ApiGatewayV2APIState> ApiGatewayV2Builder<T> {
             PermissionBuilder::new(
                 &route_permission_id,
                 LambdaPermissionAction("lambda:InvokeFunction".to_string()),
-                get_arn(&info.resource_id),
+                info.lambda_arn.clone(),
                 "apigateway.amazonaws.com".to_string(),
             )
             .source_arn(join(
@@ -236,7 +236,7 @@ impl<T: ApiGatewayV2APIState> ApiGatewayV2Builder<T> {
                     } else {
                         Some("1.0".to_string())
                     },
-                    integration_uri: Some(get_arn(&info.resource_id)),
+                    integration_uri: Some(info.lambda_arn.clone()),
                     // TODO allow passing these
                     content_handling_strategy: None, // only for websocket
                     integration_method: None,        // only for websocket - set to post for lambda integration
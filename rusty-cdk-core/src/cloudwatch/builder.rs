@@ -15,7 +15,7 @@ use crate::shared::Id;
 use crate::sns::TopicRef;
 use crate::stack::{Resource, StackBuilder};
 use crate::type_state;
-use crate::wrappers::{CloudwatchAlarmName, CloudwatchMetricName, Period};
+use crate::wrappers::{AccountId, CloudwatchAlarmName, CloudwatchMetricName, Period};
 use serde_json::Value;
 
 #[derive(Debug)]
@@ -1292,8 +1292,8 @@ pub struct DimensionBuilder {
 
 impl DimensionBuilder {
     // TODO name from 1–255 characters in length
-    pub fn new(name: String, value: Value) -> Self {
-        Self { name, value }
+    pub fn new<V: Into<Value>>(name: String, value: V) -> Self {
+        Self { name, value: value.into() }
     }
 
     pub fn build(self) -> Dimension {
@@ -1355,9 +1355,9 @@ impl MetricDataQueryBuilder {
         }
     }
 
-    pub fn account_id(self, account_id: String) -> Self {
+    pub fn account_id(self, account_id: AccountId) -> Self {
         Self {
-            account_id: Some(account_id),
+            account_id: Some(account_id.0),
             ..self
         }
     }
@@ -1500,9 +1500,9 @@ impl SingleMetricAnomalyDetectorBuilder {
         }
     }
 
-    pub fn account_id(self, account_id: String) -> Self {
+    pub fn account_id(self, account_id: AccountId) -> Self {
         Self {
-            account_id: Some(account_id),
+            account_id: Some(account_id.0),
             ..self
         }
     }
@@ -0,0 +1,182 @@
+//! The context file (`rusty.context.json`), with the results of the `lookup_*!` macros.
+//!
+//! The macros of several crates can be expanded at the same time, so updates to the file happen under a lock,
+//! and the file is replaced at once (by renaming a temporary file) so readers never see half of it.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions, read_to_string, rename, write};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// File in the directory of the build (the workspace root) with the results of earlier lookups, meant to be committed
+pub const CONTEXT_FILE: &str = "rusty.context.json";
+
+/// The key of a lookup in the context file: `<account>:<region>:<kind>:<identifier>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LookupKey {
+    pub account: String,
+    pub region: String,
+    pub kind: String,
+    pub identifier: String,
+}
+
+impl LookupKey {
+    /// Returns `None` for keys that do not have all four parts
+    pub fn parse(key: &str) -> Option<Self> {
+        match key.splitn(4, ':').collect::<Vec<_>>().as_slice() {
+            [account, region, kind, identifier] => Some(Self {
+                account: account.to_string(),
+                region: region.to_string(),
+                kind: kind.to_string(),
+                identifier: identifier.to_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl From<LookupKey> for String {
+    fn from(value: LookupKey) -> Self {
+        format!("{}:{}:{}:{}", value.account, value.region, value.kind, value.identifier)
+    }
+}
+
+/// Reads the lookups of the context file at the path, which has none when the file does not exist
+pub fn read_lookups(path: &Path) -> Result<BTreeMap<String, Value>, String> {
+    match read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content).map_err(|e| format!("{} is not valid: {e}", path.display())),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(format!("could not read {}: {e}", path.display())),
+    }
+}
+
+/// Reads the lookups, changes them and writes them back (only if something changed), while holding the lock of the context file.
+/// Returns the result of `update`
+pub fn update_lookups<R>(path: &Path, update: impl FnOnce(&mut BTreeMap<String, Value>) -> R) -> Result<R, String> {
+    let _lock = lock(path)?;
+
+    let mut lookups = read_lookups(path)?;
+    let before = lookups.clone();
+    let result = update(&mut lookups);
+    if lookups != before {
+        write_lookups(path, &lookups)?;
+    }
+
+    Ok(result)
+}
+
+/// Writes the file again without changing the lookups, which makes cargo rebuild the crates with lookups (the file is a dependency of their output)
+pub fn touch_lookups(path: &Path) -> Result<(), String> {
+    let _lock = lock(path)?;
+    let lookups = read_lookups(path)?;
+    write_lookups(path, &lookups)
+}
+
+/// Takes an exclusive lock on `<context file>.lock`, which is released when the returned file is dropped
+fn lock(path: &Path) -> Result<File, String> {
+    let lock_path = with_suffix(path, "lock");
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(|e| format!("could not open lock file {}: {e}", lock_path.display()))?;
+    file.lock().map_err(|e| format!("could not lock {}: {e}", lock_path.display()))?;
+    Ok(file)
+}
+
+fn write_lookups(path: &Path, lookups: &BTreeMap<String, Value>) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(lookups).expect("lookups to be serializable");
+    let temporary_path = with_suffix(path, &format!("{}.tmp", std::process::id()));
+
+    write(&temporary_path, content + "\n").map_err(|e| format!("could not write {}: {e}", temporary_path.display()))?;
+    rename(&temporary_path, path).map_err(|e| format!("could not write {}: {e}", path.display()))
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::{LookupKey, read_lookups, touch_lookups, update_lookups};
+    use serde_json::json;
+    use std::env::temp_dir;
+    use std::fs::{create_dir_all, read_dir, remove_dir_all, write};
+    use std::path::PathBuf;
+    use std::thread;
+
+    fn context_file(name: &str) -> PathBuf {
+        let dir = temp_dir().join(format!("rusty-cdk-context-{name}-{}", std::process::id()));
+        let _ = remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        dir.join("rusty.context.json")
+    }
+
+    #[test]
+    fn should_parse_lookup_keys() {
+        let key = LookupKey::parse("123456789012:eu-west-1:vpc:arn:aws:ec2:vpc/vpc-123").unwrap();
+
+        assert_eq!(key.account, "123456789012");
+        assert_eq!(key.kind, "vpc");
+        assert_eq!(key.identifier, "arn:aws:ec2:vpc/vpc-123");
+        assert_eq!(String::from(key), "123456789012:eu-west-1:vpc:arn:aws:ec2:vpc/vpc-123");
+        assert!(LookupKey::parse("not-a-key").is_none());
+    }
+
+    #[test]
+    fn should_have_no_lookups_without_file() {
+        assert!(read_lookups(&context_file("missing")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn should_refuse_invalid_file() {
+        let path = context_file("invalid");
+        write(&path, "{ not json").unwrap();
+
+        assert!(read_lookups(&path).is_err());
+        assert!(update_lookups(&path, |lookups| lookups.clear()).is_err());
+    }
+
+    #[test]
+    fn should_keep_all_concurrent_updates() {
+        let path = context_file("concurrent");
+
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    update_lookups(&path, |lookups| {
+                        lookups.insert(format!("123456789012:eu-west-1:vpc:vpc-{i}"), json!(i))
+                    })
+                    .unwrap();
+                })
+            })
+            .collect();
+        threads.into_iter().for_each(|t| t.join().unwrap());
+
+        let lookups = read_lookups(&path).unwrap();
+        assert_eq!(lookups.len(), 8);
+        // only the context file and its lock remain, no temporary files
+        assert_eq!(read_dir(path.parent().unwrap()).unwrap().count(), 2);
+        remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn should_not_change_lookups_when_touching() {
+        let path = context_file("touch");
+        update_lookups(&path, |lookups| {
+            lookups.insert("123456789012:eu-west-1:vpc:vpc-1".to_string(), json!("vpc"))
+        })
+        .unwrap();
+
+        touch_lookups(&path).unwrap();
+
+        assert_eq!(read_lookups(&path).unwrap().len(), 1);
+        remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
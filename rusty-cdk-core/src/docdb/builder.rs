@@ -7,6 +7,7 @@ use crate::docdb::{
     EventSubscriptionType, GlobalCluster, GlobalClusterProperties, GlobalClusterRef, GlobalClusterType,
 };
 use crate::docdb::{ServerlessV2ScalingConfiguration};
+use crate::ec2::{AvailabilityZoneRef, SecurityGroupRef, SubnetRef};
 use crate::kms::KeyRef;
 use crate::shared::{AvailabilityZone, Id, Region};
 use crate::sns::TopicRef;
@@ -99,7 +100,7 @@ type_state!(
 pub struct DBClusterBuilder<T: DbClusterState> {
     phantom: PhantomData<T>,
     id: Id,
-    availability_zones: Option<Vec<String>>,
+    availability_zones: Option<Vec<Value>>,
     manage_master_user_password: Option<bool>,
     rotate_master_user_password: Option<bool>,
     master_user_secret_kms_key_id: Option<Value>,
@@ -109,7 +110,7 @@ pub struct DBClusterBuilder<T: DbClusterState> {
     use_latest_restorable_time: Option<bool>,
     deletion_protection: Option<bool>,
     serverless_v2_scaling_configuration: Option<ServerlessV2ScalingConfiguration>,
-    vpc_security_group_ids: Option<Vec<String>>,
+    vpc_security_group_ids: Option<Vec<Value>>,
     snapshot_identifier: Option<String>,
     enable_cloudwatch_logs_exports: Option<Vec<String>>,
     global_cluster_identifier: Option<Value>,
@@ -263,9 +264,10 @@ impl DBClusterBuilder<DbClusterManualPasswordState> {
 }
 
 impl<T: DbClusterState> DBClusterBuilder<T> {
-    pub fn availability_zones(self, availability_zones: Vec<String>) -> Self {
+    /// The zones can be retrieved with `lookup_availability_zones!`
+    pub fn availability_zones(self, availability_zones: Vec<AvailabilityZoneRef>) -> Self {
         Self {
-            availability_zones: Some(availability_zones),
+            availability_zones: Some(availability_zones.iter().map(AvailabilityZoneRef::get_ref).collect()),
             ..self
         }
     }
@@ -305,9 +307,10 @@ impl<T: DbClusterState> DBClusterBuilder<T> {
         }
     }
 
-    pub fn vpc_security_group_ids(self, vpc_security_group_ids: Vec<String>) -> Self {
+    /// The groups can be retrieved with `lookup_security_group!`
+    pub fn vpc_security_group_ids(self, vpc_security_groups: Vec<SecurityGroupRef>) -> Self {
         Self {
-            vpc_security_group_ids: Some(vpc_security_group_ids),
+            vpc_security_group_ids: Some(vpc_security_groups.iter().map(SecurityGroupRef::get_ref).collect()),
             ..self
         }
     }
@@ -618,16 +621,17 @@ impl DBInstanceBuilder {
 pub struct DBSubnetGroupBuilder {
     id: Id,
     db_subnet_group_name: Option<String>,
-    subnet_ids: Vec<String>,
+    subnet_ids: Vec<Value>,
     db_subnet_group_description: String,
 }
 
 impl DBSubnetGroupBuilder {
-    pub fn new(id: &str, subnet_ids: Vec<String>, db_subnet_group_description: String) -> Self {
+    /// The subnets can be retrieved with `lookup_subnets!`
+    pub fn new(id: &str, subnets: Vec<SubnetRef>, db_subnet_group_description: String) -> Self {
         Self {
             id: Id(id.to_string()),
             db_subnet_group_name: None,
-            subnet_ids,
+            subnet_ids: subnets.iter().map(SubnetRef::get_ref).collect(),
             db_subnet_group_description,
         }
     }
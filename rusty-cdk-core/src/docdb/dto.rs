@@ -27,7 +27,7 @@ dto_methods!(DBCluster);
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DBClusterProperties {
     #[serde(rename = "AvailabilityZones", skip_serializing_if = "Option::is_none")]
-    pub(crate) availability_zones: Option<Vec<String>>,
+    pub(crate) availability_zones: Option<Vec<Value>>,
     #[serde(rename = "ManageMasterUserPassword", skip_serializing_if = "Option::is_none")]
     pub(crate) manage_master_user_password: Option<bool>,
     #[serde(rename = "DBSubnetGroupName", skip_serializing_if = "Option::is_none")]
@@ -43,7 +43,7 @@ pub struct DBClusterProperties {
     #[serde(rename = "ServerlessV2ScalingConfiguration", skip_serializing_if = "Option::is_none")]
     pub(crate) serverless_v2_scaling_configuration: Option<ServerlessV2ScalingConfiguration>,
     #[serde(rename = "VpcSecurityGroupIds", skip_serializing_if = "Option::is_none")]
-    pub(crate) vpc_security_group_ids: Option<Vec<String>>,
+    pub(crate) vpc_security_group_ids: Option<Vec<Value>>,
     #[serde(rename = "SnapshotIdentifier", skip_serializing_if = "Option::is_none")]
     pub(crate) snapshot_identifier: Option<String>,
     #[serde(rename = "UseLatestRestorableTime", skip_serializing_if = "Option::is_none")]
@@ -186,7 +186,7 @@ pub struct DBSubnetGroupProperties {
     #[serde(rename = "DBSubnetGroupName", skip_serializing_if = "Option::is_none")]
     pub(crate) db_subnet_group_name: Option<String>,
     #[serde(rename = "SubnetIds")]
-    pub(crate) subnet_ids: Vec<String>,
+    pub(crate) subnet_ids: Vec<Value>,
     #[serde(rename = "DBSubnetGroupDescription")]
     pub(crate) db_subnet_group_description: String,
 }
@@ -22,7 +22,7 @@ pub struct Table {
     #[serde(rename = "Properties")]
     pub(super) properties: TableProperties,
     #[serde(flatten)]
-    pub(super) update_delete_policy_dto: UpdateDeletePolicyDTO,
+    pub(crate) update_delete_policy_dto: UpdateDeletePolicyDTO,
 }
 dto_methods!(Table);
 
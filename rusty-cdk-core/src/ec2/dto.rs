@@ -0,0 +1,97 @@
+use serde_json::Value;
+
+/// Reference to a VPC that exists outside of this stack.
+///
+/// Use `lookup_vpc!` to retrieve the VPC from your account, or create the ref yourself with `new`.
+#[derive(Debug, Clone)]
+pub struct VpcRef {
+    vpc_id: String,
+}
+
+impl VpcRef {
+    pub fn new(vpc_id: &str) -> Self {
+        Self {
+            vpc_id: vpc_id.to_string(),
+        }
+    }
+
+    pub fn get_ref(&self) -> Value {
+        Value::String(self.vpc_id.clone())
+    }
+}
+
+/// Reference to a subnet that exists outside of this stack.
+///
+/// Use `lookup_subnets!` to retrieve the subnets of a VPC from your account, or create the ref yourself with `new`.
+#[derive(Debug, Clone)]
+pub struct SubnetRef {
+    subnet_id: String,
+    vpc_id: String,
+    availability_zone: String,
+}
+
+impl SubnetRef {
+    pub fn new(subnet_id: &str, vpc_id: &str, availability_zone: &str) -> Self {
+        Self {
+            subnet_id: subnet_id.to_string(),
+            vpc_id: vpc_id.to_string(),
+            availability_zone: availability_zone.to_string(),
+        }
+    }
+
+    pub fn get_ref(&self) -> Value {
+        Value::String(self.subnet_id.clone())
+    }
+
+    pub fn get_vpc_id(&self) -> &str {
+        &self.vpc_id
+    }
+
+    pub fn get_availability_zone(&self) -> &str {
+        &self.availability_zone
+    }
+}
+
+/// Reference to a security group that exists outside of this stack.
+///
+/// Use `lookup_security_group!` to retrieve the group from your account, or create the ref yourself with `new`.
+#[derive(Debug, Clone)]
+pub struct SecurityGroupRef {
+    group_id: String,
+    vpc_id: String,
+}
+
+impl SecurityGroupRef {
+    pub fn new(group_id: &str, vpc_id: &str) -> Self {
+        Self {
+            group_id: group_id.to_string(),
+            vpc_id: vpc_id.to_string(),
+        }
+    }
+
+    pub fn get_ref(&self) -> Value {
+        Value::String(self.group_id.clone())
+    }
+
+    pub fn get_vpc_id(&self) -> &str {
+        &self.vpc_id
+    }
+}
+
+/// Reference to an availability zone, e.g. `eu-west-1a`.
+///
+/// Use `lookup_availability_zones!` to retrieve the zones you can use, or create the ref yourself with `new`.
+#[derive(Debug, Clone)]
+pub struct AvailabilityZoneRef {
+    name: String,
+}
+
+impl AvailabilityZoneRef {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string() }
+    }
+
+    pub fn get_ref(&self) -> Value {
+        Value::String(self.name.clone())
+    }
+}
@@ -11,6 +11,7 @@ use crate::shared::Id;
 use crate::shared::Region;
 use crate::stack::{Resource, StackBuilder};
 use crate::type_state;
+use crate::wrappers::AccountId;
 use crate::wrappers::EcrRepositoryName;
 use crate::wrappers::ImageTagMutabilityExclusionFilterValue;
 use crate::wrappers::RepoAboutText;
@@ -524,15 +525,15 @@ impl ReplicationRuleBuilder {
 
 pub struct ReplicationDestinationBuilder {
     region: String,
-    // TODO account id lookup
     registry_id: String,
 }
 
 impl ReplicationDestinationBuilder {
-    pub fn new(region: String, registry_id: Region) -> Self {
+    /// Replicates to the private registry of the given account (use `lookup_account_id!()` for your own account) in the given region
+    pub fn new(region: Region, registry_id: AccountId) -> Self {
         Self {
-            region,
-            registry_id: registry_id.into(),
+            region: region.into(),
+            registry_id: registry_id.0,
         }
     }
 
@@ -739,10 +740,9 @@ impl LifecyclePolicyBuilder {
         }
     }
 
-    // TODO == account id, need lookup
-    pub fn registry_id(self, registry_id: String) -> Self {
+    pub fn registry_id(self, registry_id: AccountId) -> Self {
         Self {
-            registry_id: Some(registry_id),
+            registry_id: Some(registry_id.0),
             ..self
         }
     }
@@ -4,14 +4,14 @@ use crate::iam::{
     AWSPrincipal, AssumeRolePolicyDocument, IamRoleProperties, Policy, PolicyDocument, Principal, Role, RoleRef, RoleType,
     ServicePrincipal, Statement,
 };
-use crate::intrinsic::{AWS_ACCOUNT_PSEUDO_PARAM, get_arn, get_ref, join};
+use crate::intrinsic::{AWS_ACCOUNT_PSEUDO_PARAM, get_ref, join};
 use crate::s3::BucketRef;
 use crate::secretsmanager::SecretRef;
 use crate::shared::Id;
 use crate::sqs::QueueRef;
 use crate::stack::{Resource, StackBuilder};
 use crate::type_state;
-use crate::wrappers::{IamAction, PolicyName};
+use crate::wrappers::{AccountId, IamAction, PolicyName};
 use serde_json::Value;
 use std::marker::PhantomData;
 use std::vec;
@@ -24,12 +24,19 @@ type_state!(PrincipalState, StartState, ChosenState,);
 ///
 /// ```rust
 /// use rusty_cdk_core::iam::PrincipalBuilder;
+/// use rusty_cdk_core::wrappers::AccountId;
+/// use rusty_cdk_macros::account_id;
 ///
 /// // Service principal
 /// let service_principal = PrincipalBuilder::new()
 ///     .service("lambda.amazonaws.com")
 ///     .build();
 ///
+/// // Principal for another account
+/// let account_principal = PrincipalBuilder::new()
+///     .account(account_id!("123456789012"))
+///     .build();
+///
 /// // Custom principal
 /// let custom_principal = PrincipalBuilder::new()
 ///     .normal("*")
@@ -76,6 +83,11 @@ impl PrincipalBuilder<StartState> {
         }
     }
 
+    /// An AWS principal for the account (the root of the account, which allows the account to delegate permissions to its roles and users)
+    pub fn account(self, account: AccountId) -> PrincipalBuilder<ChosenState> {
+        self.aws(account.0)
+    }
+
     pub fn normal<T: Into<String>>(self, normal: T) -> PrincipalBuilder<ChosenState> {
         PrincipalBuilder {
             phantom_data: Default::default(),
@@ -455,7 +467,7 @@ impl Permission<'_> {
                         "dynamodb:Scan".to_string(),
                     ],
                     effect: "Allow".to_string(),
-                    resource: Some(vec![get_arn(id)]),
+                    resource: Some(vec![table.get_arn()]),
                     principal: None,
                     condition: None,
                 };
@@ -478,7 +490,7 @@ impl Permission<'_> {
                         "dynamodb:UpdateItem".to_string(),
                     ],
                     effect: "Allow".to_string(),
-                    resource: Some(vec![get_arn(id)]),
+                    resource: Some(vec![table.get_arn()]),
                     principal: None,
                     condition: None,
                 };
@@ -497,14 +509,14 @@ impl Permission<'_> {
                     ],
                     Effect::Allow,
                 )
-                .resources(vec![get_arn(id)])
+                .resources(vec![queue.get_arn()])
                 .build();
                 let policy_document = PolicyDocumentBuilder::new(vec![sqs_permissions_statement]).build();
                 PolicyBuilder::new(PolicyName(format!("{}Read", id)), policy_document).build()
             }
             Permission::S3ReadWrite(bucket) => {
                 let id = bucket.get_resource_id();
-                let arn = get_arn(id);
+                let arn = bucket.get_arn();
                 let s3_permissions_statement = StatementBuilder::internal_new(
                     vec![
                         "s3:Abort*".to_string(),
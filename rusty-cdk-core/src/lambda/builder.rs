@@ -4,6 +4,7 @@ use crate::iam::{
     RoleRef, StatementBuilder, find_missing_services, map_toml_dependencies_to_services,
 };
 use crate::intrinsic::{AWS_PARTITION_PSEUDO_PARAM, get_arn, get_ref, join};
+use crate::lambda::packaging::CargoCrateBuild;
 use crate::lambda::{
     Environment, EventSourceMapping, EventSourceMappingType, EventSourceProperties, Function, FunctionRef, FunctionType, LambdaCode,
     LambdaFunctionProperties, LambdaPermissionProperties, LoggingInfo, Permission, PermissionRef, PermissionType, ScalingConfig,
@@ -13,7 +14,7 @@ use crate::sqs::QueueRef;
 use crate::stack::{Asset, Resource, StackBuilder};
 use crate::type_state;
 use crate::wrappers::{
-    Bucket, EnvVarKey, LambdaPermissionAction, LogGroupName, Memory, RetentionInDays, SqsEventSourceMaxConcurrency,
+    AccountId, Bucket, EnvVarKey, LambdaPermissionAction, LogGroupName, Memory, RetentionInDays, SqsEventSourceMaxConcurrency,
     StringWithOnlyAlphaNumericsUnderscoresAndHyphens, Timeout, TomlFile, ZipFile,
 };
 use serde_json::Value;
@@ -85,6 +86,24 @@ impl Zip {
 pub enum Code {
     Zip(Zip),
     Inline(String),
+    /// A Rust crate (with a `main`) that is built in release mode when the stack is synthesized for `cargo rusty` or deployed (see `Stack::package_cargo_crates`),
+    /// zipped as `bootstrap` and uploaded to the bucket as an asset.
+    /// Use it with `Runtime::ProvidedAl2023` and handler `bootstrap`.
+    ///
+    /// * `bucket` - The bucket to upload the zip to
+    /// * `path` - The directory of the crate, relative to where the synth or deploy runs
+    /// * `bin` - The binary to build, only required when the crate has more than one
+    /// * `target` - The target to build for, defaults to the linux gnu target of the function `Architecture` (e.g. `aarch64-unknown-linux-gnu` for ARM64).
+    ///   The target has to be installed (`rustup target add`) and needs a linker, `cargo zigbuild` or a musl target make cross-compiling easier
+    ///
+    /// The zip is cached in `<target dir>/rusty-cdk`, the build is skipped when the sources of the crate (and its path dependencies) did not change.
+    /// The hash of those sources is also the key of the zip in the bucket, so the function code only changes when the sources do.
+    CargoCrate {
+        bucket: Bucket,
+        path: String,
+        bin: Option<String>,
+        target: Option<String>,
+    },
 }
 
 type_state!(
@@ -97,7 +116,7 @@ type_state!(
 );
 
 struct EventSourceMappingInfo {
-    id: String,
+    event_source_arn: Value,
     max_concurrency: Option<u16>,
 }
 
@@ -192,9 +211,9 @@ impl<T: FunctionBuilderState> FunctionBuilder<T> {
     /// # Arguments
     ///
     /// * `key` - The name of the environment variable.
-    /// * `value` - The value of the environment variable, as a `serde_json::Value`.
-    pub fn env_var(mut self, key: EnvVarKey, value: Value) -> FunctionBuilder<T> {
-        self.env_vars.push((key.0, value));
+    /// * `value` - The value of the environment variable, as a `serde_json::Value` or a `DynamicReference`.
+    pub fn env_var<V: Into<Value>>(mut self, key: EnvVarKey, value: V) -> FunctionBuilder<T> {
+        self.env_vars.push((key.0, value.into()));
         Self { ..self }
     }
 
@@ -250,7 +269,7 @@ impl<T: FunctionBuilderState> FunctionBuilder<T> {
                     zipfile: None,
                 };
 
-                (Some(asset), code)
+                (Some(asset), code, None)
             }
             Code::Inline(inline_code) => {
                 let code = LambdaCode {
@@ -258,7 +277,24 @@ impl<T: FunctionBuilderState> FunctionBuilder<T> {
                     s3_key: None,
                     zipfile: Some(inline_code),
                 };
-                (None, code)
+                (None, code, None)
+            }
+            Code::CargoCrate { bucket, path, bin, target } => {
+                // the path of the zip, and the hash of the sources that is its key, are only known after the crate is built, see `Stack::package_cargo_crates`
+                let asset = Asset {
+                    s3_bucket: bucket.0.clone(),
+                    s3_key: String::new(),
+                    path: String::new(),
+                };
+
+                let code = LambdaCode {
+                    s3_bucket: Some(bucket.0),
+                    s3_key: None,
+                    zipfile: None,
+                };
+                let build = CargoCrateBuild::new(path, bin, target, &self.architecture);
+
+                (Some(asset), code, Some(build))
             }
         };
 
@@ -270,7 +306,7 @@ impl<T: FunctionBuilderState> FunctionBuilder<T> {
                 resource_id: event_resource_id.clone(),
                 r#type: EventSourceMappingType::EventSourceMappingType,
                 properties: EventSourceProperties {
-                    event_source_arn: Some(get_arn(&mapping.id)),
+                    event_source_arn: Some(mapping.event_source_arn),
                     function_name: Some(get_ref(&function_resource_id)),
                     scaling_config: mapping.max_concurrency.map(|c| ScalingConfig { max_concurrency: c }),
                 },
@@ -344,6 +380,7 @@ impl<T: FunctionBuilderState> FunctionBuilder<T> {
             id: self.id.clone(),
             resource_id: function_resource_id.clone(),
             asset: code.0,
+            cargo_crate: code.2,
             r#type: FunctionType::FunctionType,
             properties,
         });
@@ -480,7 +517,7 @@ impl FunctionBuilder<ZipStateWithHandlerAndRuntime> {
         self.additional_policies.push(IamPermission::SqsRead(sqs_queue).into_policy());
 
         let mapping = EventSourceMappingInfo {
-            id: sqs_queue.get_resource_id().to_string(),
+            event_source_arn: sqs_queue.get_arn(),
             max_concurrency: max_concurrency.map(|c| c.0),
         };
 
@@ -554,9 +591,16 @@ impl PermissionBuilder {
         }
     }
 
-    pub fn source_arn(self, arn: Value) -> Self {
+    pub fn source_arn<V: Into<Value>>(self, arn: V) -> Self {
+        Self {
+            source_arn: Some(arn.into()),
+            ..self
+        }
+    }
+
+    pub fn source_account(self, account: AccountId) -> Self {
         Self {
-            source_arn: Some(arn),
+            source_account: Some(Value::String(account.0)),
             ..self
         }
     }
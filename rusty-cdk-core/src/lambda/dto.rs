@@ -1,3 +1,4 @@
+use crate::lambda::packaging::CargoCrateBuild;
 use crate::shared::Id;
 use crate::stack::Asset;
 use crate::{dto_methods, ref_struct, ref_struct_with_id_methods};
@@ -21,6 +22,8 @@ pub struct Function {
     pub(super) resource_id: String,
     #[serde(skip)]
     pub(crate) asset: Option<Asset>,
+    #[serde(skip)]
+    pub(super) cargo_crate: Option<CargoCrateBuild>,
     #[serde(rename = "Type")]
     pub(super) r#type: FunctionType,
     #[serde(rename = "Properties")]
@@ -28,6 +31,29 @@ pub struct Function {
 }
 dto_methods!(Function);
 
+impl Function {
+    /// Builds and zips the crate of a function with `Code::CargoCrate`, and points its asset and code to the zip.
+    /// The key of the zip is the hash of the sources, so it only changes when the code does.
+    /// Once packaged, the crate is not built again
+    pub(crate) fn package_cargo_crate(&mut self) -> Result<(), String> {
+        match (&self.cargo_crate, &mut self.asset) {
+            (Some(build), Some(asset)) => {
+                let (path, key) = build.package().map_err(|e| format!("function {}: {e}", self.id))?;
+                asset.path = path;
+                asset.s3_key = key.clone();
+                self.properties.code.s3_key = Some(key);
+                self.cargo_crate = None;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn is_packaged(&self) -> bool {
+        self.cargo_crate.is_none()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LambdaFunctionProperties {
     #[serde(rename = "Code")]
@@ -1,5 +1,6 @@
 mod builder;
 mod dto;
+mod packaging;
 
 pub use builder::*;
 pub use dto::*;
@@ -0,0 +1,449 @@
+use crate::lambda::Architecture;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::{File, create_dir_all, read, read_dir, rename};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, DateTime, ZipWriter};
+
+const CACHE_DIR: &str = "rusty-cdk";
+const BOOTSTRAP: &str = "bootstrap";
+
+/// A Rust crate to build and zip as the code of a function, see `Code::CargoCrate`
+#[derive(Debug, Clone)]
+pub(crate) struct CargoCrateBuild {
+    pub(crate) path: String,
+    pub(crate) bin: Option<String>,
+    pub(crate) target: String,
+}
+
+impl CargoCrateBuild {
+    pub(crate) fn new(path: String, bin: Option<String>, target: Option<String>, architecture: &Architecture) -> Self {
+        let target = target.unwrap_or_else(|| match architecture {
+            Architecture::X86_64 => "x86_64-unknown-linux-gnu".to_string(),
+            Architecture::ARM64 => "aarch64-unknown-linux-gnu".to_string(),
+        });
+        Self { path, bin, target }
+    }
+
+    /// Builds the crate in release mode for the target and zips the binary as `bootstrap`.
+    /// The zip is kept in `<target dir>/rusty-cdk`, with the hash of the sources of the crate and its local dependencies as its name,
+    /// so the build is skipped when nothing changed.
+    ///
+    /// Returns the path of the zip and its name, which is also used as its key in S3.
+    pub(crate) fn package(&self) -> Result<(String, String), String> {
+        let metadata = CargoMetadata::read(&self.path)?;
+        metadata.ensure_lock_file()?;
+        let bin = match &self.bin {
+            Some(bin) => bin.clone(),
+            None => metadata.single_bin()?,
+        };
+
+        let key = source_hash(&metadata.workspace_root, &metadata.source_paths()?, &[&self.target, &bin])?;
+        let cache_dir = metadata.target_directory.join(CACHE_DIR);
+        let zip_name = format!("{key}.zip");
+        let zip_path = cache_dir.join(&zip_name);
+        if zip_path.exists() {
+            return Ok((zip_path.to_string_lossy().to_string(), zip_name));
+        }
+
+        self.build(&bin)?;
+        let binary = metadata.target_directory.join(&self.target).join("release").join(&bin);
+        create_dir_all(&cache_dir).map_err(|e| format!("could not create directory {}: {e}", cache_dir.display()))?;
+        // write to a temporary file first, so an interrupted run does not leave a broken zip in the cache
+        let temporary_path = cache_dir.join(format!("{key}.zip.tmp"));
+        zip_binary(&binary, &temporary_path)?;
+        rename(&temporary_path, &zip_path).map_err(|e| format!("could not move zip to {}: {e}", zip_path.display()))?;
+
+        Ok((zip_path.to_string_lossy().to_string(), zip_name))
+    }
+
+    fn build(&self, bin: &str) -> Result<(), String> {
+        let manifest_path = Path::new(&self.path).join("Cargo.toml");
+        // the output of cargo goes to stderr, because a synth should only write the template to stdout
+        let status = Command::new("cargo")
+            .args(["build", "--release", "--target", &self.target, "--bin", bin, "--manifest-path"])
+            .arg(&manifest_path)
+            .stdout(Stdio::null())
+            .stderr(Stdio::inherit())
+            .status()
+            .map_err(|e| format!("could not run cargo build for crate at {}: {e}", self.path))?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!(
+                "cargo build for target {} of crate at {} failed ({status}), is the target installed (`rustup target add {}`) and is there a linker for it?",
+                self.target, self.path, self.target
+            ))
+        }
+    }
+}
+
+struct CargoMetadata {
+    manifest_path: PathBuf,
+    workspace_root: PathBuf,
+    target_directory: PathBuf,
+    packages: Vec<Value>,
+    /// The nodes of the resolved dependency graph, with the ids of the dependencies of each package
+    nodes: Vec<Value>,
+}
+
+impl CargoMetadata {
+    fn read(path: &str) -> Result<Self, String> {
+        let manifest_path = Path::new(path).join("Cargo.toml");
+        if !manifest_path.exists() {
+            return Err(format!("did not find a Cargo.toml in {path}"));
+        }
+        let manifest_path = manifest_path
+            .canonicalize()
+            .map_err(|e| format!("could not resolve path {}: {e}", manifest_path.display()))?;
+
+        // with dependencies, so path dependencies outside the workspace are part of the hash
+        let output = Command::new("cargo")
+            .args(["metadata", "--format-version", "1", "--manifest-path"])
+            .arg(&manifest_path)
+            .output()
+            .map_err(|e| format!("could not run cargo metadata for crate at {path}: {e}"))?;
+        if !output.status.success() {
+            return Err(format!(
+                "cargo metadata for crate at {path} failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        let metadata: Value =
+            serde_json::from_slice(&output.stdout).map_err(|e| format!("could not read cargo metadata for crate at {path}: {e}"))?;
+
+        Ok(Self {
+            manifest_path,
+            workspace_root: PathBuf::from(metadata["workspace_root"].as_str().unwrap_or_default()),
+            target_directory: PathBuf::from(metadata["target_directory"].as_str().unwrap_or_default()),
+            packages: metadata["packages"].as_array().cloned().unwrap_or_default(),
+            nodes: metadata["resolve"]["nodes"].as_array().cloned().unwrap_or_default(),
+        })
+    }
+
+    /// The lock file is part of the hash, so it has to exist before the first build (which would otherwise create it)
+    fn ensure_lock_file(&self) -> Result<(), String> {
+        if self.workspace_root.join("Cargo.lock").exists() {
+            return Ok(());
+        }
+        let status = Command::new("cargo")
+            .args(["generate-lockfile", "--manifest-path"])
+            .arg(&self.manifest_path)
+            .stdout(Stdio::null())
+            .stderr(Stdio::inherit())
+            .status()
+            .map_err(|e| format!("could not run cargo generate-lockfile for {}: {e}", self.manifest_path.display()))?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!(
+                "cargo generate-lockfile for {} failed ({status})",
+                self.manifest_path.display()
+            ))
+        }
+    }
+
+    fn package(&self) -> Result<&Value, String> {
+        self.packages
+            .iter()
+            .find(|p| p["manifest_path"].as_str().map(Path::new) == Some(self.manifest_path.as_path()))
+            .ok_or_else(|| {
+                format!(
+                    "could not find the package of {} in the cargo metadata",
+                    self.manifest_path.display()
+                )
+            })
+    }
+
+    fn single_bin(&self) -> Result<String, String> {
+        let package = self.package()?;
+        let bins: Vec<_> = package["targets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|t| t["kind"].as_array().is_some_and(|k| k.iter().any(|k| k == "bin")))
+            .flat_map(|t| t["name"].as_str())
+            .collect();
+
+        match bins.as_slice() {
+            [bin] => Ok(bin.to_string()),
+            [] => Err(format!("crate at {} has no binary", self.manifest_path.display())),
+            _ => Err(format!(
+                "crate at {} has multiple binaries ({}), choose one with `bin`",
+                self.manifest_path.display(),
+                bins.join(", ")
+            )),
+        }
+    }
+
+    /// The manifests, build scripts and `src` directories of the package and its local (path) dependencies, and the lock file.
+    /// Other members of the workspace are left out, so changing them does not change the hash.
+    /// Packages from a registry or git repository are pinned by the lock file
+    fn source_paths(&self) -> Result<Vec<PathBuf>, String> {
+        let root = self.package()?["id"].as_str().unwrap_or_default();
+
+        let mut seen = HashSet::from([root]);
+        let mut to_visit = vec![root];
+        while let Some(id) = to_visit.pop() {
+            let dependencies = self
+                .nodes
+                .iter()
+                .find(|n| n["id"].as_str() == Some(id))
+                .and_then(|n| n["dependencies"].as_array())
+                .into_iter()
+                .flatten()
+                .flat_map(Value::as_str);
+            for dependency in dependencies {
+                if self.is_local(dependency) && seen.insert(dependency) {
+                    to_visit.push(dependency);
+                }
+            }
+        }
+
+        let package_dirs = self
+            .packages
+            .iter()
+            .filter(|p| p["id"].as_str().is_some_and(|id| seen.contains(id)))
+            .flat_map(|p| p["manifest_path"].as_str())
+            .flat_map(|m| Path::new(m).parent().map(Path::to_path_buf));
+
+        Ok(package_dirs
+            .flat_map(|dir| [dir.join("Cargo.toml"), dir.join("build.rs"), dir.join("src")])
+            .chain([self.workspace_root.join("Cargo.toml"), self.workspace_root.join("Cargo.lock")])
+            .collect())
+    }
+
+    fn is_local(&self, id: &str) -> bool {
+        self.packages.iter().any(|p| p["id"].as_str() == Some(id) && p["source"].is_null())
+    }
+}
+
+/// Hashes the contents and the paths of the files (directories are walked), plus the extra values.
+/// The paths are relative to the root (with `/` as separator), so the hash does not depend on where the workspace is checked out
+fn source_hash(root: &Path, paths: &[PathBuf], extra: &[&str]) -> Result<String, String> {
+    let mut files = vec![];
+    for path in paths {
+        collect_files(path, &mut files)?;
+    }
+    files.sort();
+    files.dedup();
+
+    let mut hasher = Sha256::new();
+    for value in extra {
+        hasher.update(value.as_bytes());
+        hasher.update([0]);
+    }
+    for file in files {
+        let content = read(&file).map_err(|e| format!("could not read {}: {e}", file.display()))?;
+        hasher.update(relative_path(root, &file).as_bytes());
+        hasher.update([0]);
+        hasher.update(&content);
+    }
+
+    Ok(hasher.finalize().iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// The path of the file relative to the root, going up with `..` for path dependencies outside of it
+fn relative_path(root: &Path, file: &Path) -> String {
+    let root: Vec<_> = root.components().collect();
+    let file: Vec<_> = file.components().collect();
+    let common = root.iter().zip(file.iter()).take_while(|(r, f)| r == f).count();
+
+    root[common..]
+        .iter()
+        .map(|_| "..".to_string())
+        .chain(file[common..].iter().map(|c| c.as_os_str().to_string_lossy().to_string()))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+    } else if path.is_dir() {
+        for entry in read_dir(path).map_err(|e| format!("could not read dir {}: {e}", path.display()))? {
+            let entry = entry.map_err(|e| format!("could not read dir {}: {e}", path.display()))?;
+            collect_files(&entry.path(), files)?;
+        }
+    }
+    Ok(())
+}
+
+/// Zips the binary as an executable `bootstrap`, with a fixed timestamp so the same binary always results in the same zip
+fn zip_binary(binary: &Path, zip_path: &Path) -> Result<(), String> {
+    let content = read(binary).map_err(|e| format!("could not read binary {}: {e}", binary.display()))?;
+    let file = File::create(zip_path).map_err(|e| format!("could not create zip {}: {e}", zip_path.display()))?;
+
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(DateTime::default())
+        .unix_permissions(0o755);
+    let mut zip = ZipWriter::new(file);
+    zip.start_file(BOOTSTRAP, options)
+        .and_then(|_| zip.write_all(&content).map_err(Into::into))
+        .and_then(|_| zip.finish().map(|_| ()))
+        .map_err(|e| format!("could not write zip {}: {e}", zip_path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lambda::Architecture;
+    use crate::lambda::packaging::{CargoCrateBuild, CargoMetadata, relative_path, source_hash, zip_binary};
+    use serde_json::json;
+    use std::env::temp_dir;
+    use std::fs::{create_dir_all, read, remove_dir_all, write};
+    use std::path::{Path, PathBuf};
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = temp_dir().join(format!("rusty-cdk-packaging-{name}-{}", std::process::id()));
+        let _ = remove_dir_all(&dir);
+        create_dir_all(dir.join("src")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn should_default_to_linux_target_of_architecture() {
+        let arm = CargoCrateBuild::new("lambda".to_string(), None, None, &Architecture::ARM64);
+        let x86 = CargoCrateBuild::new("lambda".to_string(), None, None, &Architecture::X86_64);
+        let musl = CargoCrateBuild::new(
+            "lambda".to_string(),
+            None,
+            Some("aarch64-unknown-linux-musl".to_string()),
+            &Architecture::ARM64,
+        );
+
+        assert_eq!(arm.target, "aarch64-unknown-linux-gnu");
+        assert_eq!(x86.target, "x86_64-unknown-linux-gnu");
+        assert_eq!(musl.target, "aarch64-unknown-linux-musl");
+    }
+
+    #[test]
+    fn should_create_identical_zips_for_identical_binaries() {
+        let dir = test_dir("zip");
+        let binary = dir.join("binary");
+        write(&binary, b"not really a binary").unwrap();
+
+        zip_binary(&binary, &dir.join("first.zip")).unwrap();
+        zip_binary(&binary, &dir.join("second.zip")).unwrap();
+
+        assert_eq!(read(dir.join("first.zip")).unwrap(), read(dir.join("second.zip")).unwrap());
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn should_change_source_hash_when_source_or_target_changes() {
+        let dir = test_dir("hash");
+        write(dir.join("Cargo.toml"), "[package]\nname = \"lambda\"").unwrap();
+        write(dir.join("src").join("main.rs"), "fn main() {}").unwrap();
+        let paths = [dir.join("Cargo.toml"), dir.join("build.rs"), dir.join("src")];
+
+        let hash = source_hash(&dir, &paths, &["aarch64-unknown-linux-gnu", "lambda"]).unwrap();
+        let same = source_hash(&dir, &paths, &["aarch64-unknown-linux-gnu", "lambda"]).unwrap();
+        let other_target = source_hash(&dir, &paths, &["x86_64-unknown-linux-gnu", "lambda"]).unwrap();
+        write(dir.join("src").join("main.rs"), "fn main() { println!(\"changed\"); }").unwrap();
+        let changed = source_hash(&dir, &paths, &["aarch64-unknown-linux-gnu", "lambda"]).unwrap();
+
+        assert_eq!(hash, same);
+        assert_ne!(hash, other_target);
+        assert_ne!(hash, changed);
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn should_hash_same_sources_in_different_directories_the_same() {
+        let hash_in = |dir: &Path| {
+            write(dir.join("Cargo.toml"), "[package]\nname = \"lambda\"").unwrap();
+            write(dir.join("src").join("main.rs"), "fn main() {}").unwrap();
+            let paths = [dir.join("Cargo.toml"), dir.join("src")];
+            source_hash(dir, &paths, &["aarch64-unknown-linux-gnu", "lambda"]).unwrap()
+        };
+        let first = test_dir("hash-first");
+        let second = test_dir("hash-second");
+
+        let first_hash = hash_in(&first);
+        let second_hash = hash_in(&second);
+
+        assert_eq!(first_hash, second_hash);
+        remove_dir_all(first).unwrap();
+        remove_dir_all(second).unwrap();
+    }
+
+    #[test]
+    fn should_make_paths_relative_to_workspace_root() {
+        let root = Path::new("/workspace");
+
+        assert_eq!(
+            relative_path(root, Path::new("/workspace/lambdas/books/src/main.rs")),
+            "lambdas/books/src/main.rs"
+        );
+        assert_eq!(
+            relative_path(root, Path::new("/shared/common/src/lib.rs")),
+            "../shared/common/src/lib.rs"
+        );
+    }
+
+    fn workspace_metadata() -> CargoMetadata {
+        CargoMetadata {
+            manifest_path: PathBuf::from("/workspace/lambdas/books/Cargo.toml"),
+            workspace_root: PathBuf::from("/workspace"),
+            target_directory: PathBuf::from("/workspace/target"),
+            packages: vec![
+                json!({ "id": "path+file:///workspace/lambdas/books#0.1.0", "manifest_path": "/workspace/lambdas/books/Cargo.toml", "source": null }),
+                json!({ "id": "path+file:///workspace/lambdas/authors#0.1.0", "manifest_path": "/workspace/lambdas/authors/Cargo.toml", "source": null }),
+                json!({ "id": "path+file:///workspace/infra#0.1.0", "manifest_path": "/workspace/infra/Cargo.toml", "source": null }),
+                json!({ "id": "path+file:///shared/common#0.1.0", "manifest_path": "/shared/common/Cargo.toml", "source": null }),
+                json!({
+                    "id": "registry+https://github.com/rust-lang/crates.io-index#serde@1.0.0",
+                    "manifest_path": "/home/.cargo/registry/src/serde-1.0.0/Cargo.toml",
+                    "source": "registry+https://github.com/rust-lang/crates.io-index"
+                }),
+            ],
+            nodes: vec![
+                json!({
+                    "id": "path+file:///workspace/lambdas/books#0.1.0",
+                    "dependencies": ["path+file:///shared/common#0.1.0", "registry+https://github.com/rust-lang/crates.io-index#serde@1.0.0"]
+                }),
+                json!({ "id": "path+file:///workspace/lambdas/authors#0.1.0", "dependencies": ["path+file:///shared/common#0.1.0"] }),
+                json!({ "id": "path+file:///workspace/infra#0.1.0", "dependencies": [] }),
+                json!({ "id": "path+file:///shared/common#0.1.0", "dependencies": [] }),
+                json!({ "id": "registry+https://github.com/rust-lang/crates.io-index#serde@1.0.0", "dependencies": [] }),
+            ],
+        }
+    }
+
+    #[test]
+    fn should_hash_sources_of_package_and_its_path_dependencies_only() {
+        let paths = workspace_metadata().source_paths().unwrap();
+
+        assert!(paths.contains(&PathBuf::from("/workspace/lambdas/books/src")));
+        assert!(paths.contains(&PathBuf::from("/shared/common/src")));
+        assert!(paths.contains(&PathBuf::from("/workspace/Cargo.lock")));
+        assert!(!paths.iter().any(|p| p.starts_with("/home/.cargo")));
+    }
+
+    #[test]
+    fn should_not_hash_sources_of_sibling_workspace_members() {
+        let mut metadata = workspace_metadata();
+        let books = metadata.source_paths().unwrap();
+        metadata.manifest_path = PathBuf::from("/workspace/lambdas/authors/Cargo.toml");
+        let authors = metadata.source_paths().unwrap();
+
+        assert!(
+            !books
+                .iter()
+                .any(|p| p.starts_with("/workspace/lambdas/authors") || p.starts_with("/workspace/infra"))
+        );
+        assert!(
+            !authors
+                .iter()
+                .any(|p| p.starts_with("/workspace/lambdas/books") || p.starts_with("/workspace/infra"))
+        );
+        assert!(authors.contains(&PathBuf::from("/workspace/lambdas/authors/src")));
+    }
+}
@@ -4,8 +4,10 @@ pub mod appsync;
 pub mod cloudfront;
 pub mod cloudwatch;
 pub mod cloudwatch_logs;
+pub mod context;
 pub mod docdb;
 pub mod dynamodb;
+pub mod ec2;
 pub mod ecr;
 pub mod events;
 pub mod iam;
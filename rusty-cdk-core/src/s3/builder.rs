@@ -1325,9 +1325,9 @@ impl JournalTableConfigurationBuilder {
         }
     }
 
-    pub fn table_arn(self, arn: Value) -> Self {
+    pub fn table_arn<V: Into<Value>>(self, arn: V) -> Self {
         Self {
-            table_arn: Some(arn),
+            table_arn: Some(arn.into()),
             ..self
         }
     }
@@ -1359,9 +1359,9 @@ impl MetadataDestinationBuilder {
         }
     }
 
-    pub fn table_bucket_arn(self, table_bucket_arn: Value) -> Self {
+    pub fn table_bucket_arn<V: Into<Value>>(self, table_bucket_arn: V) -> Self {
         Self {
-            table_bucket_arn: Some(table_bucket_arn),
+            table_bucket_arn: Some(table_bucket_arn.into()),
             ..self
         }
     }
@@ -1414,9 +1414,9 @@ impl InventoryTableConfigurationBuilder {
         }
     }
 
-    pub fn table_arn(self, table_arn: Value) -> Self {
+    pub fn table_arn<V: Into<Value>>(self, table_arn: V) -> Self {
         Self {
-            table_arn: Some(table_arn),
+            table_arn: Some(table_arn.into()),
             ..self
         }
     }
@@ -50,7 +50,7 @@ pub struct Bucket {
     #[serde(rename = "Type")]
     pub(super) r#type: BucketType,
     #[serde(rename = "Properties")]
-    pub(super) properties: BucketProperties,
+    pub(crate) properties: BucketProperties,
     #[serde(flatten)]
     pub(crate) update_delete_policy_dto: UpdateDeletePolicyDTO,
 }
@@ -78,7 +78,7 @@ pub struct BucketProperties {
     #[serde(rename = "PublicAccessBlockConfiguration", skip_serializing_if = "Option::is_none")]
     pub(super) public_access_block_configuration: Option<PublicAccessBlockConfiguration>,
     #[serde(rename = "VersioningConfiguration", skip_serializing_if = "Option::is_none")]
-    pub(super) versioning_configuration: Option<VersioningConfig>,
+    pub(crate) versioning_configuration: Option<VersioningConfig>,
     #[serde(rename = "WebsiteConfiguration", skip_serializing_if = "Option::is_none")]
     pub(super) website_configuration: Option<WebsiteConfiguration>,
     // to add //
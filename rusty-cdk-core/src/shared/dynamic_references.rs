@@ -0,0 +1,75 @@
+use serde_json::Value;
+
+pub(crate) const DYNAMIC_REFERENCE_START: &str = "{{resolve:";
+pub(crate) const SECRETS_MANAGER_REFERENCE_START: &str = "{{resolve:secretsmanager:";
+
+/// A value that CloudFormation resolves during deployment, from SSM Parameter Store or Secrets Manager.
+///
+/// Dynamic references can be passed to builder methods that accept a `Value` (like `env_var` and `add_output`).
+/// Unlike the `lookup_ssm_parameter!` macro, the value is not part of the synthesized template,
+/// and a new deployment picks up changes to the parameter or secret.
+///
+/// # Recommended Usage
+/// Use the `ssm_dynamic_reference!` and `secrets_manager_dynamic_reference!` macros from `rusty-cdk-macros` for compile-time validation:
+///
+/// ```rust
+/// use rusty_cdk_core::shared::DynamicReference;
+/// use rusty_cdk_macros::{secrets_manager_dynamic_reference, ssm_dynamic_reference};
+///
+/// let image_tag = ssm_dynamic_reference!("/shared/image-tag");
+/// let pinned = ssm_dynamic_reference!("/shared/image-tag", 3);
+/// let password = secrets_manager_dynamic_reference!("database-credentials", "password");
+///
+/// assert_eq!(String::from(pinned), "{{resolve:ssm:/shared/image-tag:3}}");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum DynamicReference {
+    /// A `String` or `StringList` parameter, optionally pinned to a version
+    Ssm { name: String, version: Option<u32> },
+    /// A secret, or a key of a secret that contains JSON, optionally for a version stage other than `AWSCURRENT`
+    SecretsManager {
+        secret_id: String,
+        json_key: Option<String>,
+        version_stage: Option<String>,
+    },
+}
+
+impl From<DynamicReference> for String {
+    fn from(value: DynamicReference) -> Self {
+        match value {
+            DynamicReference::Ssm { name, version: None } => format!("{DYNAMIC_REFERENCE_START}ssm:{name}}}}}"),
+            DynamicReference::Ssm {
+                name,
+                version: Some(version),
+            } => format!("{DYNAMIC_REFERENCE_START}ssm:{name}:{version}}}}}"),
+            DynamicReference::SecretsManager {
+                secret_id,
+                json_key,
+                version_stage,
+            } => {
+                let key = json_key.unwrap_or_default();
+                match version_stage {
+                    None if key.is_empty() => format!("{SECRETS_MANAGER_REFERENCE_START}{secret_id}}}}}"),
+                    None => format!("{SECRETS_MANAGER_REFERENCE_START}{secret_id}:SecretString:{key}}}}}"),
+                    Some(stage) => format!("{SECRETS_MANAGER_REFERENCE_START}{secret_id}:SecretString:{key}:{stage}}}}}"),
+                }
+            }
+        }
+    }
+}
+
+impl From<DynamicReference> for Value {
+    fn from(value: DynamicReference) -> Self {
+        Value::String(value.into())
+    }
+}
+
+/// Counts the dynamic references in a (part of a) template
+pub(crate) fn count_dynamic_references(value: &Value) -> usize {
+    match value {
+        Value::String(s) => s.matches(DYNAMIC_REFERENCE_START).count(),
+        Value::Array(values) => values.iter().map(count_dynamic_references).sum(),
+        Value::Object(map) => map.values().map(count_dynamic_references).sum(),
+        _ => 0,
+    }
+}
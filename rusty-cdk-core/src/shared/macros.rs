@@ -86,6 +86,12 @@ macro_rules! ref_struct {
 
             $crate::internal_ref_struct_methods!();
         }
+
+        impl From<&$name> for $crate::stack::StackPolicyResource {
+            fn from(value: &$name) -> Self {
+                $crate::stack::StackPolicyResource(value.get_resource_id().to_string())
+            }
+        }
     };
 }
 
@@ -133,6 +139,12 @@ macro_rules! ref_struct_with_id_methods {
 
             $crate::internal_ref_struct_methods!();
         }
+
+        impl From<&$name> for $crate::stack::StackPolicyResource {
+            fn from(value: &$name) -> Self {
+                $crate::stack::StackPolicyResource(value.get_resource_id().to_string())
+            }
+        }
     };
 }
 
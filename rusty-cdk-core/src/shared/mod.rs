@@ -1,4 +1,5 @@
 mod constants;
+mod dynamic_references;
 mod http;
 mod id;
 pub(crate) mod macros;
@@ -6,6 +7,7 @@ mod regions;
 mod update_delete_policy;
 
 pub(crate) use constants::*;
+pub use dynamic_references::*;
 pub use http::*;
 pub use id::*;
 pub use regions::*;
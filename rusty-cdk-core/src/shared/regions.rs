@@ -1,3 +1,4 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Region {
     AfSouth1,
     ApEast1,
@@ -76,6 +77,50 @@ impl From<Region> for String {
     }
 }
 
+impl TryFrom<&str> for Region {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "af-south-1" => Ok(Region::AfSouth1),
+            "ap-east-1" => Ok(Region::ApEast1),
+            "ap-east-2" => Ok(Region::ApEast2),
+            "ap-northeast-1" => Ok(Region::ApNortheast1),
+            "ap-northeast-2" => Ok(Region::ApNortheast2),
+            "ap-northeast-3" => Ok(Region::ApNortheast3),
+            "ap-south-1" => Ok(Region::ApSouth1),
+            "ap-south-2" => Ok(Region::ApSouth2),
+            "ap-southeast-1" => Ok(Region::ApSoutheast1),
+            "ap-southeast-2" => Ok(Region::ApSoutheast2),
+            "ap-southeast-3" => Ok(Region::ApSoutheast3),
+            "ap-southeast-4" => Ok(Region::ApSoutheast4),
+            "ap-southeast-5" => Ok(Region::ApSoutheast5),
+            "ap-southeast-6" => Ok(Region::ApSoutheast6),
+            "ap-southeast-7" => Ok(Region::ApSoutheast7),
+            "ca-central-1" => Ok(Region::CaCentral1),
+            "ca-west-1" => Ok(Region::CaWest1),
+            "eu-central-1" => Ok(Region::EuCentral1),
+            "eu-central-2" => Ok(Region::EuCentral2),
+            "eu-north-1" => Ok(Region::EuNorth1),
+            "eu-south-1" => Ok(Region::EuSouth1),
+            "eu-south-2" => Ok(Region::EuSouth2),
+            "eu-west-1" => Ok(Region::EuWest1),
+            "eu-west-2" => Ok(Region::EuWest2),
+            "eu-west-3" => Ok(Region::EuWest3),
+            "il-central-1" => Ok(Region::IlCentral1),
+            "me-central-1" => Ok(Region::MeCentral1),
+            "me-south-1" => Ok(Region::MeSouth1),
+            "mx-central-1" => Ok(Region::MxCentral1),
+            "sa-east-1" => Ok(Region::SaEast1),
+            "us-east-1" => Ok(Region::UsEast1),
+            "us-east-2" => Ok(Region::UsEast2),
+            "us-west-1" => Ok(Region::UsWest1),
+            "us-west-2" => Ok(Region::UsWest2),
+            _ => Err(format!("unknown region `{value}`")),
+        }
+    }
+}
+
 pub enum AvailabilityZone {
     A,
     B,
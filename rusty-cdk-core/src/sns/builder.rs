@@ -1,5 +1,5 @@
 use crate::iam::{PolicyDocument, RoleRef};
-use crate::intrinsic::{get_arn, get_ref};
+use crate::intrinsic::get_ref;
 use crate::kms::KeyRef;
 use crate::lambda::{FunctionRef, PermissionBuilder};
 use crate::shared::{Id, TOPIC_POLICY_ID_SUFFIX};
@@ -94,7 +94,7 @@ pub struct TopicBuilder<T: TopicBuilderState> {
     content_based_deduplication: Option<bool>,
     fifo_throughput_scope: Option<FifoThroughputScope>,
     topic_policy_doc: Option<PolicyDocument>,
-    lambda_subscription_ids: Vec<(Id, String)>,
+    lambda_subscription_ids: Vec<(Id, Value)>,
     archive_policy: Option<String>,
     display_name: Option<String>,
     kms_master_key_id: Option<Value>,
@@ -226,43 +226,39 @@ impl<T: TopicBuilderState> TopicBuilder<T> {
 
     fn add_subscription_internal(&mut self, subscription: SubscriptionType) {
         match subscription {
-            SubscriptionType::Lambda(l) => self
-                .lambda_subscription_ids
-                .push((l.get_id().clone(), l.get_resource_id().to_string())),
+            SubscriptionType::Lambda(l) => self.lambda_subscription_ids.push((l.get_id().clone(), l.get_arn())),
         };
     }
 
     fn build_internal(self, fifo: bool, stack_builder: &mut StackBuilder) -> TopicRef {
         let topic_resource_id = Resource::generate_id("SnsTopic");
 
-        self.lambda_subscription_ids
-            .iter()
-            .for_each(|(to_subscribe_id, to_subscribe_resource_id)| {
-                let subscription_id = Id::combine_ids(&self.id, to_subscribe_id);
-                let subscription_resource_id = Resource::generate_id("SnsSubscription");
-
-                PermissionBuilder::new(
-                    &Id::generate_id(&subscription_id, "Permission"),
-                    LambdaPermissionAction("lambda:InvokeFunction".to_string()),
-                    get_arn(to_subscribe_resource_id),
-                    "sns.amazonaws.com",
-                )
-                .source_arn(get_ref(&topic_resource_id))
-                .build(stack_builder);
-
-                let subscription = Subscription {
-                    id: subscription_id,
-                    resource_id: subscription_resource_id,
-                    r#type: SubscriptionDtoType::SubscriptionType,
-                    properties: SnsSubscriptionProperties {
-                        protocol: "lambda".to_string(),
-                        endpoint: get_arn(to_subscribe_resource_id),
-                        topic_arn: get_ref(&topic_resource_id),
-                    },
-                };
-
-                stack_builder.add_resource(subscription);
-            });
+        self.lambda_subscription_ids.iter().for_each(|(to_subscribe_id, to_subscribe_arn)| {
+            let subscription_id = Id::combine_ids(&self.id, to_subscribe_id);
+            let subscription_resource_id = Resource::generate_id("SnsSubscription");
+
+            PermissionBuilder::new(
+                &Id::generate_id(&subscription_id, "Permission"),
+                LambdaPermissionAction("lambda:InvokeFunction".to_string()),
+                to_subscribe_arn.clone(),
+                "sns.amazonaws.com",
+            )
+            .source_arn(get_ref(&topic_resource_id))
+            .build(stack_builder);
+
+            let subscription = Subscription {
+                id: subscription_id,
+                resource_id: subscription_resource_id,
+                r#type: SubscriptionDtoType::SubscriptionType,
+                properties: SnsSubscriptionProperties {
+                    protocol: "lambda".to_string(),
+                    endpoint: to_subscribe_arn.clone(),
+                    topic_arn: get_ref(&topic_resource_id),
+                },
+            };
+
+            stack_builder.add_resource(subscription);
+        });
 
         let archive_policy = if let Some(policy_retention_time) = self.archive_policy {
             Some(json!({ "MessageRetentionPeriod": policy_retention_time }))
@@ -1,6 +1,7 @@
 use crate::iam::PolicyDocument;
 use crate::intrinsic::{get_att, get_ref};
 use crate::shared::Id;
+use crate::stack::StackPolicyResource;
 use crate::{dto_methods, ref_struct_with_id_methods};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -71,6 +72,12 @@ impl TopicRef {
     }
 }
 
+impl From<&TopicRef> for StackPolicyResource {
+    fn from(value: &TopicRef) -> Self {
+        StackPolicyResource(value.get_resource_id().to_string())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Topic {
     #[serde(skip)]
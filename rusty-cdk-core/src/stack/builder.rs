@@ -1,16 +1,23 @@
 use serde_json::Value;
 
-use crate::shared::Id;
-use crate::stack::{Output, Resource, Stack};
+use crate::cloudwatch::AlarmRef;
+use crate::shared::{DYNAMIC_REFERENCE_START, Id, SECRETS_MANAGER_REFERENCE_START, count_dynamic_references};
+use crate::sns::TopicRef;
+use crate::stack::{Output, Resource, Stack, StackPolicy, StackPolicyStatement, StackSettings, StackSettingsReference};
+use crate::wrappers::RollbackMonitoringTime;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
 
+const MAX_DYNAMIC_REFERENCES: usize = 60;
+
 #[derive(Debug)]
 pub enum StackBuilderError {
     MissingPermissionsForRole(Vec<String>),
     DuplicateIds(Vec<String>),
     DuplicateResourceIds(Vec<String>),
     ResourceSpecificIssues(Vec<String>),
+    InvalidStackSettings(Vec<String>),
+    UnsupportedDynamicReferences(Vec<String>),
 }
 
 impl Display for StackBuilderError {
@@ -31,9 +38,17 @@ impl Display for StackBuilderError {
                 ))
             }
             StackBuilderError::ResourceSpecificIssues(info) => {
+                let gathered_info = info.join(";");
+                f.write_fmt(format_args!("resource specific issues detected: `{}`", gathered_info))
+            }
+            StackBuilderError::InvalidStackSettings(info) => {
+                let gathered_info = info.join(";");
+                f.write_fmt(format_args!("invalid stack settings: `{}`", gathered_info))
+            }
+            StackBuilderError::UnsupportedDynamicReferences(info) => {
                 let gathered_info = info.join(";");
                 f.write_fmt(format_args!(
-                    "resource specific issues detected: `{}`",
+                    "dynamic references used where CloudFormation does not support them: `{}`",
                     gathered_info
                 ))
             }
@@ -81,6 +96,7 @@ pub struct StackBuilder {
     resources: Vec<Resource>,
     tags: Vec<(String, String)>,
     outputs: Vec<(String, Value)>,
+    settings: StackSettings,
 }
 
 impl Default for StackBuilder {
@@ -95,6 +111,7 @@ impl StackBuilder {
             resources: vec![],
             tags: vec![],
             outputs: vec![],
+            settings: StackSettings::default(),
         }
     }
 
@@ -108,8 +125,49 @@ impl StackBuilder {
         self
     }
 
-    pub fn add_output<T: Into<String>>(mut self, name: T, value: Value) -> Self {
-        self.outputs.push((name.into(), value));
+    /// Adds an output to the stack. The value can be a `serde_json::Value` or a `DynamicReference` to an SSM parameter
+    pub fn add_output<T: Into<String>, V: Into<Value>>(mut self, name: T, value: V) -> Self {
+        self.outputs.push((name.into(), value.into()));
+        self
+    }
+
+    /// Enables or disables termination protection. A stack with termination protection cannot be destroyed (until the protection is disabled)
+    pub fn termination_protection(mut self, enabled: bool) -> Self {
+        self.settings.termination_protection = Some(enabled);
+        self
+    }
+
+    /// Sets a stack policy, which CloudFormation checks when updating resources during a deployment.
+    /// See `StackPolicyBuilder`
+    pub fn stack_policy(mut self, policy: StackPolicy) -> Self {
+        self.settings.stack_policy = Some(policy);
+        self
+    }
+
+    /// Rolls back a deployment when this alarm goes off during the deployment or during the monitoring time afterward (max. 5 triggers).
+    ///
+    /// CloudFormation requires the alarm to exist before the deployment starts.
+    /// So alarms that are part of this stack only become rollback triggers once they are deployed.
+    pub fn add_rollback_trigger(mut self, alarm: &AlarmRef) -> Self {
+        self.settings
+            .rollback_triggers
+            .push(StackSettingsReference::from_arn(alarm.get_arn(), alarm.get_resource_id()));
+        self
+    }
+
+    /// Time that CloudFormation keeps monitoring the rollback triggers after a deployment has finished
+    pub fn rollback_monitoring_time(mut self, time: RollbackMonitoringTime) -> Self {
+        self.settings.rollback_monitoring_time_in_minutes = Some(time.0);
+        self
+    }
+
+    /// Sends the events of the stack to this topic (max. 5 topics).
+    ///
+    /// Like rollback triggers, topics that are part of this stack are only used once they are deployed.
+    pub fn add_notification_topic(mut self, topic: &TopicRef) -> Self {
+        self.settings
+            .notification_topics
+            .push(StackSettingsReference::from_arn(topic.get_arn(), topic.get_resource_id()));
         self
     }
 
@@ -123,7 +181,9 @@ impl StackBuilder {
     /// - there are duplicate ids
     /// - IAM roles are missing permissions for AWS services they need to access (only when Cargo.toml dependencies were passed in)
     /// - Too many actions are specified for an alarm
-    pub fn build(self) -> Result<Stack, StackBuilderError> {    
+    /// - Dynamic references are used in stack tags, secrets are referenced in outputs, or the stack has more than 60 dynamic references
+    /// - The crate of a function with `Code::CargoCrate` could not be built or zipped
+    pub fn build(self) -> Result<Stack, StackBuilderError> {
         let (ids, resource_ids) = self
             .resources
             .iter()
@@ -147,12 +207,22 @@ impl StackBuilder {
                 roles_with_potentially_missing_services,
             ));
         }
-        
+
         let resource_specific_issues = self.resource_specific_checks();
         if !resource_specific_issues.is_empty() {
             return Err(StackBuilderError::ResourceSpecificIssues(resource_specific_issues));
         }
 
+        let settings_issues = self.stack_settings_checks();
+        if !settings_issues.is_empty() {
+            return Err(StackBuilderError::InvalidStackSettings(settings_issues));
+        }
+
+        let dynamic_reference_issues = self.dynamic_reference_checks();
+        if !dynamic_reference_issues.is_empty() {
+            return Err(StackBuilderError::UnsupportedDynamicReferences(dynamic_reference_issues));
+        }
+
         let outputs = if self.outputs.is_empty() {
             None
         } else {
@@ -169,32 +239,96 @@ impl StackBuilder {
         Ok(Stack {
             resource_ids_to_replace: vec![],
             tags: self.tags,
+            settings: Some(self.settings),
             resources,
             outputs,
             metadata,
         })
     }
-    
+
     fn resource_specific_checks(&self) -> Vec<String> {
-        self.resources.iter().flat_map(|r| match r {
-            Resource::Alarm(a) => {
-                let mut errors = vec![];
-                let props = &a.properties;
-                
-                if props.alarm_actions.iter().len() > 5 {
-                    errors.push(format!("alarm with id {} has too many alarm actions", a.id))
-                }
-                if props.ok_actions.iter().len() > 5 {
-                    errors.push(format!("alarm with id {} has too many ok actions", a.id))
-                }
-                if props.insufficient_data_actions.iter().len() > 5 {
-                    errors.push(format!("alarm with id {} has too many insufficient data actions", a.id))
+        self.resources
+            .iter()
+            .flat_map(|r| match r {
+                Resource::Alarm(a) => {
+                    let mut errors = vec![];
+                    let props = &a.properties;
+
+                    if props.alarm_actions.iter().len() > 5 {
+                        errors.push(format!("alarm with id {} has too many alarm actions", a.id))
+                    }
+                    if props.ok_actions.iter().len() > 5 {
+                        errors.push(format!("alarm with id {} has too many ok actions", a.id))
+                    }
+                    if props.insufficient_data_actions.iter().len() > 5 {
+                        errors.push(format!("alarm with id {} has too many insufficient data actions", a.id))
+                    }
+
+                    errors
                 }
-                
-                errors
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    fn stack_settings_checks(&self) -> Vec<String> {
+        let settings = &self.settings;
+        let in_stack = |resource_id: &str| self.resources.iter().any(|r| r.get_resource_id() == resource_id);
+        let mut errors = vec![];
+
+        if settings.rollback_triggers.len() > 5 {
+            errors.push("a stack can have at most 5 rollback triggers".to_string());
+        }
+        if settings.notification_topics.len() > 5 {
+            errors.push("a stack can have at most 5 notification topics".to_string());
+        }
+
+        let referenced = settings
+            .rollback_triggers
+            .iter()
+            .chain(settings.notification_topics.iter())
+            .flat_map(|r| match r {
+                StackSettingsReference::Resource(resource_id) => Some(resource_id.as_str()),
+                StackSettingsReference::Arn(_) => None,
+            })
+            .chain(settings.stack_policy.iter().flat_map(|p| p.get_resource_ids()));
+
+        for resource_id in referenced {
+            if !in_stack(resource_id) {
+                errors.push(format!(
+                    "stack settings refer to resource {resource_id}, which is not part of the stack"
+                ));
             }
-            _ => vec![],
-        }).collect()
+        }
+
+        errors
+    }
+
+    fn dynamic_reference_checks(&self) -> Vec<String> {
+        let mut errors = vec![];
+
+        // stack tags are passed to CloudFormation as is, they are not part of the template
+        for (key, value) in &self.tags {
+            if key.contains(DYNAMIC_REFERENCE_START) || value.contains(DYNAMIC_REFERENCE_START) {
+                errors.push(format!("stack tag {key} contains a dynamic reference"));
+            }
+        }
+        for (name, value) in &self.outputs {
+            if value.to_string().contains(SECRETS_MANAGER_REFERENCE_START) {
+                errors.push(format!("output {name} refers to a secret, which would expose its value"));
+            }
+        }
+
+        let in_resources = serde_json::to_value(&self.resources).map(|v| count_dynamic_references(&v)).unwrap_or(0);
+        let in_outputs: usize = self.outputs.iter().map(|(_, v)| count_dynamic_references(v)).sum();
+        if in_resources + in_outputs > MAX_DYNAMIC_REFERENCES {
+            errors.push(format!(
+                "a stack can have at most {MAX_DYNAMIC_REFERENCES} dynamic references (found {})",
+                in_resources + in_outputs
+            ));
+        }
+
+        errors
     }
 
     fn check_for_roles_with_missing_permissions(&self) -> Vec<String> {
@@ -225,9 +359,118 @@ impl StackBuilder {
     }
 }
 
+/// The kind of update that a stack policy statement applies to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StackPolicyAction {
+    /// Updates that keep the existing resource, with or without interruption
+    Modify,
+    /// Updates that replace the resource, creating a new physical resource
+    Replace,
+    /// Removing the resource from the stack
+    Delete,
+    All,
+}
+
+impl StackPolicyAction {
+    fn as_action(&self) -> &'static str {
+        match self {
+            StackPolicyAction::Modify => "Update:Modify",
+            StackPolicyAction::Replace => "Update:Replace",
+            StackPolicyAction::Delete => "Update:Delete",
+            StackPolicyAction::All => "Update:*",
+        }
+    }
+}
+
+/// A resource in a stack policy, created from the ref of that resource
+///
+/// `TableRef`, `DBClusterRef`, `BucketRef`, and the other refs can be converted into this type.
+#[derive(Debug, Clone)]
+pub struct StackPolicyResource(pub(crate) String);
+
+/// Builder for stack policies.
+///
+/// Everything that is not explicitly denied is allowed.
+///
+/// # Example
+///
+/// ```rust
+/// use rusty_cdk_core::dynamodb::{AttributeType, Key, TableBuilder};
+/// use rusty_cdk_core::stack::{StackBuilder, StackPolicyAction, StackPolicyBuilder};
+/// use rusty_cdk_core::wrappers::*;
+/// use rusty_cdk_macros::string_with_only_alphanumerics_and_underscores;
+///
+/// let mut stack_builder = StackBuilder::new();
+/// let table = TableBuilder::new("table", Key::new(string_with_only_alphanumerics_and_underscores!("id"), AttributeType::String))
+///     .pay_per_request_billing()
+///     .build(&mut stack_builder);
+///
+/// let policy = StackPolicyBuilder::new()
+///     .deny(StackPolicyAction::Replace, &table)
+///     .deny(StackPolicyAction::Delete, &table)
+///     .build();
+///
+/// let stack = stack_builder.termination_protection(true).stack_policy(policy).build();
+/// ```
+pub struct StackPolicyBuilder {
+    denied: Vec<(StackPolicyAction, String)>,
+}
+
+impl Default for StackPolicyBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StackPolicyBuilder {
+    pub fn new() -> Self {
+        Self { denied: vec![] }
+    }
+
+    /// Denies the given kind of update of the resource during deployments
+    pub fn deny<T: Into<StackPolicyResource>>(mut self, action: StackPolicyAction, resource: T) -> Self {
+        self.denied.push((action, resource.into().0));
+        self
+    }
+
+    pub fn build(self) -> StackPolicy {
+        // when a stack has a policy, CloudFormation denies everything that is not allowed explicitly
+        let mut statements = vec![StackPolicyStatement {
+            effect: "Allow".to_string(),
+            action: StackPolicyAction::All.as_action().to_string(),
+            principal: "*".to_string(),
+            resources: vec!["*".to_string()],
+        }];
+
+        for (action, resource_id) in self.denied {
+            let resource = format!("LogicalResourceId/{resource_id}");
+
+            match statements.iter_mut().find(|s| s.effect == "Deny" && s.action == action.as_action()) {
+                Some(statement) if statement.resources.contains(&resource) => {}
+                Some(statement) => statement.resources.push(resource),
+                None => statements.push(StackPolicyStatement {
+                    effect: "Deny".to_string(),
+                    action: action.as_action().to_string(),
+                    principal: "*".to_string(),
+                    resources: vec![resource],
+                }),
+            }
+        }
+
+        StackPolicy { statements }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::stack::StackBuilder;
+    use crate::docdb::DBClusterRef;
+    use crate::lambda::{Architecture, Code, FunctionBuilder, Runtime};
+    use crate::shared::DynamicReference;
+    use crate::sns::{TopicBuilder, TopicRef};
+    use crate::sqs::QueueBuilder;
+    use crate::stack::{StackBuilder, StackBuilderError, StackPolicyAction, StackPolicyBuilder, StackSettingsReference};
+    use crate::wrappers::{Bucket, EnvVarKey, Memory, Timeout};
+    use serde_json::{Value, json};
 
     #[test]
     fn test_check_for_duplicate_ids() {
@@ -243,4 +486,127 @@ mod tests {
 
         assert_eq!(duplicates, vec!["bucket", "topic"])
     }
+
+    #[test]
+    fn should_deny_updates_of_resources_in_stack_policy() {
+        let mut stack_builder = StackBuilder::new();
+        let queue = QueueBuilder::new("queue").standard_queue().build(&mut stack_builder);
+        let topic = TopicBuilder::new("topic").build(&mut stack_builder);
+
+        let policy = StackPolicyBuilder::new()
+            .deny(StackPolicyAction::Replace, &queue)
+            .deny(StackPolicyAction::Replace, &topic)
+            .deny(StackPolicyAction::Delete, &queue)
+            .build();
+        let stack = stack_builder
+            .termination_protection(true)
+            .stack_policy(policy)
+            .add_notification_topic(&topic)
+            .build()
+            .unwrap();
+
+        let settings = stack.get_settings().unwrap();
+        let queue_id = queue.get_resource_id();
+        let topic_id = topic.get_resource_id();
+        assert_eq!(settings.termination_protection, Some(true));
+        assert_eq!(
+            settings.notification_topics,
+            vec![StackSettingsReference::Resource(topic_id.to_string())]
+        );
+        let policy: Value = serde_json::from_str(&settings.stack_policy.as_ref().unwrap().synth()).unwrap();
+        assert_eq!(
+            policy,
+            json!({
+                "Statement": [
+                    { "Effect": "Allow", "Action": "Update:*", "Principal": "*", "Resource": ["*"] },
+                    { "Effect": "Deny", "Action": "Update:Replace", "Principal": "*", "Resource": [format!("LogicalResourceId/{queue_id}"), format!("LogicalResourceId/{topic_id}")] },
+                    { "Effect": "Deny", "Action": "Update:Delete", "Principal": "*", "Resource": [format!("LogicalResourceId/{queue_id}")] },
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn should_use_arn_of_existing_topic_and_refuse_resources_outside_stack() {
+        let existing = TopicRef::new(
+            "existing",
+            "Existing",
+            "arn:aws:sns:eu-west-1:123456789012:existing",
+            "arn:aws:sns:eu-west-1:123456789012:existing",
+        );
+        let cluster = DBClusterRef::internal_new("DBCluster123".to_string());
+
+        let stack = StackBuilder::new().add_notification_topic(&existing).build().unwrap();
+        let result = StackBuilder::new()
+            .stack_policy(StackPolicyBuilder::new().deny(StackPolicyAction::Replace, &cluster).build())
+            .build();
+
+        assert_eq!(
+            stack.get_settings().unwrap().notification_topics,
+            vec![StackSettingsReference::Arn(
+                "arn:aws:sns:eu-west-1:123456789012:existing".to_string()
+            )]
+        );
+        assert!(matches!(result, Err(StackBuilderError::InvalidStackSettings(issues)) if issues[0].contains("DBCluster123")));
+    }
+
+    #[test]
+    fn should_accept_dynamic_references_in_env_vars_and_outputs() {
+        let mut stack_builder = StackBuilder::new();
+        let parameter = DynamicReference::Ssm {
+            name: "/shared/image-tag".to_string(),
+            version: None,
+        };
+        let secret = DynamicReference::SecretsManager {
+            secret_id: "credentials".to_string(),
+            json_key: Some("password".to_string()),
+            version_stage: None,
+        };
+        FunctionBuilder::new("fun", Architecture::ARM64, Memory(128), Timeout(3))
+            .code(Code::Inline("exports.handler = async () => {};".to_string()))
+            .handler("index.handler")
+            .runtime(Runtime::NodeJs22)
+            .env_var(EnvVarKey("IMAGE_TAG".to_string()), parameter.clone())
+            .env_var(EnvVarKey("PASSWORD".to_string()), secret)
+            .build(&mut stack_builder);
+
+        let stack = stack_builder.add_output("ImageTag", parameter).build().unwrap();
+        let template = stack.synth().unwrap();
+
+        assert!(template.contains("{{resolve:ssm:/shared/image-tag}}"));
+        assert!(template.contains("{{resolve:secretsmanager:credentials:SecretString:password}}"));
+    }
+
+    #[test]
+    fn should_refuse_dynamic_references_in_tags_and_secrets_in_outputs() {
+        let secret = DynamicReference::SecretsManager {
+            secret_id: "credentials".to_string(),
+            json_key: None,
+            version_stage: None,
+        };
+
+        let result = StackBuilder::new()
+            .add_tag("Team", "{{resolve:ssm:/shared/team}}")
+            .add_output("Secret", secret)
+            .build();
+
+        assert!(matches!(result, Err(StackBuilderError::UnsupportedDynamicReferences(issues)) if issues.len() == 2));
+    }
+
+    #[test]
+    fn should_refuse_more_than_sixty_dynamic_references() {
+        let stack_builder = (0..61).fold(StackBuilder::new(), |builder, i| {
+            builder.add_output(
+                format!("Output{i}"),
+                DynamicReference::Ssm {
+                    name: format!("/parameter-{i}"),
+                    version: None,
+                },
+            )
+        });
+
+        let result = stack_builder.build();
+
+        assert!(matches!(result, Err(StackBuilderError::UnsupportedDynamicReferences(issues)) if issues[0].contains("found 61")));
+    }
 }
@@ -28,7 +28,17 @@ use std::fmt::{Display, Formatter};
 #[derive(Debug)]
 pub enum Cleanable<'a> {
     Bucket(&'a str),
+    /// A bucket with a `VersioningConfiguration`, which also requires removing object versions and delete markers
+    VersionedBucket(&'a str),
     Topic(&'a str),
+    /// An ECR repository that CloudFormation will not empty, because `EmptyOnDelete` is not enabled
+    Repository(&'a str),
+    /// A DynamoDB table, which might have deletion protection enabled (outside CloudFormation)
+    Table(&'a str),
+    /// A DocumentDB cluster with deletion protection enabled
+    DBCluster(&'a str),
+    /// A DocumentDB global cluster with deletion protection enabled
+    GlobalCluster(&'a str),
 }
 
 #[derive(Debug)]
@@ -38,13 +48,27 @@ pub struct StackDiff {
     pub new_ids: Vec<(String, String)>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Asset {
+    #[serde(rename = "bucket")]
     pub s3_bucket: String,
+    #[serde(rename = "key")]
     pub s3_key: String,
     pub path: String,
 }
 
+/// An existing resource to import into a stack, see `Stack::get_resources_to_import`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceToImport {
+    pub resource_id: String,
+    /// The CloudFormation type of the resource, e.g. `AWS::DynamoDB::Table`
+    pub resource_type: String,
+    /// The property that identifies the existing resource, e.g. `TableName`
+    pub identifier_property: String,
+    /// The physical identifier of the existing resource, e.g. the name of the table
+    pub identifier: String,
+}
+
 impl Display for Asset {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
@@ -92,13 +116,16 @@ impl Display for Asset {
 /// The stack is serialized to CloudFormation-compatible JSON format, with:
 /// - `Resources`: The AWS resources map
 /// - `Metadata`: Additional metadata for resource management
-/// - Tags are *not* serialized directly
+/// - Tags and stack settings are *not* serialized directly
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Stack {
     #[serde(skip)]
     pub(crate) resource_ids_to_replace: Vec<(String, String)>,
     #[serde(skip)]
     pub(crate) tags: Vec<(String, String)>,
+    /// `None` when the settings are unknown, for a stack that was read from a template
+    #[serde(skip)]
+    pub(crate) settings: Option<StackSettings>,
     #[serde(rename = "Resources")]
     pub(crate) resources: HashMap<String, Resource>,
     #[serde(rename = "Metadata")]
@@ -113,6 +140,94 @@ pub struct Output {
     pub(crate) value: Value,
 }
 
+/// A resource that the stack settings refer to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StackSettingsReference {
+    /// A resource in the stack, identified by its resource id. Its ARN is only known once it has been deployed
+    Resource(String),
+    /// An existing resource outside the stack
+    Arn(String),
+}
+
+impl StackSettingsReference {
+    pub(crate) fn from_arn(arn: Value, resource_id: &str) -> Self {
+        match arn {
+            Value::String(arn) => StackSettingsReference::Arn(arn),
+            _ => StackSettingsReference::Resource(resource_id.to_string()),
+        }
+    }
+}
+
+/// Settings of the stack itself, rather than of one of its resources. See `StackBuilder` for setting them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StackSettings {
+    /// `None` leaves the termination protection of an existing stack unchanged
+    pub termination_protection: Option<bool>,
+    pub stack_policy: Option<StackPolicy>,
+    /// Alarms that roll back the deployment when they go off
+    pub rollback_triggers: Vec<StackSettingsReference>,
+    pub rollback_monitoring_time_in_minutes: Option<u8>,
+    /// Topics that receive the stack events
+    pub notification_topics: Vec<StackSettingsReference>,
+}
+
+impl StackSettings {
+    pub(crate) fn replace_resource_id(&mut self, current: &str, new: &str) {
+        self.rollback_triggers
+            .iter_mut()
+            .chain(self.notification_topics.iter_mut())
+            .for_each(|r| match r {
+                StackSettingsReference::Resource(resource_id) if resource_id == current => *resource_id = new.to_string(),
+                _ => {}
+            });
+
+        if let Some(policy) = &mut self.stack_policy {
+            let current = format!("LogicalResourceId/{current}");
+            policy
+                .statements
+                .iter_mut()
+                .flat_map(|s| s.resources.iter_mut())
+                .filter(|r| **r == current)
+                .for_each(|r| *r = format!("LogicalResourceId/{new}"));
+        }
+    }
+}
+
+/// A stack policy, which protects resources against (some kinds of) updates during deployments
+///
+/// Create one with `StackPolicyBuilder`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StackPolicy {
+    #[serde(rename = "Statement")]
+    pub(crate) statements: Vec<StackPolicyStatement>,
+}
+
+impl StackPolicy {
+    pub fn synth(&self) -> String {
+        serde_json::to_string(self).expect("stack policy to be serializable")
+    }
+
+    pub(crate) fn get_resource_ids(&self) -> Vec<&str> {
+        self.statements
+            .iter()
+            .flat_map(|s| s.resources.iter())
+            .flat_map(|r| r.strip_prefix("LogicalResourceId/"))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct StackPolicyStatement {
+    #[serde(rename = "Effect")]
+    pub(crate) effect: String,
+    #[serde(rename = "Action")]
+    pub(crate) action: String,
+    #[serde(rename = "Principal")]
+    pub(crate) principal: String,
+    #[serde(rename = "Resource")]
+    pub(crate) resources: Vec<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct StackOnlyMetadata {
     #[serde(rename = "Metadata")]
@@ -124,6 +239,31 @@ impl Stack {
         self.tags.clone()
     }
 
+    /// Adds a tag, replacing the value of an existing tag with the same key.
+    ///
+    /// Tags are not part of the synthesized stack, so use this to tag a stack that was read from a synth output.
+    pub fn add_tag<T: Into<String>>(&mut self, key: T, value: T) {
+        let key = key.into();
+        let value = value.into();
+
+        match self.tags.iter_mut().find(|(existing, _)| *existing == key) {
+            Some(tag) => tag.1 = value,
+            None => self.tags.push((key, value)),
+        }
+    }
+
+    /// The settings of the stack. `None` when they are unknown, because the stack was read from a template without them
+    pub fn get_settings(&self) -> Option<&StackSettings> {
+        self.settings.as_ref()
+    }
+
+    /// Replaces the settings of the stack.
+    ///
+    /// Settings are not part of the synthesized stack, so use this to deploy a stack that was read from a synth output.
+    pub fn set_settings(&mut self, settings: StackSettings) {
+        self.settings = Some(settings);
+    }
+
     pub fn get_assets(&self) -> Vec<Asset> {
         self.resources
             .values()
@@ -175,7 +315,26 @@ impl Stack {
     /// - AWS CLI: `aws cloudformation create-stack --template-body file://template.json`
     /// - AWS SDKs: Pass the template string to the CloudFormation client
     /// - AWS Console: Upload the template file directly
+    ///
+    /// When `cargo rusty synth` runs the binary that calls this method, also call `write_synth_output`,
+    /// so the assets and settings of the stack are written next to the template, see `synth_output_dir`.
+    /// The crates of functions with `Code::CargoCrate` have to be packaged before synthesizing, see `package_cargo_crates`.
     pub fn synth(&self) -> Result<String, String> {
+        let unpackaged: Vec<_> = self
+            .resources
+            .values()
+            .flat_map(|r| match r {
+                Resource::Function(f) if !f.is_packaged() => Some(f.get_id().to_string()),
+                _ => None,
+            })
+            .collect();
+        if !unpackaged.is_empty() {
+            return Err(format!(
+                "the crates of functions {} are not packaged yet, call `Stack::package_cargo_crates` first",
+                unpackaged.join(", ")
+            ));
+        }
+
         let mut naive_synth = serde_json::to_string(self).map_err(|e| format!("Could not serialize stack: {e:#?}"))?;
         // nicer way to do this? for example, a method on each DTO to look for possible arns/refs (`Value`) and replace them if needed. referenced ids should help a bit
         self.resource_ids_to_replace.iter().for_each(|(current, new)| {
@@ -242,34 +401,65 @@ impl Stack {
     pub fn get_cleanable_resources(&'_ self) -> Vec<Cleanable<'_>> {
         self.resources
             .iter()
-            .flat_map(|(k, r)| {
-                println!("Found {k:?}");
-                match r {
-                    Resource::Bucket(b) => {
-                        if let Some(pol) = &b.update_delete_policy_dto.deletion_policy {
-                            let pol: DeletionPolicy = pol.into();
-                            match pol {
-                                DeletionPolicy::Retain => None,
-                                _ => Some(Cleanable::Bucket(k)),
-                            }
-                        } else {
-                            Some(Cleanable::Bucket(k))
-                        }
+            .flat_map(|(k, r)| match r {
+                Resource::Bucket(b) => {
+                    if Self::is_retained(&b.update_delete_policy_dto.deletion_policy) {
+                        None
+                    } else if b.properties.versioning_configuration.is_some() {
+                        Some(Cleanable::VersionedBucket(k))
+                    } else {
+                        Some(Cleanable::Bucket(k))
                     }
-                    Resource::Topic(t) => {
-                        // note: no update/delete policy yet, so only check archive property
-                        if t.properties.archive_policy.is_some() {
-                            Some(Cleanable::Topic(k))
-                        } else {
-                            None
-                        }
+                }
+                Resource::Topic(t) => {
+                    // note: no update/delete policy yet, so only check archive property
+                    if t.properties.archive_policy.is_some() {
+                        Some(Cleanable::Topic(k))
+                    } else {
+                        None
                     }
-                    _ => None,
                 }
+                // note: no update/delete policy yet for repositories and clusters
+                Resource::Repository(r) if r.properties.empty_on_delete != Some(true) => Some(Cleanable::Repository(k)),
+                Resource::Table(t) if !Self::is_retained(&t.update_delete_policy_dto.deletion_policy) => Some(Cleanable::Table(k)),
+                Resource::DocDBCluster(c) if c.properties.deletion_protection == Some(true) => Some(Cleanable::DBCluster(k)),
+                Resource::DocDBGlobalCluster(c) if c.properties.deletion_protection == Some(true) => Some(Cleanable::GlobalCluster(k)),
+                _ => None,
             })
             .collect()
     }
 
+    fn is_retained(deletion_policy: &Option<String>) -> bool {
+        matches!(
+            deletion_policy.as_ref().map(DeletionPolicy::from),
+            Some(DeletionPolicy::Retain) | Some(DeletionPolicy::RetainExceptOnCreate)
+        )
+    }
+
+    /// Returns the resources that CloudFormation keeps when the stack is deleted, because of a `Retain` (or `RetainExceptOnCreate`) deletion policy.
+    ///
+    /// These resources are orphaned after a destroy, and have to be cleaned up manually (if required).
+    ///
+    /// # Returns
+    ///
+    /// The ids of the retained resources with their resource ids, sorted by id
+    pub fn get_retained_resources(&self) -> Vec<(String, String)> {
+        let mut retained: Vec<_> = self
+            .metadata
+            .iter()
+            .filter(|(_, resource_id)| {
+                self.resources
+                    .get(*resource_id)
+                    .and_then(|r| serde_json::to_value(r).ok())
+                    .and_then(|r| r.get("DeletionPolicy").and_then(Value::as_str).map(|p| p.starts_with("Retain")))
+                    .unwrap_or(false)
+            })
+            .map(|(id, resource_id)| (id.to_string(), resource_id.to_string()))
+            .collect();
+        retained.sort();
+        retained
+    }
+
     pub fn get_diff(&self, existing_stack: &str) -> Result<StackDiff, String> {
         let meta = Self::get_metadata(existing_stack)?;
         let existing_meta = meta.metadata;
@@ -310,6 +500,211 @@ impl Stack {
         })
     }
 
+    /// Retrieves the ids of an existing stack, keyed by their resource id (the logical id in the CloudFormation template).
+    ///
+    /// Useful for linking information that CloudFormation returns about deployed resources (events, drift...) back to the ids chosen in the builders.
+    /// *This will only work if the existing stack was also created with this library.*
+    pub fn get_ids_by_resource_id(existing_stack: &str) -> Result<HashMap<String, String>, String> {
+        let meta = Self::get_metadata(existing_stack)?;
+        Ok(meta.metadata.into_iter().map(|(id, resource_id)| (resource_id, id)).collect())
+    }
+
+    /// Checks whether an existing stack was created with this library, based on its `Metadata`,
+    /// which should map every id to the resource id of one of the resources of the stack.
+    /// Stacks without resources are not recognized.
+    pub fn is_created_with_this_library(existing_stack: &str) -> bool {
+        let Ok(existing) = serde_json::from_str::<Value>(existing_stack) else {
+            return false;
+        };
+
+        match (
+            existing.get("Metadata").and_then(Value::as_object),
+            existing.get("Resources").and_then(Value::as_object),
+        ) {
+            (Some(metadata), Some(resources)) => {
+                !metadata.is_empty()
+                    && metadata
+                        .values()
+                        .all(|resource_id| resource_id.as_str().is_some_and(|r| resources.contains_key(r)))
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks whether the changes compared to an existing stack can be 'hotswapped', i.e. applied without a CloudFormation deployment.
+    ///
+    /// This is only the case when the only changes are to the (zip) code of Lambda functions.
+    /// Note that every synthesis generates new asset keys for `Code::Zip`, so all functions with zip code are considered changed.
+    /// Functions with `Code::CargoCrate` are only considered changed when their sources changed.
+    /// *This will only work if the existing stack was also created with this library.*
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(..))` - The resource ids of the functions to update, with the asset containing their new code
+    /// * `Ok(None)` - The changes require a full deployment
+    /// * `Err(String)` - An error message if one of the templates could not be parsed
+    pub fn get_hotswappable_assets(&mut self, existing_stack: &str) -> Result<Option<Vec<(String, Asset)>>, String> {
+        let new: Value = serde_json::from_str(&self.synth_for_existing(existing_stack)?)
+            .map_err(|e| format!("Could not parse synthesized stack: {}", e))?;
+        let existing: Value = serde_json::from_str(existing_stack).map_err(|e| format!("Could not parse existing stack: {}", e))?;
+
+        if new.get("Outputs") != existing.get("Outputs") {
+            return Ok(None);
+        }
+
+        let empty = serde_json::Map::new();
+        let new_resources = new.get("Resources").and_then(Value::as_object).unwrap_or(&empty);
+        let existing_resources = existing.get("Resources").and_then(Value::as_object).unwrap_or(&empty);
+
+        if new_resources.len() != existing_resources.len() {
+            return Ok(None);
+        }
+
+        let mut assets = vec![];
+
+        for (resource_id, new_resource) in new_resources {
+            let Some(existing_resource) = existing_resources.get(resource_id) else {
+                return Ok(None);
+            };
+
+            if new_resource == existing_resource {
+                continue;
+            }
+            if Self::without_code(new_resource) != Self::without_code(existing_resource) {
+                return Ok(None);
+            }
+
+            match self.resources.get(resource_id) {
+                Some(Resource::Function(Function { asset: Some(asset), .. })) => assets.push((resource_id.to_string(), asset.clone())),
+                // inline code, or asset information that was lost (for example when the stack was read from a template)
+                _ => return Ok(None),
+            }
+        }
+
+        Ok(Some(assets))
+    }
+
+    /// Prepares importing existing resources (for example a DynamoDB table or S3 bucket created outside CloudFormation) into a stack.
+    ///
+    /// CloudFormation requires every imported resource to have a `Retain` deletion policy,
+    /// and does not allow creating other resources during an import.
+    /// So all resources of the stack should either be imported or already exist in the `existing_stack`.
+    /// *This will only work if the existing stack was also created with this library.*
+    ///
+    /// # Parameters
+    ///
+    /// * `identifiers_by_id` - The physical identifiers of the existing resources (table name, bucket name, ...), keyed by the id chosen in the builder
+    /// * `existing_stack` - The existing stack, as a CloudFormation template JSON string, or `None` when the import creates a new stack
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((String, Vec<ResourceToImport>))` - The template to use for the import, with the resources to import
+    /// * `Err(String)` - An error message if a resource cannot be imported
+    pub fn get_resources_to_import(
+        &mut self,
+        identifiers_by_id: &HashMap<String, String>,
+        existing_stack: Option<&str>,
+    ) -> Result<(String, Vec<ResourceToImport>), String> {
+        let template = match existing_stack {
+            None => self.synth()?,
+            Some(existing_stack) => self.synth_for_existing(existing_stack)?,
+        };
+        let new: Value = serde_json::from_str(&template).map_err(|e| format!("Could not parse synthesized stack: {}", e))?;
+        let existing: Option<Value> = existing_stack
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(|e| format!("Could not parse existing stack: {}", e))?;
+
+        let empty = serde_json::Map::new();
+        let new_resources = new.get("Resources").and_then(Value::as_object).unwrap_or(&empty);
+        let existing_resources = existing
+            .as_ref()
+            .and_then(|e| e.get("Resources"))
+            .and_then(Value::as_object)
+            .unwrap_or(&empty);
+
+        let mut ids: Vec<_> = identifiers_by_id.keys().collect();
+        ids.sort();
+
+        let mut to_import = vec![];
+
+        for id in ids {
+            let resource_id = self
+                .metadata
+                .get(id)
+                .ok_or_else(|| format!("no resource with id {id} in the stack"))?;
+            if existing_resources.contains_key(resource_id) {
+                return Err(format!("resource with id {id} is already part of the stack"));
+            }
+            let resource = new_resources
+                .get(resource_id)
+                .ok_or_else(|| format!("resource with id {id} not found in the template"))?;
+            let resource_type = resource.get("Type").and_then(Value::as_str).unwrap_or_default();
+            let identifier_property = Self::get_identifier_property(resource_type)
+                .ok_or_else(|| format!("resources of type {resource_type} cannot be imported"))?;
+            if resource.get("DeletionPolicy").and_then(Value::as_str) != Some("Retain") {
+                return Err(format!(
+                    "resource with id {id} should have a `Retain` deletion policy to be imported"
+                ));
+            }
+
+            to_import.push(ResourceToImport {
+                resource_id: resource_id.to_string(),
+                resource_type: resource_type.to_string(),
+                identifier_property: identifier_property.to_string(),
+                identifier: identifiers_by_id.get(id).expect("id to come from the map keys").to_string(),
+            });
+        }
+
+        let mut others: Vec<_> = new_resources
+            .keys()
+            .filter(|resource_id| {
+                !existing_resources.contains_key(*resource_id) && !to_import.iter().any(|r| &r.resource_id == *resource_id)
+            })
+            .map(|resource_id| {
+                self.metadata
+                    .iter()
+                    .find(|(_, r)| *r == resource_id)
+                    .map(|(id, _)| id.to_string())
+                    .unwrap_or_else(|| resource_id.to_string())
+            })
+            .collect();
+        others.sort();
+
+        if !others.is_empty() {
+            return Err(format!(
+                "an import cannot create new resources, import these resources as well or add them in a later deployment: {}",
+                others.join(", ")
+            ));
+        }
+
+        Ok((template, to_import))
+    }
+
+    fn get_identifier_property(resource_type: &str) -> Option<&'static str> {
+        match resource_type {
+            "AWS::DynamoDB::Table" => Some("TableName"),
+            "AWS::S3::Bucket" => Some("BucketName"),
+            "AWS::SQS::Queue" => Some("QueueUrl"),
+            "AWS::SNS::Topic" => Some("TopicArn"),
+            "AWS::Logs::LogGroup" => Some("LogGroupName"),
+            "AWS::Lambda::Function" => Some("FunctionName"),
+            "AWS::IAM::Role" => Some("RoleName"),
+            "AWS::SecretsManager::Secret" => Some("Id"),
+            "AWS::ECR::Repository" => Some("RepositoryName"),
+            _ => None,
+        }
+    }
+
+    fn without_code(resource: &Value) -> Option<Value> {
+        if resource.get("Type").and_then(Value::as_str) != Some("AWS::Lambda::Function") {
+            return None;
+        }
+        let mut resource = resource.clone();
+        resource.get_mut("Properties")?.as_object_mut()?.remove("Code");
+        Some(resource)
+    }
+
     fn update_resource_ids_for_existing_stack(&mut self, existing_ids_with_resource_ids: HashMap<String, String>) {
         let still_existing_after_proposed_changes: Vec<_> = existing_ids_with_resource_ids
             .into_iter()
@@ -333,6 +728,9 @@ impl Stack {
                     .expect("resource to exist in stack resources");
                 self.resources.insert(existing_resource_id.clone(), removed);
                 self.metadata.insert(existing_id, existing_resource_id.clone());
+                if let Some(settings) = &mut self.settings {
+                    settings.replace_resource_id(&current_stack_resource_id, &existing_resource_id);
+                }
                 self.resource_ids_to_replace
                     .push((current_stack_resource_id.to_string(), existing_resource_id));
             });
@@ -580,11 +978,29 @@ from_resource!(TopicPolicy);
 
 #[cfg(test)]
 mod tests {
+    use crate::lambda::{Architecture, Code, FunctionBuilder, Runtime, Zip};
+    use crate::s3::BucketBuilder;
+    use crate::shared::{DeletionPolicy, UpdateReplacePolicy};
     use crate::sns::TopicBuilder;
     use crate::sqs::QueueBuilder;
-    use crate::stack::StackBuilder;
+    use crate::stack::{Stack, StackBuilder, StackPolicyAction, StackPolicyBuilder, StackSettingsReference};
+    use crate::wrappers::{Bucket, Memory, Timeout, ZipFile};
     use std::collections::HashMap;
 
+    fn stack_with_function(memory: u16) -> Stack {
+        let mut stack_builder = StackBuilder::new();
+        TopicBuilder::new("topic").build(&mut stack_builder);
+        FunctionBuilder::new("fun", Architecture::ARM64, Memory(memory), Timeout(30))
+            .code(Code::Zip(Zip::new(
+                Bucket("some-bucket".to_string()),
+                ZipFile("./example.zip".to_string()),
+            )))
+            .handler("bootstrap")
+            .runtime(Runtime::ProvidedAl2023)
+            .build(&mut stack_builder);
+        stack_builder.build().unwrap()
+    }
+
     #[test]
     fn should_do_nothing_for_empty_stack_and_empty_existing_ids() {
         let mut stack_builder = StackBuilder::new().build().unwrap();
@@ -661,4 +1077,175 @@ mod tests {
         assert_eq!(diff.ids_to_be_removed, vec![("bucket".to_string(), "Bucket234".to_string())]);
         assert_eq!(diff.unchanged_ids, vec![("queue".to_string(), "Queue123".to_string())]);
     }
+
+    #[test]
+    fn should_map_resource_ids_to_ids() {
+        let ids =
+            Stack::get_ids_by_resource_id(r#"{"Metadata": { "queue": "Queue123", "bucket": "Bucket234" } }"#).expect("metadata to be read");
+
+        assert_eq!(ids.len(), 2);
+        assert_eq!(ids.get("Queue123").unwrap(), "queue");
+        assert_eq!(ids.get("Bucket234").unwrap(), "bucket");
+    }
+
+    #[test]
+    fn should_recognize_stacks_created_with_this_library() {
+        let stack = stack_with_function(256).synth().unwrap();
+
+        assert!(Stack::is_created_with_this_library(&stack));
+        assert!(!Stack::is_created_with_this_library(
+            r#"{"Resources": { "Queue123": { "Type": "AWS::SQS::Queue" } } }"#
+        ));
+        assert!(!Stack::is_created_with_this_library(
+            r#"{"Metadata": { "AWS::CloudFormation::Interface": { "ParameterGroups": [] } }, "Resources": { "Queue123": { "Type": "AWS::SQS::Queue" } } }"#
+        ));
+        assert!(!Stack::is_created_with_this_library(
+            r#"{"Metadata": { "queue": "Queue234" }, "Resources": { "Queue123": { "Type": "AWS::SQS::Queue" } } }"#
+        ));
+        assert!(!Stack::is_created_with_this_library(
+            "Resources:\n  Queue123:\n    Type: AWS::SQS::Queue"
+        ));
+    }
+
+    #[test]
+    fn should_return_assets_when_only_function_code_changed() {
+        let existing = stack_with_function(256).synth().unwrap();
+        let mut stack = stack_with_function(256);
+
+        let assets = stack
+            .get_hotswappable_assets(&existing)
+            .unwrap()
+            .expect("changes to be hotswappable");
+
+        assert_eq!(assets.len(), 1);
+        assert_eq!(&assets[0].0, stack.metadata.get("fun").unwrap());
+        assert_eq!(assets[0].1.path, "./example.zip");
+    }
+
+    #[test]
+    fn should_return_none_when_function_configuration_changed() {
+        let existing = stack_with_function(256).synth().unwrap();
+        let mut stack = stack_with_function(512);
+
+        let assets = stack.get_hotswappable_assets(&existing).unwrap();
+
+        assert!(assets.is_none());
+    }
+
+    #[test]
+    fn should_return_none_when_resource_was_added() {
+        let mut stack_builder = StackBuilder::new();
+        TopicBuilder::new("topic").build(&mut stack_builder);
+        let existing = stack_builder.build().unwrap().synth().unwrap();
+        let mut stack = stack_with_function(256);
+
+        let assets = stack.get_hotswappable_assets(&existing).unwrap();
+
+        assert!(assets.is_none());
+    }
+
+    fn stack_with_bucket(deletion_policy: DeletionPolicy) -> Stack {
+        let mut stack_builder = StackBuilder::new();
+        BucketBuilder::new("bucket")
+            .update_replace_and_deletion_policy(UpdateReplacePolicy::Retain, deletion_policy)
+            .build(&mut stack_builder);
+        stack_builder.build().unwrap()
+    }
+
+    #[test]
+    fn should_return_resources_to_import() {
+        let mut stack = stack_with_bucket(DeletionPolicy::Retain);
+        let identifiers = HashMap::from([("bucket".to_string(), "my-existing-bucket".to_string())]);
+
+        let (_, to_import) = stack.get_resources_to_import(&identifiers, None).expect("bucket to be importable");
+
+        assert_eq!(to_import.len(), 1);
+        assert_eq!(&to_import[0].resource_id, stack.metadata.get("bucket").unwrap());
+        assert_eq!(to_import[0].resource_type, "AWS::S3::Bucket");
+        assert_eq!(to_import[0].identifier_property, "BucketName");
+        assert_eq!(to_import[0].identifier, "my-existing-bucket");
+    }
+
+    #[test]
+    fn should_refuse_import_without_retain_policy() {
+        let mut stack = stack_with_bucket(DeletionPolicy::Delete);
+        let identifiers = HashMap::from([("bucket".to_string(), "my-existing-bucket".to_string())]);
+
+        let result = stack.get_resources_to_import(&identifiers, None);
+
+        assert!(result.unwrap_err().contains("Retain"));
+    }
+
+    #[test]
+    fn should_refuse_import_that_creates_other_resources() {
+        let mut stack = stack_with_bucket(DeletionPolicy::Retain);
+        let identifiers = HashMap::new();
+
+        let result = stack.get_resources_to_import(&identifiers, None);
+
+        assert!(result.unwrap_err().ends_with("bucket"));
+    }
+
+    #[test]
+    fn should_return_retained_resources() {
+        let mut stack_builder = StackBuilder::new();
+        BucketBuilder::new("retained")
+            .update_replace_and_deletion_policy(UpdateReplacePolicy::Retain, DeletionPolicy::Retain)
+            .build(&mut stack_builder);
+        BucketBuilder::new("deleted").build(&mut stack_builder);
+        let stack = stack_builder.build().unwrap();
+
+        let retained = stack.get_retained_resources();
+
+        assert_eq!(
+            retained,
+            vec![("retained".to_string(), stack.metadata.get("retained").unwrap().to_string())]
+        );
+    }
+
+    #[test]
+    fn should_use_existing_resource_ids_in_stack_settings() {
+        let stack_with_policy = || {
+            let mut stack_builder = StackBuilder::new();
+            let topic = TopicBuilder::new("topic").build(&mut stack_builder);
+            let policy = StackPolicyBuilder::new().deny(StackPolicyAction::Replace, &topic).build();
+            stack_builder.stack_policy(policy).add_notification_topic(&topic).build().unwrap()
+        };
+        let existing = stack_with_policy().synth().unwrap();
+        let existing_topic_id = Stack::get_ids_by_resource_id(&existing)
+            .unwrap()
+            .into_iter()
+            .find(|(_, id)| id == "topic")
+            .map(|(resource_id, _)| resource_id)
+            .unwrap();
+        let mut stack = stack_with_policy();
+
+        stack.synth_for_existing(&existing).unwrap();
+
+        let settings = stack.get_settings().unwrap();
+        assert_eq!(
+            settings.notification_topics,
+            vec![StackSettingsReference::Resource(existing_topic_id.clone())]
+        );
+        assert_eq!(
+            settings.stack_policy.as_ref().unwrap().get_resource_ids(),
+            vec![existing_topic_id.as_str()]
+        );
+    }
+
+    #[test]
+    fn should_replace_value_of_existing_tag() {
+        let mut stack = StackBuilder::new().add_tag("team", "platform").build().unwrap();
+
+        stack.add_tag("team", "payments");
+        stack.add_tag("env", "prod");
+
+        assert_eq!(
+            stack.get_tags(),
+            vec![
+                ("team".to_string(), "payments".to_string()),
+                ("env".to_string(), "prod".to_string())
+            ]
+        );
+    }
 }
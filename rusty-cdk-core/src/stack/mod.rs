@@ -1,5 +1,9 @@
 mod builder;
 mod dto;
+mod security;
+mod synth_output;
 
 pub use builder::*;
 pub use dto::*;
+pub use security::*;
+pub use synth_output::*;
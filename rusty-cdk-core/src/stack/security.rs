@@ -0,0 +1,352 @@
+use crate::stack::Stack;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+const PUBLIC_ACCESS_BLOCK_SETTINGS: [&str; 4] = ["BlockPublicAcls", "BlockPublicPolicy", "IgnorePublicAcls", "RestrictPublicBuckets"];
+
+/// A change that gives more access to resources (or to the outside world), see `Stack::get_security_diff`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SecurityChange {
+    /// A new statement in the (inline or trust) policies of a role
+    IamStatement { id: String, statement: String },
+    /// A managed policy that is attached to a role
+    ManagedPolicy { id: String, arn: String },
+    /// A principal that is allowed access by a bucket, queue or topic policy
+    ResourcePolicyPrincipal { id: String, principal: String },
+    /// A principal that is allowed to invoke a Lambda function
+    InvokePermission { id: String, principal: String },
+    /// A public access block setting of a bucket that is turned off
+    PublicAccessBlock { id: String, setting: String },
+}
+
+impl Display for SecurityChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecurityChange::IamStatement { id, statement } => write!(f, "role {id} gets a new statement: {statement}"),
+            SecurityChange::ManagedPolicy { id, arn } => write!(f, "role {id} gets managed policy {arn}"),
+            SecurityChange::ResourcePolicyPrincipal { id, principal } => write!(f, "policy {id} gives access to {principal}"),
+            SecurityChange::InvokePermission { id, principal } => {
+                write!(f, "permission {id} allows {principal} to invoke a function")
+            }
+            SecurityChange::PublicAccessBlock { id, setting } => write!(f, "bucket {id} turns off {setting}"),
+        }
+    }
+}
+
+/// The security-sensitive changes between a deployed stack and a new version of that stack
+#[derive(Debug, Default)]
+pub struct SecurityDiff {
+    pub changes: Vec<SecurityChange>,
+}
+
+impl SecurityDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub(crate) fn new(existing: &Value, new: &Value) -> Self {
+        let ids: HashMap<&str, &str> = new
+            .get("Metadata")
+            .and_then(Value::as_object)
+            .map(|m| {
+                m.iter()
+                    .flat_map(|(id, resource_id)| resource_id.as_str().map(|r| (r, id.as_str())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let empty = serde_json::Map::new();
+        let new_resources = new.get("Resources").and_then(Value::as_object).unwrap_or(&empty);
+        let existing_resources = existing.get("Resources").and_then(Value::as_object).unwrap_or(&empty);
+
+        let changes = new_resources
+            .iter()
+            .flat_map(|(resource_id, resource)| {
+                let id = ids.get(resource_id.as_str()).copied().unwrap_or(resource_id.as_str()).to_string();
+                let resource_type = resource.get("Type").and_then(Value::as_str).unwrap_or_default();
+                // a resource with the same resource id but another type is a new resource as well
+                let existing = existing_resources
+                    .get(resource_id)
+                    .filter(|e| e.get("Type").and_then(Value::as_str) == Some(resource_type))
+                    .unwrap_or(&Value::Null);
+
+                match resource_type {
+                    "AWS::IAM::Role" => Self::role_changes(id, existing, resource),
+                    "AWS::S3::BucketPolicy" | "AWS::SQS::QueuePolicy" | "AWS::SNS::TopicPolicy" => {
+                        Self::resource_policy_changes(id, existing, resource)
+                    }
+                    "AWS::Lambda::Permission" => Self::permission_changes(id, existing, resource),
+                    "AWS::S3::Bucket" => Self::public_access_block_changes(id, existing, resource),
+                    _ => vec![],
+                }
+            })
+            .collect();
+
+        Self { changes }
+    }
+
+    fn role_changes(id: String, existing: &Value, new: &Value) -> Vec<SecurityChange> {
+        let existing_statements = Self::role_statements(existing);
+        let existing_arns = Self::managed_policy_arns(existing);
+
+        let statements = Self::role_statements(new)
+            .into_iter()
+            .filter(|s| !existing_statements.contains(s))
+            .map(|s| SecurityChange::IamStatement {
+                id: id.clone(),
+                statement: s.to_string(),
+            });
+        let arns = Self::managed_policy_arns(new)
+            .into_iter()
+            .filter(|a| !existing_arns.contains(a))
+            .map(|arn| SecurityChange::ManagedPolicy { id: id.clone(), arn });
+
+        statements.chain(arns).collect()
+    }
+
+    fn role_statements(role: &Value) -> Vec<&Value> {
+        let properties = &role["Properties"];
+        let trust_statements = Self::statements(&properties["AssumeRolePolicyDocument"]);
+        let policy_statements = properties["Policies"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|p| Self::statements(&p["PolicyDocument"]));
+        trust_statements.into_iter().chain(policy_statements).collect()
+    }
+
+    fn managed_policy_arns(role: &Value) -> Vec<String> {
+        role["Properties"]["ManagedPolicyArns"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(Self::as_string)
+            .collect()
+    }
+
+    fn resource_policy_changes(id: String, existing: &Value, new: &Value) -> Vec<SecurityChange> {
+        let existing_principals = Self::allowed_principals(existing);
+        let mut principals = Self::allowed_principals(new);
+        principals.retain(|p| !existing_principals.contains(p));
+        principals.dedup();
+
+        principals
+            .into_iter()
+            .map(|principal| SecurityChange::ResourcePolicyPrincipal { id: id.clone(), principal })
+            .collect()
+    }
+
+    fn allowed_principals(policy: &Value) -> Vec<String> {
+        let mut principals: Vec<_> = Self::statements(&policy["Properties"]["PolicyDocument"])
+            .into_iter()
+            .filter(|s| s["Effect"] == "Allow")
+            .flat_map(|s| match &s["Principal"] {
+                Value::Object(principal) => principal
+                    .iter()
+                    .flat_map(|(kind, values)| match values {
+                        Value::Array(values) => values.iter().map(|v| format!("{kind} {}", Self::as_string(v))).collect(),
+                        value => vec![format!("{kind} {}", Self::as_string(value))],
+                    })
+                    .collect(),
+                Value::Null => vec![],
+                principal => vec![Self::as_string(principal)],
+            })
+            .collect();
+        principals.sort();
+        principals
+    }
+
+    fn permission_changes(id: String, existing: &Value, new: &Value) -> Vec<SecurityChange> {
+        let properties = &new["Properties"];
+
+        if properties["Action"] == "lambda:InvokeFunction" && existing["Properties"] != *properties {
+            vec![SecurityChange::InvokePermission {
+                id,
+                principal: Self::as_string(&properties["Principal"]),
+            }]
+        } else {
+            vec![]
+        }
+    }
+
+    fn public_access_block_changes(id: String, existing: &Value, new: &Value) -> Vec<SecurityChange> {
+        // buckets block public access unless told otherwise
+        let is_blocked = |bucket: &Value, setting: &str| {
+            bucket["Properties"]["PublicAccessBlockConfiguration"][setting]
+                .as_bool()
+                .unwrap_or(true)
+        };
+
+        PUBLIC_ACCESS_BLOCK_SETTINGS
+            .iter()
+            .filter(|setting| is_blocked(existing, setting) && !is_blocked(new, setting))
+            .map(|setting| SecurityChange::PublicAccessBlock {
+                id: id.clone(),
+                setting: setting.to_string(),
+            })
+            .collect()
+    }
+
+    fn statements(document: &Value) -> Vec<&Value> {
+        document["Statement"].as_array().into_iter().flatten().collect()
+    }
+
+    fn as_string(value: &Value) -> String {
+        match value {
+            Value::String(s) => s.to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+impl Stack {
+    /// Looks for changes that give more access, compared to an existing stack (or to an empty stack when it does not exist yet):
+    /// - new statements in the policies of roles, and newly attached managed policies
+    /// - new principals in bucket, queue and topic policies
+    /// - new or changed permissions to invoke Lambda functions
+    /// - public access block settings of buckets that are turned off
+    ///
+    /// Like `synth_for_existing`, this keeps the resource ids of the existing stack.
+    /// *This will only work if the existing stack was also created with this library.*
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rusty_cdk_core::stack::StackBuilder;
+    /// use rusty_cdk_core::sqs::QueueBuilder;
+    ///
+    /// let mut stack_builder = StackBuilder::new();
+    /// QueueBuilder::new("my-queue").standard_queue().build(&mut stack_builder);
+    /// let mut stack = stack_builder.build().unwrap();
+    ///
+    /// let diff = stack.get_security_diff(None).unwrap();
+    /// assert!(diff.is_empty());
+    /// ```
+    pub fn get_security_diff(&mut self, existing_stack: Option<&str>) -> Result<SecurityDiff, String> {
+        let (existing, new) = match existing_stack {
+            Some(existing_stack) => {
+                let new = self.synth_for_existing(existing_stack)?;
+                let existing = serde_json::from_str(existing_stack).map_err(|e| format!("Could not parse existing stack: {}", e))?;
+                (existing, new)
+            }
+            None => (Value::Null, self.synth()?),
+        };
+        let new: Value = serde_json::from_str(&new).map_err(|e| format!("Could not parse synthesized stack: {}", e))?;
+
+        Ok(SecurityDiff::new(&existing, &new))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stack::{SecurityChange, SecurityDiff};
+    use serde_json::{Value, json};
+
+    fn role(statements: Value, managed_policy_arns: Value) -> Value {
+        json!({
+            "Type": "AWS::IAM::Role",
+            "Properties": {
+                "AssumeRolePolicyDocument": {
+                    "Version": "2012-10-17",
+                    "Statement": [{ "Effect": "Allow", "Action": ["sts:AssumeRole"], "Principal": { "Service": "lambda.amazonaws.com" } }]
+                },
+                "ManagedPolicyArns": managed_policy_arns,
+                "Policies": [{ "PolicyName": "Policy", "PolicyDocument": { "Version": "2012-10-17", "Statement": statements } }]
+            }
+        })
+    }
+
+    fn template(resources: Value) -> Value {
+        json!({ "Resources": resources, "Metadata": { "role": "Role123", "policy": "Policy123", "bucket": "Bucket123" } })
+    }
+
+    #[test]
+    fn should_find_new_role_statements_and_managed_policies() {
+        let read = json!({ "Effect": "Allow", "Action": ["s3:GetObject"], "Resource": ["*"] });
+        let write = json!({ "Effect": "Allow", "Action": ["s3:PutObject"], "Resource": ["*"] });
+        let existing = template(json!({ "Role123": role(json!([read]), json!([])) }));
+        let new = template(json!({ "Role123": role(json!([read, write]), json!(["arn:aws:iam::aws:policy/AdministratorAccess"])) }));
+
+        let diff = SecurityDiff::new(&existing, &new);
+
+        assert_eq!(
+            diff.changes,
+            vec![
+                SecurityChange::IamStatement {
+                    id: "role".to_string(),
+                    statement: write.to_string()
+                },
+                SecurityChange::ManagedPolicy {
+                    id: "role".to_string(),
+                    arn: "arn:aws:iam::aws:policy/AdministratorAccess".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_find_new_principals_of_resource_policy() {
+        let policy = |principals: Value| {
+            json!({
+                "Type": "AWS::SQS::QueuePolicy",
+                "Properties": {
+                    "Queues": [],
+                    "PolicyDocument": {
+                        "Version": "2012-10-17",
+                        "Statement": [{ "Effect": "Allow", "Action": ["sqs:SendMessage"], "Principal": principals, "Resource": ["*"] }]
+                    }
+                }
+            })
+        };
+        let existing = template(json!({ "Policy123": policy(json!({ "Service": "sns.amazonaws.com" })) }));
+        let new = template(json!({ "Policy123": policy(json!({ "Service": ["sns.amazonaws.com", "events.amazonaws.com"] })) }));
+
+        let diff = SecurityDiff::new(&existing, &new);
+
+        assert_eq!(
+            diff.changes,
+            vec![SecurityChange::ResourcePolicyPrincipal {
+                id: "policy".to_string(),
+                principal: "Service events.amazonaws.com".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn should_find_new_invoke_permissions_and_relaxed_public_access_block() {
+        let permission = json!({
+            "Type": "AWS::Lambda::Permission",
+            "Properties": { "Action": "lambda:InvokeFunction", "FunctionName": "fun", "Principal": "s3.amazonaws.com" }
+        });
+        let bucket = json!({
+            "Type": "AWS::S3::Bucket",
+            "Properties": { "PublicAccessBlockConfiguration": { "BlockPublicPolicy": false, "RestrictPublicBuckets": true } }
+        });
+
+        let diff = SecurityDiff::new(&Value::Null, &template(json!({ "Permission123": permission, "Bucket123": bucket })));
+
+        assert_eq!(
+            diff.changes,
+            vec![
+                SecurityChange::PublicAccessBlock {
+                    id: "bucket".to_string(),
+                    setting: "BlockPublicPolicy".to_string()
+                },
+                SecurityChange::InvokePermission {
+                    id: "Permission123".to_string(),
+                    principal: "s3.amazonaws.com".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_not_report_unchanged_stack() {
+        let statement = json!({ "Effect": "Allow", "Action": ["s3:GetObject"], "Resource": ["*"] });
+        let stack = template(json!({ "Role123": role(json!([statement]), json!(["arn:aws:iam::aws:policy/ReadOnlyAccess"])) }));
+
+        let diff = SecurityDiff::new(&stack, &stack);
+
+        assert!(diff.is_empty());
+    }
+}
@@ -0,0 +1,221 @@
+use crate::lambda::Function;
+use crate::stack::{Asset, Resource, Stack};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::write;
+use std::path::{Path, PathBuf};
+
+/// `cargo rusty synth` sets this env var for the synth binary, pointing to the directory where `Stack::write_synth_output` should write to.
+/// It reads the files back when deploying the synth output
+pub const SYNTH_OUTPUT_DIR_ENV_VAR_NAME: &str = "RUSTY_CDK_SYNTH_OUTPUT_DIR";
+/// File in the synth output directory with the Lambda zip files, and the bucket and key they are uploaded to
+pub const ASSETS_FILE: &str = "assets.json";
+/// File in the synth output directory with the `StackSettings` (termination protection, stack policy, rollback triggers...)
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// The directory the synth binary should write its output to, when it is run by `cargo rusty`
+///
+/// # Example
+///
+/// ```
+/// use rusty_cdk_core::stack::{StackBuilder, synth_output_dir};
+///
+/// let mut stack = StackBuilder::new().build().unwrap();
+///
+/// if let Some(dir) = synth_output_dir() {
+///     stack.write_synth_output(dir).unwrap();
+/// }
+/// println!("{}", stack.synth().unwrap());
+/// ```
+pub fn synth_output_dir() -> Option<PathBuf> {
+    env::var_os(SYNTH_OUTPUT_DIR_ENV_VAR_NAME).map(PathBuf::from)
+}
+
+/// The assets of the functions of a stack, by the resource id of the function
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AssetManifest {
+    pub assets: Vec<FunctionAsset>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionAsset {
+    pub resource_id: String,
+    #[serde(flatten)]
+    pub asset: Asset,
+}
+
+impl Stack {
+    /// The assets of the functions of the stack, by resource id
+    pub fn get_asset_manifest(&self) -> AssetManifest {
+        let mut assets: Vec<_> = self
+            .resources
+            .iter()
+            .flat_map(|(resource_id, resource)| match resource {
+                Resource::Function(Function { asset: Some(asset), .. }) => Some(FunctionAsset {
+                    resource_id: resource_id.clone(),
+                    asset: asset.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+        assets.sort_by(|a, b| a.resource_id.cmp(&b.resource_id));
+
+        AssetManifest { assets }
+    }
+
+    /// Attaches the assets of a manifest to the functions of the stack.
+    ///
+    /// Assets are not part of the synthesized stack, so use this to deploy a stack that was read from a synth output.
+    pub fn attach_assets(&mut self, manifest: AssetManifest) -> Result<(), String> {
+        for FunctionAsset { resource_id, asset } in manifest.assets {
+            match self.resources.get_mut(&resource_id) {
+                Some(Resource::Function(function)) => function.asset = Some(asset),
+                _ => {
+                    return Err(format!(
+                        "asset {asset} belongs to {resource_id}, which is not a function of the stack"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds and zips the crates of the functions with `Code::CargoCrate` (see `Code::CargoCrate` for the details).
+    ///
+    /// Building crates is slow, so it is not part of `StackBuilder::build`.
+    /// `write_synth_output`, and `deploy`, `hotswap`, `security_diff` and `deploy_stack_set` of `rusty_cdk` package the crates themselves,
+    /// call this before using `synth` directly. Crates that were already packaged are skipped.
+    pub fn package_cargo_crates(&mut self) -> Result<(), String> {
+        let issues: Vec<_> = self
+            .resources
+            .values_mut()
+            .flat_map(|r| match r {
+                Resource::Function(f) => f.package_cargo_crate().err(),
+                _ => None,
+            })
+            .collect();
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "could not package the code of one or more functions: `{}`",
+                issues.join(";")
+            ))
+        }
+    }
+
+    /// Writes the files of the synth output that are not part of the template to the directory:
+    /// the assets of the functions (`assets.json`) and the settings of the stack (`settings.json`).
+    /// The crates of functions with `Code::CargoCrate` are packaged first, so their zips are part of the assets (and of the template synthesized afterwards).
+    ///
+    /// `cargo rusty` needs these files to deploy a synth output, see `synth_output_dir`.
+    pub fn write_synth_output(&mut self, dir: impl AsRef<Path>) -> Result<(), String> {
+        let dir = dir.as_ref();
+        self.package_cargo_crates()?;
+
+        let assets = serde_json::to_string_pretty(&self.get_asset_manifest()).map_err(|e| format!("Could not serialize assets: {e}"))?;
+        let path = dir.join(ASSETS_FILE);
+        write(&path, assets).map_err(|e| format!("Could not write assets to {}: {e}", path.display()))?;
+
+        let settings = serde_json::to_string_pretty(&self.settings).map_err(|e| format!("Could not serialize stack settings: {e}"))?;
+        let path = dir.join(SETTINGS_FILE);
+        write(&path, settings).map_err(|e| format!("Could not write stack settings to {}: {e}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lambda::{Architecture, Code, FunctionBuilder, Runtime, Zip};
+    use crate::stack::{ASSETS_FILE, AssetManifest, SETTINGS_FILE, Stack, StackBuilder};
+    use crate::wrappers::{Bucket, Memory, Timeout, ZipFile};
+    use std::env::temp_dir;
+    use std::fs::{create_dir_all, read_to_string, remove_dir_all};
+
+    fn stack_with_function() -> Stack {
+        let mut stack_builder = StackBuilder::new();
+        FunctionBuilder::new("fun", Architecture::ARM64, Memory(256), Timeout(30))
+            .code(Code::Zip(Zip::new(
+                Bucket("some-bucket".to_string()),
+                ZipFile("./example.zip".to_string()),
+            )))
+            .handler("bootstrap")
+            .runtime(Runtime::ProvidedAl2023)
+            .build(&mut stack_builder);
+        stack_builder.build().unwrap()
+    }
+
+    #[test]
+    fn should_attach_assets_to_stack_read_from_template() {
+        let stack = stack_with_function();
+        let manifest = serde_json::to_string(&stack.get_asset_manifest()).unwrap();
+        let mut read: Stack = serde_json::from_str(&stack.synth().unwrap()).unwrap();
+        assert!(read.get_assets().is_empty());
+
+        read.attach_assets(serde_json::from_str::<AssetManifest>(&manifest).unwrap())
+            .unwrap();
+
+        let assets = read.get_assets();
+        assert_eq!(assets.len(), 1);
+        assert_eq!(assets[0].path, "./example.zip");
+        assert_eq!(assets[0].s3_bucket, "some-bucket");
+        assert_eq!(assets[0].s3_key, stack.get_assets()[0].s3_key);
+    }
+
+    #[test]
+    fn should_refuse_assets_of_unknown_functions() {
+        let mut manifest = stack_with_function().get_asset_manifest();
+        manifest.assets[0].resource_id = "Unknown123".to_string();
+
+        assert!(stack_with_function().attach_assets(manifest).is_err());
+    }
+
+    #[test]
+    fn should_write_assets_and_settings_to_dir() {
+        let mut stack = stack_with_function();
+        let dir = temp_dir().join(format!("rusty-cdk-synth-output-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+
+        stack.write_synth_output(&dir).unwrap();
+
+        let manifest: AssetManifest = serde_json::from_str(&read_to_string(dir.join(ASSETS_FILE)).unwrap()).unwrap();
+        let has_settings = dir.join(SETTINGS_FILE).is_file();
+        remove_dir_all(&dir).unwrap();
+        assert_eq!(manifest.assets.len(), 1);
+        assert_eq!(manifest.assets[0].asset.path, "./example.zip");
+        assert!(has_settings);
+    }
+
+    fn stack_with_crate(path: &str) -> Stack {
+        let mut stack_builder = StackBuilder::new();
+        FunctionBuilder::new("fun", Architecture::ARM64, Memory(128), Timeout(3))
+            .code(Code::CargoCrate {
+                bucket: Bucket("some-bucket".to_string()),
+                path: path.to_string(),
+                bin: None,
+                target: None,
+            })
+            .handler("bootstrap")
+            .runtime(Runtime::ProvidedAl2023)
+            .build(&mut stack_builder);
+        stack_builder.build().unwrap()
+    }
+
+    #[test]
+    fn should_not_package_crates_when_building_stack() {
+        let stack = stack_with_crate("./does-not-exist");
+
+        let result = stack.synth();
+
+        assert!(result.is_err_and(|e| e.contains("package_cargo_crates")));
+    }
+
+    #[test]
+    fn should_return_packaging_issue_when_crate_of_function_does_not_exist() {
+        let mut stack = stack_with_crate("./does-not-exist");
+
+        let result = stack.package_cargo_crates();
+
+        assert!(result.is_err_and(|e| e.contains("does-not-exist")));
+    }
+}
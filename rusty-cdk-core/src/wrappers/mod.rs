@@ -566,6 +566,21 @@ pub struct AppConfigName(pub String);
 #[derive(Debug, Clone)]
 pub struct DeploymentDurationInMinutes(pub u16);
 
+/// Minutes that CloudFormation keeps monitoring the rollback triggers of a stack after a deployment (0 to 180).
+///
+/// # Recommended Usage
+/// Use the `rollback_monitoring_time!` macro from `rusty-cdk-macros` for compile-time validation:
+///
+/// ```rust
+/// use rusty_cdk_core::wrappers::RollbackMonitoringTime;
+/// use rusty_cdk_macros::rollback_monitoring_time;
+///
+/// let time = rollback_monitoring_time!(30);
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct RollbackMonitoringTime(pub u8);
+
 /// Growth factor percentage for AWS AppConfig deployment strategies.
 ///
 /// This wrapper configures the percentage of targets to receive the configuration
@@ -907,4 +922,22 @@ pub struct Period(pub u32);
 pub struct CloudwatchMetricName(pub String);
 
 #[derive(Debug, Clone)]
-pub struct CloudwatchAlarmName(pub String);
\ No newline at end of file
+pub struct CloudwatchAlarmName(pub String);
+
+/// A wrapper for AWS account ids, used for cross-account configuration like ECR replication and IAM principals.
+///
+/// # Validation Rules (when using the macro)
+/// - Must be exactly 12 digits
+///
+/// # Recommended Usage
+/// Use the `account_id!` macro from `rusty-cdk-macros` for compile-time validation,
+/// or `lookup_account_id!()` from `rusty-cdk-lookups` for the account of your credentials:
+///
+/// ```rust
+/// use rusty_cdk_core::wrappers::AccountId;
+/// use rusty_cdk_macros::account_id;
+///
+/// let account = account_id!("123456789012");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountId(pub String);
\ No newline at end of file
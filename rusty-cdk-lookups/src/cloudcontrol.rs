@@ -1,13 +1,15 @@
 use aws_sdk_cloudcontrol::Client;
 use aws_sdk_cloudcontrol::types::ResourceDescription;
-use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct ResourceInfo {
     pub(crate) identifier: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct ResourceInfoWithArn {
     pub(crate) identifier: String,
     pub(crate) arn: String,
@@ -31,7 +33,7 @@ impl CloudControlClient {
         }
     }
 
-    async fn get_internal_resource(&self, identifier: &str, type_name: &str) -> Result<(String, InternalResourceDescription), String> {
+    async fn get_internal_resource<T: DeserializeOwned>(&self, identifier: &str, type_name: &str) -> Result<(String, T), String> {
         let result = self
             .client
             .get_resource()
@@ -55,7 +57,7 @@ impl CloudControlClient {
     }
 
     pub(crate) async fn get_resource_arn(&self, identifier: &str, type_name: &str) -> Result<ResourceInfoWithArn, String> {
-        let (identifier, internal) = self.get_internal_resource(identifier, type_name).await?;
+        let (identifier, internal): (_, InternalResourceDescription) = self.get_internal_resource(identifier, type_name).await?;
 
         if let InternalResourceDescription { arn: Some(arn), .. } = internal {
             Ok(ResourceInfoWithArn { identifier, arn })
@@ -65,10 +67,76 @@ impl CloudControlClient {
     }
 
     pub(crate) async fn get_resource(&self, identifier: &str, type_name: &str) -> Result<ResourceInfo, String> {
-        let (identifier, _internal) = self.get_internal_resource(identifier, type_name).await?;
+        let (identifier, _internal): (_, InternalResourceDescription) = self.get_internal_resource(identifier, type_name).await?;
 
         Ok(ResourceInfo { identifier })
     }
+
+    pub(crate) async fn get_resource_properties(&self, identifier: &str, type_name: &str) -> Result<Value, String> {
+        let (_identifier, properties) = self.get_internal_resource(identifier, type_name).await?;
+        Ok(properties)
+    }
+
+    /// Lists the resources of a type, returning the properties of every resource.
+    ///
+    /// Some types only return their identifier when listed, in which case the resource is retrieved when `required_property` is missing.
+    pub(crate) async fn list_resources(
+        &self,
+        type_name: &str,
+        resource_model: Option<Value>,
+        required_property: &str,
+    ) -> Result<Vec<Value>, String> {
+        let resource_model = resource_model.map(|m| m.to_string());
+        let mut next_token = None;
+        let mut resources = vec![];
+
+        loop {
+            let result = self
+                .client
+                .list_resources()
+                .type_name(type_name)
+                .set_resource_model(resource_model.clone())
+                .set_next_token(next_token)
+                .send()
+                .await
+                .map_err(|e| format!("could not list {type_name}: {e}"))?;
+
+            for description in result.resource_descriptions.unwrap_or_default() {
+                let (identifier, properties) = match description {
+                    ResourceDescription {
+                        identifier: Some(id),
+                        properties: Some(props),
+                        ..
+                    } => (
+                        id,
+                        serde_json::from_str::<Value>(&props).map_err(|_| "could not read resource info")?,
+                    ),
+                    _ => return Err("missing required resource info".to_string()),
+                };
+
+                if properties.get(required_property).is_some() {
+                    resources.push(properties);
+                } else {
+                    resources.push(self.get_resource_properties(&identifier, type_name).await?);
+                }
+            }
+
+            next_token = result.next_token;
+            if next_token.is_none() {
+                break;
+            }
+        }
+
+        Ok(resources)
+    }
+}
+
+/// A string property of a resource, which should be present
+pub(crate) fn string_property(resource: &Value, property: &str) -> Result<String, String> {
+    resource[property]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| format!("resource info is missing {property}"))
 }
 
 pub(crate) async fn lookup_arn(identifier: &str, type_name: &str) -> Result<ResourceInfoWithArn, String> {
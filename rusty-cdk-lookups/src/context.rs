@@ -0,0 +1,185 @@
+use aws_sdk_sts::Client as StsClient;
+use proc_macro::TokenStream;
+use rusty_cdk_core::context::{CONTEXT_FILE, LookupKey, read_lookups, update_lookups};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, absolute};
+
+const NO_REMOTE_OVERRIDE_ENV_VAR_NAME: &str = "RUSTY_CDK_NO_REMOTE";
+const RUSTY_CDK_RECHECK_ENV_VAR_NAME: &str = "RUSTY_CDK_RECHECK";
+const RUSTY_CDK_ACCOUNT_ENV_VAR_NAME: &str = "RUSTY_CDK_ACCOUNT";
+const REGION_ENV_VAR_NAMES: [&str; 2] = ["AWS_REGION", "AWS_DEFAULT_REGION"];
+
+/// The account and region the lookups are done in, as far as they are known
+struct LookupEnvironment {
+    account: Option<String>,
+    region: Option<String>,
+    /// Why the account or region could not be determined
+    problem: Option<String>,
+}
+
+impl LookupEnvironment {
+    /// The account comes from `RUSTY_CDK_ACCOUNT` or the credentials, the region from the AWS config.
+    /// Offline, only the environment variables are used.
+    async fn resolve(offline: bool) -> Self {
+        let account = env::var(RUSTY_CDK_ACCOUNT_ENV_VAR_NAME).ok();
+
+        if offline {
+            let region = REGION_ENV_VAR_NAMES.iter().find_map(|name| env::var(name).ok());
+            return Self {
+                account,
+                region,
+                problem: Some(format!("{NO_REMOTE_OVERRIDE_ENV_VAR_NAME} is set")),
+            };
+        }
+
+        let config = aws_config::load_from_env().await;
+        let region = config.region().map(|r| r.to_string());
+        let account = match account {
+            Some(account) => Ok(account),
+            None => StsClient::new(&config)
+                .get_caller_identity()
+                .send()
+                .await
+                .map_err(|e| format!("could not retrieve the account of the credentials: {e}"))
+                .and_then(|identity| identity.account.ok_or_else(|| "credentials have no account".to_string())),
+        };
+
+        match (account, region) {
+            (Ok(account), Some(region)) => Self {
+                account: Some(account),
+                region: Some(region),
+                problem: None,
+            },
+            (Ok(account), None) => Self {
+                account: Some(account),
+                region: None,
+                problem: Some("no region configured".to_string()),
+            },
+            (Err(e), region) => Self {
+                account: None,
+                region,
+                problem: Some(e),
+            },
+        }
+    }
+}
+
+/// The account and region of the current credentials and AWS config
+pub(crate) async fn current_environment() -> Result<(String, String), String> {
+    match LookupEnvironment::resolve(false).await {
+        LookupEnvironment {
+            account: Some(account),
+            region: Some(region),
+            problem: None,
+        } => Ok((account, region)),
+        LookupEnvironment { problem, .. } => Err(problem.unwrap_or_else(|| "unknown environment".to_string())),
+    }
+}
+
+/// The lookups in the context file, keyed by `<account>:<region>:<kind>:<identifier>`
+struct Context {
+    lookups: BTreeMap<String, Value>,
+}
+
+impl Context {
+    fn read() -> Result<Self, String> {
+        read_lookups(Path::new(CONTEXT_FILE)).map(|lookups| Self { lookups })
+    }
+
+    /// Finds the lookup for the environment. When the account or region is unknown, all of them are considered,
+    /// as long as they agree on the result
+    fn find(&self, environment: &LookupEnvironment, kind: &str, identifier: &str) -> Result<Option<&Value>, String> {
+        let matching: Vec<_> = self
+            .lookups
+            .iter()
+            .filter(|(key, _)| match LookupKey::parse(key) {
+                Some(key) => {
+                    key.kind == kind
+                        && key.identifier == identifier
+                        && environment.account.as_deref().is_none_or(|a| a == key.account)
+                        && environment.region.as_deref().is_none_or(|r| r == key.region)
+                }
+                None => false,
+            })
+            .collect();
+
+        match matching.as_slice() {
+            [] => Ok(None),
+            [(_, value), rest @ ..] if rest.iter().all(|(_, v)| v == value) => Ok(Some(value)),
+            _ => Err(format!(
+                "{CONTEXT_FILE} contains different results for {kind} {identifier} ({}), set {RUSTY_CDK_ACCOUNT_ENV_VAR_NAME} and AWS_REGION to choose",
+                matching.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>().join(", ")
+            )),
+        }
+    }
+}
+
+/// Returns the result of the lookup from the context file, or does the lookup and adds its result to the file.
+///
+/// - with `RUSTY_CDK_NO_REMOTE=true`, or when there are no valid credentials, only the context file is used
+/// - with `RUSTY_CDK_RECHECK=true`, the lookup is always done (when possible), and the file is updated
+pub(crate) async fn lookup_with_context<T, F, Fut>(kind: &str, identifier: &str, lookup: F) -> Result<T, String>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    let offline = env_var_is_true(NO_REMOTE_OVERRIDE_ENV_VAR_NAME);
+    let environment = LookupEnvironment::resolve(offline).await;
+    let context = Context::read()?;
+
+    let replay_only = environment.problem.is_some();
+    let cached = if replay_only || !env_var_is_true(RUSTY_CDK_RECHECK_ENV_VAR_NAME) {
+        context.find(&environment, kind, identifier)?
+    } else {
+        None
+    };
+    if let Some(value) = cached {
+        return serde_json::from_value(value.clone()).map_err(|e| format!("{CONTEXT_FILE} contains an invalid {kind}: {e}"));
+    }
+
+    let (account, region) = match (&environment.account, &environment.region, &environment.problem) {
+        (Some(account), Some(region), None) => (account, region),
+        (_, _, problem) => {
+            return Err(format!(
+                "did not find {kind} {identifier} in {CONTEXT_FILE}, and could not look it up ({}). Build with valid credentials to add it",
+                problem.as_deref().unwrap_or("unknown environment")
+            ));
+        }
+    };
+
+    let result = lookup().await?;
+    let value = serde_json::to_value(&result).expect("lookup result to be serializable");
+    let key = LookupKey {
+        account: account.clone(),
+        region: region.clone(),
+        kind: kind.to_string(),
+        identifier: identifier.to_string(),
+    };
+
+    // read the file again under its lock, the lookups of other crates might have been added in the meantime
+    update_lookups(Path::new(CONTEXT_FILE), |lookups| lookups.insert(key.into(), value))?;
+
+    Ok(result)
+}
+
+/// Adds a dependency on the context file to the output of a macro, so the lookups are done again when the file changes
+/// (for example after `cargo rusty context --clear`)
+pub(crate) fn with_context_dependency(output: TokenStream) -> TokenStream {
+    let path = Path::new(CONTEXT_FILE);
+
+    match absolute(path) {
+        Ok(path) if path.exists() => format!("{{ const _: &[u8] = include_bytes!({:?}); {output} }}", path.to_string_lossy())
+            .parse()
+            .expect("output to be valid tokens"),
+        _ => output,
+    }
+}
+
+fn env_var_is_true(name: &str) -> bool {
+    env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(false)
+}
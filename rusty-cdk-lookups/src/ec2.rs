@@ -0,0 +1,327 @@
+use crate::cloudcontrol::{CloudControlClient, string_property};
+use crate::context::{lookup_with_context, with_context_dependency};
+use crate::parsing::{ResourceSelector, SecurityGroupInput, SubnetSelector, SubnetType, SubnetsInput};
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{BTreeSet, HashMap};
+use syn::Error;
+
+const VPC_TYPE: &str = "AWS::EC2::VPC";
+const SUBNET_TYPE: &str = "AWS::EC2::Subnet";
+const SECURITY_GROUP_TYPE: &str = "AWS::EC2::SecurityGroup";
+const ROUTE_TABLE_ASSOCIATION_TYPE: &str = "AWS::EC2::SubnetRouteTableAssociation";
+const ROUTE_TYPE: &str = "AWS::EC2::Route";
+// VPCs created by the AWS CDK tag their subnets with their type
+const CDK_SUBNET_TYPE_TAG: &str = "aws-cdk:subnet-type";
+
+#[derive(Serialize, Deserialize)]
+struct SubnetInfo {
+    subnet_id: String,
+    availability_zone: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SecurityGroupInfo {
+    group_id: String,
+    vpc_id: String,
+}
+
+pub(crate) async fn find_vpc(selector: &ResourceSelector) -> Result<TokenStream, Error> {
+    let vpc_id: String = lookup_with_context("vpc", &selector_key(selector), || find_vpc_id(selector))
+        .await
+        .map_err(|e| Error::new(Span::call_site(), e))?;
+
+    Ok(with_context_dependency(
+        quote!(
+            VpcRef::new(#vpc_id)
+        )
+        .into(),
+    ))
+}
+
+async fn find_vpc_id(selector: &ResourceSelector) -> Result<String, String> {
+    let client = CloudControlClient::new().await;
+
+    match selector {
+        ResourceSelector::Id(vpc_id) => {
+            let vpc = client.get_resource(vpc_id, VPC_TYPE).await?;
+            Ok(vpc.identifier)
+        }
+        ResourceSelector::Tag(key, value) => {
+            let vpcs: Vec<_> = client
+                .list_resources(VPC_TYPE, None, "VpcId")
+                .await?
+                .into_iter()
+                .filter(|v| has_tag(v, key, value))
+                .collect();
+
+            match vpcs.as_slice() {
+                [vpc] => string_property(vpc, "VpcId"),
+                [] => Err(format!("did not find a VPC with tag {key}={value}")),
+                _ => Err(format!("found {} VPCs with tag {key}={value}, expected one", vpcs.len())),
+            }
+        }
+    }
+}
+
+pub(crate) async fn find_subnets(input: &SubnetsInput) -> Result<TokenStream, Error> {
+    let key = match &input.selector {
+        SubnetSelector::All => input.vpc_id.clone(),
+        SubnetSelector::Type(subnet_type) => format!("{}/{}", input.vpc_id, format!("{subnet_type:?}").to_lowercase()),
+        SubnetSelector::Tag(key, value) => format!("{}/tag:{key}={value}", input.vpc_id),
+    };
+    let subnets: Vec<SubnetInfo> = lookup_with_context("subnets", &key, || async {
+        let subnets = find_subnets_of_vpc(input).await?;
+        subnets
+            .iter()
+            .map(|s| {
+                Ok(SubnetInfo {
+                    subnet_id: string_property(s, "SubnetId")?,
+                    availability_zone: string_property(s, "AvailabilityZone")?,
+                })
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| Error::new(Span::call_site(), e))?;
+
+    let subnet_ids = subnets.iter().map(|s| &s.subnet_id);
+    let availability_zones = subnets.iter().map(|s| &s.availability_zone);
+    let vpc_id = &input.vpc_id;
+
+    Ok(with_context_dependency(
+        quote!(vec![#(SubnetRef::new(#subnet_ids, #vpc_id, #availability_zones)),*]).into(),
+    ))
+}
+
+async fn find_subnets_of_vpc(input: &SubnetsInput) -> Result<Vec<Value>, String> {
+    let client = CloudControlClient::new().await;
+    let mut subnets: Vec<_> = client
+        .list_resources(SUBNET_TYPE, None, "VpcId")
+        .await?
+        .into_iter()
+        .filter(|s| s["VpcId"].as_str() == Some(input.vpc_id.as_str()))
+        .collect();
+    subnets.sort_by(|a, b| {
+        (a["AvailabilityZone"].as_str(), a["SubnetId"].as_str()).cmp(&(b["AvailabilityZone"].as_str(), b["SubnetId"].as_str()))
+    });
+
+    if subnets.is_empty() {
+        return Err(format!("did not find any subnets in VPC {}", input.vpc_id));
+    }
+
+    match &input.selector {
+        SubnetSelector::All => Ok(subnets),
+        SubnetSelector::Tag(key, value) => {
+            let subnets: Vec<_> = subnets.into_iter().filter(|s| has_tag(s, key, value)).collect();
+            if subnets.is_empty() {
+                Err(format!("did not find any subnets with tag {key}={value} in VPC {}", input.vpc_id))
+            } else {
+                Ok(subnets)
+            }
+        }
+        SubnetSelector::Type(subnet_type) => {
+            let types = subnet_types(&client, &subnets).await?;
+            let unknown = subnets.len() - types.len();
+            let subnets: Vec<_> = subnets
+                .into_iter()
+                .filter(|s| s["SubnetId"].as_str().and_then(|id| types.get(id)) == Some(subnet_type))
+                .collect();
+
+            if subnets.is_empty() {
+                Err(format!(
+                    "did not find any {} subnets in VPC {} ({unknown} subnets use the main route table and could not be classified, tag them with {CDK_SUBNET_TYPE_TAG} or look them up by tag)",
+                    format!("{subnet_type:?}").to_lowercase(),
+                    input.vpc_id
+                ))
+            } else {
+                Ok(subnets)
+            }
+        }
+    }
+}
+
+/// Determines the type of the subnets, by their `aws-cdk:subnet-type` tag, or else by the routes of their route table.
+/// The main route table of a VPC cannot be retrieved with Cloud Control, so subnets without their own route table are only classified as public when they assign public IPs.
+async fn subnet_types(client: &CloudControlClient, subnets: &[Value]) -> Result<HashMap<String, SubnetType>, String> {
+    let route_tables: HashMap<_, _> = client
+        .list_resources(ROUTE_TABLE_ASSOCIATION_TYPE, None, "SubnetId")
+        .await?
+        .iter()
+        .map(|a| Ok((string_property(a, "SubnetId")?, string_property(a, "RouteTableId")?)))
+        .collect::<Result<_, String>>()?;
+
+    let mut route_table_types = HashMap::new();
+    let mut types = HashMap::new();
+
+    for subnet in subnets {
+        let subnet_id = string_property(subnet, "SubnetId")?;
+
+        let subnet_type = match (subnet_type_from_tag(subnet), route_tables.get(&subnet_id)) {
+            (Some(subnet_type), _) => Some(subnet_type),
+            (None, Some(route_table_id)) => {
+                if !route_table_types.contains_key(route_table_id) {
+                    let routes = client
+                        .list_resources(ROUTE_TYPE, Some(json!({ "RouteTableId": route_table_id })), "RouteTableId")
+                        .await?;
+                    route_table_types.insert(route_table_id.clone(), subnet_type_from_routes(&routes));
+                }
+                route_table_types.get(route_table_id).copied()
+            }
+            (None, None) if subnet["MapPublicIpOnLaunch"].as_bool() == Some(true) => Some(SubnetType::Public),
+            (None, None) => None,
+        };
+
+        if let Some(subnet_type) = subnet_type {
+            types.insert(subnet_id, subnet_type);
+        }
+    }
+
+    Ok(types)
+}
+
+fn subnet_type_from_tag(subnet: &Value) -> Option<SubnetType> {
+    match tag_value(subnet, CDK_SUBNET_TYPE_TAG).as_deref() {
+        Some("Public") => Some(SubnetType::Public),
+        Some("Private") => Some(SubnetType::Private),
+        Some("Isolated") => Some(SubnetType::Isolated),
+        _ => None,
+    }
+}
+
+/// Subnets with a default route to an internet gateway are public, those with another default route (a NAT gateway for example) are private
+fn subnet_type_from_routes(routes: &[Value]) -> SubnetType {
+    let default_route = routes
+        .iter()
+        .find(|r| r["DestinationCidrBlock"].as_str() == Some("0.0.0.0/0") || r["DestinationIpv6CidrBlock"].as_str() == Some("::/0"));
+
+    match default_route {
+        Some(route) if route["GatewayId"].as_str().is_some_and(|g| g.starts_with("igw-")) => SubnetType::Public,
+        Some(_) => SubnetType::Private,
+        None => SubnetType::Isolated,
+    }
+}
+
+pub(crate) async fn find_security_group(input: &SecurityGroupInput) -> Result<TokenStream, Error> {
+    let key = match input {
+        SecurityGroupInput::Id(group_id) => group_id.clone(),
+        SecurityGroupInput::Name { vpc_id, name } => format!("{vpc_id}/{name}"),
+    };
+    let SecurityGroupInfo { group_id, vpc_id } = lookup_with_context("security-group", &key, || async {
+        let group = find_security_group_properties(input).await?;
+        Ok(SecurityGroupInfo {
+            group_id: string_property(&group, "GroupId")?,
+            vpc_id: string_property(&group, "VpcId")?,
+        })
+    })
+    .await
+    .map_err(|e| Error::new(Span::call_site(), e))?;
+
+    Ok(with_context_dependency(
+        quote!(
+            SecurityGroupRef::new(#group_id, #vpc_id)
+        )
+        .into(),
+    ))
+}
+
+async fn find_security_group_properties(input: &SecurityGroupInput) -> Result<Value, String> {
+    let client = CloudControlClient::new().await;
+
+    match input {
+        SecurityGroupInput::Id(group_id) => client.get_resource_properties(group_id, SECURITY_GROUP_TYPE).await,
+        SecurityGroupInput::Name { vpc_id, name } => client
+            .list_resources(SECURITY_GROUP_TYPE, None, "GroupName")
+            .await?
+            .into_iter()
+            .find(|g| g["VpcId"].as_str() == Some(vpc_id.as_str()) && g["GroupName"].as_str() == Some(name.as_str()))
+            .ok_or_else(|| format!("did not find a security group with name {name} in VPC {vpc_id}")),
+    }
+}
+
+pub(crate) async fn find_availability_zones(vpc_id: Option<&str>) -> Result<TokenStream, Error> {
+    let zones: BTreeSet<String> = lookup_with_context("availability-zones", vpc_id.unwrap_or("all"), || {
+        find_availability_zone_names(vpc_id)
+    })
+    .await
+    .map_err(|e| Error::new(Span::call_site(), e))?;
+
+    Ok(with_context_dependency(quote!(vec![#(AvailabilityZoneRef::new(#zones)),*]).into()))
+}
+
+/// Cloud Control has no availability zone type, so the zones are those of the subnets (of the VPC, if one was passed in).
+/// The default VPC has a subnet in every zone of the region.
+async fn find_availability_zone_names(vpc_id: Option<&str>) -> Result<BTreeSet<String>, String> {
+    let client = CloudControlClient::new().await;
+    let zones: BTreeSet<_> = client
+        .list_resources(SUBNET_TYPE, None, "AvailabilityZone")
+        .await?
+        .iter()
+        .filter(|s| vpc_id.is_none_or(|v| s["VpcId"].as_str() == Some(v)))
+        .map(|s| string_property(s, "AvailabilityZone"))
+        .collect::<Result<_, String>>()?;
+
+    if zones.is_empty() {
+        Err("did not find any subnets to retrieve availability zones from".to_string())
+    } else {
+        Ok(zones)
+    }
+}
+
+fn selector_key(selector: &ResourceSelector) -> String {
+    match selector {
+        ResourceSelector::Id(id) => id.clone(),
+        ResourceSelector::Tag(key, value) => format!("tag:{key}={value}"),
+    }
+}
+
+fn tag_value(resource: &Value, key: &str) -> Option<String> {
+    resource["Tags"]
+        .as_array()?
+        .iter()
+        .find(|t| t["Key"].as_str() == Some(key))
+        .and_then(|t| t["Value"].as_str())
+        .map(str::to_string)
+}
+
+fn has_tag(resource: &Value, key: &str, value: &str) -> bool {
+    tag_value(resource, key).is_some_and(|v| v == value)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cloudcontrol::string_property;
+    use crate::ec2::{subnet_type_from_routes, subnet_type_from_tag};
+    use crate::parsing::SubnetType;
+    use serde_json::json;
+
+    #[test]
+    fn should_classify_subnets_by_default_route() {
+        let public = [json!({ "DestinationCidrBlock": "0.0.0.0/0", "GatewayId": "igw-123" })];
+        let private = [json!({ "DestinationCidrBlock": "0.0.0.0/0", "NatGatewayId": "nat-123" })];
+        let isolated = [json!({ "DestinationCidrBlock": "10.0.0.0/16", "GatewayId": "local" })];
+
+        assert_eq!(subnet_type_from_routes(&public), SubnetType::Public);
+        assert_eq!(subnet_type_from_routes(&private), SubnetType::Private);
+        assert_eq!(subnet_type_from_routes(&isolated), SubnetType::Isolated);
+    }
+
+    #[test]
+    fn should_classify_subnets_by_cdk_tag() {
+        let subnet = json!({ "SubnetId": "subnet-123", "Tags": [{ "Key": "aws-cdk:subnet-type", "Value": "Isolated" }] });
+
+        assert_eq!(subnet_type_from_tag(&subnet), Some(SubnetType::Isolated));
+        assert_eq!(subnet_type_from_tag(&json!({ "SubnetId": "subnet-123" })), None);
+    }
+
+    #[test]
+    fn should_refuse_missing_properties() {
+        let subnet = json!({ "SubnetId": "subnet-123" });
+
+        assert_eq!(string_property(&subnet, "SubnetId").unwrap(), "subnet-123");
+        assert!(string_property(&subnet, "AvailabilityZone").is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use crate::context::{current_environment, lookup_with_context, with_context_dependency};
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use rusty_cdk_core::shared::Region;
+use syn::Error;
+
+const CURRENT: &str = "current";
+
+pub(crate) async fn find_account_id() -> Result<TokenStream, Error> {
+    let account: String = lookup_with_context("account-id", CURRENT, || async {
+        current_environment().await.map(|(account, _)| account)
+    })
+    .await
+    .map_err(|e| Error::new(Span::call_site(), e))?;
+
+    Ok(with_context_dependency(
+        quote!(
+            AccountId(#account.to_string())
+        )
+        .into(),
+    ))
+}
+
+pub(crate) async fn find_current_region() -> Result<TokenStream, Error> {
+    let region: String = lookup_with_context("region", CURRENT, || async {
+        current_environment().await.map(|(_, region)| region)
+    })
+    .await
+    .map_err(|e| Error::new(Span::call_site(), e))?;
+    let variant = format_ident!("{}", region_variant(&region).map_err(|e| Error::new(Span::call_site(), e))?);
+
+    Ok(with_context_dependency(
+        quote!(
+            Region::#variant
+        )
+        .into(),
+    ))
+}
+
+/// The name of the `Region` variant, e.g. `EuWest1` for `eu-west-1`.
+/// Regions without a variant (like those of China and GovCloud) are refused
+fn region_variant(region: &str) -> Result<String, String> {
+    Region::try_from(region)
+        .map(|region| format!("{region:?}"))
+        .map_err(|_| format!("current region {region} is not one of the regions that rusty-cdk supports"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::region_variant;
+
+    #[test]
+    fn should_return_variant_of_known_region() {
+        assert_eq!(region_variant("eu-west-1").unwrap(), "EuWest1");
+        assert_eq!(region_variant("ap-southeast-7").unwrap(), "ApSoutheast7");
+    }
+
+    #[test]
+    fn should_refuse_unknown_regions() {
+        assert!(region_variant("cn-north-1").unwrap_err().contains("cn-north-1"));
+        assert!(region_variant("us-gov-west-1").is_err());
+    }
+}
@@ -1,24 +1,39 @@
-use crate::parsing::GenericInput;
+use crate::ec2::{find_availability_zones, find_security_group, find_subnets, find_vpc};
+use crate::environment::{find_account_id, find_current_region};
+use crate::parsing::{AvailabilityZonesInput, GenericInput, MessagingInput, ResourceSelector, RoleInput, SecurityGroupInput, SubnetsInput};
+use crate::resources::{find_bucket_ref, find_function_ref, find_log_group_ref, find_queue_ref, find_table_ref, find_topic_ref};
 use crate::roles::{find_kms_ref, find_role_ref, find_secret_ref, find_user_ref};
+use crate::ssm::find_ssm_parameter;
 use proc_macro::TokenStream;
-use syn::parse_macro_input;
+use syn::{LitStr, parse_macro_input};
 
 mod cloudcontrol;
+mod context;
+mod ec2;
+mod environment;
 mod parsing;
+mod resources;
 mod roles;
+mod ssm;
 
 /// Tries to retrieve IAM role information from your AWS environment (the role ARN).
 /// This ensures that the role actually exists in your account and that your deployment will not fail.
 ///
 /// You should pass on a unique id for the role, as well as the role name, separated by a comma: `lookup_role_ref!("SomeId","SomeRoleName")`
+///
+/// Optionally, declare what you expect of the role, and the lookup fails when the role does not meet those expectations:
+/// - `trusts`: principals (services, accounts, roles) that should be allowed to assume the role, according to its trust policy.
+///   An account id matches the root ARN of the account, a role or user is trusted when its account is, and wildcards and unconditional `Deny` statements are taken into account
+/// - `allows`: actions that the policies of the role should allow, checked with the IAM policy simulator (for all resources, and requiring `iam:SimulatePrincipalPolicy`)
+///
+/// For example: `lookup_role_ref!("SomeId", "SomeRoleName", trusts = "lambda.amazonaws.com", allows = ["dynamodb:PutItem", "dynamodb:GetItem"])`
 #[proc_macro]
 pub fn lookup_role_ref(input: TokenStream) -> TokenStream {
-    let input: GenericInput = parse_macro_input!(input);
+    let input: RoleInput = parse_macro_input!(input);
 
     let rt = tokio::runtime::Runtime::new().unwrap();
 
-    rt.block_on(find_role_ref(&input.resource_id, &input.identifier))
-        .unwrap_or_else(|e| e.into_compile_error().into())
+    rt.block_on(find_role_ref(&input)).unwrap_or_else(|e| e.into_compile_error().into())
 }
 
 /// Tries to retrieve IAM user information from your AWS environment (the user ARN).
@@ -61,4 +76,186 @@ pub fn lookup_secret_ref(input: TokenStream) -> TokenStream {
 
     rt.block_on(find_secret_ref(&input.resource_id, &input.identifier))
         .unwrap_or_else(|e| e.into_compile_error().into())
-}
\ No newline at end of file
+}
+
+/// Tries to retrieve DynamoDB table information from your AWS environment (the table ARN).
+/// This ensures that the table actually exists in your account and that your deployment will not fail.
+///
+/// You should pass on a unique id for the table, as well as the table name, separated by a comma: `lookup_table_ref!("SomeId","some-table")`
+#[proc_macro]
+pub fn lookup_table_ref(input: TokenStream) -> TokenStream {
+    let input: GenericInput = parse_macro_input!(input);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(find_table_ref(&input.resource_id, &input.identifier))
+        .unwrap_or_else(|e| e.into_compile_error().into())
+}
+
+/// Tries to retrieve SQS queue information from your AWS environment (the queue URL and ARN).
+/// This ensures that the queue actually exists in your account and that your deployment will not fail.
+///
+/// You should pass on a unique id for the queue, as well as the queue name or URL, separated by a comma: `lookup_queue_ref!("SomeId","some-queue")`.
+/// Optionally, add the type you expect, `standard` or `fifo`, to check the queue before you use it, e.g. with `sqs_event_source_mapping`: `lookup_queue_ref!("SomeId","some-queue.fifo", fifo)`
+#[proc_macro]
+pub fn lookup_queue_ref(input: TokenStream) -> TokenStream {
+    let input: MessagingInput = parse_macro_input!(input);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(find_queue_ref(&input))
+        .unwrap_or_else(|e| e.into_compile_error().into())
+}
+
+/// Tries to retrieve SNS topic information from your AWS environment (the topic ARN).
+/// This ensures that the topic actually exists in your account and that your deployment will not fail.
+///
+/// You should pass on a unique id for the topic, as well as the topic name or ARN, separated by a comma: `lookup_topic_ref!("SomeId","some-topic")`.
+/// Optionally, add the type you expect, `standard` or `fifo`: `lookup_topic_ref!("SomeId","some-topic.fifo", fifo)`
+#[proc_macro]
+pub fn lookup_topic_ref(input: TokenStream) -> TokenStream {
+    let input: MessagingInput = parse_macro_input!(input);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(find_topic_ref(&input))
+        .unwrap_or_else(|e| e.into_compile_error().into())
+}
+
+/// Tries to retrieve S3 bucket information from your AWS environment (the bucket ARN).
+/// This ensures that the bucket actually exists in your account and that your deployment will not fail.
+///
+/// You should pass on a unique id for the bucket, as well as the bucket name, separated by a comma: `lookup_bucket_ref!("SomeId","some-bucket")`
+#[proc_macro]
+pub fn lookup_bucket_ref(input: TokenStream) -> TokenStream {
+    let input: GenericInput = parse_macro_input!(input);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(find_bucket_ref(&input.resource_id, &input.identifier))
+        .unwrap_or_else(|e| e.into_compile_error().into())
+}
+
+/// Tries to retrieve Lambda function information from your AWS environment (the function ARN).
+/// This ensures that the function actually exists in your account and that your deployment will not fail.
+///
+/// You should pass on a unique id for the function, as well as the function name, separated by a comma: `lookup_function_ref!("SomeId","some-function")`
+#[proc_macro]
+pub fn lookup_function_ref(input: TokenStream) -> TokenStream {
+    let input: GenericInput = parse_macro_input!(input);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(find_function_ref(&input.resource_id, &input.identifier))
+        .unwrap_or_else(|e| e.into_compile_error().into())
+}
+
+/// Tries to retrieve CloudWatch log group information from your AWS environment (the log group ARN).
+/// This ensures that the log group actually exists in your account and that your deployment will not fail.
+///
+/// You should pass on a unique id for the log group, as well as the log group name, separated by a comma: `lookup_log_group_ref!("SomeId","/some/log-group")`
+#[proc_macro]
+pub fn lookup_log_group_ref(input: TokenStream) -> TokenStream {
+    let input: GenericInput = parse_macro_input!(input);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(find_log_group_ref(&input.resource_id, &input.identifier))
+        .unwrap_or_else(|e| e.into_compile_error().into())
+}
+
+/// Retrieves the id of the account of your credentials (or `RUSTY_CDK_ACCOUNT`), returning an `AccountId`: `lookup_account_id!()`.
+/// Useful for configuration that needs a literal account id, like the destinations of ECR replication.
+#[proc_macro]
+pub fn lookup_account_id(_input: TokenStream) -> TokenStream {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(find_account_id()).unwrap_or_else(|e| e.into_compile_error().into())
+}
+
+/// Retrieves the region of your AWS config (e.g. `AWS_REGION`), returning a `Region`: `current_region!()`.
+/// Fails to compile when the region has no `Region` variant (like the regions of China and GovCloud).
+#[proc_macro]
+pub fn current_region(_input: TokenStream) -> TokenStream {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(find_current_region()).unwrap_or_else(|e| e.into_compile_error().into())
+}
+
+/// Retrieves the value of an SSM parameter from your AWS environment, returning it as a `&'static str`.
+/// Like the other lookups, the value is cached in `rusty.context.json`, so the template only changes when the lookups are refreshed.
+///
+/// The expansion is a block that evaluates to the value (it also includes `rusty.context.json`, so the crate is rebuilt when the file changes).
+/// So it is not a literal, and can not be passed to macros that expect one, like `concat!` or the validating macros of `rusty-cdk-macros`.
+///
+/// Pass in the name of the parameter: `lookup_ssm_parameter!("/shared/image-tag")`.
+/// The value of a `StringList` parameter is returned comma-separated.
+/// SecureString parameters are refused, because their value would end up in the template. Use a dynamic reference (`ssm_dynamic_reference!` or `secrets_manager_dynamic_reference!`) instead.
+#[proc_macro]
+pub fn lookup_ssm_parameter(input: TokenStream) -> TokenStream {
+    let input: LitStr = parse_macro_input!(input);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(find_ssm_parameter(&input.value()))
+        .unwrap_or_else(|e| e.into_compile_error().into())
+}
+
+/// Tries to retrieve a VPC from your AWS environment, returning a `VpcRef`.
+/// This ensures that the VPC actually exists in your account and that your deployment will not fail.
+///
+/// Pass in the VPC id, `lookup_vpc!("vpc-0123456789abcdef0")`, or a tag that only one VPC has: `lookup_vpc!(tag "Name" = "main")`
+#[proc_macro]
+pub fn lookup_vpc(input: TokenStream) -> TokenStream {
+    let input: ResourceSelector = parse_macro_input!(input);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(find_vpc(&input)).unwrap_or_else(|e| e.into_compile_error().into())
+}
+
+/// Tries to retrieve the subnets of a VPC from your AWS environment, returning a `Vec<SubnetRef>`.
+/// This ensures that the subnets actually exist in your account and that your deployment will not fail.
+///
+/// Pass in the VPC id, optionally followed by the type of subnet (`public`, `private` or `isolated`) or a tag:
+/// `lookup_subnets!("vpc-0123456789abcdef0", private)` or `lookup_subnets!("vpc-0123456789abcdef0", tag "Tier" = "data")`.
+///
+/// The type is based on the `aws-cdk:subnet-type` tag (added by the AWS CDK) or on the route table of the subnet.
+/// Public subnets route to an internet gateway, private ones have another default route (e.g. to a NAT gateway), isolated ones have none.
+/// Subnets that use the main route table of the VPC are only recognized as public when they assign public IPs, use a tag for the others.
+#[proc_macro]
+pub fn lookup_subnets(input: TokenStream) -> TokenStream {
+    let input: SubnetsInput = parse_macro_input!(input);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(find_subnets(&input)).unwrap_or_else(|e| e.into_compile_error().into())
+}
+
+/// Tries to retrieve a security group from your AWS environment, returning a `SecurityGroupRef`.
+/// This ensures that the group actually exists in your account and that your deployment will not fail.
+///
+/// Pass in the group id, `lookup_security_group!("sg-0123456789abcdef0")`, or the VPC id and the group name: `lookup_security_group!("vpc-0123456789abcdef0", "database")`
+#[proc_macro]
+pub fn lookup_security_group(input: TokenStream) -> TokenStream {
+    let input: SecurityGroupInput = parse_macro_input!(input);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(find_security_group(&input))
+        .unwrap_or_else(|e| e.into_compile_error().into())
+}
+
+/// Tries to retrieve the availability zones of your AWS environment, returning a `Vec<AvailabilityZoneRef>`.
+///
+/// The zones are those that contain subnets (the default VPC has a subnet in every zone of the region).
+/// Pass in a VPC id to only get the zones of that VPC: `lookup_availability_zones!("vpc-0123456789abcdef0")`, or nothing for all zones: `lookup_availability_zones!()`
+#[proc_macro]
+pub fn lookup_availability_zones(input: TokenStream) -> TokenStream {
+    let input: AvailabilityZonesInput = parse_macro_input!(input);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(find_availability_zones(input.vpc_id.as_deref()))
+        .unwrap_or_else(|e| e.into_compile_error().into())
+}
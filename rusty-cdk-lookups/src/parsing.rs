@@ -1,6 +1,7 @@
-use syn::LitStr;
 use syn::parse::{Parse, ParseStream};
-use syn::token::Comma;
+use syn::punctuated::Punctuated;
+use syn::token::{Comma, Eq};
+use syn::{Error, Ident, LitStr, bracketed};
 
 pub(crate) struct GenericInput {
     pub(crate) resource_id: String,
@@ -19,3 +20,253 @@ impl Parse for GenericInput {
         })
     }
 }
+
+/// Input for roles: an id and the role name, optionally followed by the principals the role should trust
+/// (`trusts = "lambda.amazonaws.com"`) and the actions it should allow (`allows = ["dynamodb:PutItem"]`)
+pub(crate) struct RoleInput {
+    pub(crate) resource_id: String,
+    pub(crate) identifier: String,
+    pub(crate) trusts: Vec<String>,
+    pub(crate) allows: Vec<String>,
+}
+
+impl Parse for RoleInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let GenericInput { resource_id, identifier } = input.parse()?;
+        let mut trusts = vec![];
+        let mut allows = vec![];
+
+        while !input.is_empty() {
+            let _: Comma = input.parse()?;
+            if input.is_empty() {
+                break;
+            }
+            let key: Ident = input.parse()?;
+            let _: Eq = input.parse()?;
+            let values = parse_string_or_list(input)?;
+
+            match key.to_string().as_str() {
+                "trusts" => trusts.extend(values),
+                "allows" => allows.extend(values),
+                _ => return Err(Error::new(key.span(), "expected trusts or allows")),
+            }
+        }
+
+        Ok(RoleInput {
+            resource_id,
+            identifier,
+            trusts,
+            allows,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum MessagingType {
+    Standard,
+    Fifo,
+}
+
+/// Input for queues and topics: an id, an identifier, and optionally the expected type (`fifo` or `standard`)
+pub(crate) struct MessagingInput {
+    pub(crate) resource_id: String,
+    pub(crate) identifier: String,
+    pub(crate) expected_type: Option<MessagingType>,
+}
+
+impl Parse for MessagingInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let GenericInput { resource_id, identifier } = input.parse()?;
+
+        let expected_type = if input.is_empty() {
+            None
+        } else {
+            let _: Comma = input.parse()?;
+            let expected_type: Ident = input.parse()?;
+            match expected_type.to_string().as_str() {
+                "standard" => Some(MessagingType::Standard),
+                "fifo" => Some(MessagingType::Fifo),
+                _ => return Err(Error::new(expected_type.span(), "expected standard or fifo")),
+            }
+        };
+
+        Ok(MessagingInput {
+            resource_id,
+            identifier,
+            expected_type,
+        })
+    }
+}
+
+/// A resource identifier, or a tag (`tag "Name" = "main"`)
+pub(crate) enum ResourceSelector {
+    Id(String),
+    Tag(String, String),
+}
+
+impl Parse for ResourceSelector {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            let identifier: LitStr = input.parse()?;
+            Ok(ResourceSelector::Id(identifier.value()))
+        } else {
+            let (key, value) = parse_tag(input)?;
+            Ok(ResourceSelector::Tag(key, value))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SubnetType {
+    Public,
+    Private,
+    Isolated,
+}
+
+pub(crate) enum SubnetSelector {
+    All,
+    Type(SubnetType),
+    Tag(String, String),
+}
+
+pub(crate) struct SubnetsInput {
+    pub(crate) vpc_id: String,
+    pub(crate) selector: SubnetSelector,
+}
+
+impl Parse for SubnetsInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let vpc_id: LitStr = input.parse()?;
+
+        let selector = if input.is_empty() {
+            SubnetSelector::All
+        } else {
+            let _: Comma = input.parse()?;
+            if input.peek(Ident) && !input.peek2(LitStr) {
+                let subnet_type: Ident = input.parse()?;
+                match subnet_type.to_string().as_str() {
+                    "public" => SubnetSelector::Type(SubnetType::Public),
+                    "private" => SubnetSelector::Type(SubnetType::Private),
+                    "isolated" => SubnetSelector::Type(SubnetType::Isolated),
+                    _ => {
+                        return Err(Error::new(
+                            subnet_type.span(),
+                            "expected public, private, isolated or a tag (`tag \"Key\" = \"Value\"`)",
+                        ));
+                    }
+                }
+            } else {
+                let (key, value) = parse_tag(input)?;
+                SubnetSelector::Tag(key, value)
+            }
+        };
+
+        Ok(SubnetsInput {
+            vpc_id: vpc_id.value(),
+            selector,
+        })
+    }
+}
+
+/// A security group id, or the id of a VPC and the name of a group in that VPC
+pub(crate) enum SecurityGroupInput {
+    Id(String),
+    Name { vpc_id: String, name: String },
+}
+
+impl Parse for SecurityGroupInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let first: LitStr = input.parse()?;
+
+        if input.is_empty() {
+            Ok(SecurityGroupInput::Id(first.value()))
+        } else {
+            let _: Comma = input.parse()?;
+            let name: LitStr = input.parse()?;
+            Ok(SecurityGroupInput::Name {
+                vpc_id: first.value(),
+                name: name.value(),
+            })
+        }
+    }
+}
+
+pub(crate) struct AvailabilityZonesInput {
+    pub(crate) vpc_id: Option<String>,
+}
+
+impl Parse for AvailabilityZonesInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let vpc_id: Option<LitStr> = input.parse()?;
+
+        Ok(AvailabilityZonesInput {
+            vpc_id: vpc_id.map(|v| v.value()),
+        })
+    }
+}
+
+fn parse_tag(input: ParseStream) -> syn::Result<(String, String)> {
+    let tag: Ident = input.parse()?;
+    if tag != "tag" {
+        return Err(Error::new(
+            tag.span(),
+            "expected an identifier or a tag (`tag \"Key\" = \"Value\"`)",
+        ));
+    }
+    let key: LitStr = input.parse()?;
+    let _: Eq = input.parse()?;
+    let value: LitStr = input.parse()?;
+
+    Ok((key.value(), value.value()))
+}
+
+/// A string (`"value"`) or a list of strings (`["first", "second"]`)
+fn parse_string_or_list(input: ParseStream) -> syn::Result<Vec<String>> {
+    if input.peek(LitStr) {
+        let value: LitStr = input.parse()?;
+        Ok(vec![value.value()])
+    } else {
+        let content;
+        bracketed!(content in input);
+        let values: Punctuated<LitStr, Comma> = content.parse_terminated(|c| c.parse::<LitStr>(), Comma)?;
+        Ok(values.iter().map(LitStr::value).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parsing::{ResourceSelector, SubnetSelector, SubnetType, SubnetsInput};
+
+    #[test]
+    fn should_parse_vpc_by_id_or_tag() {
+        let by_id: ResourceSelector = syn::parse_str(r#""vpc-0123456789abcdef0""#).unwrap();
+        let by_tag: ResourceSelector = syn::parse_str(r#"tag "Name" = "main""#).unwrap();
+
+        assert!(matches!(by_id, ResourceSelector::Id(id) if id == "vpc-0123456789abcdef0"));
+        assert!(matches!(by_tag, ResourceSelector::Tag(key, value) if key == "Name" && value == "main"));
+    }
+
+    #[test]
+    fn should_refuse_vpc_selector_that_is_not_a_tag() {
+        assert!(syn::parse_str::<ResourceSelector>(r#"label "Name" = "main""#).is_err());
+        assert!(syn::parse_str::<ResourceSelector>(r#"tag "Name""#).is_err());
+    }
+
+    #[test]
+    fn should_parse_subnets_of_vpc() {
+        let all: SubnetsInput = syn::parse_str(r#""vpc-0123456789abcdef0""#).unwrap();
+        let private: SubnetsInput = syn::parse_str(r#""vpc-0123456789abcdef0", private"#).unwrap();
+        let by_tag: SubnetsInput = syn::parse_str(r#""vpc-0123456789abcdef0", tag "Tier" = "data""#).unwrap();
+
+        assert_eq!(all.vpc_id, "vpc-0123456789abcdef0");
+        assert!(matches!(all.selector, SubnetSelector::All));
+        assert!(matches!(private.selector, SubnetSelector::Type(SubnetType::Private)));
+        assert!(matches!(by_tag.selector, SubnetSelector::Tag(key, value) if key == "Tier" && value == "data"));
+    }
+
+    #[test]
+    fn should_refuse_unknown_subnet_type() {
+        assert!(syn::parse_str::<SubnetsInput>(r#""vpc-0123456789abcdef0", internal"#).is_err());
+        assert!(syn::parse_str::<SubnetsInput>(r#""vpc-0123456789abcdef0", tag "Tier""#).is_err());
+    }
+}
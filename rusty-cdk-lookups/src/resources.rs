@@ -0,0 +1,172 @@
+use crate::cloudcontrol::{CloudControlClient, ResourceInfoWithArn, lookup_arn, string_property};
+use crate::context::{lookup_with_context, with_context_dependency};
+use crate::parsing::{MessagingInput, MessagingType};
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use serde::{Deserialize, Serialize};
+use syn::Error;
+
+const QUEUE_TYPE: &str = "AWS::SQS::Queue";
+const TOPIC_TYPE: &str = "AWS::SNS::Topic";
+
+#[derive(Serialize, Deserialize)]
+struct QueueInfo {
+    url: String,
+    arn: String,
+    fifo: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TopicInfo {
+    arn: String,
+    fifo: bool,
+}
+
+pub(crate) async fn find_table_ref(resource_id: &str, table_name: &str) -> Result<TokenStream, Error> {
+    let ResourceInfoWithArn { identifier, arn } =
+        lookup_with_context("table", table_name, || lookup_arn(table_name, "AWS::DynamoDB::Table"))
+            .await
+            .map_err(|e| Error::new(Span::call_site(), e))?;
+
+    Ok(with_context_dependency(
+        quote!(
+            TableRef::new(#resource_id, #identifier, #arn)
+        )
+        .into(),
+    ))
+}
+
+pub(crate) async fn find_bucket_ref(resource_id: &str, bucket_name: &str) -> Result<TokenStream, Error> {
+    let ResourceInfoWithArn { identifier, arn } = lookup_with_context("bucket", bucket_name, || lookup_arn(bucket_name, "AWS::S3::Bucket"))
+        .await
+        .map_err(|e| Error::new(Span::call_site(), e))?;
+
+    Ok(with_context_dependency(
+        quote!(
+            BucketRef::new(#resource_id, #identifier, #arn)
+        )
+        .into(),
+    ))
+}
+
+pub(crate) async fn find_function_ref(resource_id: &str, function_name: &str) -> Result<TokenStream, Error> {
+    let ResourceInfoWithArn { identifier, arn } =
+        lookup_with_context("function", function_name, || lookup_arn(function_name, "AWS::Lambda::Function"))
+            .await
+            .map_err(|e| Error::new(Span::call_site(), e))?;
+
+    Ok(with_context_dependency(
+        quote!(
+            FunctionRef::new(#resource_id, #resource_id, #identifier, #arn)
+        )
+        .into(),
+    ))
+}
+
+pub(crate) async fn find_log_group_ref(resource_id: &str, log_group_name: &str) -> Result<TokenStream, Error> {
+    let ResourceInfoWithArn { identifier, arn } =
+        lookup_with_context("log-group", log_group_name, || lookup_arn(log_group_name, "AWS::Logs::LogGroup"))
+            .await
+            .map_err(|e| Error::new(Span::call_site(), e))?;
+
+    Ok(with_context_dependency(
+        quote!(
+            LogGroupRef::new(#resource_id, #identifier, #arn)
+        )
+        .into(),
+    ))
+}
+
+pub(crate) async fn find_queue_ref(input: &MessagingInput) -> Result<TokenStream, Error> {
+    let QueueInfo { url, arn, fifo } = lookup_with_context("queue", &input.identifier, || find_queue(&input.identifier))
+        .await
+        .map_err(|e| Error::new(Span::call_site(), e))?;
+    check_type("queue", &input.identifier, fifo, input.expected_type)?;
+    let resource_id = &input.resource_id;
+
+    Ok(with_context_dependency(
+        quote!(
+            QueueRef::new(#resource_id, #resource_id, #url, #arn)
+        )
+        .into(),
+    ))
+}
+
+/// The identifier of a queue is its URL. When a name is passed in, the queue is found by listing the queues
+async fn find_queue(identifier: &str) -> Result<QueueInfo, String> {
+    let client = CloudControlClient::new().await;
+    let url = if identifier.starts_with("https://") {
+        identifier.to_string()
+    } else {
+        let suffix = format!("/{identifier}");
+        client
+            .list_resources(QUEUE_TYPE, None, "QueueUrl")
+            .await?
+            .iter()
+            .filter_map(|q| q["QueueUrl"].as_str())
+            .find(|url| url.ends_with(&suffix))
+            .map(str::to_string)
+            .ok_or_else(|| format!("did not find a queue with name {identifier}"))?
+    };
+
+    let queue = client.get_resource_properties(&url, QUEUE_TYPE).await?;
+    Ok(QueueInfo {
+        arn: string_property(&queue, "Arn")?,
+        fifo: queue["FifoQueue"].as_bool().unwrap_or(false),
+        url,
+    })
+}
+
+pub(crate) async fn find_topic_ref(input: &MessagingInput) -> Result<TokenStream, Error> {
+    let TopicInfo { arn, fifo } = lookup_with_context("topic", &input.identifier, || find_topic(&input.identifier))
+        .await
+        .map_err(|e| Error::new(Span::call_site(), e))?;
+    check_type("topic", &input.identifier, fifo, input.expected_type)?;
+    let resource_id = &input.resource_id;
+
+    Ok(with_context_dependency(
+        quote!(
+            TopicRef::new(#resource_id, #resource_id, #arn, #arn)
+        )
+        .into(),
+    ))
+}
+
+/// The identifier of a topic is its ARN. When a name is passed in, the topic is found by listing the topics
+async fn find_topic(identifier: &str) -> Result<TopicInfo, String> {
+    let client = CloudControlClient::new().await;
+    let arn = if identifier.starts_with("arn:") {
+        identifier.to_string()
+    } else {
+        let suffix = format!(":{identifier}");
+        client
+            .list_resources(TOPIC_TYPE, None, "TopicArn")
+            .await?
+            .iter()
+            .filter_map(|t| t["TopicArn"].as_str())
+            .find(|arn| arn.ends_with(&suffix))
+            .map(str::to_string)
+            .ok_or_else(|| format!("did not find a topic with name {identifier}"))?
+    };
+
+    let topic = client.get_resource_properties(&arn, TOPIC_TYPE).await?;
+    Ok(TopicInfo {
+        fifo: topic["FifoTopic"].as_bool().unwrap_or(false),
+        arn,
+    })
+}
+
+fn check_type(kind: &str, identifier: &str, fifo: bool, expected_type: Option<MessagingType>) -> Result<(), Error> {
+    match (expected_type, fifo) {
+        (Some(MessagingType::Fifo), false) => Err(Error::new(
+            Span::call_site(),
+            format!("{kind} {identifier} is a standard {kind}, expected a FIFO {kind}"),
+        )),
+        (Some(MessagingType::Standard), true) => Err(Error::new(
+            Span::call_site(),
+            format!("{kind} {identifier} is a FIFO {kind}, expected a standard {kind}"),
+        )),
+        _ => Ok(()),
+    }
+}
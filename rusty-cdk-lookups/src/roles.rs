@@ -1,49 +1,350 @@
-use crate::cloudcontrol::{ResourceInfo, ResourceInfoWithArn, lookup, lookup_arn};
+use crate::cloudcontrol::{CloudControlClient, ResourceInfo, ResourceInfoWithArn, lookup, lookup_arn};
+use crate::context::{lookup_with_context, with_context_dependency};
+use crate::parsing::RoleInput;
+use aws_sdk_iam::Client as IamClient;
 use proc_macro::TokenStream;
-use quote::__private::Span;
+use proc_macro2::Span;
 use quote::quote;
+use serde_json::Value;
+use std::collections::BTreeMap;
 use syn::Error;
 
-pub(crate) async fn find_role_ref(resource_id: &str, role_name: &str) -> Result<TokenStream, Error> {
-    let ResourceInfoWithArn { identifier, arn } = lookup_arn(role_name, "AWS::IAM::Role")
+const ROLE_TYPE: &str = "AWS::IAM::Role";
+const ALLOWED: &str = "allowed";
+
+pub(crate) async fn find_role_ref(input: &RoleInput) -> Result<TokenStream, Error> {
+    let RoleInput {
+        resource_id,
+        identifier: role_name,
+        trusts,
+        allows,
+    } = input;
+    let ResourceInfoWithArn { identifier, arn } = lookup_with_context("role", role_name, || lookup_arn(role_name, ROLE_TYPE))
         .await
         .map_err(|e| Error::new(Span::call_site(), e))?;
 
-    Ok(quote!(
-        RoleRef::new(#resource_id, #identifier, #arn)
-    )
-    .into())
+    let mut problems = vec![];
+    if !trusts.is_empty() {
+        let trust_policy: Value = lookup_with_context("role-trust-policy", role_name, || find_trust_policy(role_name))
+            .await
+            .map_err(|e| Error::new(Span::call_site(), e))?;
+        problems.extend(trusts.iter().filter(|t| !is_trusted(&trust_policy, t)).map(|t| {
+            format!(
+                "role {role_name} does not trust {t} (trusted: {})",
+                allowed_principals(&trust_policy).join(", ")
+            )
+        }));
+    }
+    if !allows.is_empty() {
+        let mut actions = allows.clone();
+        actions.sort();
+        actions.dedup();
+        let decisions: BTreeMap<String, String> =
+            lookup_with_context("role-permissions", &format!("{role_name}/{}", actions.join(",")), || {
+                simulate_actions(&arn, &actions)
+            })
+            .await
+            .map_err(|e| Error::new(Span::call_site(), e))?;
+        problems.extend(
+            decisions
+                .iter()
+                .filter(|(_, decision)| *decision != ALLOWED)
+                .map(|(action, decision)| format!("role {role_name} is not allowed to do {action} ({decision})")),
+        );
+    }
+
+    if !problems.is_empty() {
+        return Err(Error::new(Span::call_site(), problems.join("\n")));
+    }
+
+    Ok(with_context_dependency(
+        quote!(
+            RoleRef::new(#resource_id, #identifier, #arn)
+        )
+        .into(),
+    ))
+}
+
+/// The trust policy (`AssumeRolePolicyDocument`) of the role
+async fn find_trust_policy(role_name: &str) -> Result<Value, String> {
+    let client = CloudControlClient::new().await;
+    let role = client.get_resource_properties(role_name, ROLE_TYPE).await?;
+    match &role["AssumeRolePolicyDocument"] {
+        Value::String(document) => {
+            serde_json::from_str(document).map_err(|e| format!("could not read trust policy of role {role_name}: {e}"))
+        }
+        document => Ok(document.clone()),
+    }
+}
+
+/// Whether the trust policy allows the principal (a service, account id or ARN) to assume the role.
+///
+/// Account ids match the root ARN of the account, and trusting an account trusts its roles and users as well.
+/// An unconditional `Deny` wins over an `Allow`.
+/// Conditions can only be evaluated when the role is assumed, so a statement with a condition is assumed to apply to an `Allow`, but not to a `Deny`.
+fn is_trusted(trust_policy: &Value, principal: &str) -> bool {
+    let statements = assume_role_statements(trust_policy);
+    let allowed = statements
+        .iter()
+        .filter(|s| s["Effect"].as_str() == Some("Allow"))
+        .any(|s| matches_principal(&s["Principal"], principal));
+    let denied = statements
+        .iter()
+        .filter(|s| s["Effect"].as_str() == Some("Deny") && s.get("Condition").is_none())
+        .any(|s| match s.get("NotPrincipal") {
+            Some(not_principal) => !matches_principal(not_principal, principal),
+            None => matches_principal(&s["Principal"], principal),
+        });
+
+    allowed && !denied
+}
+
+/// The principals of the `Allow` statements, to show in error messages
+fn allowed_principals(trust_policy: &Value) -> Vec<String> {
+    let mut principals: Vec<String> = assume_role_statements(trust_policy)
+        .iter()
+        .filter(|s| s["Effect"].as_str() == Some("Allow"))
+        .flat_map(|s| match &s["Principal"] {
+            Value::Object(principal) => principal.values().flat_map(strings).collect(),
+            principal => strings(principal),
+        })
+        .collect();
+    principals.sort();
+    principals.dedup();
+    principals
+}
+
+/// The statements about assuming the role, leaving out those for other actions like `sts:TagSession`
+fn assume_role_statements(trust_policy: &Value) -> Vec<&Value> {
+    let statements: Vec<&Value> = match &trust_policy["Statement"] {
+        Value::Array(statements) => statements.iter().collect(),
+        statement => vec![statement],
+    };
+
+    statements
+        .into_iter()
+        .filter(|s| {
+            strings(&s["Action"]).iter().any(|action| {
+                let action = action.to_lowercase();
+                action == "*" || action == "sts:*" || action.starts_with("sts:assumerole")
+            })
+        })
+        .collect()
+}
+
+/// Whether the `Principal` (or `NotPrincipal`) element of a statement matches the principal, which is `*` for everyone
+fn matches_principal(element: &Value, principal: &str) -> bool {
+    let values = match element {
+        Value::Object(element) => element.values().flat_map(strings).collect(),
+        element => strings(element),
+    };
+    values.iter().any(|value| value == "*" || same_principal(value, principal))
+}
+
+fn same_principal(in_policy: &str, principal: &str) -> bool {
+    let in_policy = account_id_of_root(in_policy).unwrap_or(in_policy);
+    let principal_account = account_id_of_root(principal);
+
+    match principal_account {
+        Some(account) => in_policy == account,
+        // a role or user is trusted when its account is
+        None => in_policy == principal || (is_account_id(in_policy) && account_id_of_arn(principal) == Some(in_policy)),
+    }
+}
+
+/// The account id, for an account id or the root ARN of an account (`arn:aws:iam::123456789012:root`)
+fn account_id_of_root(principal: &str) -> Option<&str> {
+    if is_account_id(principal) {
+        return Some(principal);
+    }
+    match principal.split(':').collect::<Vec<_>>().as_slice() {
+        ["arn", _, "iam", "", account, "root"] if is_account_id(account) => Some(account),
+        _ => None,
+    }
+}
+
+fn account_id_of_arn(principal: &str) -> Option<&str> {
+    match principal.split(':').collect::<Vec<_>>().as_slice() {
+        ["arn", _, "iam" | "sts", "", account, _] if is_account_id(account) => Some(account),
+        _ => None,
+    }
+}
+
+fn is_account_id(value: &str) -> bool {
+    value.len() == 12 && value.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Uses the IAM policy simulator to check the actions against the policies of the role, for all resources.
+/// Returns the decision (`allowed`, `implicitDeny` or `explicitDeny`) for every action
+async fn simulate_actions(role_arn: &str, actions: &[String]) -> Result<BTreeMap<String, String>, String> {
+    let config = aws_config::load_from_env().await;
+    let client = IamClient::new(&config);
+    let mut decisions = BTreeMap::new();
+    let mut marker = None;
+
+    loop {
+        let result = client
+            .simulate_principal_policy()
+            .policy_source_arn(role_arn)
+            .set_action_names(Some(actions.to_vec()))
+            .set_marker(marker)
+            .send()
+            .await
+            .map_err(|e| format!("could not simulate the policies of role {role_arn}: {e}"))?;
+
+        for evaluation in result.evaluation_results() {
+            decisions.insert(
+                evaluation.eval_action_name().to_string(),
+                evaluation.eval_decision().as_str().to_string(),
+            );
+        }
+
+        marker = result.marker().map(str::to_string);
+        if marker.is_none() {
+            break;
+        }
+    }
+
+    Ok(decisions)
+}
+
+fn strings(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(value) => vec![value.clone()],
+        Value::Array(values) => values.iter().flat_map(|v| v.as_str()).map(str::to_string).collect(),
+        _ => vec![],
+    }
 }
 
 pub(crate) async fn find_user_ref(resource_id: &str, role_name: &str) -> Result<TokenStream, Error> {
-    let ResourceInfoWithArn { identifier, arn } = lookup_arn(role_name, "AWS::IAM::User")
+    let ResourceInfoWithArn { identifier, arn } = lookup_with_context("user", role_name, || lookup_arn(role_name, "AWS::IAM::User"))
         .await
         .map_err(|e| Error::new(Span::call_site(), e))?;
 
-    Ok(quote!(
-        UserRef::new(#resource_id, #identifier, #arn)
-    )
-    .into())
+    Ok(with_context_dependency(
+        quote!(
+            UserRef::new(#resource_id, #identifier, #arn)
+        )
+        .into(),
+    ))
 }
 
 pub(crate) async fn find_kms_ref(resource_id: &str, key_id: &str) -> Result<TokenStream, Error> {
-    let ResourceInfoWithArn { identifier, arn } = lookup_arn(key_id, "AWS::KMS::Key")
+    let ResourceInfoWithArn { identifier, arn } = lookup_with_context("kms-key", key_id, || lookup_arn(key_id, "AWS::KMS::Key"))
         .await
         .map_err(|e| Error::new(Span::call_site(), e))?;
 
-    Ok(quote!(
-        KeyRef::new(#resource_id, #identifier, #arn)
-    )
-    .into())
+    Ok(with_context_dependency(
+        quote!(
+            KeyRef::new(#resource_id, #identifier, #arn)
+        )
+        .into(),
+    ))
 }
 
 pub(crate) async fn find_secret_ref(resource_id: &str, secret_arn: &str) -> Result<TokenStream, Error> {
-    let ResourceInfo { identifier } = lookup(secret_arn, "AWS::SecretsManager::Secret")
+    let ResourceInfo { identifier } = lookup_with_context("secret", secret_arn, || lookup(secret_arn, "AWS::SecretsManager::Secret"))
         .await
         .map_err(|e| Error::new(Span::call_site(), e))?;
 
-    Ok(quote!(
-        SecretRef::new(#resource_id, #identifier, #identifier)
-    )
-    .into())
+    Ok(with_context_dependency(
+        quote!(
+            SecretRef::new(#resource_id, #identifier, #identifier)
+        )
+        .into(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::roles::{allowed_principals, is_trusted};
+    use serde_json::{Value, json};
+
+    fn policy(statements: Value) -> Value {
+        json!({ "Version": "2012-10-17", "Statement": statements })
+    }
+
+    #[test]
+    fn should_trust_service_of_allow_statement() {
+        let policy = policy(json!([{ "Effect": "Allow", "Principal": { "Service": "lambda.amazonaws.com" }, "Action": "sts:AssumeRole" }]));
+
+        assert!(is_trusted(&policy, "lambda.amazonaws.com"));
+        assert!(!is_trusted(&policy, "ecs-tasks.amazonaws.com"));
+    }
+
+    #[test]
+    fn should_match_account_ids_and_root_arns() {
+        let with_root =
+            policy(json!([{ "Effect": "Allow", "Principal": { "AWS": "arn:aws:iam::123456789012:root" }, "Action": "sts:AssumeRole" }]));
+        let with_id = policy(json!([{ "Effect": "Allow", "Principal": { "AWS": "123456789012" }, "Action": "sts:AssumeRole" }]));
+
+        assert!(is_trusted(&with_root, "123456789012"));
+        assert!(is_trusted(&with_id, "arn:aws:iam::123456789012:root"));
+        assert!(is_trusted(&with_root, "arn:aws:iam::123456789012:role/deployer"));
+        assert!(!is_trusted(&with_root, "210987654321"));
+        assert!(!is_trusted(&with_root, "arn:aws:iam::210987654321:role/deployer"));
+    }
+
+    #[test]
+    fn should_not_trust_account_when_only_a_role_is_trusted() {
+        let policy = policy(
+            json!([{ "Effect": "Allow", "Principal": { "AWS": ["arn:aws:iam::123456789012:role/deployer"] }, "Action": "sts:AssumeRole" }]),
+        );
+
+        assert!(is_trusted(&policy, "arn:aws:iam::123456789012:role/deployer"));
+        assert!(!is_trusted(&policy, "123456789012"));
+    }
+
+    #[test]
+    fn should_trust_everyone_with_wildcards() {
+        let star = policy(json!([{ "Effect": "Allow", "Principal": "*", "Action": "sts:AssumeRole" }]));
+        let aws_star = policy(json!([{ "Effect": "Allow", "Principal": { "AWS": "*" }, "Action": ["sts:*"] }]));
+
+        assert!(is_trusted(&star, "lambda.amazonaws.com"));
+        assert!(is_trusted(&aws_star, "123456789012"));
+    }
+
+    #[test]
+    fn should_subtract_unconditional_deny_statements() {
+        let policy = policy(json!([
+            { "Effect": "Allow", "Principal": { "AWS": "*" }, "Action": "sts:AssumeRole" },
+            { "Effect": "Deny", "Principal": { "AWS": "210987654321" }, "Action": "sts:AssumeRole" },
+            { "Effect": "Deny", "Principal": { "AWS": "123456789012" }, "Action": "sts:AssumeRole", "Condition": { "Bool": { "aws:MultiFactorAuthPresent": "false" } } }
+        ]));
+
+        assert!(!is_trusted(&policy, "arn:aws:iam::210987654321:root"));
+        assert!(is_trusted(&policy, "123456789012"));
+    }
+
+    #[test]
+    fn should_deny_everyone_but_the_not_principal() {
+        let policy = policy(json!([
+            { "Effect": "Allow", "Principal": { "AWS": ["111111111111", "222222222222"] }, "Action": "sts:AssumeRole" },
+            { "Effect": "Deny", "NotPrincipal": { "AWS": "111111111111" }, "Action": "sts:AssumeRole" }
+        ]));
+
+        assert!(is_trusted(&policy, "111111111111"));
+        assert!(!is_trusted(&policy, "222222222222"));
+    }
+
+    #[test]
+    fn should_trust_with_conditions_and_ignore_other_actions() {
+        let policy = policy(json!([
+            { "Effect": "Allow", "Principal": { "AWS": "123456789012" }, "Action": "sts:AssumeRole", "Condition": { "StringEquals": { "sts:ExternalId": "secret" } } },
+            { "Effect": "Allow", "Principal": { "Service": "lambda.amazonaws.com" }, "Action": "sts:TagSession" }
+        ]));
+
+        assert!(is_trusted(&policy, "123456789012"));
+        assert!(!is_trusted(&policy, "lambda.amazonaws.com"));
+    }
+
+    #[test]
+    fn should_list_allowed_principals_of_single_statement() {
+        let policy = json!({ "Statement": { "Effect": "Allow", "Principal": { "Service": ["lambda.amazonaws.com", "edgelambda.amazonaws.com"] }, "Action": "sts:AssumeRole" } });
+
+        assert_eq!(
+            allowed_principals(&policy),
+            vec!["edgelambda.amazonaws.com", "lambda.amazonaws.com"]
+        );
+    }
 }
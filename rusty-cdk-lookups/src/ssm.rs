@@ -0,0 +1,34 @@
+use crate::cloudcontrol::{CloudControlClient, string_property};
+use crate::context::{lookup_with_context, with_context_dependency};
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::Error;
+
+const PARAMETER_TYPE: &str = "AWS::SSM::Parameter";
+
+pub(crate) async fn find_ssm_parameter(name: &str) -> Result<TokenStream, Error> {
+    let value: String = lookup_with_context("ssm-parameter", name, || find_parameter_value(name))
+        .await
+        .map_err(|e| Error::new(Span::call_site(), e))?;
+
+    Ok(with_context_dependency(
+        quote!(
+            #value
+        )
+        .into(),
+    ))
+}
+
+/// Retrieves the value of a `String` or `StringList` parameter. The value of a `StringList` is comma-separated
+async fn find_parameter_value(name: &str) -> Result<String, String> {
+    let client = CloudControlClient::new().await;
+    let parameter = client.get_resource_properties(name, PARAMETER_TYPE).await?;
+
+    match parameter["Type"].as_str() {
+        Some("SecureString") => Err(format!(
+            "ssm parameter {name} is a SecureString, which should not end up in the template (use a dynamic reference instead)"
+        )),
+        _ => string_property(&parameter, "Value"),
+    }
+}
@@ -0,0 +1,111 @@
+use crate::strings::{StringRequirements, validate_string};
+use syn::parse::{Parse, ParseStream};
+use syn::token::Comma;
+use syn::{LitInt, LitStr, Token};
+
+pub(crate) struct SsmReference {
+    pub(crate) name: LitStr,
+    pub(crate) version: Option<LitInt>,
+}
+
+impl Parse for SsmReference {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: LitStr = input.parse()?;
+
+        let version = if input.peek(Token![,]) {
+            let _: Comma = input.parse()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        Ok(SsmReference { name, version })
+    }
+}
+
+pub(crate) struct SecretsManagerReference {
+    pub(crate) secret_id: LitStr,
+    pub(crate) json_key: Option<LitStr>,
+    pub(crate) version_stage: Option<LitStr>,
+}
+
+impl Parse for SecretsManagerReference {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let secret_id: LitStr = input.parse()?;
+
+        let json_key = if input.peek(Token![,]) {
+            let _: Comma = input.parse()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        let version_stage = if input.peek(Token![,]) {
+            let _: Comma = input.parse()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        Ok(SecretsManagerReference {
+            secret_id,
+            json_key,
+            version_stage,
+        })
+    }
+}
+
+pub(crate) fn validate_ssm_parameter_name(name: &str) -> Result<(), String> {
+    let requirements = StringRequirements::not_empty_with_allowed_chars(vec!['_', '.', '-', '/']).with_max_length(1011);
+    validate_string(name, requirements).map_err(|e| format!("invalid ssm parameter name: {e}"))?;
+
+    if name.contains('/') && !name.starts_with('/') {
+        return Err("ssm parameter name with a hierarchy should start with a slash".to_string());
+    }
+    // names starting with aws or ssm are only reserved when creating a parameter, public parameters like /aws/service/... can be referenced
+
+    Ok(())
+}
+
+pub(crate) fn validate_secret_id(secret_id: &str) -> Result<(), String> {
+    if secret_id.starts_with("arn:") {
+        if !secret_id.contains(":secretsmanager:") || !secret_id.contains(":secret:") {
+            return Err(format!("secret arn {secret_id} is not the arn of a secrets manager secret"));
+        }
+        return Ok(());
+    }
+
+    let requirements = StringRequirements::not_empty_with_allowed_chars(vec!['/', '_', '+', '=', '.', '@', '-']).with_max_length(512);
+    validate_string(secret_id, requirements).map_err(|e| format!("invalid secret name: {e}"))
+}
+
+pub(crate) fn validate_secret_part(kind: &str, value: &str) -> Result<(), String> {
+    if value.is_empty() || value.contains(':') {
+        return Err(format!("{kind} of a secret should not be empty or contain a colon"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dynamic_references::{validate_secret_id, validate_ssm_parameter_name};
+
+    #[test]
+    fn should_accept_simple_hierarchical_and_public_parameter_names() {
+        assert!(validate_ssm_parameter_name("image-tag").is_ok());
+        assert!(validate_ssm_parameter_name("/shared/network/vpc_id").is_ok());
+        assert!(validate_ssm_parameter_name("/aws/service/ami-amazon-linux-latest/al2023-ami-kernel-default-x86_64").is_ok());
+    }
+
+    #[test]
+    fn should_refuse_hierarchy_without_leading_slash_and_invalid_chars() {
+        assert!(validate_ssm_parameter_name("shared/vpc-id").is_err());
+        assert!(validate_ssm_parameter_name("with space").is_err());
+    }
+
+    #[test]
+    fn should_accept_secret_names_and_arns() {
+        assert!(validate_secret_id("prod/database@credentials").is_ok());
+        assert!(validate_secret_id("arn:aws:secretsmanager:eu-west-1:123456789012:secret:credentials-AbCdEf").is_ok());
+        assert!(validate_secret_id("arn:aws:ssm:eu-west-1:123456789012:parameter/credentials").is_err());
+    }
+}
@@ -24,6 +24,7 @@
 mod bucket;
 mod bucket_name;
 mod bucket_tiering;
+mod dynamic_references;
 mod file_util;
 mod iam_validation;
 mod instance_class;
@@ -37,6 +38,9 @@ mod timeouts;
 mod transition_in_days;
 
 use crate::bucket_tiering::BucketTiering;
+use crate::dynamic_references::{
+    SecretsManagerReference, SsmReference, validate_secret_id, validate_secret_part, validate_ssm_parameter_name,
+};
 use crate::file_util::get_absolute_file_path;
 use crate::iam_validation::{PermissionValidator, ValidationResponse};
 use crate::instance_class::validate_doc_db_instance_class;
@@ -690,6 +694,7 @@ number_check!(connection_attempts, 1, 3, ConnectionAttempts, u8);
 number_check!(s3_origin_read_timeout, 1, 120, S3OriginReadTimeout, u8);
 number_check!(deployment_duration_in_minutes, 0, 1440, DeploymentDurationInMinutes, u16);
 number_check!(growth_factor, 0, 100, GrowthFactor, u8);
+number_check!(rollback_monitoring_time, 0, 180, RollbackMonitoringTime, u8);
 number_check!(record_expiration_days, 7, 2147483647, RecordExpirationDays, u32);
 number_check!(retry_policy_event_age, 60, 86400, RetryPolicyEventAge, u32);
 number_check!(retry_policy_retries, 0, 185, RetryPolicyRetries, u8);
@@ -1441,3 +1446,105 @@ pub fn period(input: TokenStream) -> TokenStream {
     }
     .into()
 }
+
+/// Creates a validated `DynamicReference` to an SSM parameter, resolved by CloudFormation during deployment.
+///
+/// Takes the name of the parameter and, optionally, its version. Without a version, the latest version is used.
+///
+/// # Validation Rules
+///
+/// - Name should be between 1 and 1011 characters, containing only alphanumeric characters and `_`, `.`, `-` and `/`
+/// - A name with a hierarchy should start with a slash
+/// - Version should be a positive number
+///
+/// SecureString parameters are not supported by CloudFormation in most places, use a secret instead.
+#[proc_macro]
+pub fn ssm_dynamic_reference(input: TokenStream) -> TokenStream {
+    let SsmReference { name, version } = parse_macro_input!(input);
+    let value = name.value();
+
+    if let Err(e) = validate_ssm_parameter_name(&value) {
+        return Error::new(name.span(), e).into_compile_error().into();
+    }
+
+    match version {
+        None => quote!(
+            DynamicReference::Ssm { name: #value.to_string(), version: None }
+        )
+        .into(),
+        Some(version) => match version.base10_parse::<u32>() {
+            Ok(v) if v > 0 => quote!(
+                DynamicReference::Ssm { name: #value.to_string(), version: Some(#v) }
+            )
+            .into(),
+            _ => Error::new(version.span(), "version of an ssm parameter should be a positive number")
+                .into_compile_error()
+                .into(),
+        },
+    }
+}
+
+/// Creates a validated `DynamicReference` to a Secrets Manager secret, resolved by CloudFormation during deployment.
+///
+/// Takes the name or ARN of the secret and, optionally, a key (for secrets that contain JSON) and a version stage (default `AWSCURRENT`).
+///
+/// # Validation Rules
+///
+/// - A name should be between 1 and 512 characters, containing only alphanumeric characters and `/`, `_`, `+`, `=`, `.`, `@` and `-`
+/// - An ARN should be the ARN of a Secrets Manager secret
+/// - The key and version stage should not be empty or contain a colon
+#[proc_macro]
+pub fn secrets_manager_dynamic_reference(input: TokenStream) -> TokenStream {
+    let SecretsManagerReference {
+        secret_id,
+        json_key,
+        version_stage,
+    } = parse_macro_input!(input);
+    let id = secret_id.value();
+
+    if let Err(e) = validate_secret_id(&id) {
+        return Error::new(secret_id.span(), e).into_compile_error().into();
+    }
+    let parts = json_key.iter().map(|k| ("json key", k)).chain(version_stage.iter().map(|s| ("version stage", s)));
+    for (kind, part) in parts {
+        if let Err(e) = validate_secret_part(kind, &part.value()) {
+            return Error::new(part.span(), e).into_compile_error().into();
+        }
+    }
+
+    let json_key = match json_key {
+        None => quote!(None),
+        Some(key) => quote!(Some(#key.to_string())),
+    };
+    let version_stage = match version_stage {
+        None => quote!(None),
+        Some(stage) => quote!(Some(#stage.to_string())),
+    };
+
+    quote!(
+        DynamicReference::SecretsManager { secret_id: #id.to_string(), json_key: #json_key, version_stage: #version_stage }
+    )
+    .into()
+}
+
+/// Creates a validated `AccountId` wrapper at compile time.
+///
+/// # Validation Rules
+///
+/// - Must be exactly 12 digits (leading zeros included)
+#[proc_macro]
+pub fn account_id(input: TokenStream) -> TokenStream {
+    let output: LitStr = syn::parse(input).unwrap();
+    let value = output.value();
+
+    if value.len() != 12 || value.chars().any(|c| !c.is_ascii_digit()) {
+        return Error::new(output.span(), format!("account id should consist of exactly 12 digits (was {value})"))
+            .into_compile_error()
+            .into();
+    }
+
+    quote!(
+        AccountId(#value.to_string())
+    )
+    .into()
+}
@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 
 use rusty_cdk_macros::{
-    app_config_name, app_sync_api_name, bucket_tiering, channel_namespace_name, default_root_object, delay_seconds, doc_db_capacity_units, doc_db_instance_class, doc_db_master_pass, doc_db_master_username, ecr_repository_name, env_var_key, iam_action, image_tag_mutability_exclusion_filter_value, lambda_permission_action, lifecycle_object_sizes, lifecycle_transition_in_days, location_uri, log_group_name, log_retention, max_flexible_time_window, maximum_message_size, memory, message_retention_period, non_zero_number, origin_path, policy_name, receive_message_wait_time, repo_about_text, repo_description, repo_prefix, retry_policy_event_age, retry_policy_retries, schedule_at_expression, schedule_cron_expression, schedule_name, schedule_rate_expression, sqs_event_source_max_concurrency, string_for_secret, string_with_only_alphanumerics_and_hyphens, string_with_only_alphanumerics_and_underscores, string_with_only_alphanumerics_underscores_and_hyphens, timeout, toml_file, topic_display_name, url, visibility_timeout, zip_file
+    account_id, app_config_name, app_sync_api_name, bucket_tiering, channel_namespace_name, default_root_object, delay_seconds, doc_db_capacity_units, doc_db_instance_class, doc_db_master_pass, doc_db_master_username, ecr_repository_name, env_var_key, iam_action, image_tag_mutability_exclusion_filter_value, lambda_permission_action, lifecycle_object_sizes, lifecycle_transition_in_days, location_uri, log_group_name, log_retention, max_flexible_time_window, maximum_message_size, memory, message_retention_period, non_zero_number, origin_path, policy_name, receive_message_wait_time, repo_about_text, repo_description, repo_prefix, retry_policy_event_age, retry_policy_retries, schedule_at_expression, schedule_cron_expression, schedule_name, schedule_rate_expression, secrets_manager_dynamic_reference, sqs_event_source_max_concurrency, ssm_dynamic_reference, string_for_secret, string_with_only_alphanumerics_and_hyphens, string_with_only_alphanumerics_and_underscores, string_with_only_alphanumerics_underscores_and_hyphens, timeout, toml_file, topic_display_name, url, visibility_timeout, zip_file
 };
 
 // placeholders for the wrapper structs that exist in the core package //
@@ -51,6 +51,11 @@ struct DocDbCapacityUnits(f32);
 struct DocDbInstanceClass(String);
 struct DocDbMasterUsername(String);
 struct DocDbMasterPassword(String);
+struct AccountId(String);
+enum DynamicReference {
+    Ssm { name: String, version: Option<u32> },
+    SecretsManager { secret_id: String, json_key: Option<String>, version_stage: Option<String> },
+}
 
 #[test]
 fn create_non_zero_number_should_compile_for_non_zero_number() {
@@ -373,3 +378,33 @@ fn document_db_username() {
 fn document_db_pass() {
     doc_db_master_pass!("some-password");
 }
+
+#[test]
+fn ssm_dynamic_reference() {
+    ssm_dynamic_reference!("/shared/network/vpc-id");
+}
+
+#[test]
+fn ssm_dynamic_reference_to_public_parameter() {
+    ssm_dynamic_reference!("/aws/service/ami-amazon-linux-latest/al2023-ami-kernel-default-x86_64");
+}
+
+#[test]
+fn ssm_dynamic_reference_with_version() {
+    ssm_dynamic_reference!("image_tag", 3);
+}
+
+#[test]
+fn secrets_manager_dynamic_reference() {
+    secrets_manager_dynamic_reference!("prod/credentials");
+}
+
+#[test]
+fn secrets_manager_dynamic_reference_with_key_and_stage() {
+    secrets_manager_dynamic_reference!("arn:aws:secretsmanager:eu-west-1:123456789012:secret:credentials-AbCdEf", "password", "AWSPREVIOUS");
+}
+
+#[test]
+fn account_id() {
+    account_id!("012345678901");
+}
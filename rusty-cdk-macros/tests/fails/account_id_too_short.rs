@@ -0,0 +1,7 @@
+use rusty_cdk_macros::account_id;
+
+fn example() {
+    let account = account_id!("12345678901");
+}
+
+fn main() {}
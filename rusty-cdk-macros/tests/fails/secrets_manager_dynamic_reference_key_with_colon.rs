@@ -0,0 +1,7 @@
+use rusty_cdk_macros::secrets_manager_dynamic_reference;
+
+fn example() {
+    let reference = secrets_manager_dynamic_reference!("credentials", "password:AWSCURRENT");
+}
+
+fn main() {}
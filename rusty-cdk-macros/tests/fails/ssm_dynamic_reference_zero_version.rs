@@ -0,0 +1,7 @@
+use rusty_cdk_macros::ssm_dynamic_reference;
+
+fn example() {
+    let reference = ssm_dynamic_reference!("/shared/vpc-id", 0);
+}
+
+fn main() {}
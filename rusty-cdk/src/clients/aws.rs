@@ -0,0 +1,955 @@
+use crate::clients::{
+    CloudFormationApi, DeletionProtectionApi, DriftDetectionInfo, EcrApi, LambdaApi, ObjectVersion, S3Api, SnsApi, StackEventInfo,
+    StackInfo, StackInput, StackInstanceInfo, StackInstanceResult, StackResourceInfo, StackSetApi, StackSetInput, StackSettingsInput,
+    StackSummaryInfo,
+};
+use crate::drift::{DriftedResource, PropertyDrift};
+use crate::options::DeployOptions;
+use crate::stack_set::{Concurrency, RegionConcurrency, StackSetOptions, StackSetTargets};
+use aws_config::SdkConfig;
+use aws_sdk_cloudformation::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_cloudformation::primitives::DateTimeFormat;
+use aws_sdk_cloudformation::types::{
+    AutoDeployment, Capability, ChangeSetStatus, ChangeSetType, DeploymentTargets, PermissionModels, RegionConcurrencyType,
+    RollbackConfiguration, RollbackTrigger, StackResourceDriftStatus, StackSetOperationPreferences, StackSetOperationStatus, StackStatus,
+    Tag,
+};
+use aws_sdk_ecr::types::ImageIdentifier;
+use aws_sdk_lambda::types::LastUpdateStatus;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{Delete, ObjectIdentifier};
+use rusty_cdk_core::stack::ResourceToImport;
+
+pub(crate) struct AwsCloudFormation(aws_sdk_cloudformation::Client);
+
+impl AwsCloudFormation {
+    pub(crate) fn new(config: &SdkConfig) -> Self {
+        Self(aws_sdk_cloudformation::Client::new(config))
+    }
+}
+
+pub(crate) struct AwsS3(aws_sdk_s3::Client);
+
+impl AwsS3 {
+    pub(crate) fn new(config: &SdkConfig, options: &DeployOptions) -> Self {
+        // LocalStack (and most other stand-ins) do not support virtual-hosted-style bucket urls
+        let config = aws_sdk_s3::config::Builder::from(config)
+            .force_path_style(options.endpoint_url.is_some())
+            .build();
+        Self(aws_sdk_s3::Client::from_conf(config))
+    }
+}
+
+pub(crate) struct AwsLambda(aws_sdk_lambda::Client);
+
+impl AwsLambda {
+    pub(crate) fn new(config: &SdkConfig) -> Self {
+        Self(aws_sdk_lambda::Client::new(config))
+    }
+}
+
+pub(crate) struct AwsSns(aws_sdk_sns::Client);
+
+impl AwsSns {
+    pub(crate) fn new(config: &SdkConfig) -> Self {
+        Self(aws_sdk_sns::Client::new(config))
+    }
+}
+
+pub(crate) struct AwsEcr(aws_sdk_ecr::Client);
+
+impl AwsEcr {
+    pub(crate) fn new(config: &SdkConfig) -> Self {
+        Self(aws_sdk_ecr::Client::new(config))
+    }
+}
+
+pub(crate) struct AwsDeletionProtection {
+    dynamodb: aws_sdk_dynamodb::Client,
+    docdb: aws_sdk_docdb::Client,
+}
+
+impl AwsDeletionProtection {
+    pub(crate) fn new(config: &SdkConfig) -> Self {
+        Self {
+            dynamodb: aws_sdk_dynamodb::Client::new(config),
+            docdb: aws_sdk_docdb::Client::new(config),
+        }
+    }
+}
+
+fn is_missing_stack<E: ProvideErrorMetadata, R>(e: &SdkError<E, R>) -> bool {
+    e.as_service_error()
+        .and_then(|e| e.message())
+        .map(|m| m.contains("does not exist"))
+        .unwrap_or(false)
+}
+
+fn to_tags(tags: Vec<(String, String)>) -> Option<Vec<Tag>> {
+    if tags.is_empty() {
+        None
+    } else {
+        Some(tags.into_iter().map(|v| Tag::builder().key(v.0).value(v.1).build()).collect())
+    }
+}
+
+fn to_rollback_configuration(settings: &StackSettingsInput) -> Option<RollbackConfiguration> {
+    if settings.rollback_trigger_arns.is_empty() && settings.rollback_monitoring_time_in_minutes.is_none() {
+        return None;
+    }
+
+    let triggers = settings
+        .rollback_trigger_arns
+        .iter()
+        .map(|arn| RollbackTrigger::builder().arn(arn).r#type("AWS::CloudWatch::Alarm").build())
+        .collect();
+
+    Some(
+        RollbackConfiguration::builder()
+            .set_rollback_triggers(Some(triggers))
+            .set_monitoring_time_in_minutes(settings.rollback_monitoring_time_in_minutes.map(i32::from))
+            .build(),
+    )
+}
+
+fn to_notification_arns(arns: Vec<String>) -> Option<Vec<String>> {
+    if arns.is_empty() { None } else { Some(arns) }
+}
+
+fn to_operation_preferences(options: &StackSetOptions) -> StackSetOperationPreferences {
+    let region_concurrency = match options.region_concurrency {
+        RegionConcurrency::Sequential => RegionConcurrencyType::Sequential,
+        RegionConcurrency::Parallel => RegionConcurrencyType::Parallel,
+    };
+    let builder = StackSetOperationPreferences::builder().region_concurrency_type(region_concurrency);
+    let builder = match options.max_concurrent {
+        Some(Concurrency::Count(count)) => builder.max_concurrent_count(count as i32),
+        Some(Concurrency::Percentage(percentage)) => builder.max_concurrent_percentage(i32::from(percentage)),
+        None => builder,
+    };
+    let builder = match options.failure_tolerance {
+        Some(Concurrency::Count(count)) => builder.failure_tolerance_count(count as i32),
+        Some(Concurrency::Percentage(percentage)) => builder.failure_tolerance_percentage(i32::from(percentage)),
+        None => builder,
+    };
+    builder.build()
+}
+
+impl CloudFormationApi for AwsCloudFormation {
+    async fn describe_stack(&self, name: &str) -> Result<Option<StackInfo>, String> {
+        match self.0.describe_stacks().stack_name(name).send().await {
+            Ok(output) => Ok(output.stacks.and_then(|mut s| s.pop()).and_then(|s| {
+                let outputs = s
+                    .outputs
+                    .unwrap_or_default()
+                    .into_iter()
+                    .flat_map(|o| match (o.output_key, o.output_value) {
+                        (Some(key), Some(value)) => Some((key, value)),
+                        _ => None,
+                    })
+                    .collect();
+
+                s.stack_status.map(|status| StackInfo {
+                    stack_id: s.stack_id.unwrap_or_default(),
+                    status,
+                    outputs,
+                })
+            })),
+            Err(e) if is_missing_stack(&e) => Ok(None),
+            Err(e) => Err(format!("{e:?}")),
+        }
+    }
+
+    async fn list_stacks(&self) -> Result<Vec<StackSummaryInfo>, String> {
+        let mut stacks = vec![];
+        let mut next_token = None;
+
+        loop {
+            let output = self
+                .0
+                .list_stacks()
+                .set_next_token(next_token)
+                .send()
+                .await
+                .map_err(|e| format!("{e:?}"))?;
+
+            stacks.extend(output.stack_summaries.unwrap_or_default().into_iter().flat_map(|s| {
+                match (s.stack_name, s.stack_status) {
+                    (Some(name), Some(status)) if status != StackStatus::DeleteComplete => Some(StackSummaryInfo {
+                        name,
+                        status,
+                        last_updated: s
+                            .last_updated_time
+                            .or(s.creation_time)
+                            .and_then(|t| t.fmt(DateTimeFormat::DateTime).ok()),
+                        drift_status: s.drift_information.and_then(|d| d.stack_drift_status).map(|d| d.to_string()),
+                    }),
+                    _ => None,
+                }
+            }));
+            next_token = output.next_token;
+
+            if next_token.is_none() {
+                return Ok(stacks);
+            }
+        }
+    }
+
+    async fn get_template(&self, name: &str) -> Result<Option<String>, String> {
+        match self.0.get_template().stack_name(name).send().await {
+            Ok(output) => Ok(output.template_body),
+            Err(e) if is_missing_stack(&e) => Ok(None),
+            Err(e) => Err(format!("{e:?}")),
+        }
+    }
+
+    async fn create_stack(&self, input: StackInput) -> Result<(), String> {
+        let settings = input.settings.unwrap_or_default();
+        self.0
+            .create_stack()
+            .stack_name(input.name)
+            .template_body(input.template)
+            .capabilities(Capability::CapabilityNamedIam)
+            .set_role_arn(input.role_arn)
+            .set_tags(to_tags(input.tags))
+            .set_enable_termination_protection(settings.termination_protection)
+            .set_rollback_configuration(to_rollback_configuration(&settings))
+            .set_stack_policy_body(settings.stack_policy)
+            .set_notification_arns(to_notification_arns(settings.notification_arns))
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(())
+    }
+
+    async fn update_stack(&self, input: StackInput) -> Result<bool, String> {
+        let (rollback_configuration, notification_arns) = match input.settings {
+            // unlike on creation, pass empty triggers and topics, or CloudFormation keeps those of the previous deployment
+            Some(settings) => (
+                Some(
+                    to_rollback_configuration(&settings)
+                        .unwrap_or_else(|| RollbackConfiguration::builder().set_rollback_triggers(Some(vec![])).build()),
+                ),
+                Some(settings.notification_arns),
+            ),
+            // unknown settings, so keep those of the previous deployment
+            None => (None, None),
+        };
+
+        match self
+            .0
+            .update_stack()
+            .stack_name(input.name)
+            .template_body(input.template)
+            .capabilities(Capability::CapabilityNamedIam)
+            .set_role_arn(input.role_arn)
+            .set_tags(to_tags(input.tags))
+            .set_rollback_configuration(rollback_configuration)
+            .set_notification_arns(notification_arns)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => match e {
+                SdkError::ServiceError(ref s) => {
+                    let update_stack_error = s.err();
+                    if update_stack_error
+                        .message()
+                        .map(|v| v.contains("No updates are to be performed"))
+                        .unwrap_or(false)
+                    {
+                        Ok(false)
+                    } else {
+                        Err(format!("{e:?}"))
+                    }
+                }
+                _ => Err(format!("{e:?}")),
+            },
+        }
+    }
+
+    async fn delete_stack(&self, name: &str, role_arn: Option<String>) -> Result<(), String> {
+        self.0
+            .delete_stack()
+            .stack_name(name)
+            .set_role_arn(role_arn)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    async fn update_termination_protection(&self, name: &str, enabled: bool) -> Result<(), String> {
+        self.0
+            .update_termination_protection()
+            .stack_name(name)
+            .enable_termination_protection(enabled)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(())
+    }
+
+    async fn get_stack_policy(&self, name: &str) -> Result<Option<String>, String> {
+        let output = self
+            .0
+            .get_stack_policy()
+            .stack_name(name)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(output.stack_policy_body)
+    }
+
+    async fn set_stack_policy(&self, name: &str, policy: &str) -> Result<(), String> {
+        self.0
+            .set_stack_policy()
+            .stack_name(name)
+            .stack_policy_body(policy)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(())
+    }
+
+    async fn list_stack_resources(&self, name: &str) -> Result<Vec<StackResourceInfo>, String> {
+        // DescribeStackResources returns at most 100 resources, ListStackResources can be paginated
+        let mut resources = vec![];
+        let mut next_token = None;
+
+        loop {
+            let output = self
+                .0
+                .list_stack_resources()
+                .stack_name(name)
+                .set_next_token(next_token)
+                .send()
+                .await
+                .map_err(|e| format!("{e:?}"))?;
+
+            resources.extend(output.stack_resource_summaries.unwrap_or_default().into_iter().flat_map(|r| {
+                r.logical_resource_id.map(|resource_id| StackResourceInfo {
+                    resource_id,
+                    physical_id: r.physical_resource_id,
+                    status: r.resource_status,
+                })
+            }));
+            next_token = output.next_token;
+
+            if next_token.is_none() {
+                return Ok(resources);
+            }
+        }
+    }
+
+    async fn describe_stack_events(&self, name: &str) -> Result<Vec<StackEventInfo>, String> {
+        let events = self
+            .0
+            .describe_stack_events()
+            .stack_name(name)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?
+            .stack_events
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|e| {
+                e.event_id.map(|event_id| StackEventInfo {
+                    event_id,
+                    resource_id: e.logical_resource_id.unwrap_or_default(),
+                    resource_type: e.resource_type.unwrap_or_default(),
+                    status: e.resource_status.map(|s| s.to_string()).unwrap_or_default(),
+                    reason: e.resource_status_reason,
+                })
+            })
+            .collect();
+        Ok(events)
+    }
+
+    async fn get_physical_id(&self, name: &str, resource_id: &str) -> Result<Option<String>, String> {
+        let output = self
+            .0
+            .describe_stack_resource()
+            .stack_name(name)
+            .logical_resource_id(resource_id)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(output.stack_resource_detail.and_then(|r| r.physical_resource_id))
+    }
+
+    async fn cancel_update_stack(&self, name: &str) -> Result<(), String> {
+        self.0
+            .cancel_update_stack()
+            .stack_name(name)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(())
+    }
+
+    async fn continue_update_rollback(
+        &self,
+        name: &str,
+        resources_to_skip: Option<Vec<String>>,
+        role_arn: Option<String>,
+    ) -> Result<(), String> {
+        self.0
+            .continue_update_rollback()
+            .stack_name(name)
+            .set_resources_to_skip(resources_to_skip)
+            .set_role_arn(role_arn)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(())
+    }
+
+    async fn create_import_change_set(
+        &self,
+        input: StackInput,
+        change_set_name: &str,
+        resources: Vec<ResourceToImport>,
+    ) -> Result<(), String> {
+        let resources_to_import = resources
+            .into_iter()
+            .map(|r| {
+                aws_sdk_cloudformation::types::ResourceToImport::builder()
+                    .resource_type(r.resource_type)
+                    .logical_resource_id(r.resource_id)
+                    .resource_identifier(r.identifier_property, r.identifier)
+                    .build()
+            })
+            .collect();
+
+        self.0
+            .create_change_set()
+            .stack_name(input.name)
+            .change_set_name(change_set_name)
+            .change_set_type(ChangeSetType::Import)
+            .template_body(input.template)
+            .capabilities(Capability::CapabilityNamedIam)
+            .set_role_arn(input.role_arn)
+            .set_tags(to_tags(input.tags))
+            .set_resources_to_import(Some(resources_to_import))
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(())
+    }
+
+    async fn describe_change_set(&self, name: &str, change_set_name: &str) -> Result<(ChangeSetStatus, Option<String>), String> {
+        let output = self
+            .0
+            .describe_change_set()
+            .stack_name(name)
+            .change_set_name(change_set_name)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        let status = output.status.ok_or_else(|| format!("change set {change_set_name} has no status"))?;
+        Ok((status, output.status_reason))
+    }
+
+    async fn execute_change_set(&self, name: &str, change_set_name: &str) -> Result<(), String> {
+        self.0
+            .execute_change_set()
+            .stack_name(name)
+            .change_set_name(change_set_name)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(())
+    }
+
+    async fn detect_stack_drift(&self, name: &str) -> Result<String, String> {
+        self.0
+            .detect_stack_drift()
+            .stack_name(name)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?
+            .stack_drift_detection_id
+            .ok_or_else(|| "no drift detection id was returned".to_string())
+    }
+
+    async fn describe_stack_drift_detection_status(&self, detection_id: &str) -> Result<DriftDetectionInfo, String> {
+        let output = self
+            .0
+            .describe_stack_drift_detection_status()
+            .stack_drift_detection_id(detection_id)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+
+        Ok(DriftDetectionInfo {
+            status: output
+                .detection_status
+                .ok_or_else(|| "no drift detection status was returned".to_string())?,
+            reason: output.detection_status_reason,
+            drifted_resource_count: output.drifted_stack_resource_count,
+        })
+    }
+
+    async fn describe_stack_resource_drifts(&self, name: &str) -> Result<Vec<DriftedResource>, String> {
+        let mut drifts = vec![];
+        let mut next_token = None;
+
+        loop {
+            let output = self
+                .0
+                .describe_stack_resource_drifts()
+                .stack_name(name)
+                .stack_resource_drift_status_filters(StackResourceDriftStatus::Modified)
+                .stack_resource_drift_status_filters(StackResourceDriftStatus::Deleted)
+                .set_next_token(next_token)
+                .send()
+                .await
+                .map_err(|e| format!("{e:?}"))?;
+
+            drifts.extend(output.stack_resource_drifts.unwrap_or_default().into_iter().map(|drift| {
+                let differences = drift
+                    .property_differences
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|d| PropertyDrift {
+                        path: d.property_path.unwrap_or_default(),
+                        expected: d.expected_value,
+                        actual: d.actual_value,
+                        difference_type: d.difference_type.map(|t| t.to_string()).unwrap_or_default(),
+                    })
+                    .collect();
+
+                DriftedResource {
+                    id: None,
+                    resource_id: drift.logical_resource_id.unwrap_or_default(),
+                    physical_id: drift.physical_resource_id,
+                    resource_type: drift.resource_type.unwrap_or_default(),
+                    status: drift.stack_resource_drift_status.map(|s| s.to_string()).unwrap_or_default(),
+                    differences,
+                }
+            }));
+            next_token = output.next_token;
+
+            if next_token.is_none() {
+                return Ok(drifts);
+            }
+        }
+    }
+}
+
+impl StackSetApi for AwsCloudFormation {
+    async fn get_stack_set_template(&self, name: &str) -> Result<Option<String>, String> {
+        match self.0.describe_stack_set().stack_set_name(name).send().await {
+            Ok(output) => Ok(output.stack_set.and_then(|s| s.template_body)),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_stack_set_not_found_exception()) => Ok(None),
+            Err(e) => Err(format!("{e:?}")),
+        }
+    }
+
+    async fn create_stack_set(&self, input: StackSetInput) -> Result<String, String> {
+        let (permission_model, auto_deployment) = if input.service_managed {
+            let auto_deployment = AutoDeployment::builder()
+                .enabled(true)
+                .retain_stacks_on_account_removal(false)
+                .build();
+            (PermissionModels::ServiceManaged, Some(auto_deployment))
+        } else {
+            (PermissionModels::SelfManaged, None)
+        };
+
+        let output = self
+            .0
+            .create_stack_set()
+            .stack_set_name(input.name)
+            .template_body(input.template)
+            .capabilities(Capability::CapabilityNamedIam)
+            .set_tags(to_tags(input.tags))
+            .permission_model(permission_model)
+            .set_auto_deployment(auto_deployment)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(output.stack_set_id.unwrap_or_default())
+    }
+
+    async fn update_stack_set(&self, input: StackSetInput, options: &StackSetOptions) -> Result<String, String> {
+        let output = self
+            .0
+            .update_stack_set()
+            .stack_set_name(input.name)
+            .template_body(input.template)
+            .capabilities(Capability::CapabilityNamedIam)
+            .set_tags(to_tags(input.tags))
+            .operation_preferences(to_operation_preferences(options))
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        output
+            .operation_id
+            .ok_or_else(|| "stack set update did not return an operation id".to_string())
+    }
+
+    async fn list_stack_instances(&self, name: &str) -> Result<Vec<StackInstanceInfo>, String> {
+        let mut instances = vec![];
+        let mut next_token = None;
+
+        loop {
+            let output = self
+                .0
+                .list_stack_instances()
+                .stack_set_name(name)
+                .set_next_token(next_token)
+                .send()
+                .await
+                .map_err(|e| format!("{e:?}"))?;
+
+            instances.extend(output.summaries.unwrap_or_default().into_iter().flat_map(|s| {
+                // instances of service-managed stack sets belong to an organizational unit
+                let target = s.organizational_unit_id.or(s.account);
+                target.zip(s.region).map(|(target, region)| StackInstanceInfo { target, region })
+            }));
+
+            next_token = output.next_token;
+            if next_token.is_none() {
+                return Ok(instances);
+            }
+        }
+    }
+
+    async fn create_stack_instances(
+        &self,
+        name: &str,
+        targets: &StackSetTargets,
+        regions: Vec<String>,
+        options: &StackSetOptions,
+    ) -> Result<String, String> {
+        let request = self
+            .0
+            .create_stack_instances()
+            .stack_set_name(name)
+            .set_regions(Some(regions))
+            .operation_preferences(to_operation_preferences(options));
+        let request = match targets {
+            StackSetTargets::Accounts(accounts) => request.set_accounts(Some(accounts.clone())),
+            StackSetTargets::OrganizationalUnits(ids) => {
+                request.deployment_targets(DeploymentTargets::builder().set_organizational_unit_ids(Some(ids.clone())).build())
+            }
+        };
+
+        let output = request.send().await.map_err(|e| format!("{e:?}"))?;
+        output
+            .operation_id
+            .ok_or_else(|| "creating stack instances did not return an operation id".to_string())
+    }
+
+    async fn describe_stack_set_operation(
+        &self,
+        name: &str,
+        operation_id: &str,
+    ) -> Result<(StackSetOperationStatus, Option<String>), String> {
+        let operation = self
+            .0
+            .describe_stack_set_operation()
+            .stack_set_name(name)
+            .operation_id(operation_id)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?
+            .stack_set_operation
+            .ok_or_else(|| format!("operation {operation_id} not found"))?;
+        let status = operation.status.ok_or_else(|| format!("operation {operation_id} has no status"))?;
+        Ok((status, operation.status_reason))
+    }
+
+    async fn list_stack_set_operation_results(&self, name: &str, operation_id: &str) -> Result<Vec<StackInstanceResult>, String> {
+        let mut results = vec![];
+        let mut next_token = None;
+
+        loop {
+            let output = self
+                .0
+                .list_stack_set_operation_results()
+                .stack_set_name(name)
+                .operation_id(operation_id)
+                .set_next_token(next_token)
+                .send()
+                .await
+                .map_err(|e| format!("{e:?}"))?;
+
+            results.extend(
+                output
+                    .summaries
+                    .unwrap_or_default()
+                    .into_iter()
+                    .flat_map(|s| match (s.account, s.region, s.status) {
+                        (Some(account), Some(region), Some(status)) => Some(StackInstanceResult {
+                            account,
+                            region,
+                            status,
+                            reason: s.status_reason,
+                        }),
+                        _ => None,
+                    }),
+            );
+
+            next_token = output.next_token;
+            if next_token.is_none() {
+                return Ok(results);
+            }
+        }
+    }
+}
+
+impl S3Api for AwsS3 {
+    async fn put_object(&self, bucket: &str, key: &str, path: &str) -> Result<(), String> {
+        let body = ByteStream::from_path(path)
+            .await
+            .map_err(|e| format!("could not read file at {path}: {e:?}"))?;
+        self.0
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(())
+    }
+
+    async fn list_objects(&self, bucket: &str, marker: Option<String>) -> Result<(Vec<String>, Option<String>), String> {
+        let objects = self
+            .0
+            .list_objects()
+            .bucket(bucket)
+            .set_marker(marker)
+            .send()
+            .await
+            .map_err(|e| format!("could not list objects to delete: {:?}", e))?;
+
+        let keys: Vec<String> = objects.contents.unwrap_or_default().into_iter().flat_map(|o| o.key).collect();
+        // without a delimiter, S3 does not return a next marker, the last key should be used instead
+        let next_marker = if objects.is_truncated.unwrap_or(false) {
+            objects.next_marker.or_else(|| keys.last().cloned())
+        } else {
+            None
+        };
+
+        Ok((keys, next_marker))
+    }
+
+    async fn delete_objects(&self, bucket: &str, keys: Vec<String>) -> Result<(), String> {
+        let objects_to_delete = keys
+            .into_iter()
+            .map(|key| ObjectIdentifier::builder().key(key).build().map_err(|e| format!("{e:?}")))
+            .collect::<Result<Vec<_>, String>>()?;
+        let to_delete = Delete::builder()
+            .set_objects(Some(objects_to_delete))
+            .build()
+            .map_err(|e| format!("{e:?}"))?;
+
+        self.0
+            .delete_objects()
+            .bucket(bucket)
+            .delete(to_delete)
+            .send()
+            .await
+            .map_err(|e| format!("could not delete objects: {:?}", e))?;
+        Ok(())
+    }
+
+    async fn list_object_versions(
+        &self,
+        bucket: &str,
+        marker: Option<ObjectVersion>,
+    ) -> Result<(Vec<ObjectVersion>, Option<ObjectVersion>), String> {
+        let (key_marker, version_id_marker) = marker.unzip();
+        let output = self
+            .0
+            .list_object_versions()
+            .bucket(bucket)
+            .set_key_marker(key_marker)
+            .set_version_id_marker(version_id_marker)
+            .send()
+            .await
+            .map_err(|e| format!("could not list object versions to delete: {:?}", e))?;
+
+        let versions = output.versions.unwrap_or_default().into_iter().map(|v| (v.key, v.version_id));
+        let delete_markers = output.delete_markers.unwrap_or_default().into_iter().map(|m| (m.key, m.version_id));
+        let versions = versions
+            .chain(delete_markers)
+            .flat_map(|(key, version_id)| key.zip(version_id))
+            .collect();
+
+        let next_marker = if output.is_truncated.unwrap_or(false) {
+            output.next_key_marker.zip(output.next_version_id_marker)
+        } else {
+            None
+        };
+
+        Ok((versions, next_marker))
+    }
+
+    async fn delete_object_versions(&self, bucket: &str, versions: Vec<ObjectVersion>) -> Result<(), String> {
+        let objects_to_delete = versions
+            .into_iter()
+            .map(|(key, version_id)| {
+                ObjectIdentifier::builder()
+                    .key(key)
+                    .version_id(version_id)
+                    .build()
+                    .map_err(|e| format!("{e:?}"))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let to_delete = Delete::builder()
+            .set_objects(Some(objects_to_delete))
+            .build()
+            .map_err(|e| format!("{e:?}"))?;
+
+        self.0
+            .delete_objects()
+            .bucket(bucket)
+            .delete(to_delete)
+            .send()
+            .await
+            .map_err(|e| format!("could not delete object versions: {:?}", e))?;
+        Ok(())
+    }
+}
+
+impl LambdaApi for AwsLambda {
+    async fn update_function_code(&self, function_name: &str, bucket: &str, key: &str) -> Result<(), String> {
+        self.0
+            .update_function_code()
+            .function_name(function_name)
+            .s3_bucket(bucket)
+            .s3_key(key)
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("{e:?}"))
+    }
+
+    async fn get_last_update_status(&self, function_name: &str) -> Result<(Option<LastUpdateStatus>, Option<String>), String> {
+        let configuration = self
+            .0
+            .get_function_configuration()
+            .function_name(function_name)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        Ok((configuration.last_update_status, configuration.last_update_status_reason))
+    }
+}
+
+impl SnsApi for AwsSns {
+    async fn set_topic_attribute(&self, arn: &str, name: &str, value: &str) -> Result<(), String> {
+        self.0
+            .set_topic_attributes()
+            .topic_arn(arn)
+            .attribute_name(name)
+            .attribute_value(value)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(())
+    }
+}
+
+impl EcrApi for AwsEcr {
+    async fn list_image_digests(&self, repository: &str) -> Result<Vec<String>, String> {
+        let output = self
+            .0
+            .list_images()
+            .repository_name(repository)
+            .send()
+            .await
+            .map_err(|e| format!("could not list images to delete: {:?}", e))?;
+
+        let mut digests: Vec<String> = output
+            .image_ids
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|i| i.image_digest)
+            .collect();
+        // an image with multiple tags is listed once per tag
+        digests.sort();
+        digests.dedup();
+
+        Ok(digests)
+    }
+
+    async fn delete_images(&self, repository: &str, digests: Vec<String>) -> Result<(), String> {
+        let image_ids = digests
+            .into_iter()
+            .map(|digest| ImageIdentifier::builder().image_digest(digest).build())
+            .collect();
+
+        let output = self
+            .0
+            .batch_delete_image()
+            .repository_name(repository)
+            .set_image_ids(Some(image_ids))
+            .send()
+            .await
+            .map_err(|e| format!("could not delete images: {:?}", e))?;
+
+        match output.failures {
+            Some(failures) if !failures.is_empty() => Err(format!("could not delete images: {:?}", failures)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl DeletionProtectionApi for AwsDeletionProtection {
+    async fn is_table_deletion_protected(&self, table_name: &str) -> Result<bool, String> {
+        Ok(self
+            .dynamodb
+            .describe_table()
+            .table_name(table_name)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?
+            .table
+            .and_then(|t| t.deletion_protection_enabled)
+            .unwrap_or(false))
+    }
+
+    async fn disable_table_deletion_protection(&self, table_name: &str) -> Result<bool, String> {
+        let enabled = self.is_table_deletion_protected(table_name).await?;
+
+        if enabled {
+            self.dynamodb
+                .update_table()
+                .table_name(table_name)
+                .deletion_protection_enabled(false)
+                .send()
+                .await
+                .map_err(|e| format!("{e:?}"))?;
+        }
+        Ok(enabled)
+    }
+
+    async fn disable_cluster_deletion_protection(&self, cluster_identifier: &str) -> Result<(), String> {
+        self.docdb
+            .modify_db_cluster()
+            .db_cluster_identifier(cluster_identifier)
+            .deletion_protection(false)
+            .apply_immediately(true)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(())
+    }
+
+    async fn disable_global_cluster_deletion_protection(&self, global_cluster_identifier: &str) -> Result<(), String> {
+        self.docdb
+            .modify_global_cluster()
+            .global_cluster_identifier(global_cluster_identifier)
+            .deletion_protection(false)
+            .send()
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,835 @@
+use crate::clients::{
+    CloudFormationApi, DeletionProtectionApi, DriftDetectionInfo, EcrApi, LambdaApi, ObjectVersion, S3Api, SnsApi, StackEventInfo,
+    StackInfo, StackInput, StackInstanceInfo, StackInstanceResult, StackResourceInfo, StackSetApi, StackSetInput, StackSettingsInput,
+    StackSummaryInfo,
+};
+use crate::drift::DriftedResource;
+use crate::stack_set::{StackSetOptions, StackSetTargets};
+use aws_sdk_cloudformation::types::{
+    ChangeSetStatus, StackDriftDetectionStatus, StackSetOperationResultStatus, StackSetOperationStatus, StackStatus,
+};
+use aws_sdk_lambda::types::LastUpdateStatus;
+use rusty_cdk_core::stack::ResourceToImport;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+struct FakeStack {
+    template: String,
+    // every `describe_stack` moves to the next status, the last one is kept
+    statuses: VecDeque<StackStatus>,
+    resources: Vec<StackResourceInfo>,
+    outputs: HashMap<String, String>,
+    // newest first, every operation adds an event for the stack itself
+    events: Vec<StackEventInfo>,
+    settings: StackSettingsInput,
+    // the resources that drift detection finds
+    drifts: Vec<DriftedResource>,
+}
+
+/// In-memory CloudFormation, that moves stacks through a list of statuses
+pub(crate) struct FakeCloudFormation {
+    stacks: Mutex<HashMap<String, FakeStack>>,
+    calls: Mutex<Vec<String>>,
+    // templates of the change sets that were created but not executed, keyed by stack and change set name
+    change_sets: Mutex<HashMap<(String, String), String>>,
+    // stack names and statuses of the drift detections that were started, keyed by detection id. Like stacks, every describe moves to the next status
+    drift_detections: Mutex<HashMap<String, (String, VecDeque<StackDriftDetectionStatus>)>>,
+    /// When set, change sets fail with this reason
+    pub(crate) change_set_failure: Option<String>,
+    /// The reason of a drift detection that ends in `DETECTION_FAILED`
+    pub(crate) drift_detection_failure: Option<String>,
+    pub(crate) create_statuses: Vec<StackStatus>,
+    pub(crate) update_statuses: Vec<StackStatus>,
+    pub(crate) delete_statuses: Vec<StackStatus>,
+    pub(crate) rollback_statuses: Vec<StackStatus>,
+    pub(crate) import_statuses: Vec<StackStatus>,
+    pub(crate) drift_detection_statuses: Vec<StackDriftDetectionStatus>,
+}
+
+impl FakeCloudFormation {
+    pub(crate) fn new() -> Self {
+        Self {
+            stacks: Mutex::new(HashMap::new()),
+            calls: Mutex::new(vec![]),
+            change_sets: Mutex::new(HashMap::new()),
+            drift_detections: Mutex::new(HashMap::new()),
+            change_set_failure: None,
+            drift_detection_failure: None,
+            create_statuses: vec![StackStatus::CreateInProgress, StackStatus::CreateComplete],
+            update_statuses: vec![StackStatus::UpdateInProgress, StackStatus::UpdateComplete],
+            delete_statuses: vec![StackStatus::DeleteInProgress, StackStatus::DeleteComplete],
+            rollback_statuses: vec![StackStatus::UpdateRollbackInProgress, StackStatus::UpdateRollbackComplete],
+            import_statuses: vec![StackStatus::ImportInProgress, StackStatus::ImportComplete],
+            drift_detection_statuses: vec![
+                StackDriftDetectionStatus::DetectionInProgress,
+                StackDriftDetectionStatus::DetectionComplete,
+            ],
+        }
+    }
+
+    pub(crate) fn with_stack(self, name: &str, template: &str, statuses: Vec<StackStatus>) -> Self {
+        self.stacks.lock().unwrap().insert(
+            name.to_string(),
+            FakeStack {
+                template: template.to_string(),
+                statuses: statuses.into(),
+                resources: vec![],
+                outputs: HashMap::new(),
+                events: vec![],
+                settings: StackSettingsInput::default(),
+                drifts: vec![],
+            },
+        );
+        self
+    }
+
+    pub(crate) fn with_resource(self, name: &str, resource: StackResourceInfo) -> Self {
+        self.stacks
+            .lock()
+            .unwrap()
+            .get_mut(name)
+            .expect("stack to be added before its resources")
+            .resources
+            .push(resource);
+        self
+    }
+
+    pub(crate) fn with_output(self, name: &str, key: &str, value: &str) -> Self {
+        self.stacks
+            .lock()
+            .unwrap()
+            .get_mut(name)
+            .expect("stack to be added before its outputs")
+            .outputs
+            .insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub(crate) fn with_drift(self, name: &str, drift: DriftedResource) -> Self {
+        self.stacks
+            .lock()
+            .unwrap()
+            .get_mut(name)
+            .expect("stack to be added before its drifts")
+            .drifts
+            .push(drift);
+        self
+    }
+
+    /// The names of the operations that changed something, in order
+    pub(crate) fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    pub(crate) fn template(&self, name: &str) -> Option<String> {
+        self.stacks.lock().unwrap().get(name).map(|s| s.template.clone())
+    }
+
+    pub(crate) fn settings(&self, name: &str) -> Option<StackSettingsInput> {
+        self.stacks.lock().unwrap().get(name).map(|s| s.settings.clone())
+    }
+
+    fn record(&self, call: &str) {
+        self.calls.lock().unwrap().push(call.to_string());
+    }
+
+    fn transition(&self, name: &str, statuses: &[StackStatus]) -> Result<(), String> {
+        let mut stacks = self.stacks.lock().unwrap();
+        let stack = stacks.get_mut(name).ok_or_else(|| format!("stack {name} does not exist"))?;
+        stack.statuses = statuses.iter().cloned().collect();
+        Self::add_event(name, stack);
+        Ok(())
+    }
+
+    fn add_event(name: &str, stack: &mut FakeStack) {
+        let status = stack.statuses.front().map(|s| s.to_string()).unwrap_or_default();
+        stack.events.insert(
+            0,
+            StackEventInfo {
+                event_id: format!("event-{}", stack.events.len()),
+                resource_id: name.to_string(),
+                resource_type: "AWS::CloudFormation::Stack".to_string(),
+                status,
+                reason: None,
+            },
+        );
+    }
+}
+
+impl CloudFormationApi for FakeCloudFormation {
+    async fn describe_stack(&self, name: &str) -> Result<Option<StackInfo>, String> {
+        let mut stacks = self.stacks.lock().unwrap();
+        let Some(stack) = stacks.get_mut(name) else {
+            return Ok(None);
+        };
+        let status = if stack.statuses.len() > 1 {
+            stack.statuses.pop_front()
+        } else {
+            stack.statuses.front().cloned()
+        }
+        .expect("fake stack to have a status");
+
+        let info = StackInfo {
+            stack_id: format!("arn:aws:cloudformation:us-east-1:123456789012:stack/{name}/1"),
+            status: status.clone(),
+            outputs: stack.outputs.clone(),
+        };
+
+        if status == StackStatus::DeleteComplete {
+            stacks.remove(name);
+        }
+        Ok(Some(info))
+    }
+
+    async fn list_stacks(&self) -> Result<Vec<StackSummaryInfo>, String> {
+        let stacks = self.stacks.lock().unwrap();
+        let mut summaries: Vec<_> = stacks
+            .iter()
+            .map(|(name, stack)| StackSummaryInfo {
+                name: name.clone(),
+                status: stack.statuses.front().cloned().expect("fake stack to have a status"),
+                last_updated: Some("2025-01-01T00:00:00Z".to_string()),
+                drift_status: None,
+            })
+            .collect();
+        summaries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(summaries)
+    }
+
+    async fn get_template(&self, name: &str) -> Result<Option<String>, String> {
+        Ok(self.template(name))
+    }
+
+    async fn create_stack(&self, input: StackInput) -> Result<(), String> {
+        self.record("create_stack");
+        let mut stacks = self.stacks.lock().unwrap();
+        if stacks.contains_key(&input.name) {
+            return Err(format!("stack {} already exists", input.name));
+        }
+        let mut stack = FakeStack {
+            template: input.template,
+            statuses: self.create_statuses.iter().cloned().collect(),
+            resources: vec![],
+            outputs: HashMap::new(),
+            events: vec![],
+            settings: input.settings.unwrap_or_default(),
+            drifts: vec![],
+        };
+        Self::add_event(&input.name, &mut stack);
+        stacks.insert(input.name, stack);
+        Ok(())
+    }
+
+    async fn update_stack(&self, input: StackInput) -> Result<bool, String> {
+        // compare as json, because the order of the resources can differ between synths
+        let existing: Option<Value> = self.template(&input.name).and_then(|t| serde_json::from_str(&t).ok());
+        if existing.is_some() && existing == serde_json::from_str(&input.template).ok() {
+            // no updates are to be performed
+            return Ok(false);
+        }
+        self.record("update_stack");
+        self.transition(&input.name, &self.update_statuses)?;
+        let mut stacks = self.stacks.lock().unwrap();
+        let stack = stacks.get_mut(&input.name).expect("stack to exist");
+        stack.template = input.template;
+        if let Some(settings) = input.settings {
+            stack.settings.rollback_trigger_arns = settings.rollback_trigger_arns;
+            stack.settings.rollback_monitoring_time_in_minutes = settings.rollback_monitoring_time_in_minutes;
+            stack.settings.notification_arns = settings.notification_arns;
+        }
+        Ok(true)
+    }
+
+    async fn delete_stack(&self, name: &str, _role_arn: Option<String>) -> Result<(), String> {
+        if self.settings(name).and_then(|s| s.termination_protection) == Some(true) {
+            return Err(format!("stack {name} has termination protection"));
+        }
+        self.record("delete_stack");
+        // like CloudFormation, deleting a stack that does not exist succeeds
+        if self.template(name).is_some() {
+            self.transition(name, &self.delete_statuses)?;
+        }
+        Ok(())
+    }
+
+    async fn update_termination_protection(&self, name: &str, enabled: bool) -> Result<(), String> {
+        self.record(&format!("update_termination_protection {enabled}"));
+        let mut stacks = self.stacks.lock().unwrap();
+        let stack = stacks.get_mut(name).ok_or_else(|| format!("stack {name} does not exist"))?;
+        stack.settings.termination_protection = Some(enabled);
+        Ok(())
+    }
+
+    async fn get_stack_policy(&self, name: &str) -> Result<Option<String>, String> {
+        let stacks = self.stacks.lock().unwrap();
+        let stack = stacks.get(name).ok_or_else(|| format!("stack {name} does not exist"))?;
+        Ok(stack.settings.stack_policy.clone())
+    }
+
+    async fn set_stack_policy(&self, name: &str, policy: &str) -> Result<(), String> {
+        self.record("set_stack_policy");
+        let mut stacks = self.stacks.lock().unwrap();
+        let stack = stacks.get_mut(name).ok_or_else(|| format!("stack {name} does not exist"))?;
+        stack.settings.stack_policy = Some(policy.to_string());
+        Ok(())
+    }
+
+    async fn list_stack_resources(&self, name: &str) -> Result<Vec<StackResourceInfo>, String> {
+        let stacks = self.stacks.lock().unwrap();
+        let stack = stacks.get(name).ok_or_else(|| format!("stack {name} does not exist"))?;
+        Ok(stack.resources.clone())
+    }
+
+    async fn describe_stack_events(&self, name: &str) -> Result<Vec<StackEventInfo>, String> {
+        let stacks = self.stacks.lock().unwrap();
+        let stack = stacks.get(name).ok_or_else(|| format!("stack {name} does not exist"))?;
+        Ok(stack.events.clone())
+    }
+
+    async fn cancel_update_stack(&self, name: &str) -> Result<(), String> {
+        self.record("cancel_update_stack");
+        self.transition(name, &self.rollback_statuses)
+    }
+
+    async fn continue_update_rollback(
+        &self,
+        name: &str,
+        resources_to_skip: Option<Vec<String>>,
+        _role_arn: Option<String>,
+    ) -> Result<(), String> {
+        self.record(&format!(
+            "continue_update_rollback {}",
+            resources_to_skip.unwrap_or_default().join(",")
+        ));
+        self.transition(name, &self.rollback_statuses)
+    }
+
+    async fn create_import_change_set(
+        &self,
+        input: StackInput,
+        change_set_name: &str,
+        resources: Vec<ResourceToImport>,
+    ) -> Result<(), String> {
+        self.record(&format!(
+            "create_import_change_set {}",
+            resources.into_iter().map(|r| r.identifier).collect::<Vec<_>>().join(",")
+        ));
+        self.stacks.lock().unwrap().entry(input.name.clone()).or_insert_with(|| FakeStack {
+            template: "{}".to_string(),
+            statuses: vec![StackStatus::ReviewInProgress].into(),
+            resources: vec![],
+            outputs: HashMap::new(),
+            events: vec![],
+            settings: StackSettingsInput::default(),
+            drifts: vec![],
+        });
+        self.change_sets
+            .lock()
+            .unwrap()
+            .insert((input.name, change_set_name.to_string()), input.template);
+        Ok(())
+    }
+
+    async fn describe_change_set(&self, name: &str, change_set_name: &str) -> Result<(ChangeSetStatus, Option<String>), String> {
+        if !self
+            .change_sets
+            .lock()
+            .unwrap()
+            .contains_key(&(name.to_string(), change_set_name.to_string()))
+        {
+            return Err(format!("change set {change_set_name} does not exist"));
+        }
+        match &self.change_set_failure {
+            Some(reason) => Ok((ChangeSetStatus::Failed, Some(reason.clone()))),
+            None => Ok((ChangeSetStatus::CreateComplete, None)),
+        }
+    }
+
+    async fn execute_change_set(&self, name: &str, change_set_name: &str) -> Result<(), String> {
+        self.record("execute_change_set");
+        let template = self
+            .change_sets
+            .lock()
+            .unwrap()
+            .remove(&(name.to_string(), change_set_name.to_string()))
+            .ok_or_else(|| format!("change set {change_set_name} does not exist"))?;
+        self.transition(name, &self.import_statuses)?;
+        self.stacks.lock().unwrap().get_mut(name).expect("stack to exist").template = template;
+        Ok(())
+    }
+
+    async fn detect_stack_drift(&self, name: &str) -> Result<String, String> {
+        if self.template(name).is_none() {
+            return Err(format!("stack {name} does not exist"));
+        }
+        let mut detections = self.drift_detections.lock().unwrap();
+        let detection_id = format!("detection-{}", detections.len());
+        detections.insert(
+            detection_id.clone(),
+            (name.to_string(), self.drift_detection_statuses.iter().cloned().collect()),
+        );
+        Ok(detection_id)
+    }
+
+    async fn describe_stack_drift_detection_status(&self, detection_id: &str) -> Result<DriftDetectionInfo, String> {
+        let (name, status) = {
+            let mut detections = self.drift_detections.lock().unwrap();
+            let (name, statuses) = detections
+                .get_mut(detection_id)
+                .ok_or_else(|| format!("drift detection {detection_id} does not exist"))?;
+            let status = if statuses.len() > 1 {
+                statuses.pop_front()
+            } else {
+                statuses.front().cloned()
+            }
+            .expect("fake drift detection to have a status");
+            (name.clone(), status)
+        };
+
+        Ok(match status {
+            StackDriftDetectionStatus::DetectionComplete => DriftDetectionInfo {
+                status,
+                reason: None,
+                drifted_resource_count: self.describe_stack_resource_drifts(&name).await.ok().map(|d| d.len() as i32),
+            },
+            StackDriftDetectionStatus::DetectionFailed => DriftDetectionInfo {
+                status,
+                reason: self.drift_detection_failure.clone(),
+                drifted_resource_count: None,
+            },
+            status => DriftDetectionInfo {
+                status,
+                reason: None,
+                drifted_resource_count: None,
+            },
+        })
+    }
+
+    async fn describe_stack_resource_drifts(&self, name: &str) -> Result<Vec<DriftedResource>, String> {
+        let stacks = self.stacks.lock().unwrap();
+        let stack = stacks.get(name).ok_or_else(|| format!("stack {name} does not exist"))?;
+        Ok(stack.drifts.clone())
+    }
+}
+
+/// In-memory Lambda, that moves functions through a list of update statuses after their code is updated
+pub(crate) struct FakeLambda {
+    calls: Mutex<Vec<String>>,
+    // every `get_last_update_status` moves to the next status, the last one is kept
+    statuses: Mutex<HashMap<String, VecDeque<LastUpdateStatus>>>,
+    pub(crate) update_statuses: Vec<LastUpdateStatus>,
+    /// The reason of an update that ends in `Failed`
+    pub(crate) update_failure: Option<String>,
+}
+
+impl FakeLambda {
+    pub(crate) fn new() -> Self {
+        Self {
+            calls: Mutex::new(vec![]),
+            statuses: Mutex::new(HashMap::new()),
+            update_statuses: vec![LastUpdateStatus::InProgress, LastUpdateStatus::Successful],
+            update_failure: None,
+        }
+    }
+
+    /// The code updates, as function name, bucket and key, in order
+    pub(crate) fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl LambdaApi for FakeLambda {
+    async fn update_function_code(&self, function_name: &str, bucket: &str, key: &str) -> Result<(), String> {
+        self.calls.lock().unwrap().push(format!("{function_name} {bucket}/{key}"));
+        self.statuses
+            .lock()
+            .unwrap()
+            .insert(function_name.to_string(), self.update_statuses.iter().cloned().collect());
+        Ok(())
+    }
+
+    async fn get_last_update_status(&self, function_name: &str) -> Result<(Option<LastUpdateStatus>, Option<String>), String> {
+        let mut statuses = self.statuses.lock().unwrap();
+        let Some(statuses) = statuses.get_mut(function_name) else {
+            return Ok((None, None));
+        };
+        let status = if statuses.len() > 1 {
+            statuses.pop_front()
+        } else {
+            statuses.front().cloned()
+        };
+        let reason = match status {
+            Some(LastUpdateStatus::Failed) => self.update_failure.clone(),
+            _ => None,
+        };
+        Ok((status, reason))
+    }
+}
+
+/// In-memory S3, with buckets containing keys and object versions
+pub(crate) struct FakeS3 {
+    buckets: Mutex<HashMap<String, Vec<String>>>,
+    versions: Mutex<HashMap<String, Vec<(String, String)>>>,
+    page_size: usize,
+    /// When set, uploads fail with this error
+    pub(crate) put_object_failure: Option<String>,
+}
+
+impl FakeS3 {
+    pub(crate) fn new() -> Self {
+        Self {
+            buckets: Mutex::new(HashMap::new()),
+            versions: Mutex::new(HashMap::new()),
+            page_size: 2,
+            put_object_failure: None,
+        }
+    }
+
+    pub(crate) fn with_objects(self, bucket: &str, keys: Vec<&str>) -> Self {
+        self.buckets
+            .lock()
+            .unwrap()
+            .insert(bucket.to_string(), keys.into_iter().map(|k| k.to_string()).collect());
+        self
+    }
+
+    /// Object versions and delete markers, as keys with version ids
+    pub(crate) fn with_versions(self, bucket: &str, versions: Vec<(&str, &str)>) -> Self {
+        self.versions.lock().unwrap().insert(
+            bucket.to_string(),
+            versions.into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        );
+        self
+    }
+
+    pub(crate) fn keys(&self, bucket: &str) -> Vec<String> {
+        self.buckets.lock().unwrap().get(bucket).cloned().unwrap_or_default()
+    }
+
+    pub(crate) fn versions(&self, bucket: &str) -> Vec<(String, String)> {
+        self.versions.lock().unwrap().get(bucket).cloned().unwrap_or_default()
+    }
+}
+
+impl S3Api for FakeS3 {
+    async fn put_object(&self, bucket: &str, key: &str, _path: &str) -> Result<(), String> {
+        if let Some(failure) = &self.put_object_failure {
+            return Err(failure.clone());
+        }
+        self.buckets
+            .lock()
+            .unwrap()
+            .entry(bucket.to_string())
+            .or_default()
+            .push(key.to_string());
+        Ok(())
+    }
+
+    async fn list_objects(&self, bucket: &str, marker: Option<String>) -> Result<(Vec<String>, Option<String>), String> {
+        let keys = self.keys(bucket);
+        let start = marker.and_then(|m| keys.iter().position(|k| *k == m)).map(|i| i + 1).unwrap_or(0);
+        let page: Vec<_> = keys.iter().skip(start).take(self.page_size).cloned().collect();
+        let next_marker = if start + page.len() < keys.len() {
+            page.last().cloned()
+        } else {
+            None
+        };
+        Ok((page, next_marker))
+    }
+
+    async fn delete_objects(&self, bucket: &str, keys: Vec<String>) -> Result<(), String> {
+        if let Some(existing) = self.buckets.lock().unwrap().get_mut(bucket) {
+            existing.retain(|k| !keys.contains(k));
+        }
+        Ok(())
+    }
+
+    async fn list_object_versions(
+        &self,
+        bucket: &str,
+        marker: Option<ObjectVersion>,
+    ) -> Result<(Vec<ObjectVersion>, Option<ObjectVersion>), String> {
+        let versions = self.versions(bucket);
+        let start = marker
+            .and_then(|m| versions.iter().position(|v| *v == m))
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let page: Vec<_> = versions.iter().skip(start).take(self.page_size).cloned().collect();
+        let next_marker = if start + page.len() < versions.len() {
+            page.last().cloned()
+        } else {
+            None
+        };
+        Ok((page, next_marker))
+    }
+
+    async fn delete_object_versions(&self, bucket: &str, versions: Vec<ObjectVersion>) -> Result<(), String> {
+        if let Some(existing) = self.versions.lock().unwrap().get_mut(bucket) {
+            existing.retain(|v| !versions.contains(v));
+        }
+        Ok(())
+    }
+}
+
+/// In-memory SNS, that only remembers the attributes that were set
+pub(crate) struct FakeSns {
+    pub(crate) attributes: Mutex<Vec<(String, String, String)>>,
+}
+
+impl FakeSns {
+    pub(crate) fn new() -> Self {
+        Self {
+            attributes: Mutex::new(vec![]),
+        }
+    }
+}
+
+impl SnsApi for FakeSns {
+    async fn set_topic_attribute(&self, arn: &str, name: &str, value: &str) -> Result<(), String> {
+        self.attributes
+            .lock()
+            .unwrap()
+            .push((arn.to_string(), name.to_string(), value.to_string()));
+        Ok(())
+    }
+}
+
+/// In-memory ECR, with repositories containing image digests
+pub(crate) struct FakeEcr {
+    repositories: Mutex<HashMap<String, Vec<String>>>,
+}
+
+impl FakeEcr {
+    pub(crate) fn new() -> Self {
+        Self {
+            repositories: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn with_images(self, repository: &str, digests: Vec<&str>) -> Self {
+        self.repositories
+            .lock()
+            .unwrap()
+            .insert(repository.to_string(), digests.into_iter().map(|d| d.to_string()).collect());
+        self
+    }
+
+    pub(crate) fn images(&self, repository: &str) -> Vec<String> {
+        self.repositories.lock().unwrap().get(repository).cloned().unwrap_or_default()
+    }
+}
+
+impl EcrApi for FakeEcr {
+    async fn list_image_digests(&self, repository: &str) -> Result<Vec<String>, String> {
+        Ok(self.images(repository).into_iter().take(2).collect())
+    }
+
+    async fn delete_images(&self, repository: &str, digests: Vec<String>) -> Result<(), String> {
+        if let Some(existing) = self.repositories.lock().unwrap().get_mut(repository) {
+            existing.retain(|d| !digests.contains(d));
+        }
+        Ok(())
+    }
+}
+
+/// Remembers the tables and clusters whose deletion protection was disabled
+pub(crate) struct FakeDeletionProtection {
+    pub(crate) protected_tables: Vec<String>,
+    pub(crate) disabled: Mutex<Vec<String>>,
+}
+
+impl FakeDeletionProtection {
+    pub(crate) fn new() -> Self {
+        Self {
+            protected_tables: vec![],
+            disabled: Mutex::new(vec![]),
+        }
+    }
+}
+
+impl DeletionProtectionApi for FakeDeletionProtection {
+    async fn is_table_deletion_protected(&self, table_name: &str) -> Result<bool, String> {
+        Ok(self.protected_tables.iter().any(|t| t == table_name))
+    }
+
+    async fn disable_table_deletion_protection(&self, table_name: &str) -> Result<bool, String> {
+        let enabled = self.protected_tables.iter().any(|t| t == table_name);
+        if enabled {
+            self.disabled.lock().unwrap().push(table_name.to_string());
+        }
+        Ok(enabled)
+    }
+
+    async fn disable_cluster_deletion_protection(&self, cluster_identifier: &str) -> Result<(), String> {
+        self.disabled.lock().unwrap().push(cluster_identifier.to_string());
+        Ok(())
+    }
+
+    async fn disable_global_cluster_deletion_protection(&self, global_cluster_identifier: &str) -> Result<(), String> {
+        self.disabled.lock().unwrap().push(global_cluster_identifier.to_string());
+        Ok(())
+    }
+}
+
+struct FakeStackSetOperation {
+    instances: Vec<(String, String)>,
+    polls: usize,
+}
+
+/// In-memory stack sets, where every operation is running the first time it is described, and finished afterward
+pub(crate) struct FakeStackSets {
+    templates: Mutex<HashMap<String, String>>,
+    instances: Mutex<HashMap<String, Vec<StackInstanceInfo>>>,
+    operations: Mutex<HashMap<String, FakeStackSetOperation>>,
+    calls: Mutex<Vec<String>>,
+    /// When set, the instances of this account fail
+    pub(crate) failing_account: Option<String>,
+}
+
+impl FakeStackSets {
+    pub(crate) fn new() -> Self {
+        Self {
+            templates: Mutex::new(HashMap::new()),
+            instances: Mutex::new(HashMap::new()),
+            operations: Mutex::new(HashMap::new()),
+            calls: Mutex::new(vec![]),
+            failing_account: None,
+        }
+    }
+
+    pub(crate) fn with_stack_set(self, name: &str, template: &str, instances: Vec<(&str, &str)>) -> Self {
+        self.templates.lock().unwrap().insert(name.to_string(), template.to_string());
+        self.instances.lock().unwrap().insert(
+            name.to_string(),
+            instances
+                .into_iter()
+                .map(|(target, region)| StackInstanceInfo {
+                    target: target.to_string(),
+                    region: region.to_string(),
+                })
+                .collect(),
+        );
+        self
+    }
+
+    /// The operations that changed something, in order
+    pub(crate) fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn start_operation(&self, instances: Vec<(String, String)>) -> String {
+        let mut operations = self.operations.lock().unwrap();
+        let operation_id = format!("operation-{}", operations.len());
+        operations.insert(operation_id.clone(), FakeStackSetOperation { instances, polls: 0 });
+        operation_id
+    }
+
+    fn instance_status(&self, account: &str, polls: usize) -> StackSetOperationResultStatus {
+        if polls <= 1 {
+            StackSetOperationResultStatus::Running
+        } else if self.failing_account.as_deref() == Some(account) {
+            StackSetOperationResultStatus::Failed
+        } else {
+            StackSetOperationResultStatus::Succeeded
+        }
+    }
+}
+
+impl StackSetApi for FakeStackSets {
+    async fn get_stack_set_template(&self, name: &str) -> Result<Option<String>, String> {
+        Ok(self.templates.lock().unwrap().get(name).cloned())
+    }
+
+    async fn create_stack_set(&self, input: StackSetInput) -> Result<String, String> {
+        self.calls.lock().unwrap().push(format!("create_stack_set {}", input.name));
+        self.templates.lock().unwrap().insert(input.name.clone(), input.template);
+        self.instances.lock().unwrap().insert(input.name.clone(), vec![]);
+        Ok(format!("{}:1", input.name))
+    }
+
+    async fn update_stack_set(&self, input: StackSetInput, _options: &StackSetOptions) -> Result<String, String> {
+        self.calls.lock().unwrap().push(format!("update_stack_set {}", input.name));
+        self.templates.lock().unwrap().insert(input.name.clone(), input.template);
+        let instances = self.instances.lock().unwrap().get(&input.name).cloned().unwrap_or_default();
+        Ok(self.start_operation(instances.into_iter().map(|i| (i.target, i.region)).collect()))
+    }
+
+    async fn list_stack_instances(&self, name: &str) -> Result<Vec<StackInstanceInfo>, String> {
+        Ok(self.instances.lock().unwrap().get(name).cloned().unwrap_or_default())
+    }
+
+    async fn create_stack_instances(
+        &self,
+        name: &str,
+        targets: &StackSetTargets,
+        regions: Vec<String>,
+        _options: &StackSetOptions,
+    ) -> Result<String, String> {
+        let ids = targets.ids();
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("create_stack_instances {} {}", ids.join(","), regions.join(",")));
+
+        let new_instances: Vec<_> = regions
+            .iter()
+            .flat_map(|region| ids.iter().map(move |id| (id.clone(), region.clone())))
+            .collect();
+        self.instances
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_default()
+            .extend(new_instances.iter().map(|(target, region)| StackInstanceInfo {
+                target: target.clone(),
+                region: region.clone(),
+            }));
+        Ok(self.start_operation(new_instances))
+    }
+
+    async fn describe_stack_set_operation(
+        &self,
+        _name: &str,
+        operation_id: &str,
+    ) -> Result<(StackSetOperationStatus, Option<String>), String> {
+        let mut operations = self.operations.lock().unwrap();
+        let operation = operations
+            .get_mut(operation_id)
+            .ok_or_else(|| format!("operation {operation_id} does not exist"))?;
+        operation.polls += 1;
+
+        if operation.polls <= 1 {
+            Ok((StackSetOperationStatus::Running, None))
+        } else if operation
+            .instances
+            .iter()
+            .any(|(account, _)| self.failing_account.as_deref() == Some(account))
+        {
+            Ok((StackSetOperationStatus::Failed, Some("failure tolerance exceeded".to_string())))
+        } else {
+            Ok((StackSetOperationStatus::Succeeded, None))
+        }
+    }
+
+    async fn list_stack_set_operation_results(&self, _name: &str, operation_id: &str) -> Result<Vec<StackInstanceResult>, String> {
+        let operations = self.operations.lock().unwrap();
+        let operation = operations
+            .get(operation_id)
+            .ok_or_else(|| format!("operation {operation_id} does not exist"))?;
+
+        Ok(operation
+            .instances
+            .iter()
+            .map(|(account, region)| {
+                let status = self.instance_status(account, operation.polls);
+                StackInstanceResult {
+                    account: account.clone(),
+                    region: region.clone(),
+                    reason: (status == StackSetOperationResultStatus::Failed).then(|| "no permission".to_string()),
+                    status,
+                }
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,252 @@
+mod aws;
+#[cfg(test)]
+pub(crate) mod fake;
+
+pub(crate) use aws::*;
+
+use crate::drift::DriftedResource;
+use crate::stack_set::{StackSetOptions, StackSetTargets};
+use aws_sdk_cloudformation::types::{
+    ChangeSetStatus, ResourceStatus, StackDriftDetectionStatus, StackSetOperationResultStatus, StackSetOperationStatus, StackStatus,
+};
+use aws_sdk_lambda::types::LastUpdateStatus;
+use rusty_cdk_core::stack::ResourceToImport;
+use std::collections::HashMap;
+use std::future::Future;
+
+// the operations below return strings as errors, which the callers wrap in their own error type
+
+/// A deployed CloudFormation stack
+#[derive(Debug, Clone)]
+pub(crate) struct StackInfo {
+    pub(crate) stack_id: String,
+    pub(crate) status: StackStatus,
+    pub(crate) outputs: HashMap<String, String>,
+}
+
+/// A stack in the list of stacks of the account and region
+#[derive(Debug, Clone)]
+pub(crate) struct StackSummaryInfo {
+    pub(crate) name: String,
+    pub(crate) status: StackStatus,
+    /// When the stack was last updated (or created, if it was never updated), as an RFC 3339 timestamp
+    pub(crate) last_updated: Option<String>,
+    /// Result of the last drift detection, `None` when drift was never checked
+    pub(crate) drift_status: Option<String>,
+}
+
+/// A resource of a deployed CloudFormation stack
+#[derive(Debug, Clone)]
+pub(crate) struct StackResourceInfo {
+    pub(crate) resource_id: String,
+    pub(crate) physical_id: Option<String>,
+    pub(crate) status: Option<ResourceStatus>,
+}
+
+/// An event of a CloudFormation stack
+#[derive(Debug, Clone)]
+pub(crate) struct StackEventInfo {
+    pub(crate) event_id: String,
+    pub(crate) resource_id: String,
+    pub(crate) resource_type: String,
+    pub(crate) status: String,
+    pub(crate) reason: Option<String>,
+}
+
+/// The progress of a drift detection
+#[derive(Debug, Clone)]
+pub(crate) struct DriftDetectionInfo {
+    pub(crate) status: StackDriftDetectionStatus,
+    pub(crate) reason: Option<String>,
+    /// Set once drift was detected for the resources of the stack, also when the detection failed for some of them
+    pub(crate) drifted_resource_count: Option<i32>,
+}
+
+/// Everything needed to create or update a stack
+#[derive(Debug, Clone)]
+pub(crate) struct StackInput {
+    pub(crate) name: String,
+    pub(crate) template: String,
+    pub(crate) tags: Vec<(String, String)>,
+    pub(crate) role_arn: Option<String>,
+    /// `None` when the settings are unknown, which leaves those of an existing stack unchanged
+    pub(crate) settings: Option<StackSettingsInput>,
+}
+
+/// The stack settings, with the alarms and topics they refer to resolved to ARNs
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct StackSettingsInput {
+    pub(crate) termination_protection: Option<bool>,
+    pub(crate) stack_policy: Option<String>,
+    pub(crate) rollback_trigger_arns: Vec<String>,
+    pub(crate) rollback_monitoring_time_in_minutes: Option<u8>,
+    pub(crate) notification_arns: Vec<String>,
+}
+
+pub(crate) trait CloudFormationApi: Send + Sync {
+    /// Returns `None` when the stack does not exist
+    fn describe_stack(&self, name: &str) -> impl Future<Output = Result<Option<StackInfo>, String>> + Send;
+    /// Returns the stacks that were not deleted
+    fn list_stacks(&self) -> impl Future<Output = Result<Vec<StackSummaryInfo>, String>> + Send;
+    /// Returns `None` when the stack does not exist
+    fn get_template(&self, name: &str) -> impl Future<Output = Result<Option<String>, String>> + Send;
+    fn create_stack(&self, input: StackInput) -> impl Future<Output = Result<(), String>> + Send;
+    /// Returns whether an update was started. Succeeds without doing anything (returning `false`) when there are no changes to deploy
+    fn update_stack(&self, input: StackInput) -> impl Future<Output = Result<bool, String>> + Send;
+    fn delete_stack(&self, name: &str, role_arn: Option<String>) -> impl Future<Output = Result<(), String>> + Send;
+    fn update_termination_protection(&self, name: &str, enabled: bool) -> impl Future<Output = Result<(), String>> + Send;
+    /// Returns `None` when the stack has no stack policy
+    fn get_stack_policy(&self, name: &str) -> impl Future<Output = Result<Option<String>, String>> + Send;
+    fn set_stack_policy(&self, name: &str, policy: &str) -> impl Future<Output = Result<(), String>> + Send;
+    fn list_stack_resources(&self, name: &str) -> impl Future<Output = Result<Vec<StackResourceInfo>, String>> + Send;
+    /// Returns the most recent events of the stack, newest first
+    fn describe_stack_events(&self, name: &str) -> impl Future<Output = Result<Vec<StackEventInfo>, String>> + Send;
+    /// Returns `None` when the resource has no physical id (yet).
+    /// By default, the resource is found in the resources of the stack, clients can override this with a call for a single resource
+    fn get_physical_id(&self, name: &str, resource_id: &str) -> impl Future<Output = Result<Option<String>, String>> + Send {
+        async move {
+            let resources = self.list_stack_resources(name).await?;
+            resources
+                .into_iter()
+                .find(|r| r.resource_id == resource_id)
+                .map(|r| r.physical_id)
+                .ok_or_else(|| format!("resource {resource_id} does not exist"))
+        }
+    }
+    /// Cancels an update that is in progress, after which CloudFormation rolls back the stack
+    fn cancel_update_stack(&self, name: &str) -> impl Future<Output = Result<(), String>> + Send;
+    fn continue_update_rollback(
+        &self,
+        name: &str,
+        resources_to_skip: Option<Vec<String>>,
+        role_arn: Option<String>,
+    ) -> impl Future<Output = Result<(), String>> + Send;
+    /// Creates a change set of type `IMPORT`. If the stack does not exist yet, CloudFormation creates it in `REVIEW_IN_PROGRESS`
+    fn create_import_change_set(
+        &self,
+        input: StackInput,
+        change_set_name: &str,
+        resources: Vec<ResourceToImport>,
+    ) -> impl Future<Output = Result<(), String>> + Send;
+    /// Returns the status of the change set, with the reason when it failed
+    fn describe_change_set(
+        &self,
+        name: &str,
+        change_set_name: &str,
+    ) -> impl Future<Output = Result<(ChangeSetStatus, Option<String>), String>> + Send;
+    fn execute_change_set(&self, name: &str, change_set_name: &str) -> impl Future<Output = Result<(), String>> + Send;
+    /// Starts drift detection for the stack, returns the id of the detection
+    fn detect_stack_drift(&self, name: &str) -> impl Future<Output = Result<String, String>> + Send;
+    fn describe_stack_drift_detection_status(&self, detection_id: &str) -> impl Future<Output = Result<DriftDetectionInfo, String>> + Send;
+    /// Returns the resources that were modified or deleted according to the last drift detection, without their builder ids
+    fn describe_stack_resource_drifts(&self, name: &str) -> impl Future<Output = Result<Vec<DriftedResource>, String>> + Send;
+}
+
+/// Everything needed to create or update a stack set
+#[derive(Debug, Clone)]
+pub(crate) struct StackSetInput {
+    pub(crate) name: String,
+    pub(crate) template: String,
+    pub(crate) tags: Vec<(String, String)>,
+    /// Service-managed permissions (through AWS Organizations) instead of self-managed ones
+    pub(crate) service_managed: bool,
+}
+
+/// A stack instance of a stack set, with the account (or organizational unit) and region it was deployed to
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct StackInstanceInfo {
+    pub(crate) target: String,
+    pub(crate) region: String,
+}
+
+/// The result of a stack set operation for one of the stack instances
+#[derive(Debug, Clone)]
+pub(crate) struct StackInstanceResult {
+    pub(crate) account: String,
+    pub(crate) region: String,
+    pub(crate) status: StackSetOperationResultStatus,
+    pub(crate) reason: Option<String>,
+}
+
+pub(crate) trait StackSetApi: Send + Sync {
+    /// Returns `None` when the stack set does not exist
+    fn get_stack_set_template(&self, name: &str) -> impl Future<Output = Result<Option<String>, String>> + Send;
+    /// Returns the id of the new stack set
+    fn create_stack_set(&self, input: StackSetInput) -> impl Future<Output = Result<String, String>> + Send;
+    /// Updates the stack set and all of its instances, returns the id of the operation
+    fn update_stack_set(&self, input: StackSetInput, options: &StackSetOptions) -> impl Future<Output = Result<String, String>> + Send;
+    fn list_stack_instances(&self, name: &str) -> impl Future<Output = Result<Vec<StackInstanceInfo>, String>> + Send;
+    /// Creates an instance for every target in every region, returns the id of the operation
+    fn create_stack_instances(
+        &self,
+        name: &str,
+        targets: &StackSetTargets,
+        regions: Vec<String>,
+        options: &StackSetOptions,
+    ) -> impl Future<Output = Result<String, String>> + Send;
+    /// Returns the status of the operation, with the reason when it failed
+    fn describe_stack_set_operation(
+        &self,
+        name: &str,
+        operation_id: &str,
+    ) -> impl Future<Output = Result<(StackSetOperationStatus, Option<String>), String>> + Send;
+    fn list_stack_set_operation_results(
+        &self,
+        name: &str,
+        operation_id: &str,
+    ) -> impl Future<Output = Result<Vec<StackInstanceResult>, String>> + Send;
+}
+
+/// Key and version id of an object version (or delete marker)
+pub(crate) type ObjectVersion = (String, String);
+
+pub(crate) trait S3Api: Send + Sync {
+    /// Uploads the file at `path`
+    fn put_object(&self, bucket: &str, key: &str, path: &str) -> impl Future<Output = Result<(), String>> + Send;
+    /// Returns (a page of) the keys in the bucket, with a marker for retrieving the next page if there is one
+    fn list_objects(
+        &self,
+        bucket: &str,
+        marker: Option<String>,
+    ) -> impl Future<Output = Result<(Vec<String>, Option<String>), String>> + Send;
+    fn delete_objects(&self, bucket: &str, keys: Vec<String>) -> impl Future<Output = Result<(), String>> + Send;
+    /// Returns (a page of) the object versions and delete markers in the bucket, as keys with version ids,
+    /// with a key and version id marker for retrieving the next page if there is one
+    fn list_object_versions(
+        &self,
+        bucket: &str,
+        marker: Option<ObjectVersion>,
+    ) -> impl Future<Output = Result<(Vec<ObjectVersion>, Option<ObjectVersion>), String>> + Send;
+    fn delete_object_versions(&self, bucket: &str, versions: Vec<ObjectVersion>) -> impl Future<Output = Result<(), String>> + Send;
+}
+
+pub(crate) trait LambdaApi: Send + Sync {
+    /// Points the function to a new zip in S3
+    fn update_function_code(&self, function_name: &str, bucket: &str, key: &str) -> impl Future<Output = Result<(), String>> + Send;
+    /// Returns the status of the last update of the function, with the reason when it failed
+    fn get_last_update_status(
+        &self,
+        function_name: &str,
+    ) -> impl Future<Output = Result<(Option<LastUpdateStatus>, Option<String>), String>> + Send;
+}
+
+pub(crate) trait SnsApi: Send + Sync {
+    fn set_topic_attribute(&self, arn: &str, name: &str, value: &str) -> impl Future<Output = Result<(), String>> + Send;
+}
+
+pub(crate) trait EcrApi: Send + Sync {
+    /// Returns (the first page of) the digests of the images in the repository
+    fn list_image_digests(&self, repository: &str) -> impl Future<Output = Result<Vec<String>, String>> + Send;
+    fn delete_images(&self, repository: &str, digests: Vec<String>) -> impl Future<Output = Result<(), String>> + Send;
+}
+
+pub(crate) trait DeletionProtectionApi: Send + Sync {
+    fn is_table_deletion_protected(&self, table_name: &str) -> impl Future<Output = Result<bool, String>> + Send;
+    /// Returns whether deletion protection was enabled (and has now been disabled)
+    fn disable_table_deletion_protection(&self, table_name: &str) -> impl Future<Output = Result<bool, String>> + Send;
+    fn disable_cluster_deletion_protection(&self, cluster_identifier: &str) -> impl Future<Output = Result<(), String>> + Send;
+    fn disable_global_cluster_deletion_protection(
+        &self,
+        global_cluster_identifier: &str,
+    ) -> impl Future<Output = Result<(), String>> + Send;
+}
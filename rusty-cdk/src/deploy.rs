@@ -1,22 +1,42 @@
-use crate::util::{get_existing_template, get_stack_status, load_config};
-use aws_config::SdkConfig;
-use aws_sdk_cloudformation::Client;
-use aws_sdk_cloudformation::error::{ProvideErrorMetadata, SdkError};
-use aws_sdk_cloudformation::types::{Capability, StackStatus, Tag};
-use rusty_cdk_core::stack::{Asset, Stack};
+use crate::clients::{AwsCloudFormation, AwsS3, CloudFormationApi, S3Api, StackInput, StackSettingsInput};
+use crate::options::DeployOptions;
+use crate::progress::{ProgressEvent, ProgressReporter};
+use crate::util::{
+    PollInterval, StackProgress, ctrl_c_pressed, get_existing_ids_by_resource_id, get_existing_template, get_stack_description,
+    get_stack_status, load_config, wait_for_stable_status,
+};
+use aws_sdk_cloudformation::types::{ResourceStatus, StackStatus};
+use rusty_cdk_core::stack::{Asset, Stack, StackSettings, StackSettingsReference};
 use rusty_cdk_core::wrappers::StringWithOnlyAlphaNumericsAndHyphens;
+use serde_json::Value;
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::pin::{Pin, pin};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 
+const ALLOW_ALL_UPDATES_POLICY: &str = r#"{"Statement":[{"Effect":"Allow","Action":"Update:*","Principal":"*","Resource":"*"}]}"#;
+
 #[derive(Debug)]
 pub enum DeployError {
     SynthError(String),
     StackCreateError(String),
     StackUpdateError(String),
+    /// An asset could not be uploaded, contains the path of the asset and the reason
     AssetError(String),
+    /// The stack failed to create and is now empty (`ROLLBACK_COMPLETE`). It has to be deleted before it can be created again, see `delete_rolled_back_stack`
+    RollbackComplete(String),
+    /// An earlier update failed, and so did its rollback (`UPDATE_ROLLBACK_FAILED`). See `continue_update_rollback`.
+    /// Contains the ids of the resources that could not be rolled back
+    UpdateRollbackFailed(Vec<String>),
+    RecoveryError(String),
+    HotswapError(String),
+    /// The stack did not reach a final status within `DeployOptions::timeout`
+    Timeout(String),
+    /// The deployment was cancelled with ctrl-c, see `DeployOptions::cancel_on_ctrl_c`
+    Cancelled(String),
     UnknownError(String),
 }
 
@@ -29,11 +49,45 @@ impl Display for DeployError {
             DeployError::StackCreateError(_) => f.write_str("unable to create stack"),
             DeployError::StackUpdateError(_) => f.write_str("unable to update stack"),
             DeployError::AssetError(_) => f.write_str("unable to handle asset"),
+            DeployError::RollbackComplete(_) => f.write_str("stack is in ROLLBACK_COMPLETE and has to be deleted first"),
+            DeployError::UpdateRollbackFailed(_) => f.write_str("stack is in UPDATE_ROLLBACK_FAILED and has to be rolled back first"),
+            DeployError::RecoveryError(_) => f.write_str("unable to recover stack"),
+            DeployError::HotswapError(_) => f.write_str("unable to update function code"),
+            DeployError::Timeout(_) => f.write_str("timed out waiting for stack"),
+            DeployError::Cancelled(_) => f.write_str("deployment was cancelled"),
             DeployError::UnknownError(_) => f.write_str("unknown error"),
         }
     }
 }
 
+/// The result of a successful deployment
+#[derive(Debug)]
+pub struct DeployResult {
+    pub stack_id: String,
+    /// The final status of the stack, e.g. `CREATE_COMPLETE`
+    pub status: String,
+    pub elapsed: Duration,
+    /// The outputs of the stack (see `StackBuilder::add_output`), by output name
+    pub outputs: HashMap<String, String>,
+}
+
+impl Display for DeployResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "stack {} reached status {} in {}s",
+            self.stack_id,
+            self.status,
+            self.elapsed.as_secs()
+        ))?;
+        let mut outputs: Vec<_> = self.outputs.iter().collect();
+        outputs.sort();
+        for (key, value) in outputs {
+            f.write_fmt(format_args!("\n- {key}: {value}"))?;
+        }
+        Ok(())
+    }
+}
+
 /// Deploys a stack to AWS using CloudFormation.
 ///
 /// This function handles the complete deployment lifecycle:
@@ -41,25 +95,43 @@ impl Display for DeployError {
 /// - Creating or updating the CloudFormation stack
 /// - Monitoring deployment progress
 ///
-/// It returns a `Result`. In case of error, a `DeployError` is returned.
+/// If another operation is still in progress for the stack, the deployment waits for it to finish.
+/// How long `deploy` waits for its own operation can be limited with `DeployOptions::timeout`,
+/// and `DeployOptions::cancel_on_ctrl_c` allows cancelling it.
+/// A stack that failed to create (`ROLLBACK_COMPLETE`) or that could not roll back an update (`UPDATE_ROLLBACK_FAILED`) cannot be deployed to.
+/// In those cases, `DeployError::RollbackComplete` or `DeployError::UpdateRollbackFailed` is returned,
+/// and you can use `delete_rolled_back_stack` or `continue_update_rollback` to recover before deploying again.
 ///
-/// For a deployment method that shows updates and exits on failure, see `deploy`
+/// It returns a `Result`. If the deployment succeeds, you receive a `DeployResult` with the stack id, final status and outputs.
+/// In case of error, a `DeployError` is returned.
+///
+/// To quickly update the code of Lambda functions during development, see `hotswap`
 ///
 /// # Parameters
 ///
 /// * `name` - The CloudFormation stack name (alphanumeric characters and hyphens only)
 /// * `stack` - The stack to deploy, created using `StackBuilder`
-/// * `print_progress` - Print progress updates to standard out
+/// * `reporter` - Receives progress updates, see `ProgressReporter` (`SilentReporter`, `TerminalReporter` or `JsonLinesReporter`)
+/// * `options` - The AWS profile, region and roles to use, see `DeployOptions`
 ///
 /// # Tags
 ///
 /// If tags were added to the stack using `StackBuilder::add_tag()`, they will be
 /// applied to the CloudFormation stack and propagated to resources where supported.
 ///
+/// # Stack settings
+///
+/// Termination protection, the stack policy, rollback triggers and notification topics of the `StackBuilder` are applied as well.
+/// For an existing stack, termination protection and the stack policy are updated after the deployment has finished.
+/// Rollback triggers, notification topics and the stack policy that are no longer set are removed from an existing stack,
+/// where the stack policy is replaced by one that allows all updates (CloudFormation cannot remove a stack policy).
+/// Alarms and topics that are part of the stack itself are only used once they have been deployed.
+/// A stack read from a template without its settings (see `Stack::get_settings`) leaves the settings of an existing stack unchanged.
+///
 /// # Example
 ///
 /// ```no_run
-/// use rusty_cdk::deploy;
+/// use rusty_cdk::{deploy, DeployOptions, SilentReporter};
 /// use rusty_cdk::stack::StackBuilder;
 /// use rusty_cdk::sqs::QueueBuilder;
 /// use rusty_cdk_macros::string_with_only_alphanumerics_and_hyphens;
@@ -75,7 +147,7 @@ impl Display for DeployError {
 ///
 ///     let stack = stack_builder.build().expect("Stack to build successfully");
 ///
-///     let result = deploy(string_with_only_alphanumerics_and_hyphens!("my-application-stack"), stack, false).await;
+///     let result = deploy(string_with_only_alphanumerics_and_hyphens!("my-application-stack"), stack, &SilentReporter, &DeployOptions::default()).await;
 /// }
 /// ```
 ///
@@ -92,42 +164,76 @@ impl Display for DeployError {
 /// - `cloudformation:UpdateStack`
 /// - `cloudformation:DescribeStacks`
 /// - `cloudformation:GetTemplate`
+/// - `cloudformation:GetStackPolicy` and `cloudformation:SetStackPolicy` (when updating a stack)
+/// - `cloudformation:CancelUpdateStack` (if you enable `DeployOptions::cancel_on_ctrl_c`)
+/// - `cloudformation:ListStackResources` (to report the resources that failed to roll back)
 /// - `s3:PutObject` (if you have Lambdas)
+/// - `sts:AssumeRole` (if you pass in `DeployOptions::assume_role_arn`)
+/// - `iam:PassRole` (if you pass in `DeployOptions::cloudformation_role_arn`)
 /// - IAM permissions for creating roles
 /// - Service-specific permissions for resources being created
-pub async fn deploy(name: StringWithOnlyAlphaNumericsAndHyphens, mut stack: Stack, print_progress: bool) -> Result<String, DeployError> {
-    let name = name.0;
-    let config = load_config(true).await;
+pub async fn deploy(
+    name: StringWithOnlyAlphaNumericsAndHyphens,
+    stack: Stack,
+    reporter: &dyn ProgressReporter,
+    options: &DeployOptions,
+) -> Result<DeployResult, DeployError> {
+    let config = load_config(true, options).await;
+    let cloudformation_client = AwsCloudFormation::new(&config);
+    let s3_client = Arc::new(AwsS3::new(&config, options));
 
-    upload_assets(stack.get_assets(), &config).await?;
+    deploy_with_clients(
+        name.0,
+        stack,
+        reporter,
+        options,
+        &cloudformation_client,
+        s3_client,
+        ctrl_c_pressed(options),
+    )
+    .await
+}
+
+pub(crate) async fn deploy_with_clients<C: CloudFormationApi, S: S3Api + 'static>(
+    name: String,
+    mut stack: Stack,
+    reporter: &dyn ProgressReporter,
+    options: &DeployOptions,
+    cloudformation_client: &C,
+    s3_client: Arc<S>,
+    cancel: impl Future<Output = ()>,
+) -> Result<DeployResult, DeployError> {
+    let start = Instant::now();
+    // tokio time (unlike `start`), so that tests can advance it
+    let deadline = options.timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+    let mut cancel = pin!(cancel);
+    let mut interval = PollInterval::new();
+
+    check_existing_stack(&name, cloudformation_client, reporter, deadline, cancel.as_mut()).await?;
 
-    let cloudformation_client = Client::new(&config);
+    stack.package_cargo_crates().map_err(DeployError::AssetError)?;
+    upload_assets(stack.get_assets(), s3_client, reporter).await?;
 
-    create_or_update_stack(&name, &mut stack, &cloudformation_client).await?;
+    let mut progress = StackProgress::start(&name, cloudformation_client, reporter).await;
+    let (existing, updated, settings) = create_or_update_stack(&name, &mut stack, options, cloudformation_client, reporter).await?;
+
+    if existing && !updated {
+        // nothing to deploy, so the stack keeps its status, which might be UPDATE_ROLLBACK_COMPLETE
+        return complete_deployment(&name, start, existing, &settings, cloudformation_client, reporter).await;
+    }
 
     loop {
-        let status = get_stack_status(&name, &cloudformation_client)
+        let status = get_stack_status(&name, cloudformation_client)
             .await
-            .expect("status to be available for stack");
+            .ok_or_else(|| DeployError::UnknownError(format!("could not retrieve status of stack {name}")))?;
+        let progressed = progress.report(&status, cloudformation_client).await;
 
         match status {
-            StackStatus::CreateComplete => {
-                return Ok("created successfully".to_string());
-            }
-            StackStatus::UpdateComplete | StackStatus::UpdateCompleteCleanupInProgress => {
-                return Ok("updated successfully".to_string());
+            StackStatus::CreateComplete | StackStatus::UpdateComplete | StackStatus::UpdateCompleteCleanupInProgress => {
+                return complete_deployment(&name, start, existing, &settings, cloudformation_client, reporter).await;
             }
-            StackStatus::CreateInProgress => {
-                if print_progress {
-                    println!("creating...");
-                }
-            }
-            StackStatus::UpdateInProgress => {
-                if print_progress {
-                    println!("updating...");
-                }
-            }
-            StackStatus::CreateFailed => {
+            StackStatus::CreateInProgress | StackStatus::UpdateInProgress => {}
+            StackStatus::CreateFailed | StackStatus::RollbackInProgress | StackStatus::RollbackComplete | StackStatus::RollbackFailed => {
                 return Err(DeployError::StackCreateError(format!("{status}")));
             }
             StackStatus::UpdateRollbackComplete
@@ -142,92 +248,754 @@ pub async fn deploy(name: StringWithOnlyAlphaNumericsAndHyphens, mut stack: Stac
             }
         }
 
-        sleep(Duration::from_secs(10)).await;
+        if deadline.is_some_and(|deadline| tokio::time::Instant::now() >= deadline) {
+            return Err(DeployError::Timeout(format!(
+                "stack {name} did not reach a final status in time, it is still {status}"
+            )));
+        }
+
+        tokio::select! {
+            _ = sleep(interval.next(progressed)) => {}
+            _ = &mut cancel => return cancel_deployment(&name, existing, cloudformation_client, reporter).await,
+        }
     }
 }
 
-async fn create_or_update_stack(name: &String, stack: &mut Stack, cloudformation_client: &Client) -> Result<(), DeployError> {
-    let existing_template = get_existing_template(cloudformation_client, name).await;
-    let tags = stack.get_tags();
-    let tags = if tags.is_empty() {
-        None
+async fn complete_deployment<C: CloudFormationApi>(
+    name: &str,
+    start: Instant,
+    existing: bool,
+    settings: &Option<StackSettingsInput>,
+    cloudformation_client: &C,
+    reporter: &dyn ProgressReporter,
+) -> Result<DeployResult, DeployError> {
+    if let (true, Some(settings)) = (existing, settings) {
+        update_stack_settings(name, settings, cloudformation_client).await?;
+    }
+    let result = get_deploy_result(name, start, cloudformation_client).await?;
+    reporter.report(ProgressEvent::Completed {
+        status: result.status.clone(),
+        elapsed: result.elapsed,
+    });
+    Ok(result)
+}
+
+async fn cancel_deployment<C: CloudFormationApi>(
+    name: &str,
+    existing: bool,
+    cloudformation_client: &C,
+    reporter: &dyn ProgressReporter,
+) -> Result<DeployResult, DeployError> {
+    if existing {
+        cloudformation_client
+            .cancel_update_stack(name)
+            .await
+            .map_err(DeployError::StackUpdateError)?;
+        reporter.report(ProgressEvent::Info(format!(
+            "cancelled the update of stack {name}, it will roll back to its previous state"
+        )));
+        Err(DeployError::Cancelled(format!("update of stack {name} was cancelled")))
     } else {
-        Some(tags.into_iter().map(|v| Tag::builder().key(v.0).value(v.1).build()).collect())
-    };
+        Err(DeployError::Cancelled(format!(
+            "stopped waiting for stack {name}, its creation continues"
+        )))
+    }
+}
+
+pub(crate) async fn get_deploy_result<C: CloudFormationApi>(
+    name: &str,
+    start: Instant,
+    cloudformation_client: &C,
+) -> Result<DeployResult, DeployError> {
+    let description = get_stack_description(name, cloudformation_client)
+        .await
+        .ok_or_else(|| DeployError::UnknownError(format!("could not describe stack {name} after deployment")))?;
+
+    Ok(DeployResult {
+        stack_id: description.stack_id,
+        status: description.status.to_string(),
+        elapsed: start.elapsed(),
+        outputs: description.outputs,
+    })
+}
+
+async fn check_existing_stack<C: CloudFormationApi>(
+    name: &str,
+    cloudformation_client: &C,
+    reporter: &dyn ProgressReporter,
+    deadline: Option<tokio::time::Instant>,
+    cancel: Pin<&mut impl Future<Output = ()>>,
+) -> Result<(), DeployError> {
+    match wait_for_stable_status(name, cloudformation_client, reporter, deadline, cancel).await? {
+        Some(StackStatus::RollbackComplete) => Err(DeployError::RollbackComplete(format!(
+            "stack {name} failed to create and has to be deleted before it can be created again"
+        ))),
+        Some(StackStatus::UpdateRollbackFailed) => {
+            let failed = get_resources_that_failed_to_roll_back(name, cloudformation_client).await?;
+            Err(DeployError::UpdateRollbackFailed(failed))
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn get_resources_that_failed_to_roll_back<C: CloudFormationApi>(
+    name: &str,
+    cloudformation_client: &C,
+) -> Result<Vec<String>, DeployError> {
+    let resources = cloudformation_client
+        .list_stack_resources(name)
+        .await
+        .map_err(DeployError::UnknownError)?;
+    let ids = get_existing_ids_by_resource_id(cloudformation_client, name).await;
+
+    let failed = resources
+        .into_iter()
+        .filter(|r| r.status == Some(ResourceStatus::UpdateFailed))
+        .map(|r| ids.get(&r.resource_id).cloned().unwrap_or(r.resource_id))
+        .collect();
+    Ok(failed)
+}
+
+/// Returns whether the stack already existed, whether a create or update was started, and the settings that still have to be applied to an existing stack (if known)
+async fn create_or_update_stack<C: CloudFormationApi>(
+    name: &str,
+    stack: &mut Stack,
+    options: &DeployOptions,
+    cloudformation_client: &C,
+    reporter: &dyn ProgressReporter,
+) -> Result<(bool, bool, Option<StackSettingsInput>), DeployError> {
+    let existing_template = get_existing_template(cloudformation_client, name).await;
 
     match existing_template {
         Some(existing) => {
+            // synth first, so the settings refer to the resource ids of the existing stack
             let body = stack
                 .synth_for_existing(&existing)
                 .map_err(|e| DeployError::SynthError(format!("{e:?}")))?;
+            let settings = resolve_stack_settings(name, stack.get_settings(), cloudformation_client, reporter).await;
 
-            return match cloudformation_client
-                .update_stack()
-                .stack_name(name)
-                .template_body(body)
-                .capabilities(Capability::CapabilityNamedIam)
-                .set_tags(tags)
-                .send()
+            let updated = cloudformation_client
+                .update_stack(StackInput {
+                    name: name.to_string(),
+                    template: body,
+                    tags: stack.get_tags(),
+                    role_arn: options.cloudformation_role_arn.clone(),
+                    settings: settings.clone(),
+                })
                 .await
-            {
-                Ok(_) => Ok(()),
-                Err(e) => match e {
-                    SdkError::ServiceError(ref s) => {
-                        let update_stack_error = s.err();
-                        if update_stack_error
-                            .message()
-                            .map(|v| v.contains("No updates are to be performed"))
-                            .unwrap_or(false)
-                        {
-                            Ok(())
-                        } else {
-                            Err(DeployError::StackUpdateError(format!("{e:?}")))
-                        }
-                    }
-                    _ => Err(DeployError::StackUpdateError(format!("{e:?}"))),
-                },
-            };
+                .map_err(DeployError::StackUpdateError)?;
+            Ok((true, updated, settings))
         }
         None => {
             let body = stack.synth().map_err(|e| DeployError::SynthError(format!("{e:?}")))?;
+            let settings = resolve_stack_settings(name, stack.get_settings(), cloudformation_client, reporter).await;
 
             cloudformation_client
-                .create_stack()
-                .stack_name(name)
-                .template_body(body)
-                .capabilities(Capability::CapabilityNamedIam)
-                .set_tags(tags)
-                .send()
+                .create_stack(StackInput {
+                    name: name.to_string(),
+                    template: body,
+                    tags: stack.get_tags(),
+                    role_arn: options.cloudformation_role_arn.clone(),
+                    settings: settings.clone(),
+                })
+                .await
+                .map_err(DeployError::StackCreateError)?;
+            Ok((false, true, settings))
+        }
+    }
+}
+
+/// Resolves the alarms and topics of the settings to ARNs.
+/// Those that are part of the stack can only be resolved once they have been deployed, until then they are skipped with a warning.
+///
+/// Returns `None`, with a warning, when the settings are unknown
+async fn resolve_stack_settings<C: CloudFormationApi>(
+    name: &str,
+    settings: Option<&StackSettings>,
+    cloudformation_client: &C,
+    reporter: &dyn ProgressReporter,
+) -> Option<StackSettingsInput> {
+    let Some(settings) = settings else {
+        reporter.report(ProgressEvent::Warning(format!(
+            "the settings of stack {name} are unknown (is settings.json missing from the synth output?), so its termination protection, stack policy, rollback triggers and notification topics are left unchanged"
+        )));
+        return None;
+    };
+
+    let in_stack = settings
+        .rollback_triggers
+        .iter()
+        .chain(settings.notification_topics.iter())
+        .any(|r| matches!(r, StackSettingsReference::Resource(_)));
+    let stack_id = if in_stack {
+        get_stack_description(name, cloudformation_client).await.map(|d| d.stack_id)
+    } else {
+        None
+    };
+
+    let mut rollback_trigger_arns = vec![];
+    for trigger in &settings.rollback_triggers {
+        match trigger {
+            StackSettingsReference::Arn(arn) => rollback_trigger_arns.push(arn.clone()),
+            StackSettingsReference::Resource(resource_id) => {
+                // the physical id of an alarm is its name
+                let alarm_arn = match (&stack_id, cloudformation_client.get_physical_id(name, resource_id).await) {
+                    (Some(stack_id), Ok(Some(alarm_name))) => alarm_arn(stack_id, &alarm_name),
+                    _ => None,
+                };
+                match alarm_arn {
+                    Some(arn) => rollback_trigger_arns.push(arn),
+                    None => reporter.report(ProgressEvent::Warning(format!(
+                        "alarm {resource_id} is not deployed yet, it will be a rollback trigger from the next deployment on"
+                    ))),
+                }
+            }
+        }
+    }
+
+    let mut notification_arns = vec![];
+    for topic in &settings.notification_topics {
+        match topic {
+            StackSettingsReference::Arn(arn) => notification_arns.push(arn.clone()),
+            StackSettingsReference::Resource(resource_id) => {
+                // the physical id of a topic is its arn
+                let topic_arn = match &stack_id {
+                    Some(_) => cloudformation_client.get_physical_id(name, resource_id).await.ok().flatten(),
+                    None => None,
+                };
+                match topic_arn {
+                    Some(arn) => notification_arns.push(arn),
+                    None => reporter.report(ProgressEvent::Warning(format!(
+                        "topic {resource_id} is not deployed yet, it will receive stack events from the next deployment on"
+                    ))),
+                }
+            }
+        }
+    }
+
+    Some(StackSettingsInput {
+        termination_protection: settings.termination_protection,
+        stack_policy: settings.stack_policy.as_ref().map(|p| p.synth()),
+        rollback_trigger_arns,
+        rollback_monitoring_time_in_minutes: settings.rollback_monitoring_time_in_minutes,
+        notification_arns,
+    })
+}
+
+fn alarm_arn(stack_id: &str, alarm_name: &str) -> Option<String> {
+    // stack ids are arns, e.g. arn:aws:cloudformation:us-east-1:123456789012:stack/name/id
+    match stack_id.split(':').collect::<Vec<_>>().as_slice() {
+        ["arn", partition, "cloudformation", region, account, ..] => {
+            Some(format!("arn:{partition}:cloudwatch:{region}:{account}:alarm:{alarm_name}"))
+        }
+        _ => None,
+    }
+}
+
+/// Creating a stack sets termination protection and the stack policy, for an existing stack they are updated separately
+async fn update_stack_settings<C: CloudFormationApi>(
+    name: &str,
+    settings: &StackSettingsInput,
+    cloudformation_client: &C,
+) -> Result<(), DeployError> {
+    if let Some(enabled) = settings.termination_protection {
+        cloudformation_client
+            .update_termination_protection(name, enabled)
+            .await
+            .map_err(DeployError::StackUpdateError)?;
+    }
+    match &settings.stack_policy {
+        Some(policy) => cloudformation_client
+            .set_stack_policy(name, policy)
+            .await
+            .map_err(DeployError::StackUpdateError)?,
+        None => {
+            // a stack policy cannot be removed, so replace a policy that was removed from the stack with one that allows all updates
+            let existing = cloudformation_client
+                .get_stack_policy(name)
                 .await
-                .map_err(|e| DeployError::StackCreateError(format!("{e:?}")))?;
+                .map_err(DeployError::StackUpdateError)?;
+            if existing.is_some_and(|p| !is_allow_all_updates_policy(&p)) {
+                cloudformation_client
+                    .set_stack_policy(name, ALLOW_ALL_UPDATES_POLICY)
+                    .await
+                    .map_err(DeployError::StackUpdateError)?;
+            }
         }
     }
     Ok(())
 }
 
-async fn upload_assets(assets: Vec<Asset>, config: &SdkConfig) -> Result<(), DeployError> {
-    let s3_client = Arc::new(aws_sdk_s3::Client::new(config));
+fn is_allow_all_updates_policy(policy: &str) -> bool {
+    serde_json::from_str::<Value>(policy).ok() == serde_json::from_str::<Value>(ALLOW_ALL_UPDATES_POLICY).ok()
+}
 
+pub(crate) async fn upload_assets<S: S3Api + 'static>(
+    assets: Vec<Asset>,
+    s3_client: Arc<S>,
+    reporter: &dyn ProgressReporter,
+) -> Result<(), DeployError> {
     let tasks: Vec<_> = assets
         .into_iter()
         .map(|a| {
+            reporter.report(ProgressEvent::AssetUploadStarted(a.clone()));
             let s3_client = s3_client.clone();
-            tokio::spawn(async move {
-                let body = aws_sdk_s3::primitives::ByteStream::from_path(a.path).await;
-                s3_client
-                    .put_object()
-                    .bucket(a.s3_bucket)
-                    .key(a.s3_key)
-                    .body(body.unwrap())
-                    .send()
-                    .await
-                    .unwrap();
-            })
+            let path = a.path.clone();
+            let task = tokio::spawn(async move { s3_client.put_object(&a.s3_bucket, &a.s3_key, &a.path).await.map(|_| a) });
+            (path, task)
         })
         .collect();
 
-    for task in tasks {
-        task.await.map_err(|e| DeployError::AssetError(format!("{e:?}")))?;
+    for (path, task) in tasks {
+        let asset = task
+            .await
+            .map_err(|e| DeployError::AssetError(format!("could not upload {path}: {e}")))?
+            .map_err(|e| DeployError::AssetError(format!("could not upload {path}: {e}")))?;
+        reporter.report(ProgressEvent::AssetUploadFinished(asset));
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::clients::fake::{FakeCloudFormation, FakeS3};
+    use crate::clients::{CloudFormationApi, StackResourceInfo};
+    use crate::deploy::{ALLOW_ALL_UPDATES_POLICY, DeployError, DeployResult, deploy_with_clients};
+    use crate::options::DeployOptions;
+    use crate::progress::{ProgressEvent, RecordingReporter, SilentReporter};
+    use aws_sdk_cloudformation::types::{ResourceStatus, StackStatus};
+    use rusty_cdk_core::cloudwatch::{AlarmBuilder, ComparisonOperator, Namespace, Statistic};
+    use rusty_cdk_core::lambda::{Architecture, Code, FunctionBuilder, Runtime, Zip};
+    use rusty_cdk_core::sns::{TopicBuilder, TopicRef};
+    use rusty_cdk_core::sqs::QueueBuilder;
+    use rusty_cdk_core::stack::{Stack, StackBuilder, StackPolicyAction, StackPolicyBuilder};
+    use rusty_cdk_core::wrappers::{Bucket, CloudwatchMetricName, Memory, Period, RollbackMonitoringTime, Timeout, ZipFile};
+    use std::future::pending;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    fn queue_stack(with_topic: bool) -> Stack {
+        let mut stack_builder = StackBuilder::new();
+        QueueBuilder::new("queue").standard_queue().build(&mut stack_builder);
+        if with_topic {
+            TopicBuilder::new("topic").build(&mut stack_builder);
+        }
+        stack_builder.build().unwrap()
+    }
+
+    fn function_stack() -> Stack {
+        let mut stack_builder = StackBuilder::new();
+        FunctionBuilder::new("fun", Architecture::ARM64, Memory(256), Timeout(30))
+            .code(Code::Zip(Zip::new(
+                Bucket("some-bucket".to_string()),
+                ZipFile("./example.zip".to_string()),
+            )))
+            .handler("bootstrap")
+            .runtime(Runtime::ProvidedAl2023)
+            .build(&mut stack_builder);
+        stack_builder.build().unwrap()
+    }
+
+    const EXISTING_TOPIC_ARN: &str = "arn:aws:sns:us-east-1:123456789012:existing";
+
+    fn stack_with_settings(with_topic: bool) -> Stack {
+        let mut stack_builder = StackBuilder::new();
+        let queue = QueueBuilder::new("queue").standard_queue().build(&mut stack_builder);
+        if with_topic {
+            TopicBuilder::new("topic").build(&mut stack_builder);
+        }
+        let alarm = AlarmBuilder::new("alarm")
+            .metric_alarm(Period(60), CloudwatchMetricName("Errors".to_string()), Namespace::Lambda)
+            .statistic(Statistic::Sum)
+            .comparison_operator(ComparisonOperator::GreaterThanOrEqualToThreshold)
+            .threshold(1)
+            .evaluation_periods(1)
+            .build(&mut stack_builder);
+        let topic = TopicRef::new("existing", "Existing", EXISTING_TOPIC_ARN, EXISTING_TOPIC_ARN);
+
+        stack_builder
+            .termination_protection(true)
+            .stack_policy(StackPolicyBuilder::new().deny(StackPolicyAction::Replace, &queue).build())
+            .add_rollback_trigger(&alarm)
+            .rollback_monitoring_time(RollbackMonitoringTime(10))
+            .add_notification_topic(&topic)
+            .build()
+            .unwrap()
+    }
+
+    async fn deploy_to_fake(stack: Stack, cloudformation_client: &FakeCloudFormation) -> Result<DeployResult, DeployError> {
+        let s3_client = Arc::new(FakeS3::new());
+        deploy_with_clients(
+            "stack".to_string(),
+            stack,
+            &SilentReporter,
+            &DeployOptions::default(),
+            cloudformation_client,
+            s3_client,
+            pending(),
+        )
+        .await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_create_new_stack_and_upload_assets() {
+        let cloudformation_client = FakeCloudFormation::new();
+        let s3_client = Arc::new(FakeS3::new());
+        let reporter = RecordingReporter::new();
+
+        let result = deploy_with_clients(
+            "stack".to_string(),
+            function_stack(),
+            &reporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+            s3_client.clone(),
+            pending(),
+        )
+        .await
+        .expect("deploy to succeed");
+
+        assert_eq!(result.status, "CREATE_COMPLETE");
+        assert_eq!(cloudformation_client.calls(), vec!["create_stack"]);
+        assert_eq!(s3_client.keys("some-bucket").len(), 1);
+
+        let events = reporter.events();
+        assert!(matches!(&events[0], ProgressEvent::AssetUploadStarted(a) if a.path == "./example.zip"));
+        assert!(matches!(&events[1], ProgressEvent::AssetUploadFinished(a) if a.path == "./example.zip"));
+        assert!(matches!(&events[2], ProgressEvent::StackEvent(e) if e.status == "CREATE_IN_PROGRESS"));
+        assert!(matches!(&events[3], ProgressEvent::StatusChanged(s) if s == "CREATE_IN_PROGRESS"));
+        assert!(matches!(&events[4], ProgressEvent::StatusChanged(s) if s == "CREATE_COMPLETE"));
+        assert!(matches!(&events[5], ProgressEvent::Completed { status, .. } if status == "CREATE_COMPLETE"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_return_create_error_when_creation_is_rolled_back() {
+        let mut cloudformation_client = FakeCloudFormation::new();
+        cloudformation_client.create_statuses = vec![
+            StackStatus::CreateInProgress,
+            StackStatus::RollbackInProgress,
+            StackStatus::RollbackComplete,
+        ];
+
+        let result = deploy_to_fake(queue_stack(false), &cloudformation_client).await;
+
+        assert!(matches!(result, Err(DeployError::StackCreateError(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_update_existing_stack_and_return_outputs() {
+        let existing = queue_stack(false).synth().unwrap();
+        let cloudformation_client = FakeCloudFormation::new()
+            .with_stack("stack", &existing, vec![StackStatus::CreateComplete])
+            .with_output("stack", "QueueUrl", "https://example.com/queue");
+
+        let result = deploy_to_fake(queue_stack(true), &cloudformation_client)
+            .await
+            .expect("deploy to succeed");
+
+        assert_eq!(result.status, "UPDATE_COMPLETE");
+        assert_eq!(result.outputs.get("QueueUrl").unwrap(), "https://example.com/queue");
+        assert_eq!(cloudformation_client.calls(), vec!["update_stack"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_succeed_when_there_are_no_updates() {
+        let existing = queue_stack(false).synth().unwrap();
+        let cloudformation_client = FakeCloudFormation::new().with_stack("stack", &existing, vec![StackStatus::UpdateComplete]);
+
+        let result = deploy_to_fake(queue_stack(false), &cloudformation_client)
+            .await
+            .expect("deploy to succeed");
+
+        assert_eq!(result.status, "UPDATE_COMPLETE");
+        assert!(cloudformation_client.calls().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_succeed_when_there_are_no_updates_to_stack_that_rolled_back_an_update() {
+        let existing = queue_stack(false).synth().unwrap();
+        let cloudformation_client =
+            FakeCloudFormation::new().with_stack("stack", &existing, vec![StackStatus::UpdateRollbackComplete]);
+
+        let result = deploy_to_fake(queue_stack(false), &cloudformation_client)
+            .await
+            .expect("deploy to succeed");
+
+        assert_eq!(result.status, "UPDATE_ROLLBACK_COMPLETE");
+        assert!(cloudformation_client.calls().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_wait_for_operation_in_progress_before_updating() {
+        let existing = queue_stack(false).synth().unwrap();
+        let cloudformation_client =
+            FakeCloudFormation::new().with_stack("stack", &existing, vec![StackStatus::UpdateInProgress, StackStatus::UpdateComplete]);
+
+        let result = deploy_to_fake(queue_stack(true), &cloudformation_client)
+            .await
+            .expect("deploy to succeed");
+
+        assert_eq!(result.status, "UPDATE_COMPLETE");
+        assert_eq!(cloudformation_client.calls(), vec!["update_stack"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_refuse_to_deploy_to_stack_that_failed_to_create() {
+        let cloudformation_client = FakeCloudFormation::new().with_stack("stack", "{}", vec![StackStatus::RollbackComplete]);
+
+        let result = deploy_to_fake(queue_stack(false), &cloudformation_client).await;
+
+        assert!(matches!(result, Err(DeployError::RollbackComplete(_))));
+        assert!(cloudformation_client.calls().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_return_ids_of_resources_that_failed_to_roll_back() {
+        let cloudformation_client = FakeCloudFormation::new()
+            .with_stack(
+                "stack",
+                r#"{"Resources": {}, "Metadata": { "queue": "Queue123", "topic": "Topic123" } }"#,
+                vec![StackStatus::UpdateRollbackFailed],
+            )
+            .with_resource(
+                "stack",
+                StackResourceInfo {
+                    resource_id: "Queue123".to_string(),
+                    physical_id: None,
+                    status: Some(ResourceStatus::UpdateFailed),
+                },
+            )
+            .with_resource(
+                "stack",
+                StackResourceInfo {
+                    resource_id: "Topic123".to_string(),
+                    physical_id: None,
+                    status: Some(ResourceStatus::UpdateComplete),
+                },
+            );
+
+        let result = deploy_to_fake(queue_stack(false), &cloudformation_client).await;
+
+        match result {
+            Err(DeployError::UpdateRollbackFailed(ids)) => assert_eq!(ids, vec!["queue".to_string()]),
+            other => panic!("expected UpdateRollbackFailed, got {other:?}"),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_create_stack_with_settings_skipping_alarms_that_are_not_deployed() {
+        let cloudformation_client = FakeCloudFormation::new();
+        let reporter = RecordingReporter::new();
+
+        deploy_with_clients(
+            "stack".to_string(),
+            stack_with_settings(false),
+            &reporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+            Arc::new(FakeS3::new()),
+            pending(),
+        )
+        .await
+        .expect("deploy to succeed");
+
+        assert_eq!(cloudformation_client.calls(), vec!["create_stack"]);
+        let settings = cloudformation_client.settings("stack").unwrap();
+        assert_eq!(settings.termination_protection, Some(true));
+        assert!(settings.stack_policy.unwrap().contains("Update:Replace"));
+        assert!(settings.rollback_trigger_arns.is_empty());
+        assert_eq!(settings.rollback_monitoring_time_in_minutes, Some(10));
+        assert_eq!(settings.notification_arns, vec![EXISTING_TOPIC_ARN.to_string()]);
+        assert!(
+            reporter
+                .events()
+                .iter()
+                .any(|e| matches!(e, ProgressEvent::Warning(w) if w.starts_with("alarm") && w.contains("not deployed yet")))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_update_settings_of_existing_stack_with_deployed_alarm() {
+        let existing = stack_with_settings(false).synth().unwrap();
+        let alarm_resource_id = Stack::get_ids_by_resource_id(&existing)
+            .unwrap()
+            .into_iter()
+            .find(|(_, id)| id == "alarm")
+            .map(|(resource_id, _)| resource_id)
+            .unwrap();
+        let cloudformation_client = FakeCloudFormation::new()
+            .with_stack("stack", &existing, vec![StackStatus::UpdateComplete])
+            .with_resource(
+                "stack",
+                StackResourceInfo {
+                    resource_id: alarm_resource_id,
+                    physical_id: Some("my-alarm".to_string()),
+                    status: Some(ResourceStatus::CreateComplete),
+                },
+            );
+
+        deploy_to_fake(stack_with_settings(true), &cloudformation_client)
+            .await
+            .expect("deploy to succeed");
+
+        let settings = cloudformation_client.settings("stack").unwrap();
+        assert_eq!(
+            settings.rollback_trigger_arns,
+            vec!["arn:aws:cloudwatch:us-east-1:123456789012:alarm:my-alarm".to_string()]
+        );
+        let calls = cloudformation_client.calls();
+        assert!(calls.contains(&"update_termination_protection true".to_string()));
+        assert!(calls.contains(&"set_stack_policy".to_string()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_remove_settings_that_are_no_longer_set() {
+        let cloudformation_client = FakeCloudFormation::new();
+        deploy_to_fake(stack_with_settings(false), &cloudformation_client)
+            .await
+            .expect("creation to succeed");
+        let created = cloudformation_client.settings("stack").unwrap();
+        assert_eq!(created.notification_arns, vec![EXISTING_TOPIC_ARN.to_string()]);
+
+        deploy_to_fake(queue_stack(true), &cloudformation_client)
+            .await
+            .expect("update to succeed");
+
+        let settings = cloudformation_client.settings("stack").unwrap();
+        assert!(settings.rollback_trigger_arns.is_empty());
+        assert!(settings.rollback_monitoring_time_in_minutes.is_none());
+        assert!(settings.notification_arns.is_empty());
+        assert_eq!(settings.stack_policy.as_deref(), Some(ALLOW_ALL_UPDATES_POLICY));
+        // termination protection is left as it is
+        assert_eq!(settings.termination_protection, Some(true));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_keep_existing_settings_when_settings_are_unknown() {
+        let cloudformation_client = FakeCloudFormation::new();
+        deploy_to_fake(stack_with_settings(false), &cloudformation_client)
+            .await
+            .expect("creation to succeed");
+        let created = cloudformation_client.settings("stack").unwrap();
+        // like a stack read from a synth output without settings.json
+        let read: Stack = serde_json::from_str(&queue_stack(true).synth().unwrap()).unwrap();
+        assert!(read.get_settings().is_none());
+
+        deploy_to_fake(read, &cloudformation_client).await.expect("update to succeed");
+
+        let settings = cloudformation_client.settings("stack").unwrap();
+        assert_eq!(settings, created);
+        assert!(settings.stack_policy.unwrap().contains("Update:Replace"));
+        assert!(!cloudformation_client.calls().contains(&"set_stack_policy".to_string()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_not_replace_missing_or_allow_all_stack_policy() {
+        let existing = queue_stack(false).synth().unwrap();
+        let cloudformation_client = FakeCloudFormation::new().with_stack("stack", &existing, vec![StackStatus::UpdateComplete]);
+
+        deploy_to_fake(queue_stack(true), &cloudformation_client)
+            .await
+            .expect("deploy to succeed");
+        cloudformation_client.set_stack_policy("stack", ALLOW_ALL_UPDATES_POLICY).await.unwrap();
+        deploy_to_fake(queue_stack(false), &cloudformation_client)
+            .await
+            .expect("deploy to succeed");
+
+        assert_eq!(cloudformation_client.calls(), vec!["update_stack", "set_stack_policy", "update_stack"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_return_path_of_asset_that_failed_to_upload() {
+        let cloudformation_client = FakeCloudFormation::new();
+        let mut s3_client = FakeS3::new();
+        s3_client.put_object_failure = Some("access denied".to_string());
+
+        let result = deploy_with_clients(
+            "stack".to_string(),
+            function_stack(),
+            &SilentReporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+            Arc::new(s3_client),
+            pending(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(DeployError::AssetError(reason)) if reason == "could not upload ./example.zip: access denied"));
+        assert!(cloudformation_client.calls().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_time_out_when_stack_stays_in_progress() {
+        let mut cloudformation_client = FakeCloudFormation::new();
+        cloudformation_client.create_statuses = vec![StackStatus::CreateInProgress];
+        let options = DeployOptions {
+            timeout: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+
+        let result = deploy_with_clients(
+            "stack".to_string(),
+            queue_stack(false),
+            &SilentReporter,
+            &options,
+            &cloudformation_client,
+            Arc::new(FakeS3::new()),
+            pending(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(DeployError::Timeout(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_time_out_waiting_for_earlier_operation() {
+        let existing = queue_stack(false).synth().unwrap();
+        let cloudformation_client = FakeCloudFormation::new().with_stack("stack", &existing, vec![StackStatus::UpdateInProgress]);
+        let options = DeployOptions {
+            timeout: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+
+        let result = deploy_with_clients(
+            "stack".to_string(),
+            queue_stack(true),
+            &SilentReporter,
+            &options,
+            &cloudformation_client,
+            Arc::new(FakeS3::new()),
+            pending(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(DeployError::Timeout(_))));
+        assert!(cloudformation_client.calls().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_cancel_update_in_progress() {
+        let existing = queue_stack(false).synth().unwrap();
+        let mut cloudformation_client = FakeCloudFormation::new().with_stack("stack", &existing, vec![StackStatus::CreateComplete]);
+        cloudformation_client.update_statuses = vec![StackStatus::UpdateInProgress];
+
+        let result = deploy_with_clients(
+            "stack".to_string(),
+            queue_stack(true),
+            &SilentReporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+            Arc::new(FakeS3::new()),
+            sleep(Duration::from_secs(30)),
+        )
+        .await;
+
+        assert!(matches!(result, Err(DeployError::Cancelled(_))));
+        assert_eq!(cloudformation_client.calls(), vec!["update_stack", "cancel_update_stack"]);
+        assert_eq!(
+            cloudformation_client.describe_stack("stack").await.unwrap().unwrap().status,
+            StackStatus::UpdateRollbackInProgress
+        );
+    }
+}
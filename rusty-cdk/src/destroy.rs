@@ -1,17 +1,23 @@
-use crate::util::{get_existing_template, get_stack_status, load_config};
-use aws_sdk_cloudformation::Client;
+use crate::clients::{
+    AwsCloudFormation, AwsDeletionProtection, AwsEcr, AwsS3, AwsSns, CloudFormationApi, DeletionProtectionApi, EcrApi, S3Api, SnsApi,
+};
+use crate::options::DeployOptions;
+use crate::progress::{ProgressEvent, ProgressReporter};
+use crate::util::{StackProgress, get_existing_template, get_stack_status, load_config};
 use aws_sdk_cloudformation::types::StackStatus;
-use aws_sdk_s3::types::{Delete, ObjectIdentifier};
 use rusty_cdk_core::stack::{Cleanable, Stack};
 use rusty_cdk_core::wrappers::StringWithOnlyAlphaNumericsAndHyphens;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 
 #[derive(Debug)]
 pub enum DestroyError {
     EmptyError(String),
+    /// The stack contains resources with deletion protection, which would make the delete fail. Contains their resource ids
+    DeletionProtected(Vec<String>),
+    DeletionProtectionError(String),
     StackDeleteError(String),
     UnknownStack(String),
     UnknownError(String),
@@ -22,7 +28,9 @@ impl Error for DestroyError {}
 impl Display for DestroyError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            DestroyError::EmptyError(_) => f.write_str("could not empty bucket"),
+            DestroyError::EmptyError(_) => f.write_str("could not empty bucket or repository"),
+            DestroyError::DeletionProtected(_) => f.write_str("stack contains resources with deletion protection"),
+            DestroyError::DeletionProtectionError(_) => f.write_str("could not disable deletion protection"),
             DestroyError::StackDeleteError(_) => f.write_str("unable to delete stack"),
             DestroyError::UnknownStack(_) => f.write_str("stack could not be found"),
             DestroyError::UnknownError(_) => f.write_str("unknown error"),
@@ -30,33 +38,77 @@ impl Display for DestroyError {
     }
 }
 
+/// A resource that CloudFormation keeps when its stack is destroyed (because of its deletion policy), and that you will have to clean up yourself (if required)
+#[derive(Debug)]
+pub struct OrphanedResource {
+    pub id: String,
+    pub resource_id: String,
+    /// The name, url or arn of the resource, if it could be retrieved
+    pub physical_id: Option<String>,
+}
+
+impl Display for OrphanedResource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.physical_id {
+            Some(physical_id) => f.write_fmt(format_args!("{} ({})", self.id, physical_id)),
+            None => f.write_fmt(format_args!("{} (resource id {})", self.id, self.resource_id)),
+        }
+    }
+}
+
 /// Destroy a deployed stack
 /// It returns a `Result`. In case of error, a `DestroyError` is returned.
 ///
 /// # Parameters
 ///
 /// * `name` - The CloudFormation stack name (alphanumeric characters and hyphens only)
-/// * `stack` - The stack to deploy, created using `StackBuilder`
-/// * `print_progress` - Print progress updates to standard out
+/// * `reporter` - Receives progress updates, see `ProgressReporter`
+/// * `options` - The AWS profile, region and roles to use, see `DeployOptions`
 ///
-pub async fn destroy(name: StringWithOnlyAlphaNumericsAndHyphens, print_progress: bool) -> Result<(), DestroyError> {
-    let name = name.0;
-    let config = load_config(false).await;
-    let cloudformation_client = Client::new(&config);
+pub async fn destroy(
+    name: StringWithOnlyAlphaNumericsAndHyphens,
+    reporter: &dyn ProgressReporter,
+    options: &DeployOptions,
+) -> Result<(), DestroyError> {
+    let config = load_config(false, options).await;
+    let cloudformation_client = AwsCloudFormation::new(&config);
 
-    destroy_stack(&name, &cloudformation_client).await?;
+    destroy_with_client(name.0, reporter, options, &cloudformation_client).await
+}
+
+pub(crate) async fn destroy_with_client<C: CloudFormationApi>(
+    name: String,
+    reporter: &dyn ProgressReporter,
+    options: &DeployOptions,
+    cloudformation_client: &C,
+) -> Result<(), DestroyError> {
+    let start = Instant::now();
+    let mut progress = StackProgress::start(&name, cloudformation_client, reporter).await;
+
+    cloudformation_client
+        .delete_stack(&name, options.cloudformation_role_arn.clone())
+        .await
+        .map_err(DestroyError::StackDeleteError)?;
+
+    let completed = || {
+        reporter.report(ProgressEvent::Completed {
+            status: StackStatus::DeleteComplete.to_string(),
+            elapsed: start.elapsed(),
+        })
+    };
 
     loop {
-        let status = get_stack_status(&name, &cloudformation_client).await;
+        let status = get_stack_status(&name, cloudformation_client).await;
 
         if let Some(status) = status {
+            progress.report(&status, cloudformation_client).await;
+
             match status {
-                StackStatus::DeleteComplete => return Ok(()),
-                StackStatus::DeleteInProgress => {
-                    if print_progress {
-                        println!("destroying...");
-                    }
+                StackStatus::DeleteComplete => {
+                    completed();
+                    return Ok(());
                 }
+                StackStatus::DeleteInProgress => {}
                 StackStatus::DeleteFailed => {
                     return Err(DestroyError::StackDeleteError(format!("{status}")));
                 }
@@ -66,6 +118,7 @@ pub async fn destroy(name: StringWithOnlyAlphaNumericsAndHyphens, print_progress
             }
         } else {
             // no status, so stack should be gone
+            completed();
             return Ok(());
         }
 
@@ -73,139 +126,537 @@ pub async fn destroy(name: StringWithOnlyAlphaNumericsAndHyphens, print_progress
     }
 }
 
-async fn destroy_stack(name: &String, cloudformation_client: &Client) -> Result<(), DestroyError> {
-    let delete_result = cloudformation_client.delete_stack().stack_name(name).send().await;
-    match delete_result {
-        Ok(_) => Ok(()),
-        Err(e) => Err(DestroyError::StackDeleteError(e.to_string())),
-    }
+/// Prepares a deployed stack for destruction, avoiding common causes of a `DELETE_FAILED`.
+///
+/// This empties S3 buckets (including object versions and delete markers of versioned buckets) and ECR repositories
+/// that will be deleted, and removes archive policies from SNS topics.
+/// Deletion protection of DynamoDB tables and DocumentDB (global) clusters is only disabled when `disable_deletion_protection` is `true`.
+/// Without it, a stack containing any of those with deletion protection results in `DestroyError::DeletionProtected`, before anything is emptied.
+/// Resources with a `Retain` deletion policy are left alone.
+///
+/// *Use with caution*, and only if you don't need anything from your stack that is not set to 'retain'.
+///
+/// It returns the resources that will be retained, and will be orphaned after the stack is destroyed.
+///
+/// # Parameters
+///
+/// * `name` - The CloudFormation stack name (alphanumeric characters and hyphens only)
+/// * `reporter` - Receives progress updates, see `ProgressReporter`
+/// * `disable_deletion_protection` - Disable the deletion protection of tables and clusters that will be deleted
+/// * `options` - The AWS profile, region and roles to use, see `DeployOptions`
+///
+/// # AWS Credentials
+///
+/// The AWS credentials must have permissions for:
+/// - `cloudformation:GetTemplate`
+/// - `cloudformation:DescribeStackResource`
+/// - `s3:ListBucket`, `s3:ListBucketVersions`, `s3:DeleteObject` and `s3:DeleteObjectVersion`
+/// - `sns:SetTopicAttributes`
+/// - `ecr:ListImages` and `ecr:BatchDeleteImage`
+/// - `dynamodb:DescribeTable` (to check for deletion protection of tables)
+/// - `dynamodb:UpdateTable`, `rds:ModifyDBCluster` and `rds:ModifyGlobalCluster` (if you disable deletion protection)
+pub async fn clean(
+    name: StringWithOnlyAlphaNumericsAndHyphens,
+    reporter: &dyn ProgressReporter,
+    disable_deletion_protection: bool,
+    options: &DeployOptions,
+) -> Result<Vec<OrphanedResource>, DestroyError> {
+    let config = load_config(false, options).await;
+    let cloudformation_client = AwsCloudFormation::new(&config);
+    let s3_client = AwsS3::new(&config, options);
+    let sns_client = AwsSns::new(&config);
+    let ecr_client = AwsEcr::new(&config);
+    let deletion_protection_client = AwsDeletionProtection::new(&config);
+
+    let clients = CleanClients {
+        cloudformation: &cloudformation_client,
+        s3: &s3_client,
+        sns: &sns_client,
+        ecr: &ecr_client,
+        deletion_protection: &deletion_protection_client,
+    };
+
+    clean_with_clients(name.0, reporter, disable_deletion_protection, clients).await
 }
 
-pub async fn clean(name: StringWithOnlyAlphaNumericsAndHyphens, print_progress: bool) -> Result<(), DestroyError> {
-    let config = load_config(false).await;
-    let cloudformation_client = Client::new(&config);
+pub(crate) struct CleanClients<'a, C, S, N, E, D> {
+    pub(crate) cloudformation: &'a C,
+    pub(crate) s3: &'a S,
+    pub(crate) sns: &'a N,
+    pub(crate) ecr: &'a E,
+    pub(crate) deletion_protection: &'a D,
+}
 
-    let stack = get_existing_template(&cloudformation_client, &name.0)
+pub(crate) async fn clean_with_clients<C: CloudFormationApi, S: S3Api, N: SnsApi, E: EcrApi, D: DeletionProtectionApi>(
+    name: String,
+    reporter: &dyn ProgressReporter,
+    disable_deletion_protection: bool,
+    clients: CleanClients<'_, C, S, N, E, D>,
+) -> Result<Vec<OrphanedResource>, DestroyError> {
+    let cloudformation_client = clients.cloudformation;
+
+    let stack = get_existing_template(cloudformation_client, &name)
         .await
-        .ok_or_else(|| DestroyError::UnknownStack(format!("could not retrieve stack with name {}", &name.0)))?;
+        .ok_or_else(|| DestroyError::UnknownStack(format!("could not retrieve stack with name {}", &name)))?;
+
+    let stack: Stack =
+        serde_json::from_str(&stack).map_err(|e| DestroyError::UnknownError(format!("could not read template of stack {name}: {e}")))?;
+
+    let cleanable = stack.get_cleanable_resources();
 
-    let stack: Stack = serde_json::from_str(&stack).expect("to transform template into stack");
+    if !disable_deletion_protection {
+        // check before emptying anything, because the delete is going to fail anyway
+        let mut protected = vec![];
+        for resource in &cleanable {
+            match resource {
+                Cleanable::DBCluster(id) | Cleanable::GlobalCluster(id) => protected.push(id.to_string()),
+                // the protection of a table can be enabled outside CloudFormation, so ask DynamoDB
+                Cleanable::Table(id) => {
+                    let physical_id = get_physical_id(&name, id, cloudformation_client).await?;
+                    let enabled = clients
+                        .deletion_protection
+                        .is_table_deletion_protected(&physical_id)
+                        .await
+                        .map_err(DestroyError::DeletionProtectionError)?;
+                    if enabled {
+                        protected.push(id.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !protected.is_empty() {
+            return Err(DestroyError::DeletionProtected(protected));
+        }
+    }
 
-    for resource in stack.get_cleanable_resources() {
+    for resource in cleanable {
         match resource {
             Cleanable::Bucket(id) => {
-                let bucket_info = cloudformation_client
-                    .describe_stack_resource()
-                    .stack_name(&name.0)
-                    .logical_resource_id(id)
-                    .send()
-                    .await
-                    .expect("to find resource that's mentioned in the template");
-                let physical_id = bucket_info
-                    .stack_resource_detail
-                    .expect("stack resource output to have detail")
-                    .physical_resource_id
-                    .expect("physical id to be present");
-
-                if print_progress {
-                    println!("found bucket {id} (name {physical_id}) that will be deleted - emptying")
-                }
-                empty_bucket(physical_id).await?
+                let physical_id = get_physical_id(&name, id, cloudformation_client).await?;
+
+                reporter.report(ProgressEvent::Info(format!(
+                    "found bucket {id} (name {physical_id}) that will be deleted - emptying"
+                )));
+                empty_bucket(physical_id, clients.s3, reporter).await?
+            }
+            Cleanable::VersionedBucket(id) => {
+                let physical_id = get_physical_id(&name, id, cloudformation_client).await?;
+
+                reporter.report(ProgressEvent::Info(format!(
+                    "found versioned bucket {id} (name {physical_id}) that will be deleted - removing all versions"
+                )));
+                empty_versioned_bucket(physical_id, clients.s3, reporter).await?
             }
             Cleanable::Topic(id) => {
-                let bucket_info = cloudformation_client
-                    .describe_stack_resource()
-                    .stack_name(&name.0)
-                    .logical_resource_id(id)
-                    .send()
+                let physical_id = get_physical_id(&name, id, cloudformation_client).await?;
+
+                reporter.report(ProgressEvent::Info(format!(
+                    "found topic {id} (arn {physical_id}) that will be deleted - remove archival policy"
+                )));
+                clients
+                    .sns
+                    .set_topic_attribute(&physical_id, "ArchivePolicy", "{}")
                     .await
-                    .expect("to find resource that's mentioned in the template");
-                let physical_id = bucket_info
-                    .stack_resource_detail
-                    .expect("stack resource output to have detail")
-                    .physical_resource_id
-                    .expect("physical id to be present");
-
-                if print_progress {
-                    println!("found topic {id} (arn {physical_id}) that will be deleted - remove archival policy")
+                    .map_err(|e| DestroyError::EmptyError(format!("could not remove archive policy from topic: {}", e)))?;
+            }
+            Cleanable::Repository(id) => {
+                let physical_id = get_physical_id(&name, id, cloudformation_client).await?;
+
+                reporter.report(ProgressEvent::Info(format!(
+                    "found repository {id} (name {physical_id}) that will be deleted - removing images"
+                )));
+                empty_repository(physical_id, clients.ecr).await?
+            }
+            Cleanable::Table(id) if disable_deletion_protection => {
+                let physical_id = get_physical_id(&name, id, cloudformation_client).await?;
+
+                let disabled = clients
+                    .deletion_protection
+                    .disable_table_deletion_protection(&physical_id)
+                    .await
+                    .map_err(DestroyError::DeletionProtectionError)?;
+                if disabled {
+                    reporter.report(ProgressEvent::Info(format!(
+                        "disabled deletion protection of table {id} (name {physical_id})"
+                    )));
                 }
-                remove_archive_policy(physical_id).await?;
+            }
+            Cleanable::Table(_) => {}
+            Cleanable::DBCluster(id) => {
+                let physical_id = get_physical_id(&name, id, cloudformation_client).await?;
+
+                reporter.report(ProgressEvent::Info(format!(
+                    "found cluster {id} (identifier {physical_id}) with deletion protection - disabling"
+                )));
+                clients
+                    .deletion_protection
+                    .disable_cluster_deletion_protection(&physical_id)
+                    .await
+                    .map_err(DestroyError::DeletionProtectionError)?;
+            }
+            Cleanable::GlobalCluster(id) => {
+                let physical_id = get_physical_id(&name, id, cloudformation_client).await?;
+
+                reporter.report(ProgressEvent::Info(format!(
+                    "found global cluster {id} (identifier {physical_id}) with deletion protection - disabling"
+                )));
+                clients
+                    .deletion_protection
+                    .disable_global_cluster_deletion_protection(&physical_id)
+                    .await
+                    .map_err(DestroyError::DeletionProtectionError)?;
             }
         }
     }
 
-    Ok(())
-}
+    let mut orphaned = vec![];
 
-async fn remove_archive_policy(arn: String) -> Result<(), DestroyError> {
-    let config = load_config(false).await;
-    let client = aws_sdk_sns::Client::new(&config);
+    for (id, resource_id) in stack.get_retained_resources() {
+        let physical_id = cloudformation_client.get_physical_id(&name, &resource_id).await.ok().flatten();
+        orphaned.push(OrphanedResource {
+            id,
+            resource_id,
+            physical_id,
+        });
+    }
+
+    orphaned.iter().for_each(|o| {
+        reporter.report(ProgressEvent::Warning(format!(
+            "{o} is retained, and will remain after the stack is destroyed"
+        )))
+    });
+
+    Ok(orphaned)
+}
 
-    client
-        .set_topic_attributes()
-        .topic_arn(arn)
-        .attribute_name("ArchivePolicy")
-        .attribute_value("{}")
-        .send()
+async fn get_physical_id<C: CloudFormationApi>(name: &str, resource_id: &str, cloudformation_client: &C) -> Result<String, DestroyError> {
+    cloudformation_client
+        .get_physical_id(name, resource_id)
         .await
-        .map_err(|e| DestroyError::EmptyError(format!("could not remove archive policy from topic: {:?}", e)))?;
+        .map_err(DestroyError::UnknownError)?
+        .ok_or_else(|| DestroyError::UnknownError(format!("resource {resource_id} has no physical id")))
+}
+
+async fn empty_bucket<S: S3Api>(name: String, s3_client: &S, reporter: &dyn ProgressReporter) -> Result<(), DestroyError> {
+    let mut marker = None;
+
+    loop {
+        let (keys, next_marker) = s3_client.list_objects(&name, marker).await.map_err(DestroyError::EmptyError)?;
+
+        if !keys.is_empty() {
+            s3_client.delete_objects(&name, keys).await.map_err(DestroyError::EmptyError)?;
+        }
 
-    Ok(())
+        match next_marker {
+            Some(next_marker) => {
+                reporter.report(ProgressEvent::Info(format!("more cleanup required for {name}...")));
+                marker = Some(next_marker);
+            }
+            None => return Ok(()),
+        }
+    }
 }
 
-async fn empty_bucket(name: String) -> Result<(), DestroyError> {
-    let config = load_config(false).await;
-    let client = aws_sdk_s3::Client::new(&config);
+async fn empty_versioned_bucket<S: S3Api>(name: String, s3_client: &S, reporter: &dyn ProgressReporter) -> Result<(), DestroyError> {
+    let mut marker = None;
+
+    loop {
+        let (versions, next_marker) = s3_client
+            .list_object_versions(&name, marker)
+            .await
+            .map_err(DestroyError::EmptyError)?;
 
-    let mut marker_response = delete_objects(&client, &name, None).await?;
+        if !versions.is_empty() {
+            s3_client
+                .delete_object_versions(&name, versions)
+                .await
+                .map_err(DestroyError::EmptyError)?;
+        }
 
-    while let Some(marker) = marker_response {
-        println!("more cleanup required for {name}...");
-        marker_response = delete_objects(&client, &name, Some(marker)).await?;
+        match next_marker {
+            Some(next_marker) => {
+                reporter.report(ProgressEvent::Info(format!("more cleanup required for {name}...")));
+                marker = Some(next_marker);
+            }
+            None => return Ok(()),
+        }
     }
+}
+
+async fn empty_repository<E: EcrApi>(name: String, ecr_client: &E) -> Result<(), DestroyError> {
+    // deleted images disappear from the listing, so keep retrieving the first page until it is empty
+    loop {
+        let digests = ecr_client.list_image_digests(&name).await.map_err(DestroyError::EmptyError)?;
 
-    Ok(())
+        if digests.is_empty() {
+            return Ok(());
+        }
+        ecr_client.delete_images(&name, digests).await.map_err(DestroyError::EmptyError)?;
+    }
 }
 
-async fn delete_objects(client: &aws_sdk_s3::Client, name: &str, marker: Option<String>) -> Result<Option<String>, DestroyError> {
-    let mut builder = client.list_objects().bucket(name);
+#[cfg(test)]
+mod tests {
+    use crate::clients::StackResourceInfo;
+    use crate::clients::fake::{FakeCloudFormation, FakeDeletionProtection, FakeEcr, FakeS3, FakeSns};
+    use crate::destroy::{CleanClients, DestroyError, clean_with_clients, destroy_with_client};
+    use crate::options::DeployOptions;
+    use crate::progress::{ProgressEvent, RecordingReporter, SilentReporter};
+    use aws_sdk_cloudformation::types::StackStatus;
+    use rusty_cdk_core::docdb::GlobalClusterBuilder;
+    use rusty_cdk_core::dynamodb::{AttributeType, Key, TableBuilder};
+    use rusty_cdk_core::ecr::RepositoryBuilder;
+    use rusty_cdk_core::s3::{BucketBuilder, VersioningConfiguration};
+    use rusty_cdk_core::shared::{DeletionPolicy, UpdateReplacePolicy};
+    use rusty_cdk_core::sns::TopicBuilder;
+    use rusty_cdk_core::stack::{Stack, StackBuilder};
+    use rusty_cdk_core::wrappers::{ArchivePolicy, StringWithOnlyAlphaNumericsAndUnderscores};
+
+    fn resource(resource_id: &str, physical_id: &str) -> StackResourceInfo {
+        StackResourceInfo {
+            resource_id: resource_id.to_string(),
+            physical_id: Some(physical_id.to_string()),
+            status: None,
+        }
+    }
 
-    if let Some(marker) = marker {
-        builder = builder.marker(marker);
+    fn resource_id(template: &str, id: &str) -> String {
+        let ids = Stack::get_ids_by_resource_id(template).unwrap();
+        ids.into_iter().find(|(_, i)| i == id).unwrap().0
     }
 
-    let objects = builder
-        .send()
+    #[tokio::test(start_paused = true)]
+    async fn should_destroy_stack() {
+        let cloudformation_client = FakeCloudFormation::new().with_stack("stack", "{}", vec![StackStatus::CreateComplete]);
+
+        destroy_with_client(
+            "stack".to_string(),
+            &SilentReporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+        )
         .await
-        .map_err(|e| DestroyError::EmptyError(format!("could not list objects to delete: {:?}", e)))?;
-
-    let marker = objects.marker;
-
-    if let Some(content) = objects.contents {
-        let objects_to_delete = content
-            .into_iter()
-            .map(|v| {
-                ObjectIdentifier::builder()
-                    .key(v.key.expect("object to have a key"))
-                    .build()
-                    .expect("building object identifier to succeed")
-            })
-            .collect();
-
-        let to_delete = Delete::builder()
-            .set_objects(Some(objects_to_delete))
-            .build()
-            .expect("building delete object to succeed");
-        client
-            .delete_objects()
-            .bucket(name)
-            .delete(to_delete)
-            .send()
+        .expect("destroy to succeed");
+
+        assert_eq!(cloudformation_client.calls(), vec!["delete_stack"]);
+        assert!(cloudformation_client.template("stack").is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_return_error_when_delete_fails() {
+        let mut cloudformation_client = FakeCloudFormation::new().with_stack("stack", "{}", vec![StackStatus::CreateComplete]);
+        cloudformation_client.delete_statuses = vec![StackStatus::DeleteInProgress, StackStatus::DeleteFailed];
+
+        let result = destroy_with_client(
+            "stack".to_string(),
+            &SilentReporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+        )
+        .await;
+
+        assert!(matches!(result, Err(DestroyError::StackDeleteError(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_empty_buckets_and_remove_archive_policies() {
+        let mut stack_builder = StackBuilder::new();
+        BucketBuilder::new("bucket").build(&mut stack_builder);
+        TopicBuilder::new("topic")
+            .fifo()
+            .archive_policy(ArchivePolicy(30))
+            .build(&mut stack_builder);
+        let template = stack_builder.build().unwrap().synth().unwrap();
+
+        let cloudformation_client = FakeCloudFormation::new()
+            .with_stack("stack", &template, vec![StackStatus::CreateComplete])
+            .with_resource("stack", resource(&resource_id(&template, "bucket"), "my-bucket"))
+            .with_resource(
+                "stack",
+                resource(&resource_id(&template, "topic"), "arn:aws:sns:us-east-1:123456789012:my-topic.fifo"),
+            );
+        let s3_client = FakeS3::new().with_objects("my-bucket", vec!["a", "b", "c", "d", "e"]);
+        let sns_client = FakeSns::new();
+        let clients = CleanClients {
+            cloudformation: &cloudformation_client,
+            s3: &s3_client,
+            sns: &sns_client,
+            ecr: &FakeEcr::new(),
+            deletion_protection: &FakeDeletionProtection::new(),
+        };
+
+        clean_with_clients("stack".to_string(), &SilentReporter, false, clients)
             .await
-            .map_err(|e| DestroyError::EmptyError(format!("could not delete objects: {:?}", e)))?;
+            .expect("clean to succeed");
+
+        assert!(s3_client.keys("my-bucket").is_empty());
+        assert_eq!(
+            *sns_client.attributes.lock().unwrap(),
+            vec![(
+                "arn:aws:sns:us-east-1:123456789012:my-topic.fifo".to_string(),
+                "ArchivePolicy".to_string(),
+                "{}".to_string()
+            )]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_remove_object_versions_and_images_and_report_retained_resources() {
+        let mut stack_builder = StackBuilder::new();
+        BucketBuilder::new("bucket")
+            .versioning_configuration(VersioningConfiguration::Enabled)
+            .build(&mut stack_builder);
+        BucketBuilder::new("retained")
+            .update_replace_and_deletion_policy(UpdateReplacePolicy::Retain, DeletionPolicy::Retain)
+            .build(&mut stack_builder);
+        RepositoryBuilder::new("repository").build(&mut stack_builder);
+        let template = stack_builder.build().unwrap().synth().unwrap();
+
+        let cloudformation_client = FakeCloudFormation::new()
+            .with_stack("stack", &template, vec![StackStatus::CreateComplete])
+            .with_resource("stack", resource(&resource_id(&template, "bucket"), "my-bucket"))
+            .with_resource("stack", resource(&resource_id(&template, "retained"), "my-retained-bucket"))
+            .with_resource("stack", resource(&resource_id(&template, "repository"), "my-repository"));
+        let s3_client = FakeS3::new().with_versions("my-bucket", vec![("a", "1"), ("a", "2"), ("b", "1")]);
+        let ecr_client = FakeEcr::new().with_images("my-repository", vec!["sha256:1", "sha256:2", "sha256:3"]);
+        let clients = CleanClients {
+            cloudformation: &cloudformation_client,
+            s3: &s3_client,
+            sns: &FakeSns::new(),
+            ecr: &ecr_client,
+            deletion_protection: &FakeDeletionProtection::new(),
+        };
+
+        let reporter = RecordingReporter::new();
+
+        let orphaned = clean_with_clients("stack".to_string(), &reporter, false, clients)
+            .await
+            .expect("clean to succeed");
+
+        assert!(s3_client.versions("my-bucket").is_empty());
+        assert!(ecr_client.images("my-repository").is_empty());
+        assert_eq!(orphaned.len(), 1);
+        assert_eq!(orphaned[0].to_string(), "retained (my-retained-bucket)");
+        assert!(
+            matches!(reporter.events().last(), Some(ProgressEvent::Warning(w)) if w.starts_with("retained (my-retained-bucket) is retained"))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_only_disable_deletion_protection_of_tables_when_asked() {
+        let mut stack_builder = StackBuilder::new();
+        TableBuilder::new(
+            "table",
+            Key::new(StringWithOnlyAlphaNumericsAndUnderscores("id".to_string()), AttributeType::String),
+        )
+        .pay_per_request_billing()
+        .build(&mut stack_builder);
+        BucketBuilder::new("bucket").build(&mut stack_builder);
+        let template = stack_builder.build().unwrap().synth().unwrap();
+
+        let cloudformation_client = FakeCloudFormation::new()
+            .with_stack("stack", &template, vec![StackStatus::CreateComplete])
+            .with_resource("stack", resource(&resource_id(&template, "table"), "my-table"))
+            .with_resource("stack", resource(&resource_id(&template, "bucket"), "my-bucket"));
+        let s3_client = FakeS3::new().with_objects("my-bucket", vec!["a"]);
+        let mut deletion_protection_client = FakeDeletionProtection::new();
+        deletion_protection_client.protected_tables = vec!["my-table".to_string()];
+
+        for disable_deletion_protection in [false, true] {
+            let clients = CleanClients {
+                cloudformation: &cloudformation_client,
+                s3: &s3_client,
+                sns: &FakeSns::new(),
+                ecr: &FakeEcr::new(),
+                deletion_protection: &deletion_protection_client,
+            };
+            let result = clean_with_clients("stack".to_string(), &SilentReporter, disable_deletion_protection, clients).await;
+
+            if disable_deletion_protection {
+                assert!(result.is_ok());
+                assert!(s3_client.keys("my-bucket").is_empty());
+            } else {
+                assert!(matches!(result, Err(DestroyError::DeletionProtected(ids)) if ids == vec![resource_id(&template, "table")]));
+                // nothing is emptied when the delete is going to fail
+                assert_eq!(s3_client.keys("my-bucket").len(), 1);
+            }
+        }
+
+        assert_eq!(*deletion_protection_client.disabled.lock().unwrap(), vec!["my-table".to_string()]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_not_refuse_tables_without_deletion_protection() {
+        let mut stack_builder = StackBuilder::new();
+        TableBuilder::new(
+            "table",
+            Key::new(StringWithOnlyAlphaNumericsAndUnderscores("id".to_string()), AttributeType::String),
+        )
+        .pay_per_request_billing()
+        .build(&mut stack_builder);
+        let template = stack_builder.build().unwrap().synth().unwrap();
+
+        let cloudformation_client = FakeCloudFormation::new()
+            .with_stack("stack", &template, vec![StackStatus::CreateComplete])
+            .with_resource("stack", resource(&resource_id(&template, "table"), "my-table"));
+        let clients = CleanClients {
+            cloudformation: &cloudformation_client,
+            s3: &FakeS3::new(),
+            sns: &FakeSns::new(),
+            ecr: &FakeEcr::new(),
+            deletion_protection: &FakeDeletionProtection::new(),
+        };
+
+        let result = clean_with_clients("stack".to_string(), &SilentReporter, false, clients).await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_refuse_and_disable_deletion_protection_of_global_clusters() {
+        let mut stack_builder = StackBuilder::new();
+        GlobalClusterBuilder::new("global", "my-global-cluster".to_string())
+            .deletion_protection(true)
+            .build(&mut stack_builder);
+        let template = stack_builder.build().unwrap().synth().unwrap();
+
+        let cloudformation_client = FakeCloudFormation::new()
+            .with_stack("stack", &template, vec![StackStatus::CreateComplete])
+            .with_resource("stack", resource(&resource_id(&template, "global"), "my-global-cluster"));
+        let deletion_protection_client = FakeDeletionProtection::new();
+
+        for disable_deletion_protection in [false, true] {
+            let clients = CleanClients {
+                cloudformation: &cloudformation_client,
+                s3: &FakeS3::new(),
+                sns: &FakeSns::new(),
+                ecr: &FakeEcr::new(),
+                deletion_protection: &deletion_protection_client,
+            };
+            let result = clean_with_clients("stack".to_string(), &SilentReporter, disable_deletion_protection, clients).await;
+
+            assert_eq!(result.is_ok(), disable_deletion_protection);
+        }
+
+        assert_eq!(
+            *deletion_protection_client.disabled.lock().unwrap(),
+            vec!["my-global-cluster".to_string()]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_return_error_when_cleaning_unknown_stack() {
+        let clients = CleanClients {
+            cloudformation: &FakeCloudFormation::new(),
+            s3: &FakeS3::new(),
+            sns: &FakeSns::new(),
+            ecr: &FakeEcr::new(),
+            deletion_protection: &FakeDeletionProtection::new(),
+        };
+
+        let result = clean_with_clients("stack".to_string(), &SilentReporter, false, clients).await;
 
-        Ok(marker)
-    } else {
-        Ok(None)
+        assert!(matches!(result, Err(DestroyError::UnknownStack(_))));
     }
 }
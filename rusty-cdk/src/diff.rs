@@ -1,6 +1,7 @@
+use crate::clients::AwsCloudFormation;
+use crate::options::DeployOptions;
 use crate::util::{get_existing_template, load_config};
-use aws_sdk_cloudformation::Client;
-use rusty_cdk_core::stack::{Stack, StackDiff};
+use rusty_cdk_core::stack::{SecurityDiff, Stack, StackDiff};
 use rusty_cdk_core::wrappers::StringWithOnlyAlphaNumericsAndHyphens;
 
 /// Creates a diff that will show what ids are being added / removed to an existing stack, as well as showing ids that remain without being added or removed.
@@ -10,7 +11,7 @@ use rusty_cdk_core::wrappers::StringWithOnlyAlphaNumericsAndHyphens;
 ///
 /// * `name` - The existing CloudFormation stack name
 /// * `stack` - The new stack
-/// * `print_progress` - Print progress updates to standard out
+/// * `options` - The AWS profile, region and roles to use, see `DeployOptions`
 ///
 /// # AWS Credentials
 ///
@@ -18,9 +19,9 @@ use rusty_cdk_core::wrappers::StringWithOnlyAlphaNumericsAndHyphens;
 /// The AWS credentials must have permissions for:
 /// - `cloudformation:DescribeStacks`
 /// - `cloudformation:GetTemplate`
-pub async fn diff(name: StringWithOnlyAlphaNumericsAndHyphens, stack: Stack) -> Result<String, String> {
-    let config = load_config(false).await;
-    let cloudformation_client = Client::new(&config);
+pub async fn diff(name: StringWithOnlyAlphaNumericsAndHyphens, stack: Stack, options: &DeployOptions) -> Result<String, String> {
+    let config = load_config(false, options).await;
+    let cloudformation_client = AwsCloudFormation::new(&config);
 
     match get_existing_template(&cloudformation_client, &name.0).await {
         None => Err(format!("could not find existing stack with name {}", name.0)),
@@ -58,3 +59,32 @@ fn print_ids(ids: Vec<(String, String)>) -> String {
             .join(", ")
     }
 }
+
+/// Looks for security-sensitive changes compared to an existing stack, like new IAM statements or principals in resource policies.
+/// When no stack with the given name exists, all of these in the new stack are considered new.
+/// See `Stack::get_security_diff` for the changes that are detected.
+///
+/// # Parameters
+///
+/// * `name` - The CloudFormation stack name
+/// * `stack` - The new stack
+/// * `options` - The AWS profile, region and roles to use, see `DeployOptions`
+///
+/// # AWS Credentials
+///
+/// This function requires valid AWS credentials.
+/// The AWS credentials must have permissions for:
+/// - `cloudformation:DescribeStacks`
+/// - `cloudformation:GetTemplate`
+pub async fn security_diff(
+    name: StringWithOnlyAlphaNumericsAndHyphens,
+    mut stack: Stack,
+    options: &DeployOptions,
+) -> Result<SecurityDiff, String> {
+    let config = load_config(false, options).await;
+    let cloudformation_client = AwsCloudFormation::new(&config);
+
+    let existing = get_existing_template(&cloudformation_client, &name.0).await;
+    stack.package_cargo_crates()?;
+    stack.get_security_diff(existing.as_deref())
+}
@@ -0,0 +1,307 @@
+use crate::clients::{AwsCloudFormation, CloudFormationApi};
+use crate::options::DeployOptions;
+use crate::util::{PollInterval, ctrl_c_pressed, get_existing_template, load_config};
+use aws_sdk_cloudformation::types::StackDriftDetectionStatus;
+use rusty_cdk_core::stack::Stack;
+use rusty_cdk_core::wrappers::StringWithOnlyAlphaNumericsAndHyphens;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::pin::{Pin, pin};
+use tokio::time::{Instant, sleep};
+
+#[derive(Debug)]
+pub enum DriftError {
+    UnknownStack(String),
+    DetectionError(String),
+    DetectionFailed(String),
+    /// Drift detection did not finish within `DeployOptions::timeout`
+    Timeout(String),
+    /// Waiting for drift detection was cancelled with ctrl-c, see `DeployOptions::cancel_on_ctrl_c`
+    Cancelled(String),
+    UnknownError(String),
+}
+
+impl Error for DriftError {}
+
+impl Display for DriftError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DriftError::UnknownStack(_) => f.write_str("stack could not be found"),
+            DriftError::DetectionError(_) => f.write_str("unable to detect drift"),
+            DriftError::DetectionFailed(_) => f.write_str("drift detection failed"),
+            DriftError::Timeout(_) => f.write_str("timed out waiting for drift detection"),
+            DriftError::Cancelled(_) => f.write_str("drift detection was cancelled"),
+            DriftError::UnknownError(_) => f.write_str("unknown error"),
+        }
+    }
+}
+
+/// A resource whose actual configuration differs from the one in the deployed template.
+#[derive(Debug, Clone)]
+pub struct DriftedResource {
+    /// The id that was passed to the builder, if the resource could be found in the stack metadata
+    pub id: Option<String>,
+    /// The resource id (logical id) in the CloudFormation template
+    pub resource_id: String,
+    pub physical_id: Option<String>,
+    pub resource_type: String,
+    /// `MODIFIED` or `DELETED`
+    pub status: String,
+    pub differences: Vec<PropertyDrift>,
+}
+
+/// A single property that differs from the deployed template.
+#[derive(Debug, Clone)]
+pub struct PropertyDrift {
+    pub path: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+    /// `ADD`, `REMOVE` or `NOT_EQUAL`
+    pub difference_type: String,
+}
+
+impl Display for DriftedResource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let id = self.id.as_deref().unwrap_or("(unknown id)");
+        f.write_fmt(format_args!(
+            "{} (resource {}, type {}): {}",
+            id, self.resource_id, self.resource_type, self.status
+        ))?;
+        for difference in &self.differences {
+            f.write_fmt(format_args!("\n  {difference}"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for PropertyDrift {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{} ({}): expected {}, actual {}",
+            self.path,
+            self.difference_type,
+            self.expected.as_deref().unwrap_or("(none)"),
+            self.actual.as_deref().unwrap_or("(none)")
+        ))
+    }
+}
+
+/// Detects drift for a deployed stack, i.e. changes that were made to its resources outside of CloudFormation (for example in the console).
+///
+/// Starts CloudFormation drift detection, waits for it to complete, and returns the resources that were modified or deleted.
+/// The resources are linked to the ids you passed to the builders using the `Metadata` of the deployed stack.
+/// An empty list means no drift was detected.
+/// How long it waits for the detection can be limited with `DeployOptions::timeout`, and `DeployOptions::cancel_on_ctrl_c` allows cancelling the wait.
+///
+/// # Parameters
+///
+/// * `name` - The existing CloudFormation stack name
+/// * `options` - The AWS profile, region and roles to use, see `DeployOptions`
+///
+/// # AWS Credentials
+///
+/// This function requires valid AWS credentials.
+/// The AWS credentials must have permissions for:
+/// - `cloudformation:DescribeStacks`
+/// - `cloudformation:GetTemplate`
+/// - `cloudformation:DetectStackDrift`
+/// - `cloudformation:DetectStackResourceDrift`
+/// - `cloudformation:DescribeStackDriftDetectionStatus`
+/// - `cloudformation:DescribeStackResourceDrifts`
+/// - Read permissions for the resources in the stack
+pub async fn drift(name: StringWithOnlyAlphaNumericsAndHyphens, options: &DeployOptions) -> Result<Vec<DriftedResource>, DriftError> {
+    let config = load_config(false, options).await;
+    let cloudformation_client = AwsCloudFormation::new(&config);
+
+    drift_with_client(name.0, options, &cloudformation_client, ctrl_c_pressed(options)).await
+}
+
+pub(crate) async fn drift_with_client<C: CloudFormationApi>(
+    name: String,
+    options: &DeployOptions,
+    cloudformation_client: &C,
+    cancel: impl Future<Output = ()>,
+) -> Result<Vec<DriftedResource>, DriftError> {
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+
+    let existing = get_existing_template(cloudformation_client, &name)
+        .await
+        .ok_or_else(|| DriftError::UnknownStack(format!("could not retrieve stack with name {name}")))?;
+    let ids = Stack::get_ids_by_resource_id(&existing).map_err(DriftError::UnknownError)?;
+
+    let detection_id = cloudformation_client
+        .detect_stack_drift(&name)
+        .await
+        .map_err(DriftError::DetectionError)?;
+
+    wait_for_detection(&name, &detection_id, cloudformation_client, deadline, pin!(cancel)).await?;
+
+    let drifts = cloudformation_client
+        .describe_stack_resource_drifts(&name)
+        .await
+        .map_err(DriftError::DetectionError)?;
+
+    Ok(drifts
+        .into_iter()
+        .map(|drift| DriftedResource {
+            id: ids.get(&drift.resource_id).cloned(),
+            ..drift
+        })
+        .collect())
+}
+
+async fn wait_for_detection<C: CloudFormationApi>(
+    name: &str,
+    detection_id: &str,
+    cloudformation_client: &C,
+    deadline: Option<Instant>,
+    mut cancel: Pin<&mut impl Future<Output = ()>>,
+) -> Result<(), DriftError> {
+    let mut interval = PollInterval::new();
+
+    loop {
+        let detection = cloudformation_client
+            .describe_stack_drift_detection_status(detection_id)
+            .await
+            .map_err(DriftError::DetectionError)?;
+
+        match detection.status {
+            StackDriftDetectionStatus::DetectionComplete => return Ok(()),
+            StackDriftDetectionStatus::DetectionInProgress => {}
+            StackDriftDetectionStatus::DetectionFailed => {
+                // detection also fails when some resources do not support drift detection, results for the others are still available
+                if detection.drifted_resource_count.is_some() {
+                    return Ok(());
+                }
+                return Err(DriftError::DetectionFailed(detection.reason.unwrap_or_default()));
+            }
+            other => return Err(DriftError::UnknownError(format!("{other:?}"))),
+        }
+
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(DriftError::Timeout(format!(
+                "drift detection for stack {name} did not finish in time"
+            )));
+        }
+        tokio::select! {
+            _ = sleep(interval.next(false)) => {}
+            _ = &mut cancel => {
+                return Err(DriftError::Cancelled(format!("stopped waiting for drift detection for stack {name}")));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clients::fake::FakeCloudFormation;
+    use crate::drift::{DriftError, DriftedResource, PropertyDrift, drift_with_client};
+    use crate::options::DeployOptions;
+    use aws_sdk_cloudformation::types::{StackDriftDetectionStatus, StackStatus};
+    use std::future::pending;
+    use std::time::Duration;
+
+    const TEMPLATE: &str = r#"{"Resources": {}, "Metadata": { "queue": "Queue123" } }"#;
+
+    fn stack() -> FakeCloudFormation {
+        FakeCloudFormation::new().with_stack("stack", TEMPLATE, vec![StackStatus::UpdateComplete])
+    }
+
+    fn modified_queue() -> DriftedResource {
+        DriftedResource {
+            id: None,
+            resource_id: "Queue123".to_string(),
+            physical_id: Some("https://sqs.us-east-1.amazonaws.com/123456789012/queue".to_string()),
+            resource_type: "AWS::SQS::Queue".to_string(),
+            status: "MODIFIED".to_string(),
+            differences: vec![PropertyDrift {
+                path: "/VisibilityTimeout".to_string(),
+                expected: Some("30".to_string()),
+                actual: Some("60".to_string()),
+                difference_type: "NOT_EQUAL".to_string(),
+            }],
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_return_drifted_resources_with_their_ids() {
+        let cloudformation_client = stack().with_drift("stack", modified_queue());
+
+        let drifted = drift_with_client("stack".to_string(), &DeployOptions::default(), &cloudformation_client, pending())
+            .await
+            .expect("drift detection to succeed");
+
+        assert_eq!(drifted.len(), 1);
+        assert_eq!(drifted[0].id.as_deref(), Some("queue"));
+        assert_eq!(drifted[0].resource_id, "Queue123");
+        assert_eq!(drifted[0].differences[0].path, "/VisibilityTimeout");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_return_nothing_for_stack_in_sync() {
+        let cloudformation_client = stack();
+
+        let drifted = drift_with_client("stack".to_string(), &DeployOptions::default(), &cloudformation_client, pending())
+            .await
+            .expect("drift detection to succeed");
+
+        assert!(drifted.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_return_reason_when_detection_fails() {
+        let mut cloudformation_client = stack().with_drift("stack", modified_queue());
+        cloudformation_client.drift_detection_statuses = vec![
+            StackDriftDetectionStatus::DetectionInProgress,
+            StackDriftDetectionStatus::DetectionFailed,
+        ];
+        cloudformation_client.drift_detection_failure = Some("access denied".to_string());
+
+        let result = drift_with_client("stack".to_string(), &DeployOptions::default(), &cloudformation_client, pending()).await;
+
+        assert!(matches!(result, Err(DriftError::DetectionFailed(reason)) if reason == "access denied"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_fail_for_unknown_stack() {
+        let result = drift_with_client(
+            "stack".to_string(),
+            &DeployOptions::default(),
+            &FakeCloudFormation::new(),
+            pending(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(DriftError::UnknownStack(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_stop_waiting_for_detection_after_timeout() {
+        let mut cloudformation_client = stack();
+        cloudformation_client.drift_detection_statuses = vec![StackDriftDetectionStatus::DetectionInProgress];
+        let options = DeployOptions {
+            timeout: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+
+        let result = drift_with_client("stack".to_string(), &options, &cloudformation_client, pending()).await;
+
+        assert!(matches!(result, Err(DriftError::Timeout(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_stop_waiting_for_detection_when_cancelled() {
+        let mut cloudformation_client = stack();
+        cloudformation_client.drift_detection_statuses = vec![StackDriftDetectionStatus::DetectionInProgress];
+
+        let result = drift_with_client(
+            "stack".to_string(),
+            &DeployOptions::default(),
+            &cloudformation_client,
+            tokio::time::sleep(Duration::from_secs(30)),
+        )
+        .await;
+
+        assert!(matches!(result, Err(DriftError::Cancelled(_))));
+    }
+}
@@ -0,0 +1,47 @@
+use crate::options::DeployOptions;
+use crate::util::load_config;
+use std::fmt::{Display, Formatter};
+
+/// The AWS account and region that `deploy`, `diff`, `destroy`, etc. use
+#[derive(Debug, Clone, PartialEq)]
+pub struct AwsEnvironment {
+    pub account: String,
+    pub region: String,
+}
+
+impl Display for AwsEnvironment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("account {} in region {}", self.account, self.region))
+    }
+}
+
+/// Returns the account and region that the functions of this crate deploy to with the given options.
+/// Useful to verify that you are about to deploy to the right place.
+///
+/// # Parameters
+///
+/// * `options` - The AWS profile, region and roles to use, see `DeployOptions`
+///
+/// # AWS Credentials
+///
+/// The AWS credentials must have permissions for:
+/// - `sts:GetCallerIdentity` (allowed for everyone)
+/// - `sts:AssumeRole` (if you pass in `DeployOptions::assume_role_arn`)
+pub async fn get_aws_environment(options: &DeployOptions) -> Result<AwsEnvironment, String> {
+    let config = load_config(false, options).await;
+    let region = config
+        .region()
+        .map(|r| r.to_string())
+        .ok_or_else(|| "no region found in the options, profile or environment".to_string())?;
+
+    let identity = aws_sdk_sts::Client::new(&config)
+        .get_caller_identity()
+        .send()
+        .await
+        .map_err(|e| format!("could not retrieve the account of the credentials: {e:?}"))?;
+    let account = identity
+        .account
+        .ok_or_else(|| "could not retrieve the account of the credentials".to_string())?;
+
+    Ok(AwsEnvironment { account, region })
+}
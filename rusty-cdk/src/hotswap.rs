@@ -0,0 +1,349 @@
+use crate::clients::{AwsCloudFormation, AwsLambda, AwsS3, CloudFormationApi, LambdaApi, S3Api};
+use crate::deploy::{DeployError, DeployResult, deploy_with_clients, get_deploy_result, upload_assets};
+use crate::options::DeployOptions;
+use crate::progress::{ProgressEvent, ProgressReporter};
+use crate::util::{ctrl_c_pressed, get_existing_template, load_config};
+use aws_sdk_lambda::types::LastUpdateStatus;
+use rusty_cdk_core::stack::Stack;
+use rusty_cdk_core::wrappers::StringWithOnlyAlphaNumericsAndHyphens;
+use std::pin::{Pin, pin};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+/// Deploys a stack, updating the code of Lambda functions directly when possible.
+///
+/// A CloudFormation update takes a while, even for small code changes.
+/// When the only changes compared to the deployed stack are to the (zip) code of Lambda functions,
+/// this function uploads the new assets and calls `UpdateFunctionCode`, skipping CloudFormation.
+/// For any other change (or when the stack does not exist yet), it falls back to a normal `deploy`.
+///
+/// Because CloudFormation is skipped, the deployed template keeps referring to the previous assets.
+/// This means the stack has drifted, which is fine during development, but you should do a normal deploy when you are done.
+///
+/// `DeployOptions::timeout` and `DeployOptions::cancel_on_ctrl_c` also apply to waiting for the code updates.
+///
+/// # Parameters
+///
+/// * `name` - The CloudFormation stack name (alphanumeric characters and hyphens only)
+/// * `stack` - The stack to deploy, created using `StackBuilder`
+/// * `reporter` - Receives progress updates, see `ProgressReporter`
+/// * `options` - The AWS profile, region and roles to use, see `DeployOptions`
+///
+/// # AWS Credentials
+///
+/// The AWS credentials must have the permissions required by `deploy`, as well as:
+/// - `cloudformation:DescribeStackResource`
+/// - `lambda:UpdateFunctionCode`
+/// - `lambda:GetFunctionConfiguration`
+pub async fn hotswap(
+    name: StringWithOnlyAlphaNumericsAndHyphens,
+    stack: Stack,
+    reporter: &dyn ProgressReporter,
+    options: &DeployOptions,
+) -> Result<DeployResult, DeployError> {
+    let config = load_config(true, options).await;
+    let cloudformation_client = AwsCloudFormation::new(&config);
+    let s3_client = Arc::new(AwsS3::new(&config, options));
+    let lambda_client = AwsLambda::new(&config);
+
+    hotswap_with_clients(
+        name.0,
+        stack,
+        reporter,
+        options,
+        &cloudformation_client,
+        s3_client,
+        &lambda_client,
+        ctrl_c_pressed(options),
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn hotswap_with_clients<C: CloudFormationApi, S: S3Api + 'static, L: LambdaApi>(
+    name: String,
+    mut stack: Stack,
+    reporter: &dyn ProgressReporter,
+    options: &DeployOptions,
+    cloudformation_client: &C,
+    s3_client: Arc<S>,
+    lambda_client: &L,
+    cancel: impl Future<Output = ()>,
+) -> Result<DeployResult, DeployError> {
+    let start = Instant::now();
+    // tokio time (unlike `start`), so that tests can advance it
+    let deadline = options.timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+    let mut cancel = pin!(cancel);
+
+    let Some(existing) = get_existing_template(cloudformation_client, &name).await else {
+        reporter.report(ProgressEvent::Info("stack does not exist yet, doing a full deployment".to_string()));
+        return deploy_with_clients(name, stack, reporter, options, cloudformation_client, s3_client, cancel).await;
+    };
+
+    stack.package_cargo_crates().map_err(DeployError::AssetError)?;
+    let assets = stack
+        .get_hotswappable_assets(&existing)
+        .map_err(|e| DeployError::SynthError(format!("{e:?}")))?;
+
+    let Some(assets) = assets else {
+        reporter.report(ProgressEvent::Info(
+            "changes cannot be hotswapped, doing a full deployment".to_string(),
+        ));
+        return deploy_with_clients(name, stack, reporter, options, cloudformation_client, s3_client, cancel).await;
+    };
+
+    upload_assets(assets.iter().map(|(_, asset)| asset.clone()).collect(), s3_client, reporter).await?;
+
+    for (resource_id, asset) in assets {
+        let function_name = cloudformation_client
+            .get_physical_id(&name, &resource_id)
+            .await
+            .map_err(DeployError::HotswapError)?
+            .ok_or_else(|| DeployError::HotswapError(format!("could not find function name for {resource_id}")))?;
+
+        reporter.report(ProgressEvent::Info(format!("updating code of {function_name}...")));
+
+        lambda_client
+            .update_function_code(&function_name, &asset.s3_bucket, &asset.s3_key)
+            .await
+            .map_err(DeployError::HotswapError)?;
+
+        wait_for_function_update(&function_name, lambda_client, deadline, cancel.as_mut()).await?;
+    }
+
+    let result = get_deploy_result(&name, start, cloudformation_client).await?;
+    reporter.report(ProgressEvent::Completed {
+        status: result.status.clone(),
+        elapsed: result.elapsed,
+    });
+    Ok(result)
+}
+
+async fn wait_for_function_update<L: LambdaApi>(
+    function_name: &str,
+    lambda_client: &L,
+    deadline: Option<tokio::time::Instant>,
+    mut cancel: Pin<&mut impl Future<Output = ()>>,
+) -> Result<(), DeployError> {
+    loop {
+        let (status, reason) = lambda_client
+            .get_last_update_status(function_name)
+            .await
+            .map_err(DeployError::HotswapError)?;
+
+        match status {
+            Some(LastUpdateStatus::InProgress) => {}
+            Some(LastUpdateStatus::Failed) => return Err(DeployError::HotswapError(reason.unwrap_or_default())),
+            _ => return Ok(()),
+        }
+
+        if deadline.is_some_and(|deadline| tokio::time::Instant::now() >= deadline) {
+            return Err(DeployError::Timeout(format!(
+                "the code update of {function_name} did not finish in time, it continues"
+            )));
+        }
+        tokio::select! {
+            _ = sleep(Duration::from_secs(1)) => {}
+            _ = &mut cancel => {
+                return Err(DeployError::Cancelled(format!(
+                    "stopped waiting for the code update of {function_name}, it continues"
+                )));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clients::StackResourceInfo;
+    use crate::clients::fake::{FakeCloudFormation, FakeLambda, FakeS3};
+    use crate::deploy::DeployError;
+    use crate::hotswap::hotswap_with_clients;
+    use crate::options::DeployOptions;
+    use crate::progress::SilentReporter;
+    use aws_sdk_cloudformation::types::{ResourceStatus, StackStatus};
+    use aws_sdk_lambda::types::LastUpdateStatus;
+    use rusty_cdk_core::lambda::{Architecture, Code, FunctionBuilder, Runtime, Zip};
+    use rusty_cdk_core::stack::{Stack, StackBuilder};
+    use rusty_cdk_core::wrappers::{Bucket, Memory, Timeout, ZipFile};
+    use std::future::pending;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn function_stack(memory: u16) -> Stack {
+        let mut stack_builder = StackBuilder::new();
+        FunctionBuilder::new("fun", Architecture::ARM64, Memory(memory), Timeout(30))
+            .code(Code::Zip(Zip::new(
+                Bucket("some-bucket".to_string()),
+                ZipFile("./example.zip".to_string()),
+            )))
+            .handler("bootstrap")
+            .runtime(Runtime::ProvidedAl2023)
+            .build(&mut stack_builder);
+        stack_builder.build().unwrap()
+    }
+
+    fn deployed_function_stack() -> FakeCloudFormation {
+        let existing = function_stack(256).synth().unwrap();
+        let resource_id = Stack::get_ids_by_resource_id(&existing)
+            .unwrap()
+            .into_iter()
+            .find(|(_, id)| id == "fun")
+            .map(|(resource_id, _)| resource_id)
+            .unwrap();
+
+        FakeCloudFormation::new()
+            .with_stack("stack", &existing, vec![StackStatus::UpdateComplete])
+            .with_resource(
+                "stack",
+                StackResourceInfo {
+                    resource_id,
+                    physical_id: Some("stack-fun".to_string()),
+                    status: Some(ResourceStatus::UpdateComplete),
+                },
+            )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_update_function_code_when_only_code_changed() {
+        let cloudformation_client = deployed_function_stack();
+        let s3_client = Arc::new(FakeS3::new());
+        let lambda_client = FakeLambda::new();
+        // every build uploads the zip under a new key, so this is a change to the code only
+        let stack = function_stack(256);
+        let key = stack.get_assets()[0].s3_key.clone();
+
+        let result = hotswap_with_clients(
+            "stack".to_string(),
+            stack,
+            &SilentReporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+            s3_client.clone(),
+            &lambda_client,
+            pending(),
+        )
+        .await
+        .expect("hotswap to succeed");
+
+        assert_eq!(result.status, "UPDATE_COMPLETE");
+        assert_eq!(lambda_client.calls(), vec![format!("stack-fun some-bucket/{key}")]);
+        assert_eq!(s3_client.keys("some-bucket"), vec![key]);
+        assert!(cloudformation_client.calls().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_fall_back_to_full_deploy_when_configuration_changed() {
+        let cloudformation_client = deployed_function_stack();
+        let lambda_client = FakeLambda::new();
+
+        let result = hotswap_with_clients(
+            "stack".to_string(),
+            function_stack(512),
+            &SilentReporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+            Arc::new(FakeS3::new()),
+            &lambda_client,
+            pending(),
+        )
+        .await
+        .expect("deploy to succeed");
+
+        assert_eq!(result.status, "UPDATE_COMPLETE");
+        assert_eq!(cloudformation_client.calls(), vec!["update_stack"]);
+        assert!(lambda_client.calls().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_fall_back_to_full_deploy_when_stack_does_not_exist() {
+        let cloudformation_client = FakeCloudFormation::new();
+        let lambda_client = FakeLambda::new();
+
+        let result = hotswap_with_clients(
+            "stack".to_string(),
+            function_stack(256),
+            &SilentReporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+            Arc::new(FakeS3::new()),
+            &lambda_client,
+            pending(),
+        )
+        .await
+        .expect("deploy to succeed");
+
+        assert_eq!(result.status, "CREATE_COMPLETE");
+        assert_eq!(cloudformation_client.calls(), vec!["create_stack"]);
+        assert!(lambda_client.calls().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_return_reason_when_code_update_fails() {
+        let cloudformation_client = deployed_function_stack();
+        let mut lambda_client = FakeLambda::new();
+        lambda_client.update_statuses = vec![LastUpdateStatus::InProgress, LastUpdateStatus::Failed];
+        lambda_client.update_failure = Some("zip is too large".to_string());
+
+        let result = hotswap_with_clients(
+            "stack".to_string(),
+            function_stack(256),
+            &SilentReporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+            Arc::new(FakeS3::new()),
+            &lambda_client,
+            pending(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(DeployError::HotswapError(reason)) if reason == "zip is too large"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_stop_waiting_for_code_update_after_timeout() {
+        let cloudformation_client = deployed_function_stack();
+        let mut lambda_client = FakeLambda::new();
+        lambda_client.update_statuses = vec![LastUpdateStatus::InProgress];
+        let options = DeployOptions {
+            timeout: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+
+        let result = hotswap_with_clients(
+            "stack".to_string(),
+            function_stack(256),
+            &SilentReporter,
+            &options,
+            &cloudformation_client,
+            Arc::new(FakeS3::new()),
+            &lambda_client,
+            pending(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(DeployError::Timeout(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_stop_waiting_for_code_update_when_cancelled() {
+        let cloudformation_client = deployed_function_stack();
+        let mut lambda_client = FakeLambda::new();
+        lambda_client.update_statuses = vec![LastUpdateStatus::InProgress];
+
+        let result = hotswap_with_clients(
+            "stack".to_string(),
+            function_stack(256),
+            &SilentReporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+            Arc::new(FakeS3::new()),
+            &lambda_client,
+            tokio::time::sleep(Duration::from_secs(30)),
+        )
+        .await;
+
+        assert!(matches!(result, Err(DeployError::Cancelled(_))));
+    }
+}
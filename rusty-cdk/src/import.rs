@@ -0,0 +1,291 @@
+use crate::clients::{AwsCloudFormation, CloudFormationApi, StackInput};
+use crate::deploy::{DeployError, DeployResult};
+use crate::options::DeployOptions;
+use crate::progress::{ProgressEvent, ProgressReporter};
+use crate::util::{
+    StackProgress, ctrl_c_pressed, get_existing_template, get_stack_description, get_stack_status, load_config, wait_for_stable_status,
+};
+use aws_sdk_cloudformation::types::{ChangeSetStatus, StackStatus};
+use rusty_cdk_core::stack::Stack;
+use rusty_cdk_core::wrappers::StringWithOnlyAlphaNumericsAndHyphens;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::pin::pin;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::time::sleep;
+
+#[derive(Debug)]
+pub enum ImportError {
+    SynthError(String),
+    /// The resources cannot be imported, for example because they do not have a `Retain` deletion policy
+    InvalidImport(String),
+    ChangeSetError(String),
+    ImportFailed(String),
+    /// An earlier operation on the stack did not finish within `DeployOptions::timeout`
+    Timeout(String),
+    /// Waiting for an earlier operation on the stack was cancelled with ctrl-c, see `DeployOptions::cancel_on_ctrl_c`
+    Cancelled(String),
+    UnknownError(String),
+}
+
+impl Error for ImportError {}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::SynthError(_) => f.write_str("unable to synth"),
+            ImportError::InvalidImport(_) => f.write_str("resources cannot be imported"),
+            ImportError::ChangeSetError(_) => f.write_str("unable to create import change set"),
+            ImportError::ImportFailed(_) => f.write_str("import failed"),
+            ImportError::Timeout(_) => f.write_str("timed out waiting for stack"),
+            ImportError::Cancelled(_) => f.write_str("import was cancelled"),
+            ImportError::UnknownError(_) => f.write_str("unknown error"),
+        }
+    }
+}
+
+/// Imports existing resources (for example DynamoDB tables or S3 buckets that were created outside CloudFormation) into a new or existing stack.
+///
+/// This creates and executes a CloudFormation change set of type `IMPORT`.
+/// CloudFormation has some requirements for imports, which are checked before creating the change set:
+/// - every imported resource should have a `Retain` deletion policy (see for example `TableBuilder::update_replace_and_deletion_policy`)
+/// - an import cannot create other resources, so all other resources in the stack should already be deployed
+///
+/// After the import, the stack `Metadata` contains the resource ids of the imported resources, so later deployments keep managing them.
+/// Only simple resources can be imported (tables, buckets, queues, topics, log groups, functions, roles, secrets and repositories),
+/// and the configuration in the stack should match that of the existing resources.
+///
+/// # Parameters
+///
+/// * `name` - The CloudFormation stack name (alphanumeric characters and hyphens only)
+/// * `stack` - The stack containing the resources to import, created using `StackBuilder`
+/// * `identifiers_by_id` - The physical identifiers of the existing resources, keyed by the id passed to the builder.
+///   This is the table name for tables, the bucket name for buckets, the queue url for queues, the topic arn for topics, etc.
+/// * `reporter` - Receives progress updates, see `ProgressReporter`
+/// * `options` - The AWS profile, region and roles to use, see `DeployOptions`
+///
+/// # AWS Credentials
+///
+/// The AWS credentials must have permissions for:
+/// - `cloudformation:DescribeStacks`
+/// - `cloudformation:GetTemplate`
+/// - `cloudformation:CreateChangeSet`
+/// - `cloudformation:DescribeChangeSet`
+/// - `cloudformation:ExecuteChangeSet`
+/// - Read permissions for the imported resources (e.g. `dynamodb:DescribeTable`)
+pub async fn import_resources(
+    name: StringWithOnlyAlphaNumericsAndHyphens,
+    stack: Stack,
+    identifiers_by_id: HashMap<String, String>,
+    reporter: &dyn ProgressReporter,
+    options: &DeployOptions,
+) -> Result<DeployResult, ImportError> {
+    let config = load_config(true, options).await;
+    let cloudformation_client = AwsCloudFormation::new(&config);
+
+    import_resources_with_client(
+        name.0,
+        stack,
+        identifiers_by_id,
+        reporter,
+        options,
+        &cloudformation_client,
+        ctrl_c_pressed(options),
+    )
+    .await
+}
+
+pub(crate) async fn import_resources_with_client<C: CloudFormationApi>(
+    name: String,
+    mut stack: Stack,
+    identifiers_by_id: HashMap<String, String>,
+    reporter: &dyn ProgressReporter,
+    options: &DeployOptions,
+    cloudformation_client: &C,
+    cancel: impl Future<Output = ()>,
+) -> Result<DeployResult, ImportError> {
+    let start = Instant::now();
+    let deadline = options.timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+
+    wait_for_stable_status(&name, cloudformation_client, reporter, deadline, pin!(cancel))
+        .await
+        .map_err(|e| match e {
+            DeployError::Timeout(e) => ImportError::Timeout(e),
+            DeployError::Cancelled(e) => ImportError::Cancelled(e),
+            e => ImportError::UnknownError(format!("{e:?}")),
+        })?;
+    let existing = get_existing_template(cloudformation_client, &name).await;
+
+    let (template, resources) = stack
+        .get_resources_to_import(&identifiers_by_id, existing.as_deref())
+        .map_err(ImportError::InvalidImport)?;
+
+    let change_set_name = format!(
+        "rusty-cdk-import-{}",
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+    );
+    let input = StackInput {
+        name: name.clone(),
+        template,
+        tags: stack.get_tags(),
+        role_arn: options.cloudformation_role_arn.clone(),
+        // an import cannot change the settings of the stack
+        settings: None,
+    };
+
+    cloudformation_client
+        .create_import_change_set(input, &change_set_name, resources)
+        .await
+        .map_err(ImportError::ChangeSetError)?;
+    reporter.report(ProgressEvent::Info(format!("created change set {change_set_name}")));
+
+    loop {
+        let (status, reason) = cloudformation_client
+            .describe_change_set(&name, &change_set_name)
+            .await
+            .map_err(ImportError::ChangeSetError)?;
+
+        match status {
+            ChangeSetStatus::CreateComplete => break,
+            ChangeSetStatus::CreatePending | ChangeSetStatus::CreateInProgress => {}
+            _ => return Err(ImportError::ChangeSetError(reason.unwrap_or_else(|| status.to_string()))),
+        }
+
+        sleep(Duration::from_secs(5)).await;
+    }
+
+    let mut progress = StackProgress::start(&name, cloudformation_client, reporter).await;
+    cloudformation_client
+        .execute_change_set(&name, &change_set_name)
+        .await
+        .map_err(ImportError::ChangeSetError)?;
+
+    loop {
+        let status = get_stack_status(&name, cloudformation_client)
+            .await
+            .ok_or_else(|| ImportError::UnknownError(format!("could not retrieve status of stack {name}")))?;
+        progress.report(&status, cloudformation_client).await;
+
+        match status {
+            StackStatus::ImportComplete => {
+                let description = get_stack_description(&name, cloudformation_client)
+                    .await
+                    .ok_or_else(|| ImportError::UnknownError(format!("could not describe stack {name} after import")))?;
+
+                let result = DeployResult {
+                    stack_id: description.stack_id,
+                    status: description.status.to_string(),
+                    elapsed: start.elapsed(),
+                    outputs: description.outputs,
+                };
+                reporter.report(ProgressEvent::Completed {
+                    status: result.status.clone(),
+                    elapsed: result.elapsed,
+                });
+                return Ok(result);
+            }
+            // right after executing the change set, the status might not have changed yet
+            StackStatus::ImportInProgress | StackStatus::ReviewInProgress => {}
+            StackStatus::ImportRollbackInProgress | StackStatus::ImportRollbackComplete | StackStatus::ImportRollbackFailed => {
+                return Err(ImportError::ImportFailed(format!("{status}")));
+            }
+            _ => {
+                return Err(ImportError::UnknownError(format!("{status}")));
+            }
+        }
+
+        sleep(Duration::from_secs(10)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clients::fake::FakeCloudFormation;
+    use crate::import::{ImportError, import_resources_with_client};
+    use crate::options::DeployOptions;
+    use crate::progress::SilentReporter;
+    use aws_sdk_cloudformation::types::StackStatus;
+    use rusty_cdk_core::s3::BucketBuilder;
+    use rusty_cdk_core::shared::{DeletionPolicy, UpdateReplacePolicy};
+    use rusty_cdk_core::stack::{Stack, StackBuilder};
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use std::future::pending;
+
+    fn stack_with_bucket() -> Stack {
+        let mut stack_builder = StackBuilder::new();
+        BucketBuilder::new("bucket")
+            .update_replace_and_deletion_policy(UpdateReplacePolicy::Retain, DeletionPolicy::Retain)
+            .build(&mut stack_builder);
+        stack_builder.build().unwrap()
+    }
+
+    fn identifiers() -> HashMap<String, String> {
+        HashMap::from([("bucket".to_string(), "my-existing-bucket".to_string())])
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_import_into_new_stack() {
+        let cloudformation_client = FakeCloudFormation::new();
+
+        let result = import_resources_with_client(
+            "stack".to_string(),
+            stack_with_bucket(),
+            identifiers(),
+            &SilentReporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+            pending(),
+        )
+        .await
+        .expect("import to succeed");
+
+        assert_eq!(result.status, "IMPORT_COMPLETE");
+        assert_eq!(
+            cloudformation_client.calls(),
+            vec!["create_import_change_set my-existing-bucket", "execute_change_set"]
+        );
+        let template: Value = serde_json::from_str(&cloudformation_client.template("stack").unwrap()).unwrap();
+        assert!(template["Metadata"]["bucket"].is_string());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_return_reason_when_change_set_fails() {
+        let mut cloudformation_client = FakeCloudFormation::new();
+        cloudformation_client.change_set_failure = Some("bucket does not exist".to_string());
+
+        let result = import_resources_with_client(
+            "stack".to_string(),
+            stack_with_bucket(),
+            identifiers(),
+            &SilentReporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+            pending(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(ImportError::ChangeSetError(reason)) if reason == "bucket does not exist"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_not_import_resource_that_is_already_in_stack() {
+        let existing = stack_with_bucket().synth().unwrap();
+        let cloudformation_client = FakeCloudFormation::new().with_stack("stack", &existing, vec![StackStatus::CreateComplete]);
+
+        let result = import_resources_with_client(
+            "stack".to_string(),
+            stack_with_bucket(),
+            identifiers(),
+            &SilentReporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+            pending(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(ImportError::InvalidImport(_))));
+        assert!(cloudformation_client.calls().is_empty());
+    }
+}
@@ -0,0 +1,277 @@
+use crate::clients::{AwsCloudFormation, CloudFormationApi};
+use crate::options::DeployOptions;
+use crate::util::load_config;
+use rusty_cdk_core::stack::Stack;
+use rusty_cdk_core::wrappers::StringWithOnlyAlphaNumericsAndHyphens;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+const DRIFT_NOT_CHECKED: &str = "NOT_CHECKED";
+
+#[derive(Debug)]
+pub enum InspectError {
+    UnknownStack(String),
+    ListError(String),
+    DescribeError(String),
+}
+
+impl Error for InspectError {}
+
+impl Display for InspectError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InspectError::UnknownStack(_) => f.write_str("stack could not be found"),
+            InspectError::ListError(_) => f.write_str("unable to list stacks"),
+            InspectError::DescribeError(_) => f.write_str("unable to describe stack"),
+        }
+    }
+}
+
+/// A deployed stack that was created with this library
+#[derive(Debug)]
+pub struct DeployedStack {
+    pub name: String,
+    pub status: String,
+    /// When the stack was last updated (or created, if it was never updated), as an RFC 3339 timestamp
+    pub last_updated: Option<String>,
+    /// Result of the last drift detection (`IN_SYNC`, `DRIFTED`, ...), or `NOT_CHECKED`. Run `drift` to check again
+    pub drift_status: String,
+}
+
+impl Display for DeployedStack {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{}: {}, last updated {}, drift {}",
+            self.name,
+            self.status,
+            self.last_updated.as_deref().unwrap_or("(unknown)"),
+            self.drift_status
+        ))
+    }
+}
+
+/// The resources and outputs of a deployed stack
+#[derive(Debug)]
+pub struct StackDescription {
+    pub name: String,
+    pub status: String,
+    pub resources: Vec<DeployedResource>,
+    pub outputs: BTreeMap<String, String>,
+}
+
+/// A resource of a deployed stack
+#[derive(Debug)]
+pub struct DeployedResource {
+    /// The id that was passed to the builder, if the resource could be found in the stack metadata
+    pub id: Option<String>,
+    /// The resource id (logical id) in the CloudFormation template
+    pub resource_id: String,
+    pub resource_type: String,
+    pub physical_id: Option<String>,
+}
+
+impl Display for StackDescription {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{} ({})\nresources:", self.name, self.status))?;
+        for resource in &self.resources {
+            f.write_fmt(format_args!("\n  {resource}"))?;
+        }
+        f.write_str("\noutputs:")?;
+        for (key, value) in &self.outputs {
+            f.write_fmt(format_args!("\n  {key}: {value}"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for DeployedResource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{} (resource {}, type {}): {}",
+            self.id.as_deref().unwrap_or("(unknown id)"),
+            self.resource_id,
+            self.resource_type,
+            self.physical_id.as_deref().unwrap_or("(no physical id)")
+        ))
+    }
+}
+
+/// Lists the stacks in the account and region that were created with this library, recognized by their `Metadata`.
+///
+/// # Parameters
+///
+/// * `options` - The AWS profile, region and roles to use, see `DeployOptions`
+///
+/// # AWS Credentials
+///
+/// This function requires valid AWS credentials.
+/// The AWS credentials must have permissions for:
+/// - `cloudformation:ListStacks`
+/// - `cloudformation:GetTemplate`
+pub async fn list_stacks(options: &DeployOptions) -> Result<Vec<DeployedStack>, InspectError> {
+    let config = load_config(false, options).await;
+    list_stacks_with_client(&AwsCloudFormation::new(&config)).await
+}
+
+/// Describes a deployed stack: its resources, with the ids you passed to their builders and their physical ids, and its outputs.
+///
+/// # Parameters
+///
+/// * `name` - The existing CloudFormation stack name
+/// * `options` - The AWS profile, region and roles to use, see `DeployOptions`
+///
+/// # AWS Credentials
+///
+/// This function requires valid AWS credentials.
+/// The AWS credentials must have permissions for:
+/// - `cloudformation:DescribeStacks`
+/// - `cloudformation:GetTemplate`
+/// - `cloudformation:ListStackResources`
+pub async fn describe_stack(
+    name: StringWithOnlyAlphaNumericsAndHyphens,
+    options: &DeployOptions,
+) -> Result<StackDescription, InspectError> {
+    let config = load_config(false, options).await;
+    describe_stack_with_client(name.0, &AwsCloudFormation::new(&config)).await
+}
+
+pub(crate) async fn list_stacks_with_client<C: CloudFormationApi>(cloudformation_client: &C) -> Result<Vec<DeployedStack>, InspectError> {
+    let summaries = cloudformation_client.list_stacks().await.map_err(InspectError::ListError)?;
+
+    let mut stacks = vec![];
+    for summary in summaries {
+        let template = cloudformation_client
+            .get_template(&summary.name)
+            .await
+            .map_err(InspectError::ListError)?;
+
+        if template.as_deref().is_some_and(Stack::is_created_with_this_library) {
+            stacks.push(DeployedStack {
+                name: summary.name,
+                status: summary.status.to_string(),
+                last_updated: summary.last_updated,
+                drift_status: summary.drift_status.unwrap_or_else(|| DRIFT_NOT_CHECKED.to_string()),
+            });
+        }
+    }
+
+    Ok(stacks)
+}
+
+pub(crate) async fn describe_stack_with_client<C: CloudFormationApi>(
+    name: String,
+    cloudformation_client: &C,
+) -> Result<StackDescription, InspectError> {
+    let info = cloudformation_client
+        .describe_stack(&name)
+        .await
+        .map_err(InspectError::DescribeError)?
+        .ok_or_else(|| InspectError::UnknownStack(format!("could not retrieve stack with name {name}")))?;
+    let template = cloudformation_client
+        .get_template(&name)
+        .await
+        .map_err(InspectError::DescribeError)?
+        .unwrap_or_default();
+
+    // stacks that were not created with this library have no ids, but can still be described
+    let ids = Stack::get_ids_by_resource_id(&template).unwrap_or_default();
+    let template: Value = serde_json::from_str(&template).unwrap_or_default();
+
+    let mut resources: Vec<_> = cloudformation_client
+        .list_stack_resources(&name)
+        .await
+        .map_err(InspectError::DescribeError)?
+        .into_iter()
+        .map(|r| DeployedResource {
+            id: ids.get(&r.resource_id).cloned(),
+            resource_type: template["Resources"][&r.resource_id]["Type"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            resource_id: r.resource_id,
+            physical_id: r.physical_id,
+        })
+        .collect();
+    resources.sort_by(|a, b| (&a.id, &a.resource_id).cmp(&(&b.id, &b.resource_id)));
+
+    Ok(StackDescription {
+        name,
+        status: info.status.to_string(),
+        resources,
+        outputs: info.outputs.into_iter().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clients::StackResourceInfo;
+    use crate::clients::fake::FakeCloudFormation;
+    use crate::inspect::{InspectError, describe_stack_with_client, list_stacks_with_client};
+    use aws_sdk_cloudformation::types::StackStatus;
+    use rusty_cdk_core::sqs::QueueBuilder;
+    use rusty_cdk_core::stack::{Stack, StackBuilder};
+
+    fn queue_stack() -> String {
+        let mut stack_builder = StackBuilder::new();
+        QueueBuilder::new("queue").standard_queue().build(&mut stack_builder);
+        stack_builder.build().unwrap().synth().unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_only_list_stacks_created_with_this_library() {
+        let cloudformation_client = FakeCloudFormation::new()
+            .with_stack("rusty", &queue_stack(), vec![StackStatus::UpdateComplete])
+            .with_stack(
+                "other",
+                r#"{"Resources": { "Queue": { "Type": "AWS::SQS::Queue" } } }"#,
+                vec![StackStatus::CreateComplete],
+            );
+
+        let stacks = list_stacks_with_client(&cloudformation_client).await.expect("list to succeed");
+
+        assert_eq!(stacks.len(), 1);
+        assert_eq!(stacks[0].name, "rusty");
+        assert_eq!(stacks[0].status, "UPDATE_COMPLETE");
+        assert_eq!(stacks[0].drift_status, "NOT_CHECKED");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_describe_resources_with_their_ids_and_outputs() {
+        let template = queue_stack();
+        let queue_resource_id = Stack::get_ids_by_resource_id(&template).unwrap().into_keys().next().unwrap();
+        let cloudformation_client = FakeCloudFormation::new()
+            .with_stack("rusty", &template, vec![StackStatus::CreateComplete])
+            .with_resource(
+                "rusty",
+                StackResourceInfo {
+                    resource_id: queue_resource_id.clone(),
+                    physical_id: Some("https://sqs.eu-west-1.amazonaws.com/123456789012/queue".to_string()),
+                    status: None,
+                },
+            )
+            .with_output("rusty", "QueueUrl", "https://sqs.eu-west-1.amazonaws.com/123456789012/queue");
+
+        let description = describe_stack_with_client("rusty".to_string(), &cloudformation_client)
+            .await
+            .expect("describe to succeed");
+
+        assert_eq!(description.status, "CREATE_COMPLETE");
+        assert_eq!(description.resources.len(), 1);
+        assert_eq!(description.resources[0].id.as_deref(), Some("queue"));
+        assert_eq!(description.resources[0].resource_id, queue_resource_id);
+        assert_eq!(description.resources[0].resource_type, "AWS::SQS::Queue");
+        assert_eq!(
+            description.outputs.get("QueueUrl").map(|o| o.as_str()),
+            Some("https://sqs.eu-west-1.amazonaws.com/123456789012/queue")
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_return_unknown_stack_when_describing_stack_that_does_not_exist() {
+        let result = describe_stack_with_client("missing".to_string(), &FakeCloudFormation::new()).await;
+
+        assert!(matches!(result, Err(InspectError::UnknownStack(_))));
+    }
+}
@@ -1,13 +1,32 @@
 #![doc = include_str!(concat!("../", std::env!("CARGO_PKG_README")))]
 
+mod clients;
 mod deploy;
 mod destroy;
 mod diff;
+mod drift;
+mod environment;
+mod hotswap;
+mod import;
+mod inspect;
+mod options;
+mod progress;
+mod rollback;
+mod stack_set;
 mod util;
 
 pub use deploy::*;
 pub use destroy::*;
 pub use diff::*;
+pub use drift::*;
+pub use environment::*;
+pub use hotswap::*;
+pub use import::*;
+pub use inspect::*;
+pub use options::*;
+pub use progress::*;
+pub use rollback::*;
 pub use rusty_cdk_core::*;
 pub use rusty_cdk_lookups::*;
 pub use rusty_cdk_macros::*;
+pub use stack_set::*;
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+/// Options for the AWS account, region and roles used by `deploy`, `diff`, `destroy`, etc., and for how long `deploy` waits
+///
+/// By default (`DeployOptions::default()`), everything comes from the default AWS provider chain (environment variables, `~/.aws/config`, ...).
+///
+/// # Example
+///
+/// ```
+/// use rusty_cdk::DeployOptions;
+///
+/// let options = DeployOptions {
+///     region: Some("eu-west-1".to_string()),
+///     assume_role_arn: Some("arn:aws:iam::123456789012:role/deployer".to_string()),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DeployOptions {
+    /// Named profile from your AWS config and credentials files
+    pub profile: Option<String>,
+    /// Region to deploy to, overrides the region of the profile or environment
+    pub region: Option<String>,
+    /// Role to assume (with the credentials from the profile or environment) before calling AWS
+    pub assume_role_arn: Option<String>,
+    /// External id to pass when assuming `assume_role_arn`. Ignored when there is no role to assume
+    pub external_id: Option<String>,
+    /// Service role that CloudFormation uses for the stack operations (`RoleARN`), instead of the credentials of the caller
+    pub cloudformation_role_arn: Option<String>,
+    /// Custom endpoint for all AWS calls, for example `http://localhost:4566` to deploy to LocalStack
+    pub endpoint_url: Option<String>,
+    /// Maximum time that `deploy` waits for the stack to reach a final status, including the time spent waiting for an earlier operation to finish.
+    /// Also limits waiting in `import_resources`, `continue_update_rollback` and `drift`. The stack operation itself continues when the time runs out
+    pub timeout: Option<Duration>,
+    /// Whether ctrl-c cancels a running `deploy`, `import_resources`, `continue_update_rollback` or `drift`.
+    /// An update by `deploy` is cancelled (and rolled back) with `CancelUpdateStack`,
+    /// for a new stack, an earlier operation that is still in progress or a rollback, they stop waiting.
+    /// *This replaces the default ctrl-c behaviour (exiting) for the rest of the process*
+    pub cancel_on_ctrl_c: bool,
+}
@@ -0,0 +1,336 @@
+use rusty_cdk_core::stack::Asset;
+use serde_json::json;
+use std::io::{IsTerminal, Write, stdout};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// An event from a CloudFormation stack operation, describing what happened to one of the resources (or to the stack itself)
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackEvent {
+    /// The id passed to the builder, if the resource could be linked to one
+    pub id: Option<String>,
+    pub resource_id: String,
+    /// The CloudFormation type of the resource, e.g. `AWS::SQS::Queue`
+    pub resource_type: String,
+    /// The status of the resource, e.g. `CREATE_COMPLETE`
+    pub status: String,
+    /// The reason for the status, mostly useful for failures
+    pub reason: Option<String>,
+}
+
+/// The status of one of the stack instances of a stack set, in an account and region
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackInstanceEvent {
+    pub account: String,
+    pub region: String,
+    /// The status of the instance in the current operation, e.g. `RUNNING` or `SUCCEEDED`
+    pub status: String,
+    /// The reason for the status, mostly useful for failures
+    pub reason: Option<String>,
+}
+
+/// The events reported by `deploy`, `destroy`, `clean`, etc. to a `ProgressReporter`
+#[derive(Debug, Clone)]
+pub enum ProgressEvent {
+    /// Started uploading an asset (the zip file of a Lambda) to S3
+    AssetUploadStarted(Asset),
+    AssetUploadFinished(Asset),
+    /// A new CloudFormation event for the stack or one of its resources
+    StackEvent(StackEvent),
+    /// The status of a stack instance changed, while deploying a stack set
+    StackInstance(StackInstanceEvent),
+    /// The status of the stack changed, e.g. to `UPDATE_IN_PROGRESS`
+    StatusChanged(String),
+    /// Additional information, like waiting for an ongoing operation or emptying a bucket
+    Info(String),
+    /// Something that needs attention, like resources that remain after destroying a stack
+    Warning(String),
+    /// The operation finished successfully
+    Completed {
+        status: String,
+        elapsed: Duration,
+    },
+}
+
+/// Receives progress events from the functions of this crate.
+///
+/// Implement this trait to integrate the progress of deployments into your own tooling.
+/// Or use one of the implementations in this crate:
+/// - `SilentReporter` ignores all events
+/// - `TerminalReporter` shows the events in a terminal, with colours and a spinner
+/// - `JsonLinesReporter` writes every event as a JSON object on a line of its own, for CI logs
+///
+/// # Example
+///
+/// ```
+/// use rusty_cdk::{ProgressEvent, ProgressReporter};
+///
+/// struct FailuresOnly;
+///
+/// impl ProgressReporter for FailuresOnly {
+///     fn report(&self, event: ProgressEvent) {
+///         match event {
+///             ProgressEvent::StackEvent(e) if e.status.ends_with("_FAILED") => eprintln!("{} failed: {:?}", e.resource_id, e.reason),
+///             _ => {}
+///         }
+///     }
+/// }
+/// ```
+pub trait ProgressReporter: Send + Sync {
+    fn report(&self, event: ProgressEvent);
+}
+
+/// Ignores all progress events
+pub struct SilentReporter;
+
+impl ProgressReporter for SilentReporter {
+    fn report(&self, _event: ProgressEvent) {}
+}
+
+/// Writes the progress events to standard out, as JSON objects separated by newlines
+///
+/// Every object has a `type` (e.g. `stack_event`) and the fields of the event.
+pub struct JsonLinesReporter;
+
+impl JsonLinesReporter {
+    fn to_json(event: ProgressEvent) -> serde_json::Value {
+        match event {
+            ProgressEvent::AssetUploadStarted(asset) => {
+                json!({ "type": "asset_upload_started", "path": asset.path, "bucket": asset.s3_bucket, "key": asset.s3_key })
+            }
+            ProgressEvent::AssetUploadFinished(asset) => {
+                json!({ "type": "asset_upload_finished", "path": asset.path, "bucket": asset.s3_bucket, "key": asset.s3_key })
+            }
+            ProgressEvent::StackEvent(e) => json!({
+                "type": "stack_event",
+                "id": e.id,
+                "resource_id": e.resource_id,
+                "resource_type": e.resource_type,
+                "status": e.status,
+                "reason": e.reason,
+            }),
+            ProgressEvent::StackInstance(e) => json!({
+                "type": "stack_instance",
+                "account": e.account,
+                "region": e.region,
+                "status": e.status,
+                "reason": e.reason,
+            }),
+            ProgressEvent::StatusChanged(status) => json!({ "type": "status_changed", "status": status }),
+            ProgressEvent::Info(message) => json!({ "type": "info", "message": message }),
+            ProgressEvent::Warning(message) => json!({ "type": "warning", "message": message }),
+            ProgressEvent::Completed { status, elapsed } => {
+                json!({ "type": "completed", "status": status, "elapsed_seconds": elapsed.as_secs() })
+            }
+        }
+    }
+}
+
+impl ProgressReporter for JsonLinesReporter {
+    fn report(&self, event: ProgressEvent) {
+        println!("{}", Self::to_json(event));
+    }
+}
+
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+struct Spinner {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+/// Shows the progress events in a terminal.
+///
+/// Statuses are coloured (green for success, red for failures and rollbacks, yellow for everything in progress),
+/// and a spinner is shown while the stack is in progress.
+/// When standard out is not a terminal (e.g. when piped to a file), colours and spinner are left out.
+pub struct TerminalReporter {
+    interactive: bool,
+    spinner: Mutex<Option<Spinner>>,
+    // the status to show next to the spinner, with the moment the stack reached it
+    in_progress: Mutex<Option<(String, Instant)>>,
+}
+
+impl TerminalReporter {
+    pub fn new() -> Self {
+        Self {
+            interactive: stdout().is_terminal(),
+            spinner: Mutex::new(None),
+            in_progress: Mutex::new(None),
+        }
+    }
+
+    fn colour(&self, status: &str) -> &'static str {
+        if !self.interactive {
+            ""
+        } else if status.contains("FAILED") || status.contains("ROLLBACK") || status == "CANCELLED" {
+            RED
+        } else if status.ends_with("_IN_PROGRESS") || status == "PENDING" || status == "RUNNING" {
+            YELLOW
+        } else {
+            GREEN
+        }
+    }
+
+    fn reset(&self) -> &'static str {
+        if self.interactive { RESET } else { "" }
+    }
+
+    fn stop_spinner(&self) {
+        if let Some(spinner) = self.spinner.lock().unwrap().take() {
+            spinner.stop.store(true, Ordering::Relaxed);
+            let _ = spinner.handle.join();
+            // clear the spinner line
+            print!("\r\x1b[2K");
+            let _ = stdout().flush();
+        }
+    }
+
+    fn start_spinner(&self, status: String, start: Instant) {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_for_thread = stop.clone();
+
+        let handle = std::thread::spawn(move || {
+            let mut frame = 0;
+            while !stop_for_thread.load(Ordering::Relaxed) {
+                print!(
+                    "\r{} {status} ({}s)",
+                    SPINNER_FRAMES[frame % SPINNER_FRAMES.len()],
+                    start.elapsed().as_secs()
+                );
+                let _ = stdout().flush();
+                frame += 1;
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        });
+
+        *self.spinner.lock().unwrap() = Some(Spinner { stop, handle });
+    }
+}
+
+impl Default for TerminalReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TerminalReporter {
+    fn drop(&mut self) {
+        self.stop_spinner();
+    }
+}
+
+impl ProgressReporter for TerminalReporter {
+    fn report(&self, event: ProgressEvent) {
+        self.stop_spinner();
+
+        match event {
+            ProgressEvent::AssetUploadStarted(asset) => println!("uploading {}...", asset.path),
+            ProgressEvent::AssetUploadFinished(asset) => println!("uploaded {} to {}/{}", asset.path, asset.s3_bucket, asset.s3_key),
+            ProgressEvent::StackEvent(e) => {
+                let name = e.id.unwrap_or(e.resource_id);
+                let reason = e.reason.map(|r| format!(" - {r}")).unwrap_or_default();
+                println!(
+                    "{}{}{} {name} ({}){reason}",
+                    self.colour(&e.status),
+                    e.status,
+                    self.reset(),
+                    e.resource_type
+                );
+            }
+            ProgressEvent::StackInstance(e) => {
+                let reason = e.reason.map(|r| format!(" - {r}")).unwrap_or_default();
+                println!(
+                    "{}{}{} {} ({}){reason}",
+                    self.colour(&e.status),
+                    e.status,
+                    self.reset(),
+                    e.account,
+                    e.region
+                );
+            }
+            ProgressEvent::StatusChanged(status) => {
+                let coloured = format!("{}{status}{}", self.colour(&status), self.reset());
+                if self.interactive && status.ends_with("_IN_PROGRESS") {
+                    *self.in_progress.lock().unwrap() = Some((coloured, Instant::now()));
+                } else {
+                    *self.in_progress.lock().unwrap() = None;
+                    println!("{coloured}");
+                }
+            }
+            ProgressEvent::Info(message) => println!("{message}"),
+            ProgressEvent::Warning(message) => {
+                let colour = if self.interactive { YELLOW } else { "" };
+                println!("{colour}{message}{}", self.reset())
+            }
+            ProgressEvent::Completed { status, elapsed } => {
+                *self.in_progress.lock().unwrap() = None;
+                println!("{}{status}{} in {}s", self.colour(&status), self.reset(), elapsed.as_secs())
+            }
+        }
+
+        // other events interrupt the spinner, so start it again while the stack is still in progress
+        if let Some((status, start)) = self.in_progress.lock().unwrap().clone() {
+            self.start_spinner(status, start);
+        }
+    }
+}
+
+/// Keeps all events, for checking them in tests
+#[cfg(test)]
+pub(crate) struct RecordingReporter(pub(crate) Mutex<Vec<ProgressEvent>>);
+
+#[cfg(test)]
+impl RecordingReporter {
+    pub(crate) fn new() -> Self {
+        Self(Mutex::new(vec![]))
+    }
+
+    pub(crate) fn events(&self) -> Vec<ProgressEvent> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl ProgressReporter for RecordingReporter {
+    fn report(&self, event: ProgressEvent) {
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::progress::{JsonLinesReporter, ProgressEvent, StackEvent};
+    use serde_json::json;
+
+    #[test]
+    fn should_write_stack_event_as_json() {
+        let event = ProgressEvent::StackEvent(StackEvent {
+            id: Some("queue".to_string()),
+            resource_id: "Queue123".to_string(),
+            resource_type: "AWS::SQS::Queue".to_string(),
+            status: "CREATE_FAILED".to_string(),
+            reason: Some("no permission".to_string()),
+        });
+
+        let json = JsonLinesReporter::to_json(event);
+
+        assert_eq!(
+            json,
+            json!({
+                "type": "stack_event",
+                "id": "queue",
+                "resource_id": "Queue123",
+                "resource_type": "AWS::SQS::Queue",
+                "status": "CREATE_FAILED",
+                "reason": "no permission",
+            })
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use crate::clients::{AwsCloudFormation, CloudFormationApi};
+use crate::deploy::DeployError;
+use crate::destroy::destroy_with_client;
+use crate::options::DeployOptions;
+use crate::progress::ProgressReporter;
+use crate::util::{PollInterval, StackProgress, ctrl_c_pressed, get_existing_ids_by_resource_id, get_stack_status, load_config};
+use aws_sdk_cloudformation::types::StackStatus;
+use rusty_cdk_core::wrappers::StringWithOnlyAlphaNumericsAndHyphens;
+use std::pin::pin;
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// Deletes a stack that failed to create and was rolled back (`ROLLBACK_COMPLETE`), so that it can be created again with `deploy`.
+///
+/// A stack in this status contains no resources, and CloudFormation does not allow updating it.
+/// To avoid accidents, stacks in any other status are *not* deleted.
+///
+/// # Parameters
+///
+/// * `name` - The CloudFormation stack name
+/// * `reporter` - Receives progress updates, see `ProgressReporter`
+/// * `options` - The AWS profile, region and roles to use, see `DeployOptions`
+///
+/// # AWS Credentials
+///
+/// The AWS credentials must have permissions for:
+/// - `cloudformation:DescribeStacks`
+/// - `cloudformation:DeleteStack`
+pub async fn delete_rolled_back_stack(
+    name: StringWithOnlyAlphaNumericsAndHyphens,
+    reporter: &dyn ProgressReporter,
+    options: &DeployOptions,
+) -> Result<(), DeployError> {
+    let config = load_config(false, options).await;
+    let cloudformation_client = AwsCloudFormation::new(&config);
+
+    delete_rolled_back_stack_with_client(name.0, reporter, options, &cloudformation_client).await
+}
+
+pub(crate) async fn delete_rolled_back_stack_with_client<C: CloudFormationApi>(
+    name: String,
+    reporter: &dyn ProgressReporter,
+    options: &DeployOptions,
+    cloudformation_client: &C,
+) -> Result<(), DeployError> {
+    match get_stack_status(&name, cloudformation_client).await {
+        Some(StackStatus::RollbackComplete) => {}
+        other => {
+            return Err(DeployError::RecoveryError(format!(
+                "stack {name} should have status ROLLBACK_COMPLETE to be deleted, found {other:?}"
+            )));
+        }
+    }
+
+    destroy_with_client(name, reporter, options, cloudformation_client)
+        .await
+        .map_err(|e| DeployError::RecoveryError(format!("{e:?}")))
+}
+
+/// Continues rolling back a stack whose update rollback failed (`UPDATE_ROLLBACK_FAILED`), waiting until the rollback is complete.
+///
+/// Resources that cannot be rolled back (because they were changed or deleted outside CloudFormation, for example) can be skipped.
+/// CloudFormation then considers those resources to be rolled back, so make sure their actual state matches the previous template.
+///
+/// Waiting for the rollback is limited by `DeployOptions::timeout`, and stops on ctrl-c if `DeployOptions::cancel_on_ctrl_c` is enabled.
+/// The rollback itself continues in both cases.
+///
+/// # Parameters
+///
+/// * `name` - The CloudFormation stack name
+/// * `resources_to_skip` - Ids of the resources to skip. These are the ids passed to the builders, resource ids from the template also work
+/// * `reporter` - Receives progress updates, see `ProgressReporter`
+/// * `options` - The AWS profile, region and roles to use, see `DeployOptions`
+///
+/// # AWS Credentials
+///
+/// The AWS credentials must have permissions for:
+/// - `cloudformation:DescribeStacks`
+/// - `cloudformation:GetTemplate`
+/// - `cloudformation:ContinueUpdateRollback`
+/// - Permissions for rolling back the resources in the stack
+pub async fn continue_update_rollback(
+    name: StringWithOnlyAlphaNumericsAndHyphens,
+    resources_to_skip: Vec<String>,
+    reporter: &dyn ProgressReporter,
+    options: &DeployOptions,
+) -> Result<(), DeployError> {
+    let config = load_config(false, options).await;
+    let cloudformation_client = AwsCloudFormation::new(&config);
+
+    continue_update_rollback_with_client(
+        name.0,
+        resources_to_skip,
+        reporter,
+        options,
+        &cloudformation_client,
+        ctrl_c_pressed(options),
+    )
+    .await
+}
+
+pub(crate) async fn continue_update_rollback_with_client<C: CloudFormationApi>(
+    name: String,
+    resources_to_skip: Vec<String>,
+    reporter: &dyn ProgressReporter,
+    options: &DeployOptions,
+    cloudformation_client: &C,
+    cancel: impl Future<Output = ()>,
+) -> Result<(), DeployError> {
+    let deadline = options.timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+    let mut cancel = pin!(cancel);
+    let mut interval = PollInterval::new();
+
+    let resources_to_skip = if resources_to_skip.is_empty() {
+        None
+    } else {
+        let resource_ids = get_existing_ids_by_resource_id(cloudformation_client, &name).await;
+        Some(
+            resources_to_skip
+                .into_iter()
+                .map(|id| resource_ids.iter().find(|(_, v)| **v == id).map(|(k, _)| k.clone()).unwrap_or(id))
+                .collect(),
+        )
+    };
+
+    let mut progress = StackProgress::start(&name, cloudformation_client, reporter).await;
+    cloudformation_client
+        .continue_update_rollback(&name, resources_to_skip, options.cloudformation_role_arn.clone())
+        .await
+        .map_err(DeployError::RecoveryError)?;
+
+    // wait first, long enough to give CloudFormation time to leave the failed status
+    let mut wait = Duration::from_secs(10);
+    loop {
+        tokio::select! {
+            _ = sleep(wait) => {}
+            _ = &mut cancel => {
+                return Err(DeployError::Cancelled(format!("stopped waiting for the rollback of stack {name}, it continues")));
+            }
+        }
+
+        let status = get_stack_status(&name, cloudformation_client).await;
+        let progressed = match &status {
+            Some(status) => progress.report(status, cloudformation_client).await,
+            None => false,
+        };
+
+        match status {
+            Some(StackStatus::UpdateRollbackComplete) | Some(StackStatus::UpdateRollbackCompleteCleanupInProgress) => return Ok(()),
+            Some(StackStatus::UpdateRollbackInProgress) => {
+                if deadline.is_some_and(|deadline| tokio::time::Instant::now() >= deadline) {
+                    return Err(DeployError::Timeout(format!(
+                        "the rollback of stack {name} did not finish in time, it continues"
+                    )));
+                }
+            }
+            other => return Err(DeployError::RecoveryError(format!("{other:?}"))),
+        }
+        wait = interval.next(progressed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clients::fake::FakeCloudFormation;
+    use crate::deploy::DeployError;
+    use crate::options::DeployOptions;
+    use crate::progress::SilentReporter;
+    use crate::rollback::{continue_update_rollback_with_client, delete_rolled_back_stack_with_client};
+    use aws_sdk_cloudformation::types::StackStatus;
+    use std::future::pending;
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn should_delete_stack_that_failed_to_create() {
+        let cloudformation_client = FakeCloudFormation::new().with_stack("stack", "{}", vec![StackStatus::RollbackComplete]);
+
+        delete_rolled_back_stack_with_client(
+            "stack".to_string(),
+            &SilentReporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+        )
+        .await
+        .expect("delete to succeed");
+
+        assert_eq!(cloudformation_client.calls(), vec!["delete_stack"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_not_delete_stack_with_other_status() {
+        let cloudformation_client = FakeCloudFormation::new().with_stack("stack", "{}", vec![StackStatus::UpdateComplete]);
+
+        let result = delete_rolled_back_stack_with_client(
+            "stack".to_string(),
+            &SilentReporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+        )
+        .await;
+
+        assert!(matches!(result, Err(DeployError::RecoveryError(_))));
+        assert!(cloudformation_client.calls().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_continue_rollback_skipping_resources_by_id() {
+        let cloudformation_client = FakeCloudFormation::new().with_stack(
+            "stack",
+            r#"{"Resources": {}, "Metadata": { "queue": "Queue123" } }"#,
+            vec![StackStatus::UpdateRollbackFailed],
+        );
+
+        continue_update_rollback_with_client(
+            "stack".to_string(),
+            vec!["queue".to_string()],
+            &SilentReporter,
+            &DeployOptions::default(),
+            &cloudformation_client,
+            pending(),
+        )
+        .await
+        .expect("rollback to succeed");
+
+        assert_eq!(cloudformation_client.calls(), vec!["continue_update_rollback Queue123"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_stop_waiting_for_rollback_after_timeout() {
+        let mut cloudformation_client = FakeCloudFormation::new().with_stack("stack", "{}", vec![StackStatus::UpdateRollbackFailed]);
+        cloudformation_client.rollback_statuses = vec![StackStatus::UpdateRollbackInProgress];
+        let options = DeployOptions {
+            timeout: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+
+        let result = continue_update_rollback_with_client(
+            "stack".to_string(),
+            vec![],
+            &SilentReporter,
+            &options,
+            &cloudformation_client,
+            pending(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(DeployError::Timeout(_))));
+    }
+}
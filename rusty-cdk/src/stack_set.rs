@@ -0,0 +1,552 @@
+use crate::clients::{AwsCloudFormation, AwsS3, S3Api, StackInstanceInfo, StackSetApi, StackSetInput};
+use crate::deploy::{DeployError, upload_assets};
+use crate::options::DeployOptions;
+use crate::progress::{ProgressEvent, ProgressReporter, StackInstanceEvent};
+use crate::util::{PollInterval, load_config};
+use aws_sdk_cloudformation::types::StackSetOperationStatus;
+use rusty_cdk_core::shared::Region;
+use rusty_cdk_core::stack::Stack;
+use rusty_cdk_core::wrappers::StringWithOnlyAlphaNumericsAndHyphens;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+#[derive(Debug)]
+pub enum StackSetError {
+    SynthError(String),
+    AssetError(String),
+    StackSetCreateError(String),
+    StackSetUpdateError(String),
+    StackInstancesError(String),
+    /// A stack set operation failed or was stopped. Contains the reason, and the instances that failed
+    OperationFailed(String),
+    UnknownError(String),
+}
+
+impl Error for StackSetError {}
+
+impl Display for StackSetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StackSetError::SynthError(_) => f.write_str("unable to synth"),
+            StackSetError::AssetError(_) => f.write_str("unable to handle asset"),
+            StackSetError::StackSetCreateError(_) => f.write_str("unable to create stack set"),
+            StackSetError::StackSetUpdateError(_) => f.write_str("unable to update stack set"),
+            StackSetError::StackInstancesError(_) => f.write_str("unable to create stack instances"),
+            StackSetError::OperationFailed(_) => f.write_str("stack set operation failed"),
+            StackSetError::UnknownError(_) => f.write_str("unknown error"),
+        }
+    }
+}
+
+/// The accounts to deploy the instances of a stack set to
+#[derive(Debug, Clone)]
+pub enum StackSetTargets {
+    /// Account ids, using self-managed permissions.
+    /// This requires the `AWSCloudFormationStackSetAdministrationRole` role in the calling account,
+    /// and the `AWSCloudFormationStackSetExecutionRole` role in the target accounts
+    Accounts(Vec<String>),
+    /// Ids of organizational units, using service-managed permissions (trusted access for AWS Organizations).
+    /// Accounts that are added to these organizational units later on receive the stack automatically
+    OrganizationalUnits(Vec<String>),
+}
+
+impl StackSetTargets {
+    pub(crate) fn ids(&self) -> &[String] {
+        match self {
+            StackSetTargets::Accounts(ids) => ids,
+            StackSetTargets::OrganizationalUnits(ids) => ids,
+        }
+    }
+
+    fn with_ids(&self, ids: Vec<String>) -> Self {
+        match self {
+            StackSetTargets::Accounts(_) => StackSetTargets::Accounts(ids),
+            StackSetTargets::OrganizationalUnits(_) => StackSetTargets::OrganizationalUnits(ids),
+        }
+    }
+}
+
+/// The number of accounts, or percentage of the accounts, for the concurrency and failure tolerance of stack set operations
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Concurrency {
+    Count(u32),
+    Percentage(u8),
+}
+
+/// Whether stack set operations deploy to one region at a time, or to all regions at once
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum RegionConcurrency {
+    #[default]
+    Sequential,
+    Parallel,
+}
+
+/// Where and how to deploy the instances of a stack set
+///
+/// # Example
+///
+/// ```
+/// use rusty_cdk::{Concurrency, StackSetOptions, StackSetTargets};
+/// use rusty_cdk::shared::Region;
+///
+/// let options = StackSetOptions {
+///     max_concurrent: Some(Concurrency::Percentage(25)),
+///     failure_tolerance: Some(Concurrency::Count(1)),
+///     ..StackSetOptions::new(
+///         StackSetTargets::Accounts(vec!["123456789012".to_string(), "210987654321".to_string()]),
+///         vec![Region::EuWest1, Region::UsEast1],
+///     )
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct StackSetOptions {
+    pub targets: StackSetTargets,
+    pub regions: Vec<Region>,
+    /// The maximum number of accounts to deploy to at the same time (per region). Defaults to one account at a time
+    pub max_concurrent: Option<Concurrency>,
+    /// The number of accounts that can fail (per region) before the operation stops. Defaults to zero
+    pub failure_tolerance: Option<Concurrency>,
+    pub region_concurrency: RegionConcurrency,
+}
+
+impl StackSetOptions {
+    pub fn new(targets: StackSetTargets, regions: Vec<Region>) -> Self {
+        Self {
+            targets,
+            regions,
+            max_concurrent: None,
+            failure_tolerance: None,
+            region_concurrency: RegionConcurrency::default(),
+        }
+    }
+}
+
+/// The result of a successful stack set deployment
+#[derive(Debug)]
+pub struct StackSetResult {
+    pub stack_set_id: String,
+    pub elapsed: Duration,
+    /// The results for the instances that were created or updated
+    pub instances: Vec<StackInstanceEvent>,
+}
+
+impl Display for StackSetResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "stack set {} deployed in {}s",
+            self.stack_set_id,
+            self.elapsed.as_secs()
+        ))?;
+        for instance in &self.instances {
+            f.write_fmt(format_args!("\n- {} ({}): {}", instance.account, instance.region, instance.status))?;
+        }
+        Ok(())
+    }
+}
+
+/// Deploys a stack to multiple accounts and/or regions, as a CloudFormation StackSet.
+///
+/// Creates the stack set if it does not exist yet, or updates it (and all of its instances) if it does.
+/// Afterward, instances are created for the targets and regions of the `StackSetOptions` that do not have one yet.
+/// Instances outside those targets and regions are left alone.
+/// The progress of every instance is reported as a `ProgressEvent::StackInstance`.
+///
+/// Lambda assets are uploaded to their bucket, which has to be accessible from the target accounts.
+/// Lambda only accepts code from a bucket in the region of the function, so stack sets with assets are limited to a single region
+/// (deploy one stack set per region, each with a bucket in that region, instead).
+/// Stack settings (like termination protection) do not apply to stack sets, but tags do.
+///
+/// # Parameters
+///
+/// * `name` - The name of the stack set (alphanumeric characters and hyphens only)
+/// * `stack` - The stack to deploy, created using `StackBuilder`
+/// * `stack_set_options` - The accounts and regions to deploy to, and how many at a time, see `StackSetOptions`
+/// * `reporter` - Receives progress updates, see `ProgressReporter`
+/// * `options` - The AWS profile, region and roles to use, see `DeployOptions`
+///
+/// # AWS Credentials
+///
+/// The AWS credentials must have permissions for:
+/// - `cloudformation:DescribeStackSet`
+/// - `cloudformation:CreateStackSet`
+/// - `cloudformation:UpdateStackSet`
+/// - `cloudformation:ListStackInstances`
+/// - `cloudformation:CreateStackInstances`
+/// - `cloudformation:DescribeStackSetOperation`
+/// - `cloudformation:ListStackSetOperationResults`
+/// - `iam:PassRole` for the `AWSCloudFormationStackSetAdministrationRole` (with `StackSetTargets::Accounts`)
+/// - `s3:PutObject` (if you have Lambdas)
+pub async fn deploy_stack_set(
+    name: StringWithOnlyAlphaNumericsAndHyphens,
+    stack: Stack,
+    stack_set_options: &StackSetOptions,
+    reporter: &dyn ProgressReporter,
+    options: &DeployOptions,
+) -> Result<StackSetResult, StackSetError> {
+    let config = load_config(true, options).await;
+    let cloudformation_client = AwsCloudFormation::new(&config);
+    let s3_client = Arc::new(AwsS3::new(&config, options));
+
+    deploy_stack_set_with_clients(name.0, stack, stack_set_options, reporter, &cloudformation_client, s3_client).await
+}
+
+pub(crate) async fn deploy_stack_set_with_clients<C: StackSetApi, S: S3Api + 'static>(
+    name: String,
+    mut stack: Stack,
+    stack_set_options: &StackSetOptions,
+    reporter: &dyn ProgressReporter,
+    cloudformation_client: &C,
+    s3_client: Arc<S>,
+) -> Result<StackSetResult, StackSetError> {
+    let start = Instant::now();
+
+    stack.package_cargo_crates().map_err(StackSetError::AssetError)?;
+    check_assets_in_single_region(&name, &stack, stack_set_options)?;
+    upload_assets(stack.get_assets(), s3_client, reporter).await.map_err(|e| match e {
+        DeployError::AssetError(e) => StackSetError::AssetError(e),
+        e => StackSetError::UnknownError(format!("{e:?}")),
+    })?;
+
+    let existing_template = cloudformation_client
+        .get_stack_set_template(&name)
+        .await
+        .map_err(StackSetError::UnknownError)?;
+    let template = match &existing_template {
+        Some(existing) => stack.synth_for_existing(existing),
+        None => stack.synth(),
+    }
+    .map_err(StackSetError::SynthError)?;
+
+    let input = StackSetInput {
+        name: name.clone(),
+        template,
+        tags: stack.get_tags(),
+        service_managed: matches!(stack_set_options.targets, StackSetTargets::OrganizationalUnits(_)),
+    };
+    let mut instances = vec![];
+
+    let stack_set_id = match existing_template {
+        Some(_) => {
+            let operation_id = cloudformation_client
+                .update_stack_set(input, stack_set_options)
+                .await
+                .map_err(StackSetError::StackSetUpdateError)?;
+            reporter.report(ProgressEvent::Info(format!("updating stack set {name} (operation {operation_id})")));
+            instances.extend(wait_for_operation(&name, &operation_id, cloudformation_client, reporter).await?);
+            name.clone()
+        }
+        None => {
+            let stack_set_id = cloudformation_client
+                .create_stack_set(input)
+                .await
+                .map_err(StackSetError::StackSetCreateError)?;
+            reporter.report(ProgressEvent::Info(format!("created stack set {name}")));
+            stack_set_id
+        }
+    };
+
+    let existing_instances = cloudformation_client
+        .list_stack_instances(&name)
+        .await
+        .map_err(StackSetError::StackInstancesError)?;
+
+    for (targets, regions) in get_missing_instances(stack_set_options, &existing_instances) {
+        let operation_id = cloudformation_client
+            .create_stack_instances(&name, &stack_set_options.targets.with_ids(targets), regions, stack_set_options)
+            .await
+            .map_err(StackSetError::StackInstancesError)?;
+        reporter.report(ProgressEvent::Info(format!(
+            "creating stack instances for stack set {name} (operation {operation_id})"
+        )));
+        instances.extend(wait_for_operation(&name, &operation_id, cloudformation_client, reporter).await?);
+    }
+
+    let elapsed = start.elapsed();
+    reporter.report(ProgressEvent::Completed {
+        status: StackSetOperationStatus::Succeeded.to_string(),
+        elapsed,
+    });
+
+    Ok(StackSetResult {
+        stack_set_id,
+        elapsed,
+        instances,
+    })
+}
+
+/// The assets are uploaded to one bucket, which is in a single region, while Lambda needs the code in the region of the function
+fn check_assets_in_single_region(name: &str, stack: &Stack, stack_set_options: &StackSetOptions) -> Result<(), StackSetError> {
+    let regions: BTreeSet<String> = stack_set_options.regions.iter().map(|r| String::from(*r)).collect();
+    let assets = stack.get_assets();
+
+    if regions.len() > 1 && !assets.is_empty() {
+        let buckets: BTreeSet<_> = assets.iter().map(|a| a.s3_bucket.as_str()).collect();
+        return Err(StackSetError::AssetError(format!(
+            "stack set {name} targets several regions ({}), but the code of its functions is in bucket(s) {}, and Lambda only accepts code from a bucket in the region of the function. Deploy a stack set per region instead, each with a bucket in that region",
+            regions.into_iter().collect::<Vec<_>>().join(", "),
+            buckets.into_iter().collect::<Vec<_>>().join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Returns the targets without an instance, grouped by the regions they are missing from
+fn get_missing_instances(stack_set_options: &StackSetOptions, existing: &[StackInstanceInfo]) -> Vec<(Vec<String>, Vec<String>)> {
+    let mut regions_by_targets: BTreeMap<Vec<String>, Vec<String>> = BTreeMap::new();
+
+    for region in stack_set_options.regions.iter().map(|r| String::from(*r)) {
+        let missing: Vec<_> = stack_set_options
+            .targets
+            .ids()
+            .iter()
+            .filter(|id| !existing.iter().any(|e| &e.target == *id && e.region == region))
+            .cloned()
+            .collect();
+
+        if !missing.is_empty() {
+            regions_by_targets.entry(missing).or_default().push(region);
+        }
+    }
+
+    regions_by_targets.into_iter().collect()
+}
+
+/// Waits for the operation to finish, reporting the status of every instance when it changes.
+/// Returns the final results of the instances
+async fn wait_for_operation<C: StackSetApi>(
+    name: &str,
+    operation_id: &str,
+    cloudformation_client: &C,
+    reporter: &dyn ProgressReporter,
+) -> Result<Vec<StackInstanceEvent>, StackSetError> {
+    let mut interval = PollInterval::new();
+    let mut reported: HashMap<(String, String), String> = HashMap::new();
+
+    loop {
+        let (status, reason) = cloudformation_client
+            .describe_stack_set_operation(name, operation_id)
+            .await
+            .map_err(StackSetError::UnknownError)?;
+        // results are only informational while the operation is running, so failing to retrieve them is not a problem
+        let results: Vec<_> = cloudformation_client
+            .list_stack_set_operation_results(name, operation_id)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|r| StackInstanceEvent {
+                account: r.account,
+                region: r.region,
+                status: r.status.to_string(),
+                reason: r.reason,
+            })
+            .collect();
+
+        let mut progressed = false;
+        for result in &results {
+            let key = (result.account.clone(), result.region.clone());
+            if reported.get(&key) != Some(&result.status) {
+                reported.insert(key, result.status.clone());
+                reporter.report(ProgressEvent::StackInstance(result.clone()));
+                progressed = true;
+            }
+        }
+
+        match status {
+            StackSetOperationStatus::Succeeded => return Ok(results),
+            StackSetOperationStatus::Failed | StackSetOperationStatus::Stopped => {
+                let failed: Vec<_> = results
+                    .iter()
+                    .filter(|r| r.status != "SUCCEEDED")
+                    .map(|r| format!("{} ({}): {}", r.account, r.region, r.reason.as_deref().unwrap_or(&r.status)))
+                    .collect();
+                return Err(StackSetError::OperationFailed(format!(
+                    "operation {operation_id} {status} ({}), instances: {}",
+                    reason.unwrap_or_default(),
+                    failed.join(", ")
+                )));
+            }
+            _ => {}
+        }
+
+        sleep(interval.next(progressed)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clients::fake::{FakeS3, FakeStackSets};
+    use crate::progress::{ProgressEvent, RecordingReporter};
+    use crate::stack_set::{StackSetError, StackSetOptions, StackSetTargets, deploy_stack_set_with_clients};
+    use rusty_cdk_core::cloudwatch_logs::LogGroupBuilder;
+    use rusty_cdk_core::lambda::{Architecture, Code, FunctionBuilder, Runtime, Zip};
+    use rusty_cdk_core::shared::Region;
+    use rusty_cdk_core::stack::{Stack, StackBuilder};
+    use rusty_cdk_core::wrappers::{Bucket, Memory, Timeout, ZipFile};
+    use std::sync::Arc;
+
+    fn log_group_stack() -> Stack {
+        let mut stack_builder = StackBuilder::new();
+        LogGroupBuilder::new("logs").build(&mut stack_builder);
+        stack_builder.build().unwrap()
+    }
+
+    fn function_stack() -> Stack {
+        let mut stack_builder = StackBuilder::new();
+        FunctionBuilder::new("fun", Architecture::ARM64, Memory(256), Timeout(30))
+            .code(Code::Zip(Zip::new(
+                Bucket("some-bucket".to_string()),
+                ZipFile("./example.zip".to_string()),
+            )))
+            .handler("bootstrap")
+            .runtime(Runtime::ProvidedAl2023)
+            .build(&mut stack_builder);
+        stack_builder.build().unwrap()
+    }
+
+    fn accounts(ids: &[&str]) -> StackSetOptions {
+        StackSetOptions::new(
+            StackSetTargets::Accounts(ids.iter().map(|id| id.to_string()).collect()),
+            vec![Region::EuWest1, Region::UsEast1],
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_create_stack_set_with_instances() {
+        let client = FakeStackSets::new();
+        let reporter = RecordingReporter::new();
+
+        let result = deploy_stack_set_with_clients(
+            "baseline".to_string(),
+            log_group_stack(),
+            &accounts(&["111111111111", "222222222222"]),
+            &reporter,
+            &client,
+            Arc::new(FakeS3::new()),
+        )
+        .await
+        .expect("stack set deployment to succeed");
+
+        assert_eq!(
+            client.calls(),
+            vec![
+                "create_stack_set baseline",
+                "create_stack_instances 111111111111,222222222222 eu-west-1,us-east-1"
+            ]
+        );
+        assert_eq!(result.instances.len(), 4);
+        let instance_events = reporter
+            .events()
+            .into_iter()
+            .filter(|e| matches!(e, ProgressEvent::StackInstance(i) if i.status == "SUCCEEDED"))
+            .count();
+        assert_eq!(instance_events, 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_update_stack_set_and_only_create_missing_instances() {
+        let existing = log_group_stack().synth().unwrap();
+        let client = FakeStackSets::new().with_stack_set(
+            "baseline",
+            &existing,
+            vec![
+                ("111111111111", "eu-west-1"),
+                ("111111111111", "us-east-1"),
+                ("222222222222", "eu-west-1"),
+            ],
+        );
+
+        deploy_stack_set_with_clients(
+            "baseline".to_string(),
+            log_group_stack(),
+            &accounts(&["111111111111", "222222222222"]),
+            &RecordingReporter::new(),
+            &client,
+            Arc::new(FakeS3::new()),
+        )
+        .await
+        .expect("stack set deployment to succeed");
+
+        assert_eq!(
+            client.calls(),
+            vec!["update_stack_set baseline", "create_stack_instances 222222222222 us-east-1"]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_return_failed_instances_when_operation_fails() {
+        let mut client = FakeStackSets::new();
+        client.failing_account = Some("222222222222".to_string());
+
+        let result = deploy_stack_set_with_clients(
+            "baseline".to_string(),
+            log_group_stack(),
+            &accounts(&["111111111111", "222222222222"]),
+            &RecordingReporter::new(),
+            &client,
+            Arc::new(FakeS3::new()),
+        )
+        .await;
+
+        match result {
+            Err(StackSetError::OperationFailed(reason)) => {
+                assert!(reason.contains("222222222222 (eu-west-1): no permission"));
+                assert!(!reason.contains("111111111111"));
+            }
+            other => panic!("expected OperationFailed, got {other:?}"),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_refuse_stack_set_with_assets_in_several_regions() {
+        let client = FakeStackSets::new();
+        let s3 = Arc::new(FakeS3::new());
+
+        let result = deploy_stack_set_with_clients(
+            "baseline".to_string(),
+            function_stack(),
+            &accounts(&["111111111111"]),
+            &RecordingReporter::new(),
+            &client,
+            s3.clone(),
+        )
+        .await;
+
+        match result {
+            Err(StackSetError::AssetError(reason)) => {
+                assert!(reason.contains("several regions (eu-west-1, us-east-1)"));
+                assert!(reason.contains("some-bucket"));
+            }
+            other => panic!("expected AssetError, got {other:?}"),
+        }
+        assert!(s3.keys("some-bucket").is_empty());
+        assert!(client.calls().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_upload_assets_of_stack_set_in_single_region() {
+        let client = FakeStackSets::new();
+        let s3 = Arc::new(FakeS3::new());
+        let options = StackSetOptions::new(StackSetTargets::Accounts(vec!["111111111111".to_string()]), vec![Region::EuWest1]);
+
+        deploy_stack_set_with_clients(
+            "baseline".to_string(),
+            function_stack(),
+            &options,
+            &RecordingReporter::new(),
+            &client,
+            s3.clone(),
+        )
+        .await
+        .expect("stack set deployment to succeed");
+
+        assert_eq!(s3.keys("some-bucket").len(), 1);
+        assert_eq!(
+            client.calls(),
+            vec!["create_stack_set baseline", "create_stack_instances 111111111111 eu-west-1"]
+        );
+    }
+}
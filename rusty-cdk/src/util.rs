@@ -1,34 +1,347 @@
+use crate::clients::{CloudFormationApi, StackInfo};
+use crate::deploy::DeployError;
+use crate::options::DeployOptions;
+use crate::progress::{ProgressEvent, ProgressReporter, StackEvent};
 use aws_config::SdkConfig;
+use aws_config::retry::RetryConfig;
 use aws_config::stalled_stream_protection::StalledStreamProtectionConfig;
-use aws_sdk_cloudformation::Client;
+use aws_config::sts::AssumeRoleProvider;
+use aws_sdk_cloudformation::config::{Region, SharedCredentialsProvider};
 use aws_sdk_cloudformation::types::StackStatus;
+use rusty_cdk_core::stack::Stack;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::time::{Instant, sleep};
 
-pub(crate) async fn load_config(with_stall_protection: bool) -> SdkConfig {
-    let mut config = aws_config::defaults(aws_config::BehaviorVersion::latest());
+// CloudFormation throttles quickly, especially when polling for events, so retry (with exponential backoff) more than the default 3 times
+const MAX_ATTEMPTS: u32 = 8;
+const MAX_BACKOFF: Duration = Duration::from_secs(20);
+const MIN_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(20);
+
+pub(crate) async fn load_config(with_stall_protection: bool, options: &DeployOptions) -> SdkConfig {
+    let mut config = aws_config::defaults(aws_config::BehaviorVersion::latest()).retry_config(
+        RetryConfig::standard()
+            .with_max_attempts(MAX_ATTEMPTS)
+            .with_max_backoff(MAX_BACKOFF),
+    );
 
     if with_stall_protection {
         // https://github.com/awslabs/aws-sdk-rust/issues/1146
         config = config.stalled_stream_protection(StalledStreamProtectionConfig::disabled());
     }
+    if let Some(profile) = &options.profile {
+        config = config.profile_name(profile);
+    }
+    if let Some(region) = &options.region {
+        config = config.region(Region::new(region.clone()));
+    }
+    if let Some(endpoint_url) = &options.endpoint_url {
+        config = config.endpoint_url(endpoint_url);
+    }
+
+    let config = config.load().await;
+
+    match &options.assume_role_arn {
+        Some(role_arn) => {
+            let mut provider = AssumeRoleProvider::builder(role_arn).session_name("rusty-cdk").configure(&config);
+            if let Some(external_id) = &options.external_id {
+                provider = provider.external_id(external_id);
+            }
+            let provider = provider.build().await;
+
+            config
+                .into_builder()
+                .credentials_provider(SharedCredentialsProvider::new(provider))
+                .build()
+        }
+        None => config,
+    }
+}
 
-    config.load().await
+pub(crate) async fn get_existing_template<C: CloudFormationApi>(client: &C, stack_name: &str) -> Option<String> {
+    client.get_template(stack_name).await.ok().flatten()
 }
 
-pub(crate) async fn get_existing_template(client: &Client, stack_name: &str) -> Option<String> {
-    match client.describe_stacks().stack_name(stack_name).send().await {
-        Ok(_) => {
-            let template = client.get_template().stack_name(stack_name).send().await;
-            template.unwrap().template_body
+/// Returns the ids of the existing stack, keyed by resource id. Empty if the stack or its metadata could not be found
+pub(crate) async fn get_existing_ids_by_resource_id<C: CloudFormationApi>(client: &C, stack_name: &str) -> HashMap<String, String> {
+    get_existing_template(client, stack_name)
+        .await
+        .and_then(|t| Stack::get_ids_by_resource_id(&t).ok())
+        .unwrap_or_default()
+}
+
+pub(crate) async fn get_stack_description<C: CloudFormationApi>(name: &str, cloudformation_client: &C) -> Option<StackInfo> {
+    cloudformation_client.describe_stack(name).await.ok().flatten()
+}
+
+pub(crate) async fn get_stack_status<C: CloudFormationApi>(name: &str, cloudformation_client: &C) -> Option<StackStatus> {
+    get_stack_description(name, cloudformation_client).await.map(|v| v.status)
+}
+
+/// Waits until the stack is no longer in an `*_IN_PROGRESS` status, for example because of a concurrent operation, and returns that status.
+/// `REVIEW_IN_PROGRESS` is not waited for, because that status only changes when someone executes a change set.
+///
+/// Stops waiting with `DeployError::Timeout` when the deadline passes, or with `DeployError::Cancelled` when `cancel` completes.
+/// The ongoing operation itself continues in both cases.
+pub(crate) async fn wait_for_stable_status<C: CloudFormationApi>(
+    name: &str,
+    cloudformation_client: &C,
+    reporter: &dyn ProgressReporter,
+    deadline: Option<Instant>,
+    mut cancel: Pin<&mut impl Future<Output = ()>>,
+) -> Result<Option<StackStatus>, DeployError> {
+    let mut interval = PollInterval::new();
+    let mut last_status = None;
+
+    loop {
+        let status = get_stack_status(name, cloudformation_client).await;
+
+        match status {
+            Some(status) if status.as_str().ends_with("_IN_PROGRESS") && status != StackStatus::ReviewInProgress => {
+                if last_status.is_none() {
+                    reporter.report(ProgressEvent::Info(format!(
+                        "waiting for ongoing operation to finish ({status})..."
+                    )));
+                }
+                let progressed = last_status.as_ref() != Some(&status);
+
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return Err(DeployError::Timeout(format!(
+                        "the ongoing operation of stack {name} did not finish in time, it is still {status}"
+                    )));
+                }
+                last_status = Some(status);
+
+                tokio::select! {
+                    _ = sleep(interval.next(progressed)) => {}
+                    _ = &mut cancel => {
+                        return Err(DeployError::Cancelled(format!(
+                            "stopped waiting for the ongoing operation of stack {name}, it continues"
+                        )));
+                    }
+                }
+            }
+            status => return Ok(status),
         }
-        Err(_) => None,
     }
 }
 
-pub(crate) async fn get_stack_status(name: &String, cloudformation_client: &Client) -> Option<StackStatus> {
-    let status = cloudformation_client.describe_stacks().stack_name(name).send().await;
-    status
-        .ok()
-        .and_then(|v| v.stacks)
-        .and_then(|mut v| v.pop())
-        .and_then(|v| v.stack_status)
+/// Completes when ctrl-c is pressed, if enabled in the options. Otherwise, it never completes
+pub(crate) async fn ctrl_c_pressed(options: &DeployOptions) {
+    if !options.cancel_on_ctrl_c || tokio::signal::ctrl_c().await.is_err() {
+        std::future::pending::<()>().await
+    }
+}
+
+/// The time to wait between status checks: short while the stack is making progress, growing when nothing changes
+pub(crate) struct PollInterval(Duration);
+
+impl PollInterval {
+    pub(crate) fn new() -> Self {
+        Self(MIN_POLL_INTERVAL)
+    }
+
+    pub(crate) fn next(&mut self, progressed: bool) -> Duration {
+        self.0 = if progressed {
+            MIN_POLL_INTERVAL
+        } else {
+            (self.0 * 2).min(MAX_POLL_INTERVAL)
+        };
+        self.0
+    }
+}
+
+/// Reports the progress of a stack operation: status changes and the stack events that happened since the operation started
+pub(crate) struct StackProgress<'a> {
+    name: String,
+    reporter: &'a dyn ProgressReporter,
+    last_event_id: Option<String>,
+    last_status: Option<StackStatus>,
+    ids_by_resource_id: Option<HashMap<String, String>>,
+}
+
+impl<'a> StackProgress<'a> {
+    /// Call this *before* starting the operation, so older events are not reported
+    pub(crate) async fn start<C: CloudFormationApi>(name: &str, cloudformation_client: &C, reporter: &'a dyn ProgressReporter) -> Self {
+        let last_event_id = cloudformation_client
+            .describe_stack_events(name)
+            .await
+            .ok()
+            .and_then(|events| events.into_iter().next())
+            .map(|e| e.event_id);
+
+        Self {
+            name: name.to_string(),
+            reporter,
+            last_event_id,
+            last_status: None,
+            ids_by_resource_id: None,
+        }
+    }
+
+    /// Returns whether anything changed since the previous report
+    pub(crate) async fn report<C: CloudFormationApi>(&mut self, status: &StackStatus, cloudformation_client: &C) -> bool {
+        let mut progressed = false;
+
+        // events are only informational, so failing to retrieve them is not a problem
+        if let Ok(events) = cloudformation_client.describe_stack_events(&self.name).await {
+            let new: Vec<_> = events
+                .into_iter()
+                .take_while(|e| Some(&e.event_id) != self.last_event_id.as_ref())
+                .collect();
+
+            if let Some(latest) = new.first() {
+                self.last_event_id = Some(latest.event_id.clone());
+                progressed = true;
+
+                if self.ids_by_resource_id.is_none() {
+                    self.ids_by_resource_id = Some(get_existing_ids_by_resource_id(cloudformation_client, &self.name).await);
+                }
+            }
+
+            let ids = self.ids_by_resource_id.as_ref();
+            for event in new.into_iter().rev() {
+                self.reporter.report(ProgressEvent::StackEvent(StackEvent {
+                    id: ids.and_then(|ids| ids.get(&event.resource_id)).cloned(),
+                    resource_id: event.resource_id,
+                    resource_type: event.resource_type,
+                    status: event.status,
+                    reason: event.reason,
+                }));
+            }
+        }
+
+        if self.last_status.as_ref() != Some(status) {
+            self.reporter.report(ProgressEvent::StatusChanged(status.to_string()));
+            self.last_status = Some(status.clone());
+            progressed = true;
+        }
+
+        progressed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clients::fake::FakeCloudFormation;
+    use crate::clients::{CloudFormationApi, StackInput, StackSettingsInput};
+    use crate::deploy::DeployError;
+    use crate::progress::{ProgressEvent, RecordingReporter, SilentReporter};
+    use crate::util::{PollInterval, StackProgress, wait_for_stable_status};
+    use aws_sdk_cloudformation::types::StackStatus;
+    use std::future::pending;
+    use std::pin::pin;
+    use std::time::Duration;
+    use tokio::time::{Instant, sleep};
+
+    #[tokio::test(start_paused = true)]
+    async fn should_wait_for_operation_in_progress() {
+        let client = FakeCloudFormation::new().with_stack(
+            "stack",
+            "{}",
+            vec![
+                StackStatus::UpdateInProgress,
+                StackStatus::UpdateCompleteCleanupInProgress,
+                StackStatus::UpdateComplete,
+            ],
+        );
+
+        let status = wait_for_stable_status("stack", &client, &SilentReporter, None, pin!(pending()))
+            .await
+            .unwrap();
+
+        assert_eq!(status, Some(StackStatus::UpdateComplete));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_not_wait_for_review_in_progress() {
+        let client =
+            FakeCloudFormation::new().with_stack("stack", "{}", vec![StackStatus::ReviewInProgress, StackStatus::CreateInProgress]);
+
+        let status = wait_for_stable_status("stack", &client, &SilentReporter, None, pin!(pending()))
+            .await
+            .unwrap();
+
+        assert_eq!(status, Some(StackStatus::ReviewInProgress));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_return_none_for_missing_stack() {
+        let client = FakeCloudFormation::new();
+
+        let status = wait_for_stable_status("stack", &client, &SilentReporter, None, pin!(pending()))
+            .await
+            .unwrap();
+
+        assert_eq!(status, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_stop_waiting_for_operation_in_progress_after_deadline() {
+        let client = FakeCloudFormation::new().with_stack("stack", "{}", vec![StackStatus::UpdateInProgress]);
+        let deadline = Instant::now() + Duration::from_secs(60);
+
+        let result = wait_for_stable_status("stack", &client, &SilentReporter, Some(deadline), pin!(pending())).await;
+
+        assert!(matches!(result, Err(DeployError::Timeout(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_stop_waiting_for_operation_in_progress_when_cancelled() {
+        let client = FakeCloudFormation::new().with_stack("stack", "{}", vec![StackStatus::DeleteInProgress]);
+
+        let result = wait_for_stable_status("stack", &client, &SilentReporter, None, pin!(sleep(Duration::from_secs(30)))).await;
+
+        assert!(matches!(result, Err(DeployError::Cancelled(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_only_report_new_events_and_status_changes() {
+        let client = FakeCloudFormation::new().with_stack("stack", "{}", vec![StackStatus::CreateComplete]);
+        client.update_stack(update_input(r#"{"Resources": {}}"#)).await.unwrap();
+        let reporter = RecordingReporter::new();
+
+        let mut progress = StackProgress::start("stack", &client, &reporter).await;
+        client
+            .update_stack(update_input(r#"{"Resources": {}, "Metadata": {}}"#))
+            .await
+            .unwrap();
+        progress.report(&StackStatus::UpdateInProgress, &client).await;
+        progress.report(&StackStatus::UpdateInProgress, &client).await;
+
+        let events = reporter.events();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], ProgressEvent::StackEvent(e) if e.status == "UPDATE_IN_PROGRESS"));
+        assert!(matches!(&events[1], ProgressEvent::StatusChanged(s) if s == "UPDATE_IN_PROGRESS"));
+    }
+
+    fn update_input(template: &str) -> StackInput {
+        StackInput {
+            name: "stack".to_string(),
+            template: template.to_string(),
+            tags: vec![],
+            role_arn: None,
+            settings: Some(StackSettingsInput::default()),
+        }
+    }
+
+    #[test]
+    fn should_poll_less_often_until_there_is_progress() {
+        let mut interval = PollInterval::new();
+
+        let intervals: Vec<_> = [false, false, false, false, true].into_iter().map(|p| interval.next(p)).collect();
+
+        assert_eq!(
+            intervals,
+            vec![
+                Duration::from_secs(4),
+                Duration::from_secs(8),
+                Duration::from_secs(16),
+                Duration::from_secs(20),
+                Duration::from_secs(2)
+            ]
+        );
+    }
 }
@@ -15,11 +15,12 @@ use rusty_cdk_core::cloudwatch_logs::LogGroupBuilder;
 use rusty_cdk_core::dynamodb::AttributeType;
 use rusty_cdk_core::dynamodb::Key;
 use rusty_cdk_core::dynamodb::TableBuilder;
+use rusty_cdk_core::dynamodb::TableRef;
 use rusty_cdk_core::events::{FlexibleTimeWindowBuilder, JsonTarget, Mode, ScheduleBuilder, State, TargetBuilder};
 use rusty_cdk_core::iam::{CustomPermission, Effect, Permission, PolicyDocumentBuilder, PrincipalBuilder, RoleRef, StatementBuilder};
-use rusty_cdk_core::lambda::{Architecture, Code, FunctionBuilder, Runtime, Zip};
+use rusty_cdk_core::lambda::{Architecture, Code, FunctionBuilder, FunctionRef, Runtime, Zip};
 use rusty_cdk_core::s3::{
-    BucketBuilder, ConfigurationState, CorsConfigurationBuilder, CorsRuleBuilder, Encryption, Expiration,
+    BucketBuilder, BucketRef, ConfigurationState, CorsConfigurationBuilder, CorsRuleBuilder, Encryption, Expiration,
     IntelligentTieringConfigurationBuilder, IntelligentTieringStatus, InventoryTableConfigurationBuilder, JournalTableConfigurationBuilder,
     LifecycleConfigurationBuilder, LifecycleRuleBuilder, LifecycleRuleStatus, LifecycleRuleTransitionBuilder, LifecycleStorageClass,
     MetadataConfigurationBuilder, MetadataDestinationBuilder, NotificationDestination, NotificationEventType,
@@ -29,7 +30,7 @@ use rusty_cdk_core::secretsmanager::{GenerateSecretStringBuilder, SecretBuilder}
 use rusty_cdk_core::shared::HttpMethod;
 use rusty_cdk_core::shared::{DeletionPolicy, UpdateReplacePolicy};
 use rusty_cdk_core::sns::{FifoThroughputScope, SubscriptionType, TopicBuilder, TracingConfig};
-use rusty_cdk_core::sqs::QueueBuilder;
+use rusty_cdk_core::sqs::{QueueBuilder, QueueRef};
 use rusty_cdk_core::stack::StackBuilder;
 use rusty_cdk_core::wrappers::*;
 use rusty_cdk_macros::*;
@@ -612,6 +613,65 @@ fn lambda_with_dynamodb_and_sqs() {
     });
 }
 
+#[test]
+fn lambda_with_existing_resources() {
+    let mut stack_builder = StackBuilder::new();
+
+    // direct construction of the refs to avoid depending on specific AWS env
+    // in most use cases, prefer the `lookup_table_ref`, `lookup_queue_ref`,... macros for safety
+    let table = TableRef::new(
+        "ExistingTable",
+        "existing-table",
+        "arn:aws:dynamodb:eu-west-1:1234:table/existing-table",
+    );
+    let queue = QueueRef::new(
+        "ExistingQueue",
+        "ExistingQueue",
+        "https://sqs.eu-west-1.amazonaws.com/1234/existing-queue",
+        "arn:aws:sqs:eu-west-1:1234:existing-queue",
+    );
+    let existing_bucket = BucketRef::new("ExistingBucket", "existing-bucket", "arn:aws:s3:::existing-bucket");
+    let existing_function = FunctionRef::new(
+        "ExistingFunction",
+        "ExistingFunction",
+        "existing-function",
+        "arn:aws:lambda:eu-west-1:1234:function:existing-function",
+    );
+
+    let zip_file = zip_file!("./rusty-cdk/tests/example.zip");
+    let memory = memory!(512);
+    let timeout = timeout!(30);
+    let bucket = get_bucket();
+    FunctionBuilder::new("fun", Architecture::ARM64, memory, timeout)
+        .add_permission(Permission::DynamoDBReadWrite(&table))
+        .add_permission(Permission::S3ReadWrite(&existing_bucket))
+        .code(Code::Zip(Zip::new(bucket, zip_file)))
+        .handler("bootstrap")
+        .runtime(Runtime::ProvidedAl2023)
+        .env_var(env_var_key!("TABLE_NAME"), table.get_ref())
+        .sqs_event_source_mapping(&queue, None)
+        .build(&mut stack_builder);
+    TopicBuilder::new("topic")
+        .add_subscription(SubscriptionType::Lambda(&existing_function))
+        .build(&mut stack_builder);
+    let stack = stack_builder.build().unwrap();
+
+    let synthesized = stack.synth().unwrap();
+    let synthesized: Value = serde_json::from_str(&synthesized).unwrap();
+
+    insta::with_settings!({filters => vec![
+            (r"LambdaFunction[0-9]+", "[LambdaFunction]"),
+            (r"LambdaFunctionRole[0-9]+", "[LambdaFunctionRole]"),
+            (r"LogGroup[0-9]+", "[LogGroup]"),
+            (r"SnsTopic[0-9]+", "[SnsTopic]"),
+            (r"SnsSubscription[0-9]+", "[SnsSubscription]"),
+            (r"LambdaPermission[0-9]+", "[LambdaPermission]"),
+            (r"Asset[0-9]+\.zip", "[Asset]"),
+        ]},{
+            insta::assert_json_snapshot!(synthesized);
+    });
+}
+
 #[test]
 fn cloudfront_with_s3_origin() {
     let mut stack_builder = StackBuilder::new();